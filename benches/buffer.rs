@@ -0,0 +1,56 @@
+//! Typing at the top of a 10 MB file, into the text kept as one `String` the
+//! way it was before `Buffer` and into a `Buffer`. Run with
+//! `cargo bench --bench buffer`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+#[allow(dead_code)]
+#[path = "../src/buffer.rs"]
+mod buffer;
+
+use buffer::Buffer;
+
+const LINE: &str = "    let value = compute(first, second) + other; // what it is for\n";
+const TEXT_LEN: usize = 10 * 1024 * 1024;
+const KEYSTROKES: u32 = 1_000;
+
+/// Runs `type_key` once per keystroke and prints how long one took.
+fn bench(name: &str, mut type_key: impl FnMut(usize)) {
+    let start = Instant::now();
+    for i in 0..KEYSTROKES {
+        type_key(i as usize);
+    }
+    let per_keystroke: Duration = start.elapsed() / KEYSTROKES;
+    println!("{name:<24} {per_keystroke:.2?} per keystroke");
+}
+
+fn main() {
+    let text = LINE.repeat(TEXT_LEN / LINE.len());
+    println!(
+        "typing at the top of {} lines, {} MB",
+        text.lines().count(),
+        text.len() / 1024 / 1024
+    );
+
+    let mut string = text.clone();
+    bench("before, String", |i| string.insert(i, 'a'));
+    black_box(&string);
+
+    let mut buffer = Buffer::new(&text);
+    bench("after, Buffer", |i| buffer.replace(i..i, "a"));
+    black_box(&buffer);
+
+    // every newline adds a line, all the lines after it move down
+    let mut string = text.clone();
+    bench("before, String, enter", |i| string.insert(i * 2, '\n'));
+    black_box(&string);
+
+    let mut buffer = Buffer::new(&text);
+    bench("after, Buffer, enter", |i| {
+        buffer.replace(i * 2..i * 2, "\n")
+    });
+    black_box(&buffer);
+}
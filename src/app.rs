@@ -1,9 +1,39 @@
-use crate::{theme::Theme, window::get_window_options, workspace::build_workspace_view};
+use crate::{
+    args::Args, clipboard_history::ClipboardHistory, command::CommandRegistry, config::Config,
+    keymap::Keymap, macros::MacroRecorder, modal::ModalState, recent_files::RecentFiles,
+    session::Session, snippet::Snippets, spell::Dictionary, theme::Theme, toast::ToastQueue,
+    window::get_window_options, workspace::build_workspace_view,
+};
 
-pub fn run_app(app: gpui::App) {
+pub fn run_app(app: gpui::App, args: Args) {
     app.run(move |cx| {
-        Theme::init(cx);
-
-        cx.open_window(get_window_options(), build_workspace_view);
+        CommandRegistry::init(cx);
+        ModalState::init(cx);
+        ToastQueue::init(cx);
+        Keymap::init(cx);
+        MacroRecorder::init(cx);
+        ClipboardHistory::init(cx);
+        Snippets::init(cx);
+        RecentFiles::init(cx);
+        let config = Config::load();
+        cx.set_global(config.editor.clone());
+        Dictionary::init(&config.editor, cx);
+        let session = args.restores_session().then(Session::load).flatten();
+        let window = session.as_ref().and_then(|session| session.window);
+        cx.open_window(get_window_options(window), move |cx| {
+            // the theme follows the window's light or dark mode
+            Theme::init(&config, cx);
+            let watch_theme = args.watch_theme || config.watch_theme;
+            let workspace = build_workspace_view(cx, args, session);
+            if config.vim_mode {
+                workspace.update(cx, |workspace, cx| workspace.set_vim_mode(true, cx));
+            }
+            if let Some(path) = config.theme.as_deref().and_then(Theme::path) {
+                if watch_theme {
+                    workspace.update(cx, |workspace, cx| workspace.watch_theme(path, cx));
+                }
+            }
+            workspace
+        });
     });
 }
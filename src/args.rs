@@ -0,0 +1,139 @@
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+const USAGE: &str = "\
+Usage: ted-itor [OPTIONS] [FILE[:LINE[:COLUMN]]]...
+
+Opens each FILE in a tab, a file that doesn't exist yet opens empty.
+A FILE that is a folder is shown in the sidebar.
+LINE and COLUMN start at 1 and put the cursor there. A FILE of - opens
+what's piped into stdin, which also happens when no FILE is given.
+
+Without a FILE the files open last time are opened again.
+
+Options:
+      --no-restore   Start with nothing open instead of the last session
+      --watch-theme  Apply changes to the theme file while running
+  -h, --help         Print this help and exit
+  -V, --version      Print the version and exit";
+
+/// A file named on the command line.
+pub struct FileArg {
+    pub path: PathBuf,
+    /// The zero-based row and char column to put the cursor at.
+    pub position: Option<(usize, usize)>,
+}
+
+/// Whether the text piped into stdin is opened.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stdin {
+    Ignore,
+    /// Asked for with "-".
+    Read,
+    /// Not a terminal and no files given, opened unless it's empty.
+    Piped,
+}
+
+/// What to open in the window.
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub stdin: Stdin,
+    /// Whether the last session is opened when nothing else is.
+    pub restore: bool,
+    /// Whether the theme file is reloaded when it changes.
+    pub watch_theme: bool,
+}
+
+impl Args {
+    /// Whether the last session should be opened, only when the command line
+    /// doesn't say what to open.
+    pub fn restores_session(&self) -> bool {
+        self.restore && self.files.is_empty() && self.stdin == Stdin::Ignore
+    }
+}
+
+/// What the command line asks for.
+pub enum Command {
+    Open(Args),
+    /// Print this and exit without opening a window.
+    Print(String),
+}
+
+impl Command {
+    /// Parses the arguments after the program name. An unknown option is an
+    /// error, its message includes the usage.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut files = Vec::new();
+        let mut stdin = Stdin::Ignore;
+        let mut restore = true;
+        let mut watch_theme = false;
+        let mut options_done = false;
+        for arg in args {
+            if !options_done && arg.starts_with('-') && arg.len() > 1 {
+                match arg.as_str() {
+                    "-h" | "--help" => return Ok(Self::Print(USAGE.to_string())),
+                    "-V" | "--version" => {
+                        let version = format!("ted-itor {}", env!("CARGO_PKG_VERSION"));
+                        return Ok(Self::Print(version));
+                    }
+                    "--no-restore" => restore = false,
+                    "--watch-theme" => watch_theme = true,
+                    // everything after it is a file, even when it starts with '-'
+                    "--" => options_done = true,
+                    _ => return Err(format!("Unknown option {arg}\n\n{USAGE}")),
+                }
+                continue;
+            }
+            if arg == "-" {
+                stdin = Stdin::Read;
+            } else {
+                files.push(FileArg::parse(&arg));
+            }
+        }
+        if stdin == Stdin::Ignore && files.is_empty() && !io::stdin().is_terminal() {
+            stdin = Stdin::Piped;
+        }
+        Ok(Self::Open(Args {
+            files,
+            stdin,
+            restore,
+            watch_theme,
+        }))
+    }
+}
+
+impl FileArg {
+    /// Splits `path:line:column` or `path:line` into its parts. Anything that
+    /// isn't a number after a colon is part of the path.
+    fn parse(arg: &str) -> Self {
+        let mut path = arg;
+        // the numbers at the end, last one first
+        let mut numbers = Vec::new();
+        while numbers.len() < 2 {
+            let Some((rest, number)) = path.rsplit_once(':') else {
+                break;
+            };
+            let Ok(number) = number.parse::<usize>() else {
+                break;
+            };
+            if rest.is_empty() {
+                break;
+            }
+            numbers.push(number);
+            path = rest;
+        }
+        // 1-based on the command line, a 0 means the first line or column too
+        let position = match numbers[..] {
+            [] => None,
+            [line] => Some((line.saturating_sub(1), 0)),
+            [column, line] => Some((line.saturating_sub(1), column.saturating_sub(1))),
+            _ => unreachable!(),
+        };
+        Self {
+            path: PathBuf::from(path),
+            position,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Local};
+use gpui::{prelude::FluentBuilder, *};
+use serde::Deserialize;
+
+use crate::{
+    editor::write_atomically,
+    recovery::path_hash,
+    theme::Theme,
+    ui::{Background, List, ListEvent, TextEvent, TextInput},
+};
+
+/// The height of a backup in the picker.
+const ROW_HEIGHT: Pixels = px(28.);
+/// More backups than this scroll.
+const MAX_VISIBLE_ROWS: usize = 10;
+
+/// Where `backup_on_save` puts the copies.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupLocation {
+    /// `<name>.bak` next to the file, only the latest one.
+    NextToFile,
+    /// `<hash>-<timestamp>` in the `backups` folder of the platform's data
+    /// directory, the latest `backup_count` of each file.
+    #[default]
+    DataDir,
+}
+
+/// A copy of a file from before a save overwrote it.
+#[derive(Clone)]
+pub struct Backup {
+    /// The file it's a copy of.
+    pub original: PathBuf,
+    pub path: PathBuf,
+    pub created: SystemTime,
+    pub len: u64,
+}
+
+impl Backup {
+    /// When it was made, in local time.
+    pub fn created_label(&self) -> String {
+        let created: DateTime<Local> = self.created.into();
+        created.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+fn backups_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("ted-itor").join("backups"))
+}
+
+/// `<name>.bak`, next to `path`.
+fn bak_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Copies the file at `path` as it's on disk, before a save overwrites it. A
+/// file that doesn't exist yet has nothing to back up. In the data directory
+/// the oldest backups go once there are more than `count`.
+pub fn back_up(path: &Path, location: BackupLocation, count: usize) -> io::Result<()> {
+    if !path.is_file() {
+        return Ok(());
+    }
+    let bytes = fs::read(path)?;
+    match location {
+        BackupLocation::NextToFile => write_atomically(&bak_path(path), &bytes),
+        BackupLocation::DataDir => {
+            let dir = backups_dir().ok_or(io::ErrorKind::NotFound)?;
+            fs::create_dir_all(&dir)?;
+            let path = path.canonicalize()?;
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis());
+            write_atomically(&dir.join(format!("{}-{millis}", path_hash(&path))), &bytes)?;
+            for old in data_dir_backups(&path).into_iter().skip(count.max(1)) {
+                // one that can't go now goes with the next save
+                fs::remove_file(old.path).ok();
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The backups of `path` in the data directory, the latest first.
+fn data_dir_backups(path: &Path) -> Vec<Backup> {
+    let Some(entries) = backups_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}-", path_hash(path));
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name();
+            let millis: u64 = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some(Backup {
+                original: path.to_path_buf(),
+                path: entry.path(),
+                created: UNIX_EPOCH + Duration::from_millis(millis),
+                len: entry.metadata().ok()?.len(),
+            })
+        })
+        .collect();
+    backups.sort_by(|a, b| b.created.cmp(&a.created));
+    backups
+}
+
+/// The backups of `path` wherever they are, the latest first.
+pub fn backups_for(path: &Path) -> Vec<Backup> {
+    let mut backups = path
+        .canonicalize()
+        .map(|canonical| data_dir_backups(&canonical))
+        .unwrap_or_default();
+    let bak_path = bak_path(path);
+    if let Ok(metadata) = fs::metadata(&bak_path) {
+        backups.push(Backup {
+            original: path.to_path_buf(),
+            path: bak_path,
+            created: metadata.modified().unwrap_or(UNIX_EPOCH),
+            len: metadata.len(),
+        });
+        backups.sort_by(|a, b| b.created.cmp(&a.created));
+    }
+    backups
+}
+
+/// `len` bytes in B, KB or MB.
+fn format_size(len: u64) -> String {
+    match len {
+        0..=1023 => format!("{len} B"),
+        1024..=1_048_575 => format!("{:.1} KB", len as f64 / 1024.),
+        _ => format!("{:.1} MB", len as f64 / 1_048_576.),
+    }
+}
+
+pub enum BackupPickerEvent {
+    /// A backup was chosen, the owner opens it and drops the picker.
+    Picked(Backup),
+    /// Escape was pressed or the mouse went down outside. The owner drops it.
+    Dismissed,
+}
+
+/// The backups of a file with when they were made and their size, typing
+/// narrows them down by date. Enter picks one.
+pub struct BackupPicker {
+    input: TextInput,
+    list: View<List>,
+    backups: Vec<Backup>,
+    /// `Backup::created_label` for each backup.
+    labels: Vec<String>,
+    /// The indices of the backups matching the input.
+    matches: Vec<usize>,
+}
+
+impl EventEmitter<BackupPickerEvent> for BackupPicker {}
+
+impl BackupPicker {
+    pub fn new(backups: Vec<Backup>, cx: &mut ViewContext<Self>) -> Self {
+        let labels: Vec<String> = backups.iter().map(Backup::created_label).collect();
+        let matches: Vec<usize> = (0..backups.len()).collect();
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        let picker = cx.view().downgrade();
+        let list = cx.new_view(|cx| {
+            let mut list = List::new(
+                matches.len(),
+                move |index, _highlighted, cx| {
+                    picker.upgrade().map_or_else(
+                        || div().into_any_element(),
+                        |picker| picker.read(cx).render_backup(index, cx),
+                    )
+                },
+                cx,
+            );
+            list.follow_input(&input.model, cx);
+            list
+        });
+        cx.subscribe(&input.model, |this, _model, event, cx| match event {
+            TextEvent::Edited { .. } => this.filter(cx),
+            TextEvent::Cancel => cx.emit(BackupPickerEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        cx.subscribe(&list, |this, _list, event, cx| {
+            if let ListEvent::Activate(index) = event {
+                this.pick(*index, cx);
+            }
+        })
+        .detach();
+        input.focus(cx);
+        Self {
+            input,
+            list,
+            backups,
+            labels,
+            matches,
+        }
+    }
+
+    /// Whether the focus is still in the picker's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+
+    fn filter(&mut self, cx: &mut ViewContext<Self>) {
+        let query = self.input.model.read(cx).text();
+        self.matches = (0..self.backups.len())
+            .filter(|i| self.labels[*i].contains(query.trim()))
+            .collect();
+        let count = self.matches.len();
+        self.list
+            .update(cx, |list, cx| list.set_item_count(count, cx));
+        cx.notify();
+    }
+
+    fn pick(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some(backup) = self.matches.get(index) else {
+            return;
+        };
+        cx.emit(BackupPickerEvent::Picked(self.backups[*backup].clone()));
+    }
+
+    /// When a backup was made, with its size and where it is when that's
+    /// next to the file.
+    fn render_backup(&self, index: usize, cx: &WindowContext) -> AnyElement {
+        let Some(backup) = self.matches.get(index) else {
+            return div().into_any_element();
+        };
+        let label = self.labels[*backup].clone();
+        let backup = &self.backups[*backup];
+        let muted_color = cx.global::<Theme>().muted_color;
+        let next_to_file = backup.path == bak_path(&backup.original);
+        let detail = if next_to_file {
+            format!("{}, next to the file", format_size(backup.len))
+        } else {
+            format_size(backup.len)
+        };
+
+        div()
+            .h(ROW_HEIGHT)
+            .px_3()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_4()
+            .child(div().overflow_hidden().whitespace_nowrap().child(label))
+            .child(div().flex_none().text_color(muted_color).child(detail))
+            .into_any_element()
+    }
+}
+
+impl Render for BackupPicker {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let border_color = theme.border_color;
+        let muted_color = theme.muted_color;
+        let rows = self.matches.len().min(MAX_VISIBLE_ROWS);
+
+        // covers the window, a press outside the picker closes it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(BackupPickerEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w(px(480.))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(self.input.clone())
+                                .when(self.matches.is_empty(), |this| {
+                                    this.child(
+                                        div()
+                                            .h(ROW_HEIGHT)
+                                            .px_3()
+                                            .flex()
+                                            .items_center()
+                                            .text_color(muted_color)
+                                            .child("No matching backups"),
+                                    )
+                                })
+                                .child(div().h(ROW_HEIGHT * rows as f32).child(self.list.clone())),
+                        ),
+                    ),
+            )
+    }
+}
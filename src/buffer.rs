@@ -0,0 +1,343 @@
+use std::{fmt, ops::Range};
+
+use gpui::SharedString;
+
+/// How many lines a chunk gets when it's built. An edit rebuilds only the
+/// chunks it touches, so it costs about this many lines plus one step per
+/// chunk after it.
+const CHUNK_LINES: usize = 256;
+
+/// The text of a `TextModel`, stored line by line so an edit only copies the
+/// lines it touches instead of the whole document. The lines are grouped in
+/// chunks with offsets relative to their chunk, so an edit doesn't have to
+/// move the start of every line after it either.
+pub struct Buffer {
+    /// Never empty, and neither is any chunk.
+    chunks: Vec<Chunk>,
+    /// The row each chunk's first line is.
+    chunk_rows: Vec<usize>,
+    /// The offset each chunk starts at.
+    chunk_starts: Vec<usize>,
+}
+
+struct Chunk {
+    /// The lines without their trailing newline.
+    lines: Vec<SharedString>,
+    /// The offset each line starts at, from the start of the chunk.
+    line_starts: Vec<usize>,
+}
+
+impl Chunk {
+    fn new(lines: Vec<SharedString>) -> Self {
+        let mut line_starts = Vec::with_capacity(lines.len());
+        let mut offset = 0;
+        for line in &lines {
+            line_starts.push(offset);
+            offset += line.len() + 1;
+        }
+        Self { lines, line_starts }
+    }
+
+    /// The length of the lines, each with a newline after it.
+    fn len(&self) -> usize {
+        self.line_starts[self.lines.len() - 1] + self.lines[self.lines.len() - 1].len() + 1
+    }
+}
+
+impl Buffer {
+    pub fn new(text: &str) -> Self {
+        let lines = text
+            .split('\n')
+            .map(|line| SharedString::from(line.to_string()));
+        let mut buffer = Self {
+            chunks: chunked(lines),
+            chunk_rows: Vec::new(),
+            chunk_starts: Vec::new(),
+        };
+        buffer.update_chunk_starts(0);
+        buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.line_end(self.line_count() - 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.chunk_rows[self.chunks.len() - 1] + self.chunks[self.chunks.len() - 1].lines.len()
+    }
+
+    pub fn line(&self, row: usize) -> &SharedString {
+        let (chunk, row) = self.chunk_for_row(row);
+        &self.chunks[chunk].lines[row]
+    }
+
+    pub fn line_start(&self, row: usize) -> usize {
+        let (chunk, row) = self.chunk_for_row(row);
+        self.chunk_starts[chunk] + self.chunks[chunk].line_starts[row]
+    }
+
+    /// The offset of the newline ending `row`, or the end of the text.
+    pub fn line_end(&self, row: usize) -> usize {
+        let (chunk, row) = self.chunk_for_row(row);
+        let chunk_start = self.chunk_starts[chunk];
+        let chunk = &self.chunks[chunk];
+        chunk_start + chunk.line_starts[row] + chunk.lines[row].len()
+    }
+
+    /// The row containing `offset`, a newline belongs to the row it ends.
+    pub fn row_for_offset(&self, offset: usize) -> usize {
+        let chunk = self
+            .chunk_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+        let offset = offset - self.chunk_starts[chunk];
+        let row = self.chunks[chunk]
+            .line_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+        self.chunk_rows[chunk] + row
+    }
+
+    /// The chunk `row` is in and its row in there.
+    fn chunk_for_row(&self, row: usize) -> (usize, usize) {
+        let chunk = self
+            .chunk_rows
+            .partition_point(|&first_row| first_row <= row)
+            .saturating_sub(1);
+        (chunk, row - self.chunk_rows[chunk])
+    }
+
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        if offset > self.len() {
+            return false;
+        }
+        let (row, column) = self.point_for_offset(offset);
+        self.line(row).is_char_boundary(column)
+    }
+
+    /// The row of `offset` and its column in bytes.
+    pub fn point_for_offset(&self, offset: usize) -> (usize, usize) {
+        let row = self.row_for_offset(offset);
+        (row, offset - self.line_start(row))
+    }
+
+    pub fn text_for_range(&self, range: Range<usize>) -> String {
+        let mut text = String::with_capacity(range.len());
+        let start_row = self.row_for_offset(range.start);
+        let end_row = self.row_for_offset(range.end);
+        for row in start_row..=end_row {
+            let line_start = self.line_start(row);
+            let line = self.line(row);
+            if row > start_row {
+                text.push('\n');
+            }
+            let start = range.start.saturating_sub(line_start).min(line.len());
+            let end = (range.end - line_start).min(line.len());
+            text.push_str(&line[start..end]);
+        }
+        text
+    }
+
+    /// The chars from `offset` to the end of the text, with their offsets.
+    pub fn chars_at(&self, offset: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let row = self.row_for_offset(offset);
+        let line_count = self.line_count();
+        (row..line_count).flat_map(move |row| {
+            let line_start = self.line_start(row);
+            let line = self.line(row);
+            let start = offset.saturating_sub(line_start);
+            let newline = (row + 1 < line_count).then(|| (line_start + line.len(), '\n'));
+            line[start..]
+                .char_indices()
+                .map(move |(i, c)| (line_start + start + i, c))
+                .chain(newline)
+        })
+    }
+
+    /// The chars from `offset` back to the start of the text, with their offsets.
+    pub fn reversed_chars_at(&self, offset: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let row = self.row_for_offset(offset);
+        (0..=row).rev().flat_map(move |row| {
+            let line_start = self.line_start(row);
+            let line = self.line(row);
+            let end = (offset - line_start).min(line.len());
+            let newline = (row > 0).then(|| (line_start - 1, '\n'));
+            line[..end]
+                .char_indices()
+                .rev()
+                .map(move |(i, c)| (line_start + i, c))
+                .chain(newline)
+        })
+    }
+
+    /// Replaces `range`, which has to lie on char boundaries, with `new_text`.
+    /// Only the chunks the range touches are rebuilt, the ones after it only
+    /// move.
+    pub fn replace(&mut self, range: Range<usize>, new_text: &str) {
+        let (start_row, start_column) = self.point_for_offset(range.start);
+        let (end_row, end_column) = self.point_for_offset(range.end);
+        let (first_chunk, start_row) = self.chunk_for_row(start_row);
+        let (mut last_chunk, end_row) = self.chunk_for_row(end_row);
+
+        let prefix = &self.chunks[first_chunk].lines[start_row][..start_column];
+        let suffix = &self.chunks[last_chunk].lines[end_row][end_column..];
+        let mut text = String::with_capacity(prefix.len() + new_text.len() + suffix.len());
+        text.push_str(prefix);
+        text.push_str(new_text);
+        text.push_str(suffix);
+
+        let mut lines = std::mem::take(&mut self.chunks[first_chunk].lines);
+        let after = if first_chunk == last_chunk {
+            lines.split_off(end_row + 1)
+        } else {
+            self.chunks[last_chunk].lines.split_off(end_row + 1)
+        };
+        lines.truncate(start_row);
+        lines.extend(
+            text.split('\n')
+                .map(|line| SharedString::from(line.to_string())),
+        );
+        lines.extend(after);
+        // a chunk that got small takes the next one in, so deletes don't
+        // leave lots of tiny chunks behind
+        if lines.len() < CHUNK_LINES / 2 && last_chunk + 1 < self.chunks.len() {
+            last_chunk += 1;
+            lines.append(&mut self.chunks[last_chunk].lines);
+        }
+
+        self.chunks
+            .splice(first_chunk..=last_chunk, chunked(lines.into_iter()));
+        self.update_chunk_starts(first_chunk);
+    }
+
+    /// Recomputes where the chunks from `first_chunk` on start.
+    fn update_chunk_starts(&mut self, first_chunk: usize) {
+        self.chunk_rows.truncate(first_chunk);
+        self.chunk_starts.truncate(first_chunk);
+        let (mut row, mut offset) = match first_chunk.checked_sub(1) {
+            Some(previous) => (
+                self.chunk_rows[previous] + self.chunks[previous].lines.len(),
+                self.chunk_starts[previous] + self.chunks[previous].len(),
+            ),
+            None => (0, 0),
+        };
+        for chunk in &self.chunks[first_chunk..] {
+            self.chunk_rows.push(row);
+            self.chunk_starts.push(offset);
+            row += chunk.lines.len();
+            offset += chunk.len();
+        }
+    }
+}
+
+/// `lines` in chunks of `CHUNK_LINES`, the last one may be shorter.
+fn chunked(lines: impl Iterator<Item = SharedString>) -> Vec<Chunk> {
+    let mut lines = lines.peekable();
+    let mut chunks = Vec::new();
+    while lines.peek().is_some() {
+        chunks.push(Chunk::new(lines.by_ref().take(CHUNK_LINES).collect()));
+    }
+    chunks
+}
+
+impl fmt::Display for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self.chunks.iter().flat_map(|chunk| &chunk.lines);
+        for (row, line) in lines.enumerate() {
+            if row > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks `buffer` against `text` line by line and offset by offset.
+    fn assert_matches(buffer: &Buffer, text: &str) {
+        assert_eq!(buffer.to_string(), text);
+        assert_eq!(buffer.len(), text.len());
+        assert_eq!(buffer.line_count(), text.split('\n').count());
+        let mut line_start = 0;
+        for (row, line) in text.split('\n').enumerate() {
+            assert_eq!(&buffer.line(row)[..], line);
+            assert_eq!(buffer.line_start(row), line_start);
+            assert_eq!(buffer.line_end(row), line_start + line.len());
+            assert_eq!(buffer.row_for_offset(line_start), row);
+            assert_eq!(buffer.row_for_offset(line_start + line.len()), row);
+            line_start += line.len() + 1;
+        }
+    }
+
+    #[test]
+    fn edits_within_and_across_chunks() {
+        let mut text: String = (0..CHUNK_LINES * 5)
+            .map(|i| format!("line {i}\n"))
+            .collect();
+        let mut buffer = Buffer::new(&text);
+        assert_matches(&buffer, &text);
+
+        // a small generator, so the edits are the same every run
+        let mut seed = 7_u64;
+        let mut next = |max: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % max.max(1)
+        };
+        for step in 0..300 {
+            let mut start = next(text.len() + 1);
+            let mut end = (start + next(if step % 10 == 0 { 5000 } else { 20 })).min(text.len());
+            while !text.is_char_boundary(start) {
+                start -= 1;
+            }
+            while !text.is_char_boundary(end) {
+                end += 1;
+            }
+            let new_text = match step % 4 {
+                0 => String::new(),
+                1 => "x".to_string(),
+                2 => "ä\nb\n".repeat(next(CHUNK_LINES)),
+                _ => "\n".to_string(),
+            };
+            buffer.replace(start..end, &new_text);
+            text.replace_range(start..end, &new_text);
+            assert_matches(&buffer, &text);
+        }
+    }
+
+    #[test]
+    fn deleting_everything_leaves_one_line() {
+        let text = "a\n".repeat(CHUNK_LINES * 3);
+        let mut buffer = Buffer::new(&text);
+        buffer.replace(0..text.len(), "");
+        assert_matches(&buffer, "");
+        buffer.replace(0..0, "é\n");
+        assert_matches(&buffer, "é\n");
+        assert!(!buffer.is_char_boundary(1));
+        assert_eq!(buffer.point_for_offset(3), (1, 0));
+    }
+
+    #[test]
+    fn chars_across_chunks() {
+        let text = "ab\n".repeat(CHUNK_LINES + 1);
+        let buffer = Buffer::new(&text);
+        let offset = CHUNK_LINES * 3 - 1;
+        let chars: Vec<(usize, char)> = buffer.chars_at(offset).take(3).collect();
+        assert_eq!(
+            chars,
+            [(offset, '\n'), (offset + 1, 'a'), (offset + 2, 'b')]
+        );
+        let chars: Vec<(usize, char)> = buffer.reversed_chars_at(offset + 1).take(2).collect();
+        assert_eq!(chars, [(offset, '\n'), (offset - 1, 'b')]);
+        assert_eq!(buffer.text_for_range(offset - 1..offset + 2), "b\na");
+    }
+}
@@ -0,0 +1,227 @@
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    config::EditorSettings,
+    theme::Theme,
+    ui::{Background, List, ListEvent, TextEvent, TextInput},
+};
+
+/// The height of an entry in the picker.
+const ROW_HEIGHT: Pixels = px(28.);
+/// More entries than this scroll.
+const MAX_VISIBLE_ROWS: usize = 10;
+/// Entries longer than this many chars are cut short in the picker, they
+/// still paste in full.
+const MAX_PREVIEW_LEN: usize = 80;
+
+/// The texts copied or cut in the editor, the latest first, a global so every
+/// input adds to the same history.
+#[derive(Default)]
+pub struct ClipboardHistory {
+    entries: Vec<String>,
+}
+
+impl ClipboardHistory {
+    pub fn init(cx: &mut AppContext) {
+        cx.set_global(ClipboardHistory::default())
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Puts `text` first, a text copied before moves up instead of showing
+    /// twice. The oldest entries go once there are `clipboard_history_size`.
+    fn push(&mut self, text: String, size: usize) {
+        self.entries.retain(|entry| *entry != text);
+        self.entries.insert(0, text);
+        self.entries.truncate(size);
+    }
+}
+
+/// Writes `text` to the system clipboard and adds it to the history. Masked
+/// inputs never get here, their text can't be copied.
+pub fn copy_to_clipboard(text: String, cx: &mut AppContext) {
+    cx.write_to_clipboard(ClipboardItem::new(text.clone()));
+    let size = cx.global::<EditorSettings>().clipboard_history_size;
+    if !text.is_empty() {
+        cx.global_mut::<ClipboardHistory>().push(text, size);
+    }
+}
+
+pub enum ClipboardPickerEvent {
+    /// An entry was chosen, the owner pastes it and drops the picker.
+    Picked(String),
+    /// Escape was pressed or the mouse went down outside the picker. The
+    /// owner drops it.
+    Dismissed,
+}
+
+/// An input above the clipboard history, typing narrows it down to the
+/// entries containing the text. Enter picks one.
+pub struct ClipboardPicker {
+    input: TextInput,
+    list: View<List>,
+    /// The history when the picker opened.
+    entries: Vec<String>,
+    /// The indices of the entries matching the input.
+    matches: Vec<usize>,
+}
+
+impl EventEmitter<ClipboardPickerEvent> for ClipboardPicker {}
+
+impl ClipboardPicker {
+    pub fn new(cx: &mut ViewContext<Self>) -> Self {
+        let entries = cx.global::<ClipboardHistory>().entries().to_vec();
+        let matches: Vec<usize> = (0..entries.len()).collect();
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        let picker = cx.view().downgrade();
+        let list = cx.new_view(|cx| {
+            let mut list = List::new(
+                matches.len(),
+                move |index, _highlighted, cx| {
+                    picker.upgrade().map_or_else(
+                        || div().into_any_element(),
+                        |picker| picker.read(cx).render_entry(index, cx),
+                    )
+                },
+                cx,
+            );
+            list.follow_input(&input.model, cx);
+            list
+        });
+        cx.subscribe(&input.model, |this, _model, event, cx| match event {
+            TextEvent::Edited { .. } => this.filter(cx),
+            TextEvent::Cancel => cx.emit(ClipboardPickerEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        cx.subscribe(&list, |this, _list, event, cx| {
+            if let ListEvent::Activate(index) = event {
+                this.pick(*index, cx);
+            }
+        })
+        .detach();
+        input.focus(cx);
+        Self {
+            input,
+            list,
+            entries,
+            matches,
+        }
+    }
+
+    /// Whether the focus is still in the picker's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+
+    fn filter(&mut self, cx: &mut ViewContext<Self>) {
+        let query = self.input.model.read(cx).text().to_lowercase();
+        self.matches = (0..self.entries.len())
+            .filter(|i| self.entries[*i].to_lowercase().contains(&query))
+            .collect();
+        let count = self.matches.len();
+        self.list
+            .update(cx, |list, cx| list.set_item_count(count, cx));
+        cx.notify();
+    }
+
+    fn pick(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some(entry) = self.matches.get(index) else {
+            return;
+        };
+        cx.emit(ClipboardPickerEvent::Picked(self.entries[*entry].clone()));
+    }
+
+    /// The start of an entry on one line, with how many lines it has.
+    fn render_entry(&self, index: usize, cx: &WindowContext) -> AnyElement {
+        let Some(entry) = self.matches.get(index) else {
+            return div().into_any_element();
+        };
+        let entry = &self.entries[*entry];
+        let muted_color = cx.global::<Theme>().muted_color;
+        let mut preview: String = entry
+            .trim()
+            .chars()
+            .take(MAX_PREVIEW_LEN)
+            .map(|c| if c.is_whitespace() { ' ' } else { c })
+            .collect();
+        if entry.trim().chars().count() > MAX_PREVIEW_LEN {
+            preview.push('…');
+        }
+        let lines = entry.lines().count();
+
+        div()
+            .h(ROW_HEIGHT)
+            .px_3()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_4()
+            .child(div().overflow_hidden().whitespace_nowrap().child(preview))
+            .when(lines > 1, |this| {
+                this.child(
+                    div()
+                        .flex_none()
+                        .text_color(muted_color)
+                        .child(format!("{lines} lines")),
+                )
+            })
+            .into_any_element()
+    }
+}
+
+impl Render for ClipboardPicker {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let border_color = theme.border_color;
+        let muted_color = theme.muted_color;
+        let rows = self.matches.len().min(MAX_VISIBLE_ROWS);
+
+        // covers the window, a press outside the picker closes it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(ClipboardPickerEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w(px(480.))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(self.input.clone())
+                                .when(self.entries.is_empty(), |this| {
+                                    this.child(
+                                        div()
+                                            .h(ROW_HEIGHT)
+                                            .px_3()
+                                            .flex()
+                                            .items_center()
+                                            .text_color(muted_color)
+                                            .child("Nothing copied yet"),
+                                    )
+                                })
+                                .child(div().h(ROW_HEIGHT * rows as f32).child(self.list.clone())),
+                        ),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,79 @@
+use std::rc::Rc;
+
+use gpui::*;
+
+/// Something the command palette can run.
+#[derive(Clone)]
+pub struct Command {
+    /// Tells commands apart, registering the same id again replaces it.
+    pub id: SharedString,
+    pub label: SharedString,
+    /// The keys that do the same, shown next to the label.
+    pub keybinding: Option<SharedString>,
+    pub run: Rc<dyn Fn(&mut WindowContext)>,
+    /// Set for commands that take a repeat count, typed after the query in
+    /// the palette, e.g. "play macro 3".
+    pub run_with_count: Option<Rc<dyn Fn(usize, &mut WindowContext)>>,
+}
+
+impl Command {
+    pub fn new(
+        id: impl Into<SharedString>,
+        label: impl Into<SharedString>,
+        run: impl Fn(&mut WindowContext) + 'static,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            keybinding: None,
+            run: Rc::new(run),
+            run_with_count: None,
+        }
+    }
+
+    /// A command that runs with a count of 1 unless the palette gets another.
+    pub fn with_count(
+        id: impl Into<SharedString>,
+        label: impl Into<SharedString>,
+        run: impl Fn(usize, &mut WindowContext) + 'static,
+    ) -> Self {
+        let run = Rc::new(run);
+        Self {
+            run_with_count: Some(run.clone()),
+            ..Self::new(id, label, move |cx| run(1, cx))
+        }
+    }
+
+    pub fn keybinding(mut self, keybinding: impl Into<SharedString>) -> Self {
+        self.keybinding = Some(keybinding.into());
+        self
+    }
+}
+
+/// Every command the palette offers, a global so any feature can add its own.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    pub fn init(cx: &mut AppContext) {
+        cx.set_global(CommandRegistry::default())
+    }
+
+    /// Adds `command` after the others, or in place of the one with its id.
+    pub fn register(&mut self, command: Command) {
+        match self
+            .commands
+            .iter_mut()
+            .find(|other| other.id == command.id)
+        {
+            Some(other) => *other = command,
+            None => self.commands.push(command),
+        }
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+}
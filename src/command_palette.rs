@@ -0,0 +1,213 @@
+use gpui::*;
+
+use crate::{
+    command::{Command, CommandRegistry},
+    fuzzy::{rank, FuzzyMatch},
+    theme::Theme,
+    ui::{Background, List, ListEvent, TextEvent, TextInput},
+};
+
+/// The height of a command in the list.
+const ROW_HEIGHT: Pixels = px(28.);
+/// More commands than this scroll.
+const MAX_VISIBLE_ROWS: usize = 10;
+
+pub enum CommandPaletteEvent {
+    /// Escape was pressed, a command ran or the mouse went down outside the
+    /// palette. The owner drops it.
+    Dismissed,
+}
+
+/// An input above the registered commands, typing narrows them down to the
+/// ones it fuzzy matches. Up and down move through the commands while the
+/// input keeps the focus, enter runs one.
+pub struct CommandPalette {
+    input: TextInput,
+    list: View<List>,
+    /// The registry's commands when the palette opened.
+    commands: Vec<Command>,
+    /// The commands matching the input, the best first.
+    matches: Vec<(usize, FuzzyMatch)>,
+    /// A number at the end of the input, passed to commands that take a count.
+    count: Option<usize>,
+}
+
+impl EventEmitter<CommandPaletteEvent> for CommandPalette {}
+
+impl CommandPalette {
+    pub fn new(cx: &mut ViewContext<Self>) -> Self {
+        let commands = cx.global::<CommandRegistry>().commands().to_vec();
+        let matches = rank("", commands.iter().map(|command| command.label.as_ref()));
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        let palette = cx.view().downgrade();
+        let list = cx.new_view(|cx| {
+            let mut list = List::new(
+                matches.len(),
+                move |index, _highlighted, cx| {
+                    palette.upgrade().map_or_else(
+                        || div().into_any_element(),
+                        |palette| palette.read(cx).render_match(index, cx),
+                    )
+                },
+                cx,
+            );
+            list.follow_input(&input.model, cx);
+            list
+        });
+        cx.subscribe(&input.model, |this, _model, event, cx| match event {
+            TextEvent::Edited { .. } => this.filter(cx),
+            TextEvent::Cancel => cx.emit(CommandPaletteEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        cx.subscribe(&list, |this, _list, event, cx| {
+            if let ListEvent::Activate(index) = event {
+                this.run(*index, cx);
+            }
+        })
+        .detach();
+        input.focus(cx);
+        Self {
+            input,
+            list,
+            commands,
+            matches,
+            count: None,
+        }
+    }
+
+    /// Whether the focus is still in the palette's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+
+    fn filter(&mut self, cx: &mut ViewContext<Self>) {
+        let text = self.input.model.read(cx).text();
+        let (query, count) = split_count(&text);
+        self.count = count;
+        self.matches = rank(
+            query,
+            self.commands.iter().map(|command| command.label.as_ref()),
+        );
+        let count = self.matches.len();
+        self.list
+            .update(cx, |list, cx| list.set_item_count(count, cx));
+        cx.notify();
+    }
+
+    /// Closes the palette and runs the command of the match at `index`, with
+    /// the typed count if it takes one.
+    fn run(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some((command, _)) = self.matches.get(index) else {
+            return;
+        };
+        let command = self.commands[*command].clone();
+        cx.emit(CommandPaletteEvent::Dismissed);
+        match (command.run_with_count, self.count) {
+            (Some(run), Some(count)) => run(count, cx),
+            _ => (command.run)(cx),
+        }
+    }
+
+    /// The label of a matching command with the matched characters in bold,
+    /// and its keybinding.
+    fn render_match(&self, index: usize, cx: &WindowContext) -> AnyElement {
+        let Some((command, fuzzy_match)) = self.matches.get(index) else {
+            return div().into_any_element();
+        };
+        let command = &self.commands[*command];
+        let muted_color = cx.global::<Theme>().muted_color;
+
+        // runs of matched and unmatched characters
+        let label = command.label.as_ref();
+        let mut runs: Vec<(String, bool)> = Vec::new();
+        for (offset, c) in label.char_indices() {
+            let matched = fuzzy_match.positions.contains(&offset);
+            match runs.last_mut() {
+                Some((run, run_matched)) if *run_matched == matched => run.push(c),
+                _ => runs.push((c.to_string(), matched)),
+            }
+        }
+
+        div()
+            .h(ROW_HEIGHT)
+            .px_3()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_4()
+            .child(
+                div()
+                    .flex()
+                    .children(runs.into_iter().map(|(run, matched)| {
+                        let run = div().child(run);
+                        if matched {
+                            run.font_weight(FontWeight::BOLD)
+                        } else {
+                            run
+                        }
+                    })),
+            )
+            .children(
+                command
+                    .keybinding
+                    .clone()
+                    .map(|keybinding| div().text_color(muted_color).child(keybinding)),
+            )
+            .into_any_element()
+    }
+}
+
+/// Splits a trailing number off `text`, "play macro 3" is the query "play
+/// macro" with a count of 3.
+fn split_count(text: &str) -> (&str, Option<usize>) {
+    let Some((query, last)) = text.trim_end().rsplit_once(' ') else {
+        return (text, None);
+    };
+    match last.parse() {
+        Ok(count) if count > 0 => (query, Some(count)),
+        _ => (text, None),
+    }
+}
+
+impl Render for CommandPalette {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let border_color = theme.border_color;
+        let rows = self.matches.len().min(MAX_VISIBLE_ROWS);
+
+        // covers the window, a press outside the palette closes it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(CommandPaletteEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w(px(480.))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(self.input.clone())
+                                .child(div().h(ROW_HEIGHT * rows as f32).child(self.list.clone())),
+                        ),
+                    ),
+            )
+    }
+}
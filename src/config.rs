@@ -0,0 +1,358 @@
+use std::{collections::HashMap, fs, io, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use gpui::WindowContext;
+use serde::Deserialize;
+
+use crate::{
+    backup::BackupLocation,
+    editor::{write_atomically, DEFAULT_AUTOSAVE_DELAY},
+};
+
+/// The settings in `~/.config/ted-itor/config.toml`, all of them optional.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The name of a theme in the `themes` folder next to the config, or
+    /// `light` and `dark` for the built-in ones.
+    pub theme: Option<String>,
+    /// Reloads the theme file when it changes, like `--watch-theme`.
+    pub watch_theme: bool,
+    /// How far the editor font is zoomed in, negative for out.
+    pub zoom: i32,
+    /// Edits the text with vim's modes and keys.
+    pub vim_mode: bool,
+    /// The `[editor]` table.
+    pub editor: EditorSettings,
+}
+
+/// How the text is edited, a global set from the config.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+    /// The number of spaces in one level of indentation.
+    pub tab_size: usize,
+    /// Indents with a tab instead of spaces.
+    pub hard_tabs: bool,
+    /// Typing a bracket or quote adds its closing partner.
+    pub auto_pairs: bool,
+    /// Wraps long lines at the edge of the window. Unset, only prose is
+    /// wrapped, see `soft_wrap_for_extension`.
+    pub soft_wrap: Option<bool>,
+    /// Draws dots for spaces, arrows for tabs and a pilcrow at the end of
+    /// every line.
+    pub render_whitespace: bool,
+    /// Only the whitespace inside the selections is drawn, see `render_whitespace`.
+    pub whitespace_in_selection: bool,
+    /// Saving removes the whitespace at the end of every line first.
+    pub trim_trailing_whitespace_on_save: bool,
+    /// Saving makes the text end in exactly one newline.
+    pub ensure_final_newline_on_save: bool,
+    /// Draws a line at every level of indentation.
+    pub indent_guides: bool,
+    /// Blank lines get the guides of the lines around them, instead of none.
+    pub indent_guides_through_blank_lines: bool,
+    /// Draws a line down the editor at `ruler_column`.
+    pub show_ruler: bool,
+    pub ruler_column: usize,
+    /// How many copied texts the paste from history picker offers.
+    pub clipboard_history_size: usize,
+    /// Pasting turns `\r\n` and `\r` into `\n`, see `paste::normalize_pasted_text`.
+    pub paste_normalize_line_endings: bool,
+    /// Pasting turns curly quotes into straight ones.
+    pub paste_straighten_quotes: bool,
+    /// Pasting turns no-break spaces into plain ones.
+    pub paste_replace_nbsp: bool,
+    /// Pasting drops control chars other than tabs and newlines.
+    pub paste_strip_control_chars: bool,
+    /// A formatter for each file extension, e.g. `rs = "rustfmt --emit stdout"`.
+    /// Saving pipes the text through it and saves what it prints instead.
+    pub format_on_save: HashMap<String, String>,
+    /// A linter for each file extension, e.g. `py = "ruff check {file}"`, with
+    /// `{file}` replaced by the file's path. It runs after every save, what it
+    /// reports is underlined. See `lint::run_linter` for the output it reads.
+    pub lint_command: HashMap<String, String>,
+    /// Underlines the words of plain text and Markdown the dictionary doesn't
+    /// know.
+    pub spell_check: bool,
+    /// A word list to spell check with instead of the bundled English one, one
+    /// word per line.
+    pub spell_check_dictionary: Option<PathBuf>,
+    /// Offers the other words of the text while typing one, once it's
+    /// `auto_complete_min_chars` long. Ctrl-space offers them anytime.
+    pub auto_complete: bool,
+    pub auto_complete_min_chars: usize,
+    /// The file tree and quick open list files and folders whose name starts
+    /// with a dot.
+    pub show_hidden_files: bool,
+    /// The file tree and quick open leave out what `.gitignore` files ignore.
+    pub respect_gitignore: bool,
+    /// Saves files by themselves once typing pauses for `autosave_delay_ms`.
+    /// Untitled texts are never autosaved.
+    pub autosave: bool,
+    pub autosave_delay_ms: u64,
+    /// Saving copies the file as it was on disk to a backup first, a backup
+    /// that fails doesn't stop the save. Autosaves make none, they'd push the
+    /// older backups out.
+    pub backup_on_save: bool,
+    /// `data_dir` or `next_to_file`, see `BackupLocation`.
+    pub backup_location: BackupLocation,
+    /// How many backups of each file are kept in the data directory.
+    pub backup_count: usize,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            tab_size: 4,
+            hard_tabs: false,
+            auto_pairs: true,
+            soft_wrap: None,
+            render_whitespace: false,
+            whitespace_in_selection: false,
+            trim_trailing_whitespace_on_save: false,
+            ensure_final_newline_on_save: false,
+            indent_guides: true,
+            indent_guides_through_blank_lines: true,
+            show_ruler: false,
+            ruler_column: 100,
+            clipboard_history_size: 20,
+            paste_normalize_line_endings: true,
+            paste_straighten_quotes: false,
+            paste_replace_nbsp: true,
+            paste_strip_control_chars: true,
+            format_on_save: HashMap::new(),
+            lint_command: HashMap::new(),
+            spell_check: false,
+            spell_check_dictionary: None,
+            auto_complete: true,
+            auto_complete_min_chars: 3,
+            show_hidden_files: false,
+            respect_gitignore: true,
+            autosave: false,
+            autosave_delay_ms: DEFAULT_AUTOSAVE_DELAY.as_millis() as u64,
+            backup_on_save: false,
+            backup_location: BackupLocation::default(),
+            backup_count: 10,
+        }
+    }
+}
+
+impl EditorSettings {
+    /// The settings a new buffer starts out with, before anything is detected.
+    pub fn buffer_settings(&self) -> BufferSettings {
+        BufferSettings {
+            tab_size: self.tab_size,
+            hard_tabs: self.hard_tabs,
+            language: None,
+            indent_overridden: false,
+            trim_trailing_whitespace: self.trim_trailing_whitespace_on_save,
+            final_newline: self.ensure_final_newline_on_save,
+            write_bom: false,
+        }
+    }
+
+    /// How long typing pauses before an autosave, `None` while it's off.
+    pub fn autosave_delay(&self) -> Option<Duration> {
+        self.autosave
+            .then(|| Duration::from_millis(self.autosave_delay_ms))
+    }
+
+    /// Shows or hides the whitespace marks in every editor.
+    pub fn toggle_render_whitespace(cx: &mut WindowContext) {
+        let settings = cx.global_mut::<EditorSettings>();
+        settings.render_whitespace = !settings.render_whitespace;
+        // every editor reads the settings when it renders
+        cx.refresh();
+    }
+
+    pub fn toggle_indent_guides(cx: &mut WindowContext) {
+        let settings = cx.global_mut::<EditorSettings>();
+        settings.indent_guides = !settings.indent_guides;
+        cx.refresh();
+    }
+
+    pub fn toggle_ruler(cx: &mut WindowContext) {
+        let settings = cx.global_mut::<EditorSettings>();
+        settings.show_ruler = !settings.show_ruler;
+        cx.refresh();
+    }
+
+    /// The command line of the formatter for files with `extension`, see
+    /// `format_on_save`.
+    pub fn formatter_for_extension(&self, extension: &str) -> Option<&str> {
+        command_for_extension(&self.format_on_save, extension)
+    }
+
+    /// The command line of the linter for files with `extension`, see
+    /// `lint_command`.
+    pub fn linter_for_extension(&self, extension: &str) -> Option<&str> {
+        command_for_extension(&self.lint_command, extension)
+    }
+
+    /// Whether a file with `extension` starts out soft wrapped, a file
+    /// without one counts as plain text.
+    pub fn soft_wrap_for_extension(&self, extension: Option<&str>) -> bool {
+        self.soft_wrap.unwrap_or_else(|| {
+            extension.map_or(true, |extension| {
+                matches!(
+                    extension.to_ascii_lowercase().as_str(),
+                    "md" | "markdown" | "txt" | "text" | "rst" | "adoc"
+                )
+            })
+        })
+    }
+}
+
+/// The command line `commands` has for `extension`, ignoring case.
+fn command_for_extension<'a>(
+    commands: &'a HashMap<String, String>,
+    extension: &str,
+) -> Option<&'a str> {
+    commands
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(extension))
+        .map(|(_, command_line)| command_line.as_str())
+}
+
+/// How many lines `BufferSettings::detect_indentation` looks at, the start of
+/// a file tells as much as all of it.
+const MAX_DETECTED_LINES: usize = 1_000;
+
+/// How one buffer is edited: the defaults from the config, with the
+/// indentation detected from its text, the language from its extension and
+/// both overridden by `.editorconfig` files, see `EditorConfig::apply`.
+/// Soft wrap is resolved the same way, it lives on the `TextModel`.
+#[derive(Clone, PartialEq)]
+pub struct BufferSettings {
+    pub tab_size: usize,
+    pub hard_tabs: bool,
+    /// The id of the language, e.g. `rust`, `None` for plain text. See
+    /// `highlight::language_for_extension`.
+    pub language: Option<&'static str>,
+    /// Set when the indentation was picked in the status bar, detection
+    /// leaves it alone from then on.
+    pub indent_overridden: bool,
+    /// Saving removes the whitespace at the end of every line first.
+    pub trim_trailing_whitespace: bool,
+    /// Saving makes the text end in exactly one newline.
+    pub final_newline: bool,
+    /// Saving puts a byte order mark in front of the text.
+    pub write_bom: bool,
+}
+
+impl BufferSettings {
+    /// What one level of indentation is made of.
+    pub fn indent(&self) -> String {
+        if self.hard_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.tab_size.max(1))
+        }
+    }
+
+    /// Indents like most of the indented `lines` do: with tabs when more of
+    /// them start with a tab than with spaces, otherwise by the most common
+    /// step between neighboring lines. Without indented lines the settings
+    /// stay as they are, and so do ones picked by hand.
+    pub fn detect_indentation<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) {
+        if self.indent_overridden {
+            return;
+        }
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        // how often each step in indentation from one line to the next occurs
+        let mut steps = [0; 9];
+        let mut previous = 0;
+        for line in lines.into_iter().take(MAX_DETECTED_LINES) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            // a single space is more likely alignment, e.g. in a block comment
+            if spaces > 1 {
+                space_lines += 1;
+            }
+            let step = spaces.abs_diff(previous);
+            if (2..steps.len()).contains(&step) {
+                steps[step] += 1;
+            }
+            previous = spaces;
+        }
+        if tab_lines > space_lines {
+            self.hard_tabs = true;
+        } else if space_lines > tab_lines {
+            self.hard_tabs = false;
+            // the smaller step when two are as common
+            let step = (2..steps.len())
+                .filter(|step| steps[*step] > 0)
+                .max_by_key(|step| (steps[*step], std::cmp::Reverse(*step)));
+            if let Some(step) = step {
+                self.tab_size = step;
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config, a missing file is the same as an empty one. A broken
+    /// one is reported and ignored, so the editor still starts.
+    pub fn load() -> Self {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return Self::default();
+        };
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(error) => {
+                eprintln!("Couldn't read {}: {error}", path.display());
+                return Self::default();
+            }
+        };
+        toml::from_str(&source).unwrap_or_else(|error| {
+            eprintln!("Couldn't parse {}: {error}", path.display());
+            Self::default()
+        })
+    }
+
+    /// Writes `zoom` into the config file, leaving the rest of it as it is.
+    pub fn save_zoom(zoom: i32) -> anyhow::Result<()> {
+        Self::edit(|document| {
+            if zoom == 0 {
+                document.remove("zoom");
+            } else {
+                document["zoom"] = toml_edit::value(zoom as i64);
+            }
+        })
+    }
+
+    /// Writes the name of the theme to start with into the config file.
+    pub fn save_theme(name: &str) -> anyhow::Result<()> {
+        Self::edit(|document| document["theme"] = toml_edit::value(name))
+    }
+
+    /// Applies `change` to the config file, keeping its comments and layout.
+    fn edit(change: impl FnOnce(&mut toml_edit::Document)) -> anyhow::Result<()> {
+        let dir = config_dir().context("no home directory")?;
+        let path = dir.join("config.toml");
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.into()),
+        };
+        let mut document: toml_edit::Document = source.parse()?;
+        change(&mut document);
+        fs::create_dir_all(&dir)?;
+        write_atomically(&path, document.to_string().as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Where the config and themes live, `~/.config/ted-itor` on every platform.
+pub fn config_dir() -> Option<PathBuf> {
+    Some(dirs::home_dir()?.join(".config").join("ted-itor"))
+}
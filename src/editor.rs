@@ -1,18 +1,1331 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
 use gpui::*;
 
-use crate::ui::TextInput;
+use crate::{
+    backup::{back_up, Backup},
+    clipboard_history::copy_to_clipboard,
+    config::EditorSettings,
+    editorconfig::EditorConfig,
+    format::run_formatter,
+    git::{diff_hunks, head_text},
+    highlight::{language_for_extension, TokenKind},
+    html::{highlighted_pre, html_document},
+    lint::run_linter,
+    recent_files::RecentFiles,
+    recovery::{remove_draft, Draft, DRAFT_DELAY, MAX_DRAFT_DELAY, MAX_DRAFT_LEN},
+    theme::Theme,
+    toast::{Toast, ToastQueue},
+    ui::{TextEvent, TextInput},
+    vim::Vim,
+};
+
+/// How long typing pauses before an autosave, unless the config says otherwise.
+pub const DEFAULT_AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+/// How much of a file is read before it's added to the text.
+const LOAD_CHUNK_SIZE: usize = 1024 * 1024;
+/// How long "Saved" stays in the title bar after an autosave.
+const SAVED_FLASH_DURATION: Duration = Duration::from_millis(1500);
+/// How often the file is checked for changes made by other programs.
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// How long typing pauses before the text is diffed against git again.
+const DIFF_DELAY: Duration = Duration::from_millis(300);
+
+/// What an `Editor` knows about the file behind its text.
+pub struct EditorState {
+    pub path: Option<PathBuf>,
+    /// The last thing that went wrong with the file, shown in the title bar.
+    pub error: Option<SharedString>,
+    /// The `TextModel::version` that was last read from or written to `path`.
+    pub saved_version: usize,
+    /// Whether the text changed since it was last read or saved.
+    pub dirty: bool,
+    /// How newlines are written, the text itself only ever has '\n'.
+    pub line_ending: LineEnding,
+    /// The line ending `path` was last read or written with.
+    pub saved_line_ending: LineEnding,
+    /// How long after the last edit the text is saved by itself.
+    pub autosave: Option<Duration>,
+    /// Dropped on every edit, so a save never happens mid-word.
+    autosave_task: Option<Task<()>>,
+    /// Set for a moment after an autosave, the title bar shows "Saved" meanwhile.
+    pub saved_flash: Option<Task<()>>,
+    /// The file being read into the editor.
+    pub loading: Option<Loading>,
+    /// When `path` was last modified as far as the editor knows, i.e. when it
+    /// was read or written by the editor itself.
+    pub saved_mtime: Option<SystemTime>,
+    /// Set when another program changed `path` while the text was dirty, until
+    /// the user reloads or keeps their edits.
+    pub external_change: Option<SystemTime>,
+    /// The number of writes still running, the file changes under them.
+    saves_in_flight: usize,
+    /// Something the user should know about the text, shown in a banner until
+    /// it's dismissed.
+    pub warning: Option<SharedString>,
+    /// Polls `path` for changes made by other programs, see `Editor::stop_watching`.
+    watch_task: Option<Task<()>>,
+    /// `path`'s text in the last git commit, what the diff markers compare
+    /// the text to. `None` when it isn't committed.
+    git_base: Option<Arc<str>>,
+    /// Reads `git_base`, see `Editor::load_git_base`.
+    git_base_task: Option<Task<()>>,
+    /// Dropped on every edit, so the text is diffed once typing pauses.
+    diff_task: Option<Task<()>>,
+    /// Runs the linter, see `Editor::lint`.
+    lint_task: Option<Task<()>>,
+    /// Whether the linter failed last time, so a broken one shows its error
+    /// once instead of on every save.
+    lint_failed: bool,
+    /// Dropped on every edit, so a draft is written once typing pauses.
+    draft_task: Option<Task<()>>,
+    /// The first edit since the last draft, typing that doesn't pause still
+    /// gets one `MAX_DRAFT_DELAY` after it.
+    draft_pending_since: Option<Instant>,
+    /// Where the text's draft was last written, see `recovery::Draft`.
+    draft_file: Option<PathBuf>,
+    /// Whether the text was too long for a draft, so that's said only once.
+    draft_skipped: bool,
+    /// The backup the read-only text is, see `Editor::open_backup`.
+    pub backup: Option<Backup>,
+}
+
+/// A file streaming into an editor.
+pub struct Loading {
+    pub read: u64,
+    pub total: u64,
+    /// When the file was last modified, read along with its size.
+    modified: Option<SystemTime>,
+    /// Where the cursor goes once the file is loaded.
+    cursor: LoadedCursor,
+    /// Whether the first chunk replaced the previous text yet.
+    started: bool,
+    /// A recovered draft that replaces the text once it's loaded.
+    draft: Option<String>,
+    _task: Task<()>,
+}
+
+/// Where `Editor::open_path_at` puts the cursor.
+pub enum LoadedCursor {
+    Start,
+    /// Byte offsets, e.g. the selection from before a reload.
+    Selection(Range<usize>),
+    /// A zero-based row and char column, clamped to the text.
+    Point(usize, usize),
+}
+
+impl Loading {
+    /// How much of the file was read, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            0.
+        } else {
+            (self.read as f32 / self.total as f32).min(1.)
+        }
+    }
+}
+
+impl EditorState {
+    fn update_dirty(&mut self, version: usize, cx: &mut ModelContext<Self>) {
+        let dirty = version != self.saved_version || self.line_ending != self.saved_line_ending;
+        if self.dirty != dirty {
+            self.dirty = dirty;
+            cx.notify();
+        }
+    }
+}
+
+/// The newline a file uses on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// The ending used by most lines, a tie goes to LF.
+    pub fn dominant(crlf: usize, lf: usize) -> Self {
+        if crlf > lf {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Lf => Self::Crlf,
+            Self::Crlf => Self::Lf,
+        }
+    }
+}
 
 #[derive(IntoElement, Clone)]
 pub struct Editor {
     pub text_input: TextInput,
+    pub state: Model<EditorState>,
 }
 
 impl Editor {
+    pub fn new(cx: &mut WindowContext, initial_text: String) -> Self {
+        let editor = Self {
+            text_input: TextInput::new(cx, initial_text),
+            state: cx.new_model(|_cx| EditorState {
+                path: None,
+                error: None,
+                saved_version: 0,
+                dirty: false,
+                line_ending: LineEnding::default(),
+                saved_line_ending: LineEnding::default(),
+                autosave: None,
+                autosave_task: None,
+                saved_flash: None,
+                loading: None,
+                saved_mtime: None,
+                external_change: None,
+                saves_in_flight: 0,
+                warning: None,
+                watch_task: None,
+                git_base: None,
+                git_base_task: None,
+                diff_task: None,
+                lint_task: None,
+                lint_failed: false,
+                draft_task: None,
+                draft_pending_since: None,
+                draft_file: None,
+                draft_skipped: false,
+                backup: None,
+            }),
+        };
+        cx.subscribe(&editor.text_input.model, {
+            let editor = editor.clone();
+            move |model, event, cx| {
+                if let TextEvent::Edited { .. } = event {
+                    // compared to the version so opening a file doesn't count
+                    let version = model.read(cx).version;
+                    editor
+                        .state
+                        .update(cx, |state, cx| state.update_dirty(version, cx));
+                    editor.schedule_autosave(cx);
+                    editor.schedule_draft(cx);
+                    editor.schedule_diff(DIFF_DELAY, cx);
+                }
+            }
+        })
+        .detach();
+        // an untitled text is plain text
+        let soft_wrap = cx.global::<EditorSettings>().soft_wrap_for_extension(None);
+        editor
+            .text_input
+            .model
+            .update(cx, |editor, cx| editor.set_soft_wrap(soft_wrap, cx));
+        let watch_task = editor.watch(cx);
+        editor
+            .state
+            .update(cx, |state, _cx| state.watch_task = Some(watch_task));
+        editor
+    }
+
+    /// Shows 1-based line numbers in a gutter left of the text.
+    pub fn show_line_numbers(self, show: bool, cx: &mut WindowContext) -> Self {
+        self.text_input.model.update(cx, |editor, cx| {
+            editor.show_line_numbers = show;
+            cx.notify();
+        });
+        self
+    }
+
+    /// Turns vim mode on or off, see `Vim`. It starts in normal mode.
+    pub fn set_vim_mode(&self, enabled: bool, cx: &mut WindowContext) {
+        self.text_input.model.update(cx, |editor, cx| {
+            if editor.vim.is_some() != enabled {
+                editor.vim = enabled.then(Vim::default);
+                cx.notify();
+            }
+        });
+    }
+
     pub fn clear(self, cx: &mut WindowContext) {
         self.text_input.model.update(cx, |editor, cx| {
             editor.reset(cx);
         })
     }
+
+    /// Saves the text `delay` after the last edit, `None` turns autosave off.
+    /// Editors without a path are never autosaved.
+    pub fn autosave(self, delay: Option<Duration>, cx: &mut WindowContext) -> Self {
+        self.state.update(cx, |state, _cx| {
+            state.autosave = delay;
+            state.autosave_task = None;
+        });
+        self
+    }
+
+    fn schedule_autosave(&self, cx: &mut WindowContext) {
+        let state = self.state.read(cx);
+        let delay = state
+            .autosave
+            .filter(|_| state.dirty && state.path.is_some());
+        let task = delay.map(|delay| {
+            let editor = self.clone();
+            cx.spawn(|mut cx| async move {
+                cx.background_executor().timer(delay).await;
+                cx.update(|cx| {
+                    let state = editor.state.read(cx);
+                    if let Some(path) = state.path.clone().filter(|_| state.dirty) {
+                        editor.save_to(path, true, cx);
+                    }
+                })
+                .ok();
+            })
+        });
+        self.state
+            .update(cx, |state, _cx| state.autosave_task = task);
+    }
+
+    /// Writes a draft of the dirty text once typing pauses for `DRAFT_DELAY`,
+    /// or `MAX_DRAFT_DELAY` after the first edit when it doesn't pause.
+    fn schedule_draft(&self, cx: &mut WindowContext) {
+        let state = self.state.read(cx);
+        // what a load appends isn't an edit, and a clean text only needs its
+        // old draft deleted
+        if state.loading.is_some() || (!state.dirty && state.draft_file.is_none()) {
+            self.state.update(cx, |state, _cx| state.draft_task = None);
+            return;
+        }
+        let now = Instant::now();
+        let since = state.draft_pending_since.unwrap_or(now);
+        let delay = DRAFT_DELAY.min(MAX_DRAFT_DELAY.saturating_sub(now - since));
+        let editor = self.clone();
+        let task = cx.spawn(|mut cx| async move {
+            cx.background_executor().timer(delay).await;
+            cx.update(|cx| editor.write_draft(cx)).ok();
+        });
+        self.state.update(cx, |state, _cx| {
+            state.draft_pending_since = Some(since);
+            state.draft_task = Some(task);
+        });
+    }
+
+    /// Writes the text to its draft in the background, or deletes the draft
+    /// when the text is clean. A text longer than `MAX_DRAFT_LEN` gets none.
+    fn write_draft(&self, cx: &mut WindowContext) {
+        self.state.update(cx, |state, _cx| {
+            state.draft_task = None;
+            state.draft_pending_since = None;
+        });
+        let state = self.state.read(cx);
+        let (dirty, skipped) = (state.dirty, state.draft_skipped);
+        let (path, old_file) = (state.path.clone(), state.draft_file.clone());
+        if !dirty {
+            self.discard_draft(cx);
+            return;
+        }
+        if self.text_input.model.read(cx).len() > MAX_DRAFT_LEN {
+            if !skipped {
+                let message = format!(
+                    "{} is too long to keep a draft of, a crash loses what isn't saved",
+                    self.title(cx)
+                );
+                cx.global_mut::<ToastQueue>().push(Toast::info(message));
+            }
+            self.discard_draft(cx);
+            self.state
+                .update(cx, |state, _cx| state.draft_skipped = true);
+            return;
+        }
+        let Some(file) = Draft::file_for(path.as_deref(), self.state.entity_id().as_u64()) else {
+            return;
+        };
+        // e.g. an untitled text that got a path
+        if let Some(old_file) = old_file.filter(|old_file| *old_file != file) {
+            remove_draft(&old_file);
+        }
+        let draft = Draft {
+            path,
+            text: self.text_input.model.read(cx).text(),
+            file: file.clone(),
+        };
+        self.state
+            .update(cx, |state, _cx| state.draft_file = Some(file));
+        cx.background_executor()
+            .spawn(async move {
+                if let Err(error) = draft.write() {
+                    eprintln!("Couldn't write the draft of {}: {error}", draft.label());
+                }
+            })
+            .detach();
+    }
+
+    /// Deletes the text's draft, e.g. once it's saved or the editor closes.
+    pub fn discard_draft(&self, cx: &mut WindowContext) {
+        let file = self.state.update(cx, |state, _cx| {
+            state.draft_task = None;
+            state.draft_pending_since = None;
+            state.draft_file.take()
+        });
+        if let Some(file) = file {
+            remove_draft(&file);
+        }
+    }
+
+    /// Shows a recovered `draft`, as an edit of its file so the text is dirty
+    /// and undo goes back to what's on disk. The file is opened first unless
+    /// it's open already.
+    pub fn open_draft(&self, draft: Draft, cx: &mut WindowContext) {
+        let state = self.state.read(cx);
+        let open = draft.path.is_some() && state.path == draft.path;
+        let loading = state.loading.is_some();
+        self.state
+            .update(cx, |state, _cx| state.draft_file = Some(draft.file));
+        match draft.path {
+            Some(path) if !open => {
+                self.open_path(&path, cx);
+                self.state.update(cx, |state, _cx| {
+                    if let Some(loading) = &mut state.loading {
+                        loading.draft = Some(draft.text);
+                    }
+                });
+            }
+            Some(_) if loading => self.state.update(cx, |state, _cx| {
+                if let Some(loading) = &mut state.loading {
+                    loading.draft = Some(draft.text);
+                }
+            }),
+            _ => self
+                .text_input
+                .model
+                .update(cx, |editor, cx| editor.replace_text(&draft.text, cx)),
+        }
+    }
+
+    /// Changes how newlines are written on the next save.
+    pub fn set_line_ending(&self, line_ending: LineEnding, cx: &mut WindowContext) {
+        let version = self.text_input.model.read(cx).version;
+        self.state.update(cx, |state, cx| {
+            state.line_ending = line_ending;
+            state.update_dirty(version, cx);
+            cx.notify();
+        });
+        self.schedule_autosave(cx);
+    }
+
+    pub fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.state.read(cx).dirty
+    }
+
+    /// The file name, or "Untitled" before the editor has a path.
+    pub fn title(&self, cx: &AppContext) -> SharedString {
+        let state = self.state.read(cx);
+        if let Some(backup) = &state.backup {
+            let name = backup.original.file_name().unwrap_or_default();
+            return format!("{} (backup)", name.to_string_lossy()).into();
+        }
+        state
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or("Untitled".into(), |name| {
+                name.to_string_lossy().to_string().into()
+            })
+    }
+
+    /// The whole path with the home directory as `~`, for the title bar.
+    pub fn path_title(&self, cx: &AppContext) -> SharedString {
+        let Some(path) = self.state.read(cx).path.as_ref() else {
+            return self.title(cx);
+        };
+        match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok()) {
+            Some(relative) => format!("~/{}", relative.display()).into(),
+            None => path.display().to_string().into(),
+        }
+    }
+
+    /// Checks the file for changes every `WATCH_INTERVAL`. The task holds on
+    /// to the editor, so it runs until `stop_watching` drops it.
+    fn watch(&self, cx: &mut WindowContext) -> Task<()> {
+        let editor = self.clone();
+        cx.spawn(|mut cx| async move {
+            loop {
+                cx.background_executor().timer(WATCH_INTERVAL).await;
+                let Ok(path) = cx.update(|cx| editor.state.read(cx).path.clone()) else {
+                    break;
+                };
+                let Some(path) = path else {
+                    continue;
+                };
+                let modified = cx
+                    .background_executor()
+                    .spawn(async move { modified_time(&path) })
+                    .await;
+                if cx.update(|cx| editor.check_modified(modified, cx)).is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Stops checking the file for changes, for an editor that is closed.
+    pub fn stop_watching(&self, cx: &mut WindowContext) {
+        self.state.update(cx, |state, _cx| state.watch_task = None);
+    }
+
+    /// Reacts to the file's modification time, a clean text is reloaded and a
+    /// dirty one asks first. Our own reads and writes are never a change.
+    fn check_modified(&self, modified: Option<SystemTime>, cx: &mut WindowContext) {
+        let state = self.state.read(cx);
+        let busy = state.loading.is_some() || state.saves_in_flight > 0;
+        // a deleted file has no time, the text stays until it's saved again
+        let changed =
+            modified.is_some() && state.saved_mtime.is_some() && modified != state.saved_mtime;
+        if busy || !changed || state.external_change.is_some() {
+            return;
+        }
+        if state.dirty {
+            self.state.update(cx, |state, cx| {
+                state.external_change = modified;
+                cx.notify();
+            });
+        } else {
+            self.reload(cx);
+        }
+    }
+
+    /// Reads the file again, throwing away unsaved edits. The selection stays
+    /// where it was as far as the new text allows.
+    pub fn reload(&self, cx: &mut WindowContext) {
+        let Some(path) = self.state.read(cx).path.clone() else {
+            return;
+        };
+        let selection = self.text_input.model.read(cx).selection.clone();
+        self.state
+            .update(cx, |state, _cx| state.external_change = None);
+        self.load_path(path, LoadedCursor::Selection(selection), cx);
+    }
+
+    /// Ignores the change another program made to the file, the next save
+    /// overwrites it.
+    pub fn keep_local_changes(&self, cx: &mut WindowContext) {
+        self.state.update(cx, |state, cx| {
+            if let Some(modified) = state.external_change.take() {
+                state.saved_mtime = Some(modified);
+            }
+            cx.notify();
+        });
+    }
+
+    /// Reads the file at `path` in the background and replaces the text with
+    /// it, chunk by chunk so large files don't block the UI. A path that doesn't
+    /// exist yet opens as an empty text. Opening another file cancels the load.
+    pub fn open_path(&self, path: &Path, cx: &mut WindowContext) {
+        self.open_path_at(path, LoadedCursor::Start, cx);
+    }
+
+    /// Like `open_path`, then puts the cursor at `cursor` and scrolls to it. A
+    /// position past the end of a line or the text ends up at that end.
+    pub fn open_path_at(&self, path: &Path, cursor: LoadedCursor, cx: &mut WindowContext) {
+        self.state
+            .update(cx, |state, _cx| state.external_change = None);
+        self.load_path(path.to_path_buf(), cursor, cx);
+    }
+
+    fn load_path(&self, path: PathBuf, cursor: LoadedCursor, cx: &mut WindowContext) {
+        self.stop_loading(cx);
+        let editor = self.clone();
+        let task = cx.spawn(|mut cx| async move {
+            let result = editor.load(&path, &mut cx).await;
+            cx.update(|cx| editor.finish_open(path, result, cx)).ok();
+        });
+        self.state.update(cx, |state, cx| {
+            state.loading = Some(Loading {
+                read: 0,
+                total: 0,
+                modified: None,
+                cursor,
+                started: false,
+                draft: None,
+                _task: task,
+            });
+            cx.notify();
+        });
+    }
+
+    /// Streams the file into the text and returns the line ending most of its
+    /// lines use. The text is read-only until the load finishes.
+    async fn load(&self, path: &Path, cx: &mut AsyncWindowContext) -> io::Result<LineEnding> {
+        let background = cx.background_executor().clone();
+        let (mut file, metadata) = background
+            .spawn({
+                let path = path.to_path_buf();
+                async move {
+                    let file = File::open(path)?;
+                    let metadata = file.metadata()?;
+                    io::Result::Ok((file, metadata))
+                }
+            })
+            .await?;
+        self.update_loading(cx, |loading| {
+            loading.total = metadata.len();
+            loading.modified = metadata.modified().ok();
+        })?;
+
+        // bytes read but not appended yet, a char or "\r\n" split by the chunk end
+        let mut pending = Vec::new();
+        let (mut crlf_count, mut lf_count) = (0, 0);
+        loop {
+            let (returned, chunk) = background
+                .spawn(async move {
+                    let mut chunk = vec![0; LOAD_CHUNK_SIZE];
+                    let result = file.read(&mut chunk).map(|len| {
+                        chunk.truncate(len);
+                        chunk
+                    });
+                    (file, result)
+                })
+                .await;
+            file = returned;
+            let chunk = chunk?;
+            let done = chunk.is_empty();
+            pending.extend_from_slice(&chunk);
+
+            let mut end = match std::str::from_utf8(&pending) {
+                Ok(_) => pending.len(),
+                Err(error) if error.error_len().is_none() && !done => error.valid_up_to(),
+                Err(_) => return Err(io::ErrorKind::InvalidData.into()),
+            };
+            if !done && pending[..end].ends_with(b"\r") {
+                end -= 1;
+            }
+            let text = String::from_utf8(pending.drain(..end).collect())
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+            crlf_count += text.matches("\r\n").count();
+            lf_count += text.matches('\n').count();
+            // all offsets count '\n' as one byte, so the text never has "\r\n"
+            let text = text.replace("\r\n", "\n");
+            cx.update(|cx| self.append_loaded(path, &text, chunk.len(), cx))
+                .map_err(|_| io::Error::from(io::ErrorKind::Interrupted))?;
+            if done {
+                break;
+            }
+        }
+        Ok(LineEnding::dominant(crlf_count, lf_count - crlf_count))
+    }
+
+    fn update_loading(
+        &self,
+        cx: &mut AsyncWindowContext,
+        f: impl FnOnce(&mut Loading),
+    ) -> io::Result<()> {
+        self.state
+            .update(cx, |state, cx| {
+                if let Some(loading) = &mut state.loading {
+                    f(loading);
+                }
+                cx.notify();
+            })
+            .map_err(|_| io::ErrorKind::Interrupted.into())
+    }
+
+    /// Adds a loaded chunk to the end of the text. The first one replaces the
+    /// text, so a file that can't be read at all keeps the current one.
+    fn append_loaded(&self, path: &Path, text: &str, read: usize, cx: &mut WindowContext) {
+        let (started, reloading) =
+            self.state
+                .read(cx)
+                .loading
+                .as_ref()
+                .map_or((false, false), |loading| {
+                    (
+                        loading.started,
+                        matches!(loading.cursor, LoadedCursor::Selection(_)),
+                    )
+                });
+        let version = self.text_input.model.update(cx, |editor, cx| {
+            if started {
+                editor.insert_at(editor.len(), text, cx);
+            } else {
+                // a reload keeps wrapping and indentation the way they were picked
+                if !reloading {
+                    editor.settings = cx.global::<EditorSettings>().buffer_settings();
+                    // the markers of the previous file, until `load_git_base`
+                    editor.diff_hunks = None;
+                }
+                editor.set_language(language_for_path(path), cx);
+                editor.set_text(text.to_string(), cx);
+                if !reloading {
+                    editor.move_to(0);
+                    editor.set_soft_wrap(soft_wrap_for_path(path, cx), cx);
+                    editor.detect_indentation(cx);
+                    EditorConfig::for_path(path).apply(&mut editor.settings);
+                }
+                editor.read_only = true;
+            }
+            editor.version
+        });
+        self.state.update(cx, |state, cx| {
+            if let Some(loading) = &mut state.loading {
+                loading.started = true;
+                loading.read += read as u64;
+            }
+            // the loaded text is what's on disk, so it never counts as dirty
+            state.path = Some(path.to_path_buf());
+            state.saved_version = version;
+            cx.notify();
+        });
+    }
+
+    fn finish_open(&self, path: PathBuf, result: io::Result<LineEnding>, cx: &mut WindowContext) {
+        let line_ending = match result {
+            Ok(line_ending) => {
+                RecentFiles::add(&path, cx);
+                line_ending
+            }
+            // a new file, only the `.editorconfig` says how its lines end
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.append_loaded(&path, "", 0, cx);
+                EditorConfig::for_path(&path)
+                    .line_ending
+                    .unwrap_or_default()
+            }
+            Err(error) => {
+                let error = if error.kind() == io::ErrorKind::InvalidData {
+                    format!("{} is not valid UTF-8", path.display())
+                } else {
+                    format!("Couldn't open {}: {error}", path.display())
+                };
+                self.stop_loading(cx);
+                self.set_error(Some(error.into()), cx);
+                return;
+            }
+        };
+
+        let loading = self.state.update(cx, |state, _cx| state.loading.take());
+        let (modified, cursor, draft) = loading
+            .map_or((None, LoadedCursor::Start, None), |loading| {
+                (loading.modified, loading.cursor, loading.draft)
+            });
+        self.text_input.model.update(cx, |editor, cx| {
+            editor.read_only = false;
+            // the loaded text is where undo stops
+            editor.clear_undo_history();
+            let selection = match cursor {
+                LoadedCursor::Start => return,
+                LoadedCursor::Selection(selection) => selection,
+                LoadedCursor::Point(row, column) => {
+                    let offset = editor.offset_for_point(row, column);
+                    offset..offset
+                }
+            };
+            editor.select(selection);
+            // scrolls the cursor into view
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+        self.state.update(cx, |state, cx| {
+            state.saved_mtime = modified;
+            state.error = None;
+            state.line_ending = line_ending;
+            state.saved_line_ending = line_ending;
+            state.dirty = false;
+            state.backup = None;
+            cx.notify();
+        });
+        if let Some(draft) = draft {
+            self.text_input
+                .model
+                .update(cx, |editor, cx| editor.replace_text(&draft, cx));
+        }
+        self.load_git_base(cx);
+        self.lint(cx);
+    }
+
+    /// Shows `bytes` as a read-only text without a path, e.g. what was piped
+    /// into stdin. Invalid UTF-8 is replaced and leaves a warning, saving asks
+    /// for a path.
+    pub fn open_scratch(&self, bytes: Vec<u8>, cx: &mut WindowContext) {
+        self.stop_loading(cx);
+        let (text, warning) = match String::from_utf8(bytes) {
+            Ok(text) => (text, None),
+            Err(error) => {
+                let text = String::from_utf8_lossy(error.as_bytes()).into_owned();
+                let warning = "The input isn't valid UTF-8, invalid bytes are shown as \u{FFFD}.";
+                (text, Some(warning.into()))
+            }
+        };
+        let crlf_count = text.matches("\r\n").count();
+        let lf_count = text.matches('\n').count() - crlf_count;
+        let line_ending = LineEnding::dominant(crlf_count, lf_count);
+        let text = text.replace("\r\n", "\n");
+        let version = self.text_input.model.update(cx, |editor, cx| {
+            editor.settings = cx.global::<EditorSettings>().buffer_settings();
+            editor.set_language(None, cx);
+            editor.set_text(text, cx);
+            editor.detect_indentation(cx);
+            editor.move_to(0);
+            editor.read_only = true;
+            editor.version
+        });
+        self.state.update(cx, |state, cx| {
+            state.path = None;
+            state.saved_mtime = None;
+            state.external_change = None;
+            state.error = None;
+            state.warning = warning;
+            state.backup = None;
+            state.saved_version = version;
+            state.line_ending = line_ending;
+            state.saved_line_ending = line_ending;
+            state.dirty = false;
+            cx.notify();
+        });
+        self.load_git_base(cx);
+    }
+
+    /// Shows the `bytes` of `backup` read-only like `open_scratch`, highlighted
+    /// like its file, to compare before copying it over the file.
+    pub fn open_backup(&self, backup: Backup, bytes: Vec<u8>, cx: &mut WindowContext) {
+        self.open_scratch(bytes, cx);
+        let language = language_for_path(&backup.original);
+        self.text_input
+            .model
+            .update(cx, |editor, cx| editor.set_language(language, cx));
+        self.state.update(cx, |state, cx| {
+            state.backup = Some(backup);
+            cx.notify();
+        });
+    }
+
+    /// Aborts a load, returns whether there was one. What was loaded so far
+    /// stays as an untitled text, so saving can't truncate the file.
+    pub fn cancel_loading(&self, cx: &mut WindowContext) -> bool {
+        if self.state.read(cx).loading.is_none() {
+            return false;
+        }
+        self.stop_loading(cx);
+        true
+    }
+
+    fn stop_loading(&self, cx: &mut WindowContext) {
+        let Some(loading) = self.state.update(cx, |state, _cx| state.loading.take()) else {
+            return;
+        };
+        if !loading.started {
+            // the text wasn't touched yet
+            self.state.update(cx, |_state, cx| cx.notify());
+            return;
+        }
+        let version = self.text_input.model.update(cx, |editor, _cx| {
+            editor.read_only = false;
+            editor.version
+        });
+        self.state.update(cx, |state, cx| {
+            state.path = None;
+            state.saved_mtime = None;
+            state.saved_version = 0;
+            state.update_dirty(version, cx);
+            cx.notify();
+        });
+        self.load_git_base(cx);
+    }
+
+    /// Writes the text to the editor's path, or asks for one first.
+    pub fn save(&self, cx: &mut WindowContext) {
+        if self.state.read(cx).loading.is_some() {
+            return;
+        }
+        match self.state.read(cx).path.clone() {
+            Some(path) => self.format_and_save(path, cx),
+            None => self.save_as(cx),
+        }
+    }
+
+    /// Where a save dialog starts, next to the file or else in the current
+    /// directory.
+    fn dialog_directory(&self, cx: &AppContext) -> PathBuf {
+        self.state
+            .read(cx)
+            .path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
+    }
+
+    /// Asks for a new path, then saves the text there.
+    pub fn save_as(&self, cx: &mut WindowContext) {
+        let path = cx.prompt_for_new_path(&self.dialog_directory(cx));
+        let editor = self.clone();
+        cx.spawn(|mut cx| async move {
+            if let Ok(Some(path)) = path.await {
+                cx.update(|cx| editor.format_and_save(path, cx)).ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Asks for a path, then writes the text there as an HTML page colored
+    /// like the editor.
+    pub fn export_html(&self, cx: &mut WindowContext) {
+        let html = {
+            let editor = self.text_input.model.read(cx);
+            html_document(
+                &self.title(cx),
+                &editor.text(),
+                editor.tokens(),
+                cx.global::<Theme>(),
+            )
+        };
+        let path = cx.prompt_for_new_path(&self.dialog_directory(cx));
+        cx.spawn(|mut cx| async move {
+            let Ok(Some(path)) = path.await else {
+                return;
+            };
+            let write = {
+                let path = path.clone();
+                cx.background_executor()
+                    .spawn(async move { std::fs::write(path, html) })
+            };
+            let result = write.await;
+            cx.update(|cx| {
+                let toast = match result {
+                    Ok(()) => Toast::success(format!("Exported {}", path.display())),
+                    Err(error) => {
+                        Toast::error(format!("Couldn't export {}: {error}", path.display()))
+                    }
+                };
+                cx.global_mut::<ToastQueue>().push(toast);
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Copies the selection, or the whole text without one, as HTML colored
+    /// like the editor.
+    pub fn copy_as_html(&self, cx: &mut WindowContext) {
+        let html = {
+            let editor = self.text_input.model.read(cx);
+            let range = if editor.selection.is_empty() {
+                0..editor.len()
+            } else {
+                editor.selection.clone()
+            };
+            // the tokens in the range, moved to start with it
+            let tokens: Vec<(Range<usize>, TokenKind)> = editor
+                .tokens()
+                .iter()
+                .filter(|(token, _)| token.end > range.start && token.start < range.end)
+                .map(|(token, kind)| {
+                    let start = token.start.max(range.start) - range.start;
+                    let end = token.end.min(range.end) - range.start;
+                    (start..end, *kind)
+                })
+                .collect();
+            highlighted_pre(&editor.text_for_range(range), &tokens, cx.global::<Theme>())
+        };
+        copy_to_clipboard(html, cx);
+    }
+
+    /// Runs the formatter for the editor's path over the text, without saving.
+    pub fn format_document(&self, cx: &mut WindowContext) {
+        let Some(path) = self.state.read(cx).path.clone() else {
+            return;
+        };
+        match self.format(&path, cx) {
+            Some(task) => task.detach(),
+            None => {
+                let message = format!("No formatter is set for {}", path.display());
+                cx.global_mut::<ToastQueue>().push(Toast::info(message));
+            }
+        }
+    }
+
+    /// Formats the text for `path` first when there's a formatter for it,
+    /// autosaves never do, so the text doesn't jump around while typing.
+    fn format_and_save(&self, path: PathBuf, cx: &mut WindowContext) {
+        let Some(task) = self.format(&path, cx) else {
+            self.save_to(path, false, cx);
+            return;
+        };
+        let editor = self.clone();
+        cx.spawn(|mut cx| async move {
+            task.await;
+            cx.update(|cx| editor.save_to(path, false, cx)).ok();
+        })
+        .detach();
+    }
+
+    /// Pipes the text through the formatter `format_on_save` has for the
+    /// extension of `path` in the background, then replaces it with the
+    /// result as one undoable edit. A failing formatter leaves the text as it
+    /// is and shows why. `None` when there's no formatter for `path`.
+    fn format(&self, path: &Path, cx: &mut WindowContext) -> Option<Task<()>> {
+        let extension = path.extension()?.to_string_lossy();
+        let command_line = cx
+            .global::<EditorSettings>()
+            .formatter_for_extension(&extension)?
+            .to_string();
+        let (text, version) = {
+            let editor = self.text_input.model.read(cx);
+            (editor.text(), editor.version)
+        };
+        let dir = path.parent().map(Path::to_path_buf);
+        let format = cx
+            .background_executor()
+            .spawn(async move { run_formatter(&command_line, text, dir.as_deref()) });
+        let editor = self.clone();
+        Some(cx.spawn(|mut cx| async move {
+            let result = format.await;
+            cx.update(|cx| match result {
+                Ok(formatted) => editor.text_input.model.update(cx, |editor, cx| {
+                    // edits made meanwhile win over the formatted text
+                    if editor.version == version {
+                        editor.replace_text(&formatted, cx);
+                    }
+                }),
+                Err(error) => {
+                    cx.global_mut::<ToastQueue>().push(Toast::error(error));
+                }
+            })
+            .ok();
+        }))
+    }
+
+    /// Writes the text to `path` in the background. The text goes to a temporary
+    /// file next to it first, so a failed write never leaves half a file.
+    /// Newlines are written with the editor's line ending, a trailing newline
+    /// stays the way it was read unless the settings trim the text first.
+    fn save_to(&self, path: PathBuf, autosave: bool, cx: &mut WindowContext) {
+        let settings = &self.text_input.model.read(cx).settings;
+        let trim = settings.trim_trailing_whitespace;
+        let final_newline = settings.final_newline;
+        let write_bom = settings.write_bom;
+        // not while typing, an autosave would take the space before the next word
+        if !autosave && (trim || final_newline) {
+            // one undoable edit, so the text on disk is the text in the editor
+            self.text_input.model.update(cx, |editor, cx| {
+                editor.transact(cx, |editor, cx| {
+                    if trim {
+                        editor.trim_trailing_whitespace(cx);
+                    }
+                    if final_newline {
+                        editor.ensure_final_newline(cx);
+                    }
+                })
+            });
+        }
+        let line_ending = self.state.read(cx).line_ending;
+        let (text, version) = {
+            let editor = self.text_input.model.read(cx);
+            (editor.text(), editor.version)
+        };
+        let mut text = match line_ending {
+            LineEnding::Lf => text,
+            LineEnding::Crlf => text.replace('\n', line_ending.as_str()),
+        };
+        if write_bom && !text.starts_with('\u{feff}') {
+            text.insert(0, '\u{feff}');
+        }
+        let settings = cx.global::<EditorSettings>();
+        let backup = (settings.backup_on_save && !autosave)
+            .then_some((settings.backup_location, settings.backup_count));
+        let write = cx.background_executor().spawn({
+            let path = path.clone();
+            async move {
+                let backed_up = backup.map(|(location, count)| back_up(&path, location, count));
+                let written =
+                    write_atomically(&path, text.as_bytes()).map(|()| modified_time(&path));
+                (backed_up, written)
+            }
+        });
+        self.state
+            .update(cx, |state, _cx| state.saves_in_flight += 1);
+        let editor = self.clone();
+        cx.spawn(|mut cx| async move {
+            let (backed_up, result) = write.await;
+            cx.update(|cx| {
+                // the save went ahead anyway
+                if let Some(Err(error)) = backed_up {
+                    let error = format!("Couldn't back up {}: {error}", path.display());
+                    cx.global_mut::<ToastQueue>().push(Toast::error(error));
+                }
+                editor.finish_save(path, version, line_ending, autosave, result, cx)
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn finish_save(
+        &self,
+        path: PathBuf,
+        version: usize,
+        line_ending: LineEnding,
+        autosave: bool,
+        result: io::Result<Option<SystemTime>>,
+        cx: &mut WindowContext,
+    ) {
+        self.state
+            .update(cx, |state, _cx| state.saves_in_flight -= 1);
+        let modified = match result {
+            Ok(modified) => modified,
+            Err(error) => {
+                let error = format!("Couldn't save {}: {error}", path.display());
+                cx.global_mut::<ToastQueue>().push(Toast::error(error));
+                return;
+            }
+        };
+        if self.state.read(cx).path.as_ref() != Some(&path) {
+            // saved under a new name, which may be another language
+            self.text_input.model.update(cx, |editor, cx| {
+                editor.set_language(language_for_path(&path), cx);
+                EditorConfig::for_path(&path).apply(&mut editor.settings);
+                editor.set_soft_wrap(soft_wrap_for_path(&path, cx), cx);
+            });
+        }
+        let current_version = self.text_input.model.read(cx).version;
+        let saved_flash = autosave.then(|| {
+            let state = self.state.clone();
+            cx.spawn(|mut cx| async move {
+                cx.background_executor().timer(SAVED_FLASH_DURATION).await;
+                state
+                    .update(&mut cx, |state, cx| {
+                        state.saved_flash = None;
+                        cx.notify();
+                    })
+                    .ok();
+            })
+        });
+        self.state.update(cx, |state, cx| {
+            state.path = Some(path);
+            state.error = None;
+            // our own write is not a change to reload
+            state.saved_mtime = modified;
+            state.external_change = None;
+            state.saved_version = version;
+            state.saved_line_ending = line_ending;
+            // edits made while writing keep the editor dirty
+            state.update_dirty(current_version, cx);
+            if saved_flash.is_some() {
+                state.saved_flash = saved_flash;
+            }
+            cx.notify();
+        });
+        if !self.is_dirty(cx) {
+            self.discard_draft(cx);
+        }
+        // the file may have been committed since, or saved under a new name
+        self.load_git_base(cx);
+        self.lint(cx);
+    }
+
+    /// Runs the `lint_command` for the editor's path in the background and
+    /// underlines what it reports. The linter reads the file, so what it says
+    /// is dropped when the text was edited since it was saved. Without a
+    /// linter the underlines go away.
+    fn lint(&self, cx: &mut WindowContext) {
+        let (path, version) = {
+            let state = self.state.read(cx);
+            (state.path.clone(), state.saved_version)
+        };
+        let command_line = path.as_ref().and_then(|path| {
+            let extension = path.extension()?.to_string_lossy();
+            let settings = cx.global::<EditorSettings>();
+            Some(settings.linter_for_extension(&extension)?.to_string())
+        });
+        let (Some(path), Some(command_line)) = (path, command_line) else {
+            self.state.update(cx, |state, _cx| state.lint_task = None);
+            self.text_input.model.update(cx, |editor, cx| {
+                if !editor.diagnostics.is_empty() {
+                    editor.set_diagnostics(Vec::new(), cx);
+                }
+            });
+            return;
+        };
+        let run = cx
+            .background_executor()
+            .spawn(async move { run_linter(&command_line, &path) });
+        let editor = self.clone();
+        let task = cx.spawn(|mut cx| async move {
+            let result = run.await;
+            cx.update(|cx| match result {
+                Ok(messages) => {
+                    editor
+                        .state
+                        .update(cx, |state, _cx| state.lint_failed = false);
+                    editor.text_input.model.update(cx, |editor, cx| {
+                        if editor.version == version {
+                            editor.set_diagnostics(messages, cx);
+                        }
+                    });
+                }
+                Err(error) => {
+                    let failed_before = editor.state.update(cx, |state, _cx| {
+                        std::mem::replace(&mut state.lint_failed, true)
+                    });
+                    if !failed_before {
+                        cx.global_mut::<ToastQueue>().push(Toast::error(error));
+                    }
+                }
+            })
+            .ok();
+        });
+        self.state
+            .update(cx, |state, _cx| state.lint_task = Some(task));
+    }
+
+    /// Reads the text of the editor's path in the last git commit in the
+    /// background, then diffs against it. Without a path or a commit of it
+    /// the diff markers go away.
+    fn load_git_base(&self, cx: &mut WindowContext) {
+        let Some(path) = self.state.read(cx).path.clone() else {
+            self.state.update(cx, |state, _cx| {
+                state.git_base = None;
+                state.git_base_task = None;
+                state.diff_task = None;
+            });
+            self.text_input
+                .model
+                .update(cx, |editor, cx| editor.set_diff_hunks(None, cx));
+            return;
+        };
+        let head = cx
+            .background_executor()
+            .spawn(async move { head_text(&path) });
+        let editor = self.clone();
+        let task = cx.spawn(|mut cx| async move {
+            let base = head.await;
+            cx.update(|cx| {
+                let committed = base.is_some();
+                editor
+                    .state
+                    .update(cx, |state, _cx| state.git_base = base.map(Arc::from));
+                if committed {
+                    editor.schedule_diff(Duration::ZERO, cx);
+                } else {
+                    editor
+                        .text_input
+                        .model
+                        .update(cx, |editor, cx| editor.set_diff_hunks(None, cx));
+                }
+            })
+            .ok();
+        });
+        self.state
+            .update(cx, |state, _cx| state.git_base_task = Some(task));
+    }
+
+    /// Diffs the text against `git_base` in the background after `delay`, the
+    /// result becomes the diff markers unless the text changed meanwhile.
+    fn schedule_diff(&self, delay: Duration, cx: &mut WindowContext) {
+        if self.state.read(cx).git_base.is_none() {
+            return;
+        }
+        let editor = self.clone();
+        let task = cx.spawn(|mut cx| async move {
+            cx.background_executor().timer(delay).await;
+            let Ok(Some((base, text, version))) = cx.update(|cx| {
+                let base = editor.state.read(cx).git_base.clone()?;
+                let model = editor.text_input.model.read(cx);
+                Some((base, model.text(), model.version))
+            }) else {
+                return;
+            };
+            let hunks = cx
+                .background_executor()
+                .spawn(async move { diff_hunks(&base, &text) })
+                .await;
+            editor
+                .text_input
+                .model
+                .update(&mut cx, |editor, cx| {
+                    // a diff of an older text would mark the wrong lines
+                    if editor.version == version {
+                        editor.set_diff_hunks(Some(hunks), cx);
+                    }
+                })
+                .ok();
+        });
+        self.state
+            .update(cx, |state, _cx| state.diff_task = Some(task));
+    }
+
+    pub fn set_warning(&self, warning: Option<SharedString>, cx: &mut WindowContext) {
+        self.state.update(cx, |state, cx| {
+            state.warning = warning;
+            cx.notify();
+        });
+    }
+
+    /// Shows `error` in the title bar while the editor is active.
+    pub fn set_error(&self, error: Option<SharedString>, cx: &mut WindowContext) {
+        self.state.update(cx, |state, cx| {
+            state.error = error;
+            cx.notify();
+        });
+    }
+}
+
+fn language_for_path(path: &Path) -> Option<&'static str> {
+    path.extension()
+        .and_then(|extension| language_for_extension(&extension.to_string_lossy()))
+}
+
+fn soft_wrap_for_path(path: &Path, cx: &AppContext) -> bool {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy());
+    cx.global::<EditorSettings>()
+        .soft_wrap_for_extension(extension.as_deref())
+}
+
+/// When the file at `path` was last modified, `None` when it can't be read.
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Writes `contents` to a temporary file next to `path`, then moves it over
+/// `path`, so a failed write never leaves half a file. A symlink stays one,
+/// the file it points to is replaced, and the file keeps its permissions.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // a file that doesn't exist yet has nothing to follow
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(directory)?;
+    file.write_all(contents)?;
+    // the temporary file is only readable by its owner
+    if let Ok(metadata) = std::fs::metadata(&path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.as_file().sync_all()?;
+    file.persist(&path).map_err(|error| error.error)?;
+    Ok(())
 }
 
 impl RenderOnce for Editor {
@@ -20,3 +1333,51 @@ impl RenderOnce for Editor {
         self.text_input
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn write_atomically_creates_and_replaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+        write_atomically(&path, b"one").unwrap();
+        write_atomically(&path, b"two").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        // no temporary files left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomically_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "echo one").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o754)).unwrap();
+        write_atomically(&path, b"echo two").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o754);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomically_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&target, "one").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write_atomically(&link, b"two").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"two");
+    }
+}
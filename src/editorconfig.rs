@@ -0,0 +1,461 @@
+use std::{fs, path::Path};
+
+use crate::{config::BufferSettings, editor::LineEnding};
+
+/// Braces like `{1..1000000}` expand to more patterns than are worth matching,
+/// a range larger than this matches nothing.
+const MAX_RANGE_LEN: i64 = 1_000;
+
+/// The properties the `.editorconfig` files above a file set for it, see
+/// https://editorconfig.org. Unset ones leave the settings as they are.
+#[derive(Default)]
+pub struct EditorConfig {
+    pub hard_tabs: Option<bool>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub line_ending: Option<LineEnding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    /// Only `utf-8` and `utf-8-bom` can be written, other charsets are ignored.
+    pub write_bom: Option<bool>,
+}
+
+impl EditorConfig {
+    /// Reads the `.editorconfig` files in the folders of `path`, from the
+    /// closest up to the first one with `root = true`. Closer files win over
+    /// those further up, later sections over earlier ones. Files that can't
+    /// be read count as empty.
+    pub fn for_path(path: &Path) -> Self {
+        let mut files = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            let Ok(text) = fs::read_to_string(dir.join(".editorconfig")) else {
+                continue;
+            };
+            let file = parse(&text);
+            let root = file.root;
+            files.push((dir, file));
+            if root {
+                break;
+            }
+        }
+
+        let mut config = Self::default();
+        for (dir, file) in files.iter().rev() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            for (glob, properties) in &file.sections {
+                if !section_matches(glob, &relative) {
+                    continue;
+                }
+                for (key, value) in properties {
+                    config.set(key, value);
+                }
+            }
+        }
+        config
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        // `unset` takes a property back to the editor's own setting
+        if value == "unset" {
+            match key {
+                "indent_style" => self.hard_tabs = None,
+                "indent_size" => self.indent_size = None,
+                "tab_width" => self.tab_width = None,
+                "end_of_line" => self.line_ending = None,
+                "trim_trailing_whitespace" => self.trim_trailing_whitespace = None,
+                "insert_final_newline" => self.insert_final_newline = None,
+                "charset" => self.write_bom = None,
+                _ => {}
+            }
+            return;
+        }
+        match key {
+            "indent_style" => match value {
+                "tab" => self.hard_tabs = Some(true),
+                "space" => self.hard_tabs = Some(false),
+                _ => {}
+            },
+            // `tab` means as wide as a tab, see `tab_size`
+            "indent_size" if value == "tab" => self.indent_size = None,
+            "indent_size" => self.indent_size = value.parse().ok().filter(|size| *size > 0),
+            "tab_width" => self.tab_width = value.parse().ok().filter(|size| *size > 0),
+            "end_of_line" => match value {
+                "lf" => self.line_ending = Some(LineEnding::Lf),
+                "crlf" => self.line_ending = Some(LineEnding::Crlf),
+                _ => {}
+            },
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(value),
+            "insert_final_newline" => self.insert_final_newline = parse_bool(value),
+            "charset" => match value {
+                "utf-8" => self.write_bom = Some(false),
+                "utf-8-bom" => self.write_bom = Some(true),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// How many columns one level of indentation is.
+    fn tab_size(&self) -> Option<usize> {
+        self.indent_size.or(self.tab_width)
+    }
+
+    /// Overrides what `settings` got from the config file and detection,
+    /// indentation picked by hand stays.
+    pub fn apply(&self, settings: &mut BufferSettings) {
+        if !settings.indent_overridden {
+            if let Some(hard_tabs) = self.hard_tabs {
+                settings.hard_tabs = hard_tabs;
+            }
+            if let Some(tab_size) = self.tab_size() {
+                settings.tab_size = tab_size;
+            }
+        }
+        if let Some(trim) = self.trim_trailing_whitespace {
+            settings.trim_trailing_whitespace = trim;
+        }
+        if let Some(final_newline) = self.insert_final_newline {
+            settings.final_newline = final_newline;
+        }
+        if let Some(write_bom) = self.write_bom {
+            settings.write_bom = write_bom;
+        }
+    }
+}
+
+/// One `.editorconfig` file.
+struct ConfigFile {
+    /// Set by `root = true` before the first section.
+    root: bool,
+    /// Each section's glob with its properties, keys and values lowercased.
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+fn parse(text: &str) -> ConfigFile {
+    let mut file = ConfigFile {
+        root: false,
+        sections: Vec::new(),
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(glob) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            file.sections.push((glob.to_string(), Vec::new()));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_lowercase();
+        match file.sections.last_mut() {
+            Some((_, properties)) => properties.push((key, value)),
+            None if key == "root" => file.root = value == "true",
+            None => {}
+        }
+    }
+    file
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the section `glob` covers the file at `path`, relative to the
+/// folder of its `.editorconfig`. A glob without a slash matches the file
+/// name in any folder below.
+fn section_matches(glob: &str, path: &str) -> bool {
+    let glob = if has_separator(glob) {
+        glob.strip_prefix('/').unwrap_or(glob).to_string()
+    } else {
+        format!("**/{glob}")
+    };
+    let path: Vec<char> = path.chars().collect();
+    expand_braces(&glob).into_iter().any(|glob| {
+        let glob: Vec<char> = glob.chars().collect();
+        glob_matches(&glob, &path)
+    })
+}
+
+/// Whether `glob` has a slash outside of brackets.
+fn has_separator(glob: &str) -> bool {
+    let mut in_brackets = false;
+    let mut escaped = false;
+    for c in glob.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            '/' if !in_brackets => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Turns the first `{a,b}` or `{1..3}` of `glob` into one glob per
+/// alternative, and so on for the rest. Braces without either are literal.
+fn expand_braces(glob: &str) -> Vec<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '{' => {
+                if let Some((end, alternatives)) = brace_alternatives(&chars, i) {
+                    let prefix: String = chars[..i].iter().collect();
+                    let suffix: String = chars[end + 1..].iter().collect();
+                    return alternatives
+                        .into_iter()
+                        .flat_map(|alternative| expand_braces(&format!("{alternative}{suffix}")))
+                        .map(|rest| format!("{prefix}{rest}"))
+                        .collect();
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    vec![glob.to_string()]
+}
+
+/// The closing brace of the brace at `start` and the alternatives between
+/// them, `None` when it isn't closed or has no comma or range.
+fn brace_alternatives(chars: &[char], start: usize) -> Option<(usize, Vec<String>)> {
+    let mut depth = 0;
+    let mut alternatives = Vec::new();
+    let mut current = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.get(i + 1) {
+                    current.push(*next);
+                }
+                i += 2;
+                continue;
+            }
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '}' => {
+                if alternatives.is_empty() {
+                    return number_range(&current).map(|range| (i, range));
+                }
+                alternatives.push(current);
+                return Some((i, alternatives));
+            }
+            ',' if depth == 0 => {
+                alternatives.push(std::mem::take(&mut current));
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        i += 1;
+    }
+    None
+}
+
+/// The numbers of a `1..3` range, in either order.
+fn number_range(text: &str) -> Option<Vec<String>> {
+    let (start, end) = text.split_once("..")?;
+    let start: i64 = start.parse().ok()?;
+    let end: i64 = end.parse().ok()?;
+    let (start, end) = (start.min(end), start.max(end));
+    if end - start > MAX_RANGE_LEN {
+        return Some(Vec::new());
+    }
+    Some((start..=end).map(|n| n.to_string()).collect())
+}
+
+/// Matches `path` against `glob`, with `*` for anything but a slash, `**` for
+/// anything, `?` for one char, `[abc]`, `[a-z]` and `[!abc]` for one of a set,
+/// and `\` escaping the char after it.
+fn glob_matches(glob: &[char], path: &[char]) -> bool {
+    let Some(&c) = glob.first() else {
+        return path.is_empty();
+    };
+    match c {
+        '*' if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            // `**/` also matches no folder at all
+            (rest.first() == Some(&'/') && glob_matches(&rest[1..], path))
+                || (0..=path.len()).any(|i| glob_matches(rest, &path[i..]))
+        }
+        '*' => {
+            let rest = &glob[1..];
+            let segment = path.iter().position(|c| *c == '/').unwrap_or(path.len());
+            (0..=segment).any(|i| glob_matches(rest, &path[i..]))
+        }
+        '?' => path.first().is_some_and(|c| *c != '/') && glob_matches(&glob[1..], &path[1..]),
+        '[' => match bracket_end(glob) {
+            Some(end) => {
+                path.first()
+                    .is_some_and(|c| *c != '/' && set_contains(&glob[1..end], *c))
+                    && glob_matches(&glob[end + 1..], &path[1..])
+            }
+            None => path.first() == Some(&'[') && glob_matches(&glob[1..], &path[1..]),
+        },
+        '\\' if glob.len() > 1 => {
+            path.first() == Some(&glob[1]) && glob_matches(&glob[2..], &path[1..])
+        }
+        c => path.first() == Some(&c) && glob_matches(&glob[1..], &path[1..]),
+    }
+}
+
+/// The index of the `]` closing the set that starts `glob`.
+fn bracket_end(glob: &[char]) -> Option<usize> {
+    // a `]` right after the opening bracket is part of the set
+    let first = match glob.get(1) {
+        Some('!' | '^') => 3,
+        _ => 2,
+    };
+    (first..glob.len()).find(|i| glob[*i] == ']')
+}
+
+/// Whether the set between brackets contains `c`.
+fn set_contains(set: &[char], c: char) -> bool {
+    let (negated, set) = match set.first() {
+        Some('!' | '^') => (true, &set[1..]),
+        _ => (false, set),
+    };
+    let mut contains = false;
+    let mut i = 0;
+    while i < set.len() {
+        if set.get(i + 1) == Some(&'-') && i + 2 < set.len() {
+            contains |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            contains |= set[i] == c;
+            i += 1;
+        }
+    }
+    contains != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes each `(path, text)` into a new temporary folder.
+    fn fixture(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, text) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn closer_files_override_further_ones() {
+        let dir = fixture(&[
+            (
+                ".editorconfig",
+                "root = true\n[*]\ninsert_final_newline = false\n",
+            ),
+            (
+                "outer/.editorconfig",
+                "root = true\n[*]\nindent_style = tab\nend_of_line = crlf\n\n[*.md]\ntrim_trailing_whitespace = false\n",
+            ),
+            (
+                "outer/project/.editorconfig",
+                "[*.{rs,toml}]\nindent_style = space\nindent_size = 4\n\n[src/**.rs]\nindent_size = 2\n\n[*]\ncharset = utf-8-bom\n",
+            ),
+        ]);
+        let project = dir.path().join("outer/project");
+
+        let rust = EditorConfig::for_path(&project.join("src/bin/main.rs"));
+        assert_eq!(rust.hard_tabs, Some(false));
+        // the later section of the same file wins
+        assert_eq!(rust.indent_size, Some(2));
+        assert_eq!(rust.line_ending, Some(LineEnding::Crlf));
+        assert_eq!(rust.write_bom, Some(true));
+        // the file above `root = true` is never read
+        assert_eq!(rust.insert_final_newline, None);
+
+        let toml = EditorConfig::for_path(&project.join("Cargo.toml"));
+        assert_eq!(toml.indent_size, Some(4));
+
+        let markdown = EditorConfig::for_path(&project.join("docs/notes.md"));
+        assert_eq!(markdown.hard_tabs, Some(true));
+        assert_eq!(markdown.indent_size, None);
+        assert_eq!(markdown.trim_trailing_whitespace, Some(false));
+
+        let outside = EditorConfig::for_path(&dir.path().join("other.rs"));
+        assert_eq!(outside.insert_final_newline, Some(false));
+        assert_eq!(outside.hard_tabs, None);
+    }
+
+    #[test]
+    fn unset_goes_back_to_the_editor_setting() {
+        let dir = fixture(&[
+            (
+                "a/.editorconfig",
+                "root = true\n[*]\nindent_style = tab\ntab_width = 8\n",
+            ),
+            ("a/b/.editorconfig", "[*.py]\nindent_style = unset\n"),
+        ]);
+        let config = EditorConfig::for_path(&dir.path().join("a/b/x.py"));
+        assert_eq!(config.hard_tabs, None);
+        assert_eq!(config.tab_size(), Some(8));
+    }
+
+    #[test]
+    fn apply_keeps_indentation_picked_by_hand() {
+        let config = EditorConfig {
+            hard_tabs: Some(true),
+            indent_size: Some(2),
+            trim_trailing_whitespace: Some(true),
+            ..Default::default()
+        };
+        let mut settings = crate::config::EditorSettings::default().buffer_settings();
+        settings.indent_overridden = true;
+        let (hard_tabs, tab_size) = (settings.hard_tabs, settings.tab_size);
+        config.apply(&mut settings);
+        assert_eq!(
+            (settings.hard_tabs, settings.tab_size),
+            (hard_tabs, tab_size)
+        );
+        assert!(settings.trim_trailing_whitespace);
+
+        settings.indent_overridden = false;
+        config.apply(&mut settings);
+        assert_eq!((settings.hard_tabs, settings.tab_size), (true, 2));
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(section_matches("*", "a/b.rs"));
+        assert!(section_matches("*.rs", "src/main.rs"));
+        assert!(!section_matches("/*.rs", "src/main.rs"));
+        assert!(section_matches("/*.rs", "main.rs"));
+        assert!(section_matches("src/**.rs", "src/a/b.rs"));
+        assert!(section_matches("lib/**/x", "lib/x"));
+        assert!(section_matches("*.{js,ts}", "a.ts"));
+        assert!(!section_matches("*.{js,ts}", "a.rs"));
+        assert!(section_matches("file{1..3}", "file2"));
+        assert!(!section_matches("file{1..3}", "file4"));
+        assert!(section_matches("[!a]?.c", "bx.c"));
+        assert!(!section_matches("[!a]?.c", "ax.c"));
+        assert!(section_matches("\\*.c", "*.c"));
+        assert!(!section_matches("\\*.c", "x.c"));
+    }
+}
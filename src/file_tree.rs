@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use gpui::{prelude::FluentBuilder, *};
+use ignore::WalkBuilder;
+
+use crate::{
+    config::EditorSettings,
+    theme::Theme,
+    ui::{List, ListEvent},
+};
+
+/// The height of a file or folder in the tree.
+const ROW_HEIGHT: Pixels = px(24.);
+/// How far each level of folders is indented.
+const INDENT_WIDTH: Pixels = px(12.);
+const SIDEBAR_WIDTH: Pixels = px(240.);
+
+pub enum FileTreeEvent {
+    /// A file got enter or a double click, the owner opens it.
+    Open(PathBuf),
+}
+
+/// A file or folder in a folder that was read.
+struct FolderEntry {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// A line of the tree, what's in the expanded folders one after the other.
+struct Row {
+    path: PathBuf,
+    is_dir: bool,
+    /// How many folders it's nested in below the root.
+    depth: usize,
+}
+
+/// The files and folders in a root folder, for the sidebar. A folder is only
+/// read when it's expanded, so a huge one doesn't hold anything up. What a
+/// `.gitignore` ignores and hidden files are left out, unless the settings
+/// say otherwise. Up and down move through it, right and left expand and
+/// collapse a folder, enter opens a file.
+pub struct FileTree {
+    root: PathBuf,
+    list: View<List>,
+    /// What's in each folder read so far, folders first.
+    folders: HashMap<PathBuf, Vec<FolderEntry>>,
+    /// Folders being read in the background.
+    reading: HashSet<PathBuf>,
+    expanded: HashSet<PathBuf>,
+    rows: Vec<Row>,
+    /// The active editor's file, it's highlighted.
+    active: Option<PathBuf>,
+    /// A file to highlight once the folders around it are read, see `reveal`.
+    revealing: Option<PathBuf>,
+}
+
+impl EventEmitter<FileTreeEvent> for FileTree {}
+
+impl FileTree {
+    pub fn new(root: PathBuf, cx: &mut ViewContext<Self>) -> Self {
+        let root = root.canonicalize().unwrap_or(root);
+        let tree = cx.view().downgrade();
+        let list = cx.new_view(|cx| {
+            let mut list = List::new(
+                0,
+                move |index, _highlighted, cx| {
+                    tree.upgrade().map_or_else(
+                        || div().into_any_element(),
+                        |tree| tree.read(cx).render_row(index, cx),
+                    )
+                },
+                cx,
+            );
+            list.set_activate_on_click(false);
+            list
+        });
+        cx.subscribe(&list, |this, _list, event, cx| match event {
+            ListEvent::Activate(index) => this.activate(*index, cx),
+            ListEvent::Click { index, click_count } => {
+                let is_dir = this.rows.get(*index).is_some_and(|row| row.is_dir);
+                // folders toggle on every click, files open on a double one
+                if (is_dir && *click_count == 1) || (!is_dir && *click_count == 2) {
+                    this.activate(*index, cx);
+                }
+            }
+            ListEvent::Movement(_) => {}
+        })
+        .detach();
+        let mut tree = Self {
+            root: root.clone(),
+            list,
+            folders: HashMap::new(),
+            reading: HashSet::new(),
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            active: None,
+            revealing: None,
+        };
+        tree.expand(root, cx);
+        tree
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn focus(&self, cx: &mut WindowContext) {
+        self.list.update(cx, |list, cx| list.focus(cx));
+    }
+
+    /// Highlights `path` as the active editor's file.
+    pub fn set_active(&mut self, path: Option<&Path>, cx: &mut ViewContext<Self>) {
+        let path = path.map(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        if self.active != path {
+            self.active = path;
+            cx.notify();
+        }
+    }
+
+    /// Expands the folders `path` is in and scrolls to it. False when it
+    /// isn't under the root.
+    pub fn reveal(&mut self, path: &Path, cx: &mut ViewContext<Self>) -> bool {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !path.starts_with(&self.root) || path == self.root {
+            return false;
+        }
+        let folders: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|folder| folder.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect();
+        for folder in folders {
+            self.expand(folder, cx);
+        }
+        self.revealing = Some(path);
+        self.update_rows(cx);
+        true
+    }
+
+    /// Toggles a folder, opens a file.
+    fn activate(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some(row) = self.rows.get(index) else {
+            return;
+        };
+        let path = row.path.clone();
+        if !row.is_dir {
+            cx.emit(FileTreeEvent::Open(path));
+        } else if self.expanded.contains(&path) {
+            self.expanded.remove(&path);
+            self.update_rows(cx);
+        } else {
+            self.expand(path, cx);
+            self.update_rows(cx);
+        }
+    }
+
+    /// Marks `folder` expanded and reads it if it wasn't yet.
+    fn expand(&mut self, folder: PathBuf, cx: &mut ViewContext<Self>) {
+        if !self.folders.contains_key(&folder) && self.reading.insert(folder.clone()) {
+            let settings = cx.global::<EditorSettings>();
+            let show_hidden = settings.show_hidden_files;
+            let respect_gitignore = settings.respect_gitignore;
+            let entries = cx.background_executor().spawn({
+                let folder = folder.clone();
+                async move { read_folder(&folder, show_hidden, respect_gitignore) }
+            });
+            let read = folder.clone();
+            cx.spawn(|this, mut cx| async move {
+                let entries = entries.await;
+                this.update(&mut cx, |this, cx| {
+                    this.reading.remove(&read);
+                    this.folders.insert(read, entries);
+                    this.update_rows(cx);
+                })
+                .ok();
+            })
+            .detach();
+        }
+        self.expanded.insert(folder);
+    }
+
+    /// Lays out the expanded folders again, keeping the highlight on the
+    /// same path.
+    fn update_rows(&mut self, cx: &mut ViewContext<Self>) {
+        let highlighted = self
+            .list
+            .read(cx)
+            .highlighted()
+            .and_then(|index| self.rows.get(index))
+            .map(|row| row.path.clone());
+        let mut rows = Vec::new();
+        self.push_rows(&self.root, 0, &mut rows);
+        self.rows = rows;
+
+        let target = match self.revealing.take() {
+            Some(path) if self.rows.iter().any(|row| row.path == path) => Some(path),
+            // a folder on the way is still being read
+            Some(path) => {
+                self.revealing = Some(path);
+                highlighted
+            }
+            None => highlighted,
+        };
+        let index = target.and_then(|path| self.rows.iter().position(|row| row.path == path));
+        let count = self.rows.len();
+        self.list.update(cx, |list, cx| {
+            list.set_item_count(count, cx);
+            if let Some(index) = index {
+                list.highlight(index, cx);
+            }
+        });
+        cx.notify();
+    }
+
+    fn push_rows(&self, folder: &Path, depth: usize, rows: &mut Vec<Row>) {
+        let Some(entries) = self.folders.get(folder) else {
+            return;
+        };
+        for entry in entries {
+            rows.push(Row {
+                path: entry.path.clone(),
+                is_dir: entry.is_dir,
+                depth,
+            });
+            if entry.is_dir && self.expanded.contains(&entry.path) {
+                self.push_rows(&entry.path, depth + 1, rows);
+            }
+        }
+    }
+
+    /// Right expands the highlighted folder or goes into it when it's
+    /// expanded, left collapses it or goes up to the folder it's in.
+    fn on_key_down(&mut self, ev: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let Some(index) = self.list.read(cx).highlighted() else {
+            return;
+        };
+        let Some(row) = self.rows.get(index) else {
+            return;
+        };
+        let (is_dir, depth) = (row.is_dir, row.depth);
+        let expanded = is_dir && self.expanded.contains(&row.path);
+        match ev.keystroke.key.as_str() {
+            "right" if expanded => {
+                if self
+                    .rows
+                    .get(index + 1)
+                    .is_some_and(|next| next.depth > depth)
+                {
+                    self.list
+                        .update(cx, |list, cx| list.highlight(index + 1, cx));
+                }
+            }
+            "right" if is_dir => self.activate(index, cx),
+            "left" if expanded => self.activate(index, cx),
+            "left" => {
+                let parent = self.rows[..index].iter().rposition(|row| row.depth < depth);
+                if let Some(parent) = parent {
+                    self.list.update(cx, |list, cx| list.highlight(parent, cx));
+                }
+            }
+            _ => return,
+        }
+        cx.stop_propagation();
+    }
+
+    fn render_row(&self, index: usize, cx: &WindowContext) -> AnyElement {
+        let Some(row) = self.rows.get(index) else {
+            return div().into_any_element();
+        };
+        let theme = cx.global::<Theme>();
+        let active = self.active.as_ref() == Some(&row.path);
+        let name = row
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let chevron = match (row.is_dir, self.expanded.contains(&row.path)) {
+            (false, _) => "",
+            (true, false) => "▸",
+            (true, true) => "▾",
+        };
+
+        div()
+            .h(ROW_HEIGHT)
+            .pl(px(8.) + INDENT_WIDTH * row.depth as f32)
+            .pr_2()
+            .flex()
+            .items_center()
+            .gap_1()
+            .overflow_hidden()
+            .whitespace_nowrap()
+            .when(active, |this| {
+                this.bg(theme.selection_color)
+                    .when_some(theme.selection_text_color, |this, color| {
+                        this.text_color(color)
+                    })
+            })
+            .child(
+                div()
+                    .w(INDENT_WIDTH)
+                    .flex_none()
+                    .text_color(theme.muted_color)
+                    .child(chevron),
+            )
+            .child(name)
+            .into_any_element()
+    }
+}
+
+/// What's right in `folder`, folders first and each sorted by name.
+fn read_folder(folder: &Path, show_hidden: bool, respect_gitignore: bool) -> Vec<FolderEntry> {
+    let mut entries: Vec<FolderEntry> = WalkBuilder::new(folder)
+        .max_depth(Some(1))
+        .hidden(!show_hidden)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        // a folder that isn't a repository can still have a `.gitignore`
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != OsStr::new(".git"))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| FolderEntry {
+            // follows links, a link to a folder expands like one
+            is_dir: entry.path().is_dir(),
+            path: entry.into_path(),
+        })
+        .collect();
+    entries.sort_by_cached_key(|entry| {
+        let name = entry.path.file_name().unwrap_or_default();
+        (!entry.is_dir, name.to_string_lossy().to_lowercase())
+    });
+    entries
+}
+
+impl Render for FileTree {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let border_color = theme.border_color;
+        let muted_color = theme.muted_color;
+        let root_name = self
+            .root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.root.display().to_string());
+        let loading = self.reading.contains(&self.root);
+
+        div()
+            .w(SIDEBAR_WIDTH)
+            .h_full()
+            .flex_none()
+            .flex()
+            .flex_col()
+            .border_r_1()
+            .border_color(border_color)
+            .text_sm()
+            .on_key_down(cx.listener(Self::on_key_down))
+            .child(
+                div()
+                    .h(ROW_HEIGHT)
+                    .px_2()
+                    .flex()
+                    .items_center()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .font_weight(FontWeight::BOLD)
+                    .child(root_name),
+            )
+            .when(loading, |this| {
+                this.child(
+                    div()
+                        .px_2()
+                        .text_color(muted_color)
+                        .child("Reading the folder…"),
+                )
+            })
+            .child(div().flex_1().min_h_0().child(self.list.clone()))
+    }
+}
@@ -0,0 +1,31 @@
+use std::{path::Path, process::Command, time::Duration};
+
+use crate::process::{run_piped, split_command_line};
+
+/// How long a formatter may run before it's killed and the text is left as
+/// it is.
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+/// More output than this is no formatted version of a text file.
+const MAX_FORMATTED_LEN: usize = 256 * 1024 * 1024;
+
+/// Pipes `text` through `command_line`, e.g. `rustfmt --emit stdout`, run in
+/// `dir`. Returns what it printed when it exits with 0, otherwise an error
+/// with what it printed to stderr. Blocks until the formatter is done, so it
+/// belongs on a background task.
+pub fn run_formatter(
+    command_line: &str,
+    text: String,
+    dir: Option<&Path>,
+) -> Result<String, String> {
+    let args = split_command_line(command_line);
+    let Some((program, args)) = args.split_first() else {
+        return Err("The formatter command is empty".to_string());
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    run_piped(command, text, FORMAT_TIMEOUT, MAX_FORMATTED_LEN)
+        .map_err(|error| error.message(program))
+}
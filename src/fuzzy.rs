@@ -0,0 +1,80 @@
+/// Where the characters of a query were found in a text, and how good a
+/// match that is.
+#[derive(Clone, Default)]
+pub struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i32,
+    /// The byte offset in the text of each character of the query.
+    pub positions: Vec<usize>,
+}
+
+/// A match right after the previous one.
+const CONSECUTIVE_BONUS: i32 = 5;
+/// A match at the start of a word, e.g. after a space or a lowercase letter
+/// followed by an uppercase one.
+const WORD_START_BONUS: i32 = 8;
+/// A match on the first character of the text.
+const LEADING_BONUS: i32 = 10;
+/// Taken off for each character skipped between two matches.
+const GAP_PENALTY: i32 = 1;
+
+/// Matches when every character of `query` appears in `text` in order,
+/// ignoring case. Each character takes the first fitting place, preferring
+/// the start of a word nearby so `np` finds the initials of "New Tab...".
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let lowercase: Vec<char> = chars
+        .iter()
+        .map(|(_, c)| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let word_start = |i: usize| {
+        i == 0 || {
+            let (previous, current) = (chars[i - 1].1, chars[i].1);
+            !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase())
+        }
+    };
+
+    let mut result = FuzzyMatch::default();
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        let query_char = query_char.to_lowercase().next().unwrap_or(query_char);
+        let mut candidates = (next..chars.len()).filter(|i| lowercase[*i] == query_char);
+        let first = candidates.next()?;
+        // a word start further on beats a match in the middle of a word,
+        // unless the match continues the previous one
+        let i = if previous == Some(first.wrapping_sub(1)) || word_start(first) {
+            first
+        } else {
+            candidates.find(|i| word_start(*i)).unwrap_or(first)
+        };
+        if i == 0 {
+            result.score += LEADING_BONUS;
+        }
+        if word_start(i) {
+            result.score += WORD_START_BONUS;
+        }
+        match previous {
+            Some(previous) if previous + 1 == i => result.score += CONSECUTIVE_BONUS,
+            Some(previous) => result.score -= GAP_PENALTY * (i - previous - 1) as i32,
+            None => result.score -= GAP_PENALTY * i as i32,
+        }
+        result.positions.push(chars[i].0);
+        previous = Some(i);
+        next = i + 1;
+    }
+    Some(result)
+}
+
+/// The indices of the `texts` matching `query` with their matches, the best
+/// first. Equally good ones keep their order, and an empty query matches
+/// everything.
+pub fn rank<'a>(query: &str, texts: impl IntoIterator<Item = &'a str>) -> Vec<(usize, FuzzyMatch)> {
+    let mut matches: Vec<(usize, FuzzyMatch)> = texts
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, text)| Some((index, fuzzy_match(query, text)?)))
+        .collect();
+    matches.sort_by_key(|(_, fuzzy_match)| -fuzzy_match.score);
+    matches
+}
@@ -0,0 +1,286 @@
+use std::{ops::Range, path::Path, process::Command, time::Duration};
+
+use crate::{paste::normalize_line_endings, process::run_piped};
+
+/// How long `git show` may take before the file counts as not tracked.
+const GIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Files larger than this in HEAD get no markers.
+const MAX_HEAD_LEN: usize = 64 * 1024 * 1024;
+/// Texts further apart than this many added and removed lines are marked as
+/// one change, finding the exact lines would take too long.
+const MAX_DIFF_EDITS: usize = 2_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Added,
+    Modified,
+    /// Lines of HEAD that are gone, they were above the hunk's row.
+    Removed,
+}
+
+/// Lines that differ from the committed file.
+#[derive(Clone)]
+pub struct DiffHunk {
+    /// The rows in the text, empty for `DiffKind::Removed`.
+    pub rows: Range<usize>,
+    pub kind: DiffKind,
+    /// How many lines of HEAD it replaces.
+    pub old_len: usize,
+}
+
+/// The text of `path` in the HEAD commit of the repository it's in, with
+/// '\n' newlines like the editor's text. `None` outside a repository, for a
+/// file that isn't committed and when git isn't installed.
+pub fn head_text(path: &Path) -> Option<String> {
+    let dir = path.parent()?;
+    let name = path.file_name()?.to_str()?;
+    let mut command = Command::new("git");
+    // `./` makes the path relative to `dir` instead of the repository's root
+    command
+        .arg("show")
+        .arg(format!("HEAD:./{name}"))
+        .current_dir(dir);
+    let text = run_piped(command, String::new(), GIT_TIMEOUT, MAX_HEAD_LEN).ok()?;
+    Some(normalize_line_endings(&text))
+}
+
+/// The lines added, changed and removed in `text` compared to `base`, in
+/// order.
+pub fn diff_hunks(base: &str, text: &str) -> Vec<DiffHunk> {
+    // split like the buffer's rows, a final newline ends in an empty row
+    let old: Vec<&str> = base.split('\n').collect();
+    let new: Vec<&str> = text.split('\n').collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+    let regions = changed_regions(old_changed, new_changed)
+        .unwrap_or_else(|| vec![(0..old_changed.len(), 0..new_changed.len())]);
+
+    regions
+        .into_iter()
+        .filter(|(old, new)| !old.is_empty() || !new.is_empty())
+        .map(|(old, new)| {
+            let kind = if old.is_empty() {
+                DiffKind::Added
+            } else if new.is_empty() {
+                DiffKind::Removed
+            } else {
+                DiffKind::Modified
+            };
+            DiffHunk {
+                rows: prefix + new.start..prefix + new.end,
+                kind,
+                old_len: old.len(),
+            }
+        })
+        .collect()
+}
+
+/// How many lines are added, changed and removed in all `hunks`.
+pub fn diff_stats(hunks: &[DiffHunk]) -> (usize, usize, usize) {
+    let mut stats = (0, 0, 0);
+    for hunk in hunks {
+        match hunk.kind {
+            DiffKind::Added => stats.0 += hunk.rows.len(),
+            DiffKind::Modified => stats.1 += hunk.rows.len(),
+            DiffKind::Removed => stats.2 += hunk.old_len,
+        }
+    }
+    stats
+}
+
+/// The ranges of `old` and `new` lines that replace each other, found with
+/// Myers' diff. `None` when they take more than `MAX_DIFF_EDITS` edits.
+fn changed_regions(old: &[&str], new: &[&str]) -> Option<Vec<(Range<usize>, Range<usize>)>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (old.len() + new.len()).min(MAX_DIFF_EDITS) as isize;
+    // how far along `old` the furthest path on each diagonal `k = x - y` got
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // the diagonals around each step, for walking the path back
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut end = None;
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let down = k == -d || (k != d && v[i - 1] < v[i + 1]);
+            let mut x = if down { v[i + 1] } else { v[i - 1] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                end = Some(d);
+                break 'search;
+            }
+        }
+    }
+    let end = end?;
+
+    // walks back from the end, one edit per step, each a removed or an
+    // added line
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..=end).rev() {
+        let previous = &trace[d as usize];
+        let at = |k: isize| previous[(k + d) as usize];
+        let k = x - y;
+        let down = k == -d || (k != d && at(k - 1) < at(k + 1));
+        let previous_k = if down { k + 1 } else { k - 1 };
+        let previous_x = at(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+        }
+        edits.push((down, x, y));
+        x = previous_x;
+        y = previous_y;
+    }
+    edits.reverse();
+
+    // neighboring edits make one region
+    let mut regions: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for (added, x, y) in edits {
+        // the position before the edit
+        let (x, y) = if added {
+            (x as usize, y as usize - 1)
+        } else {
+            (x as usize - 1, y as usize)
+        };
+        match regions.last_mut() {
+            Some((old, new)) if old.end == x && new.end == y => {
+                if added {
+                    new.end += 1;
+                } else {
+                    old.end += 1;
+                }
+            }
+            _ if added => regions.push((x..x, y..y + 1)),
+            _ => regions.push((x..x + 1, y..y)),
+        }
+    }
+    Some(regions)
+}
+
+/// Moves `hunks` along with an edit that turned the rows
+/// `start_row..=old_end_row` into `start_row..=new_end_row`, so they stay on
+/// their lines until the text is diffed again. A hunk the edit is in grows or
+/// shrinks with it.
+pub fn shift_hunks(
+    hunks: &mut [DiffHunk],
+    start_row: usize,
+    old_end_row: usize,
+    new_end_row: usize,
+) {
+    for hunk in hunks {
+        if hunk.rows.start > old_end_row {
+            hunk.rows.start = hunk.rows.start + new_end_row - old_end_row;
+            hunk.rows.end = hunk.rows.end + new_end_row - old_end_row;
+        } else if hunk.rows.end > start_row {
+            hunk.rows.end = (hunk.rows.end + new_end_row)
+                .saturating_sub(old_end_row)
+                .max(hunk.rows.start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The hunks of `base` and `text` as rows, kind and old length.
+    fn hunks(base: &str, text: &str) -> Vec<(Range<usize>, &'static str, usize)> {
+        summary(&diff_hunks(base, text))
+    }
+
+    fn summary(hunks: &[DiffHunk]) -> Vec<(Range<usize>, &'static str, usize)> {
+        hunks
+            .iter()
+            .map(|hunk| {
+                let kind = match hunk.kind {
+                    DiffKind::Added => "added",
+                    DiffKind::Modified => "modified",
+                    DiffKind::Removed => "removed",
+                };
+                (hunk.rows.clone(), kind, hunk.old_len)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_texts() {
+        assert!(hunks("", "").is_empty());
+        assert!(hunks("a\nb\n", "a\nb\n").is_empty());
+    }
+
+    #[test]
+    fn added_and_removed_lines() {
+        assert_eq!(hunks("a\nc", "a\nb\nc"), [(1..2, "added", 0)]);
+        assert_eq!(hunks("a\nb\nc", "a\nc"), [(1..1, "removed", 1)]);
+        assert_eq!(hunks("", "a\nb"), [(0..2, "modified", 1)]);
+    }
+
+    #[test]
+    fn modified_lines_between_unchanged_ones() {
+        let hunks = hunks("a\nb\nc\nd\ne", "a\nB\nc\nD\nD2\ne");
+        assert_eq!(hunks, [(1..2, "modified", 1), (3..5, "modified", 1)]);
+        assert_eq!(diff_stats(&diff_hunks("a\nb\nc", "x\nb")), (0, 1, 1));
+    }
+
+    #[test]
+    fn final_newline() {
+        assert_eq!(hunks("a\nb\n", "a\nb"), [(2..2, "removed", 1)]);
+        assert_eq!(hunks("a\nb", "a\nb\n"), [(2..3, "added", 0)]);
+    }
+
+    #[test]
+    fn too_many_edits_are_one_change() {
+        let base: Vec<String> = (0..MAX_DIFF_EDITS).map(|i| format!("a{i}")).collect();
+        let text: Vec<String> = (0..MAX_DIFF_EDITS).map(|i| format!("b{i}")).collect();
+        let old: Vec<&str> = base.iter().map(String::as_str).collect();
+        let new: Vec<&str> = text.iter().map(String::as_str).collect();
+        assert!(changed_regions(&old, &new).is_none());
+        assert_eq!(
+            hunks(
+                &format!("top\n{}\nbottom", base.join("\n")),
+                &format!("top\n{}\nbottom", text.join("\n"))
+            ),
+            [(1..1 + MAX_DIFF_EDITS, "modified", MAX_DIFF_EDITS)]
+        );
+    }
+
+    #[test]
+    fn hunks_move_with_edits() {
+        let shifted = |rows: Range<usize>, kind, edit: (usize, usize, usize)| {
+            let mut hunks = [DiffHunk {
+                rows,
+                kind,
+                old_len: 1,
+            }];
+            shift_hunks(&mut hunks, edit.0, edit.1, edit.2);
+            hunks[0].rows.clone()
+        };
+        let modified = DiffKind::Modified;
+        // above it
+        assert_eq!(shifted(5..8, modified, (1, 1, 3)), 7..10);
+        assert_eq!(shifted(5..8, modified, (1, 3, 1)), 3..6);
+        assert_eq!(shifted(5..5, DiffKind::Removed, (2, 2, 4)), 7..7);
+        // inside it
+        assert_eq!(shifted(5..8, modified, (6, 6, 8)), 5..10);
+        assert_eq!(shifted(5..8, modified, (6, 7, 6)), 5..7);
+        assert_eq!(shifted(5..8, modified, (4, 9, 4)), 5..5);
+        // below it
+        assert_eq!(shifted(5..8, modified, (10, 10, 12)), 5..8);
+        assert_eq!(shifted(5..8, modified, (8, 9, 8)), 5..8);
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use gpui::*;
+
+use crate::{
+    theme::Theme,
+    ui::{Background, TextEvent, TextInput, TextModel},
+};
+
+/// How long the line jumped to stays highlighted.
+const FLASH_DURATION: Duration = Duration::from_millis(600);
+
+pub enum GoToLineEvent {
+    /// The cursor moved, or escape was pressed. The owner drops the dialog.
+    Dismissed,
+}
+
+/// Asks for a `line` or `line:column`, both counted from 1, and moves the
+/// cursor of an editor there.
+pub struct GoToLine {
+    input: TextInput,
+    editor: Model<TextModel>,
+    /// Set when enter was pressed on something that isn't a position, until
+    /// the next edit.
+    invalid: bool,
+}
+
+impl EventEmitter<GoToLineEvent> for GoToLine {}
+
+impl GoToLine {
+    pub fn new(editor: Model<TextModel>, cx: &mut ViewContext<Self>) -> Self {
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        cx.subscribe(&input.model, |this, _model, event, cx| match event {
+            TextEvent::Edited { .. } => {
+                this.invalid = false;
+                cx.notify();
+            }
+            TextEvent::Submit { text } => this.go_to(text, cx),
+            TextEvent::Cancel => cx.emit(GoToLineEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        input.focus(cx);
+        Self {
+            input,
+            editor,
+            invalid: false,
+        }
+    }
+
+    /// Whether the focus is still in the dialog's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+
+    /// Moves the cursor to the position in `text`, clamped to the text, and
+    /// flashes its line.
+    fn go_to(&mut self, text: &str, cx: &mut ViewContext<Self>) {
+        let Some((row, column)) = parse_position(text) else {
+            self.invalid = true;
+            cx.notify();
+            return;
+        };
+        let flash_color = cx.global::<Theme>().match_color;
+        self.editor.update(cx, |editor, cx| {
+            let offset = editor.offset_for_point(row, column);
+            editor.move_to(offset);
+            // scrolls the cursor into view
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+            let line = editor.line_start(offset)..editor.line_end(offset);
+            let style = HighlightStyle {
+                background_color: Some(flash_color),
+                ..Default::default()
+            };
+            editor.set_highlights::<Self>(vec![(line, style)], cx);
+        });
+        let editor = self.editor.clone();
+        cx.spawn(|_this, mut cx| async move {
+            cx.background_executor().timer(FLASH_DURATION).await;
+            editor
+                .update(&mut cx, |editor, cx| editor.clear_highlights::<Self>(cx))
+                .ok();
+        })
+        .detach();
+        cx.emit(GoToLineEvent::Dismissed);
+    }
+}
+
+/// The zero-based row and column of `line` or `line:column`.
+fn parse_position(text: &str) -> Option<(usize, usize)> {
+    let (line, column) = match text.trim().split_once(':') {
+        Some((line, column)) => (line, Some(column)),
+        None => (text.trim(), None),
+    };
+    let line: usize = line.trim().parse().ok()?;
+    let column: usize = column.map_or(Some(1), |column| column.trim().parse().ok())?;
+    Some((line.checked_sub(1)?, column.checked_sub(1)?))
+}
+
+impl Render for GoToLine {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let (border_color, muted_color, danger_color) =
+            (theme.border_color, theme.muted_color, theme.danger_color);
+
+        // covers the window, a press outside the dialog closes it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(GoToLineEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w_80()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(self.input.clone().invalid(self.invalid))
+                                .child(
+                                    div()
+                                        .px_4()
+                                        .py_2()
+                                        .text_color(if self.invalid {
+                                            danger_color
+                                        } else {
+                                            muted_color
+                                        })
+                                        .child(if self.invalid {
+                                            "Type a line number, or line:column"
+                                        } else {
+                                            "Go to line, or line:column"
+                                        }),
+                                ),
+                        ),
+                    ),
+            )
+    }
+}
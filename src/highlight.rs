@@ -0,0 +1,230 @@
+use std::{ops::Range, sync::Arc};
+
+/// What a highlighted piece of text is, each kind has its own color in `Theme`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+/// A change to the text a `Highlighter` saw last, positions are (row, byte
+/// column) pairs.
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub new_len: usize,
+    pub start: (usize, usize),
+    pub old_end: (usize, usize),
+    pub new_end: (usize, usize),
+}
+
+/// Splits a text into the tokens that get colors. Ranges are byte offsets into
+/// the text, sorted and not overlapping.
+pub trait Highlighter {
+    fn highlight(&self, text: &str) -> Vec<(Range<usize>, TokenKind)>;
+
+    /// Called for every edit before the next `highlight`, for highlighters
+    /// that keep state between texts.
+    fn edit(&self, _edit: &TextEdit) {}
+}
+
+/// A highlighter driven by a few rules that fit most C-like languages, no
+/// parsing involved.
+pub struct RuleHighlighter {
+    pub line_comment: &'static str,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    pub quotes: &'static [char],
+    pub keywords: &'static [&'static str],
+}
+
+const RUST: RuleHighlighter = RuleHighlighter {
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"'],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+};
+
+const JAVASCRIPT: RuleHighlighter = RuleHighlighter {
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "let",
+        "new",
+        "null",
+        "return",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+};
+
+const PYTHON: RuleHighlighter = RuleHighlighter {
+    line_comment: "#",
+    block_comment: None,
+    quotes: &['"', '\''],
+    keywords: &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+        "try", "while", "with", "yield",
+    ],
+};
+
+/// The id of the language of files ending in `extension`, e.g. `rust`, or
+/// `None` for plain text.
+pub fn language_for_extension(extension: &str) -> Option<&'static str> {
+    Some(match extension.to_ascii_lowercase().as_str() {
+        "rs" => "rust",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "py" => "python",
+        "toml" => "toml",
+        "sh" | "bash" | "zsh" => "shell",
+        "yaml" | "yml" => "yaml",
+        "rb" => "ruby",
+        "lua" => "lua",
+        "sql" => "sql",
+        "hs" => "haskell",
+        "md" | "markdown" => "markdown",
+        _ => return None,
+    })
+}
+
+/// The built-in highlighter for `language`, if there is one.
+pub fn highlighter_for_language(language: &str) -> Option<Arc<dyn Highlighter>> {
+    #[cfg(feature = "tree-sitter")]
+    if let Some(highlighter) =
+        crate::tree_sitter_highlight::TreeSitterHighlighter::for_language(language)
+    {
+        return Some(Arc::new(highlighter));
+    }
+    match language {
+        "rust" => Some(Arc::new(RUST)),
+        "javascript" | "typescript" => Some(Arc::new(JAVASCRIPT)),
+        "python" => Some(Arc::new(PYTHON)),
+        _ => None,
+    }
+}
+
+/// The line comment of texts in an unknown language.
+pub const DEFAULT_LINE_COMMENT: &str = "//";
+
+/// What starts a line comment in `language`.
+pub fn line_comment_for_language(language: &str) -> &'static str {
+    match language {
+        "python" | "toml" | "shell" | "yaml" | "ruby" => "#",
+        "lua" | "sql" | "haskell" => "--",
+        _ => DEFAULT_LINE_COMMENT,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Highlighter for RuleHighlighter {
+    fn highlight(&self, text: &str) -> Vec<(Range<usize>, TokenKind)> {
+        let mut tokens = Vec::new();
+        let mut offset = 0;
+        let mut prev = None;
+        while let Some(c) = text[offset..].chars().next() {
+            let rest = &text[offset..];
+            let token = if rest.starts_with(self.line_comment) {
+                let len = rest.find('\n').unwrap_or(rest.len());
+                Some((len, TokenKind::Comment))
+            } else if let Some((start, end)) = self
+                .block_comment
+                .filter(|(start, _)| rest.starts_with(start))
+            {
+                // an unterminated comment runs to the end of the text
+                let len = rest[start.len()..]
+                    .find(end)
+                    .map_or(rest.len(), |i| start.len() + i + end.len());
+                Some((len, TokenKind::Comment))
+            } else if self.quotes.contains(&c) {
+                Some((string_len(rest, c), TokenKind::String))
+            } else if c.is_ascii_digit() && !prev.is_some_and(is_word_char) {
+                let len = rest
+                    .find(|c: char| !is_word_char(c) && c != '.')
+                    .unwrap_or(rest.len());
+                Some((len, TokenKind::Number))
+            } else if is_word_char(c) && !prev.is_some_and(is_word_char) {
+                let len = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+                if self.keywords.contains(&&rest[..len]) {
+                    Some((len, TokenKind::Keyword))
+                } else {
+                    // skip the whole word, a keyword inside it doesn't count
+                    prev = rest[..len].chars().next_back();
+                    offset += len;
+                    continue;
+                }
+            } else {
+                None
+            };
+            match token {
+                Some((len, kind)) => {
+                    tokens.push((offset..offset + len, kind));
+                    prev = rest[..len].chars().next_back();
+                    offset += len;
+                }
+                None => {
+                    prev = Some(c);
+                    offset += c.len_utf8();
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// The length of the string literal at the start of `text`, up to and
+/// including the closing `quote`. Unterminated strings end with the line.
+fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '\n' => return i,
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return i + c.len_utf8(),
+            _ => escaped = false,
+        }
+    }
+    text.len()
+}
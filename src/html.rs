@@ -0,0 +1,167 @@
+use std::{fmt::Write, ops::Range};
+
+use gpui::*;
+
+use crate::{highlight::TokenKind, theme::Theme};
+
+/// A standalone page showing `text` like the editor does, in the theme's
+/// font and colors with its syntax `tokens` colored. Token ranges are byte
+/// offsets into `text`.
+pub fn html_document(
+    title: &str,
+    text: &str,
+    tokens: &[(Range<usize>, TokenKind)],
+    theme: &Theme,
+) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{}</title>\n\
+         </head>\n\
+         <body style=\"margin: 0; background: {};\">\n\
+         {}\n\
+         </body>\n\
+         </html>\n",
+        escape_html(title),
+        css_color(theme.background_color),
+        highlighted_pre(text, tokens, theme),
+    )
+}
+
+/// `text` in a `<pre>` with the styles inline, so it keeps its colors when
+/// pasted without the page around it.
+pub fn highlighted_pre(text: &str, tokens: &[(Range<usize>, TokenKind)], theme: &Theme) -> String {
+    let mut html = format!(
+        "<pre style=\"margin: 0; padding: 16px; background: {}; color: {}; \
+         font-family: {}, monospace; font-size: {}px;\">",
+        css_color(theme.background_color),
+        css_color(theme.text_color),
+        escape_html(&format!("'{}'", theme.editor_font_family)),
+        theme.editor_font_size / px(1.),
+    );
+    // how far the text is written, a token starting before that only gets
+    // the part after it
+    let mut written = 0;
+    for (range, kind) in tokens {
+        let start = range.start.max(written).min(text.len());
+        let end = range.end.min(text.len());
+        if start >= end {
+            continue;
+        }
+        html.push_str(&escape_html(&text[written..start]));
+        write!(
+            html,
+            "<span style=\"color: {};\">{}</span>",
+            css_color(theme.token_color(*kind)),
+            escape_html(&text[start..end])
+        )
+        .ok();
+        written = end;
+    }
+    html.push_str(&escape_html(&text[written..]));
+    html.push_str("</pre>");
+    html
+}
+
+/// `text` with the chars that mean something in HTML replaced by entities,
+/// safe in text and in quoted attributes.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `#rrggbbaa`, which every browser reads.
+fn css_color(color: Hsla) -> String {
+    let Rgba { r, g, b, a } = Rgba::from(color);
+    let byte = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        byte(r),
+        byte(g),
+        byte(b),
+        byte(a)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What `highlighted_pre` puts between `<pre …>` and `</pre>`.
+    fn pre_body(text: &str, tokens: &[(Range<usize>, TokenKind)], theme: &Theme) -> String {
+        let html = highlighted_pre(text, tokens, theme);
+        let start = html.find("\">").unwrap() + 2;
+        html[start..html.len() - "</pre>".len()].to_string()
+    }
+
+    fn span(kind: TokenKind, text: &str, theme: &Theme) -> String {
+        format!(
+            "<span style=\"color: {};\">{text}</span>",
+            css_color(theme.token_color(kind))
+        )
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">'&amp;'</a> é"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;amp;&#39;&lt;/a&gt; é"
+        );
+        assert_eq!(escape_html(""), "");
+    }
+
+    #[test]
+    fn escapes_inside_and_between_tokens() {
+        let theme = Theme::dark();
+        let text = "a<b // <c>";
+        let body = pre_body(text, &[(4..10, TokenKind::Comment)], &theme);
+        let comment = span(TokenKind::Comment, "// &lt;c&gt;", &theme);
+        assert_eq!(body, format!("a&lt;b {comment}"));
+    }
+
+    #[test]
+    fn overlapping_tokens_are_clipped() {
+        let theme = Theme::dark();
+        let text = "fn x() { \"s\" }";
+        let tokens = [
+            (0..2, TokenKind::Keyword),
+            // starts inside the keyword, only the part after it is colored
+            (1..4, TokenKind::Number),
+            // entirely inside what's written already
+            (2..3, TokenKind::Comment),
+            (9..12, TokenKind::String),
+            // runs past the end of the text
+            (11..40, TokenKind::Comment),
+        ];
+        let body = pre_body(text, &tokens, &theme);
+        assert_eq!(
+            body,
+            format!(
+                "{}{}() {{ {}{}",
+                span(TokenKind::Keyword, "fn", &theme),
+                span(TokenKind::Number, " x", &theme),
+                span(TokenKind::String, "&quot;s&quot;", &theme),
+                span(TokenKind::Comment, " }", &theme),
+            )
+        );
+    }
+
+    #[test]
+    fn tokens_past_the_end_are_skipped() {
+        let theme = Theme::light();
+        let body = pre_body("ab", &[(5..9, TokenKind::Keyword)], &theme);
+        assert_eq!(body, "ab");
+    }
+}
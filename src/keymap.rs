@@ -0,0 +1,474 @@
+use std::{collections::HashMap, fs, io};
+
+use anyhow::{bail, Context};
+use gpui::*;
+
+use crate::{
+    config::config_dir,
+    toast::{Toast, ToastQueue},
+};
+
+/// Something a key does in a `TextInput`, see `TextModel::perform`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditorAction {
+    MoveLeft,
+    MoveRight,
+    /// Past the first line the input's owner is told, see `TextEvent::Movement`.
+    MoveUp,
+    MoveDown,
+    MoveWordLeft,
+    MoveWordRight,
+    /// To the first non-blank character, or the very start when already there.
+    MoveToLineStart,
+    MoveToLineEnd,
+    MoveToStart,
+    MoveToEnd,
+    PageUp,
+    PageDown,
+    SelectLeft,
+    SelectRight,
+    SelectUp,
+    SelectDown,
+    SelectWordLeft,
+    SelectWordRight,
+    SelectToLineStart,
+    SelectToLineEnd,
+    SelectToStart,
+    SelectToEnd,
+    SelectPageUp,
+    SelectPageDown,
+    SelectAll,
+    JumpToBracket,
+    Copy,
+    Cut,
+    Paste,
+    /// Pastes the clipboard as it is, without `paste::normalize_pasted_text`.
+    PasteRaw,
+    /// Reverts the last group of edits, see `TextModel::undo`.
+    Undo,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWordBackward,
+    DeleteWordForward,
+    /// Deletes to the end of the line, or the newline at the end of it.
+    DeleteToLineEnd,
+    /// Deletes the lines the selection touches.
+    DeleteLine,
+    /// Duplicates the selection, or the cursor's line when nothing is selected.
+    DuplicateSelection,
+    /// Swaps the lines the selection touches with the line above them.
+    MoveLineUp,
+    MoveLineDown,
+    /// Joins the lines of the selection, or the cursor's line with the next.
+    JoinLines,
+    /// Grows or shrinks a rectangular selection, one cursor per row.
+    SelectColumnUp,
+    SelectColumnDown,
+    SelectColumnLeft,
+    SelectColumnRight,
+    /// Adds a cursor at the next place the selected text is, or selects the
+    /// word at the cursor.
+    SelectNextOccurrence,
+    /// Adds a cursor at every place the selected text is.
+    SelectAllOccurrences,
+    /// Changes the case of the selected text and keeps it selected.
+    Uppercase,
+    Lowercase,
+    TitleCase,
+    /// Comments out the lines of the selection, or uncomments them.
+    ToggleComment,
+    /// Indents the lines of the selection, or inserts an indent at the cursor.
+    Indent,
+    /// Removes one level of indentation from the lines of the selection.
+    Outdent,
+    /// Inserts a line break, or submits a single line input.
+    Newline,
+    /// Like `Newline`, without carrying the indentation over.
+    NewlineWithoutIndent,
+    /// Tells the input's owner, e.g. to close the search bar.
+    Cancel,
+    /// Offers the words of the text that finish the one at the cursor, see
+    /// `TextModel::show_completions`.
+    ShowCompletions,
+}
+
+/// The name of each action in `keymap.toml`.
+const ACTION_NAMES: &[(&str, EditorAction)] = &[
+    ("move_left", EditorAction::MoveLeft),
+    ("move_right", EditorAction::MoveRight),
+    ("move_up", EditorAction::MoveUp),
+    ("move_down", EditorAction::MoveDown),
+    ("move_word_left", EditorAction::MoveWordLeft),
+    ("move_word_right", EditorAction::MoveWordRight),
+    ("move_to_line_start", EditorAction::MoveToLineStart),
+    ("move_to_line_end", EditorAction::MoveToLineEnd),
+    ("move_to_start", EditorAction::MoveToStart),
+    ("move_to_end", EditorAction::MoveToEnd),
+    ("page_up", EditorAction::PageUp),
+    ("page_down", EditorAction::PageDown),
+    ("select_left", EditorAction::SelectLeft),
+    ("select_right", EditorAction::SelectRight),
+    ("select_up", EditorAction::SelectUp),
+    ("select_down", EditorAction::SelectDown),
+    ("select_word_left", EditorAction::SelectWordLeft),
+    ("select_word_right", EditorAction::SelectWordRight),
+    ("select_to_line_start", EditorAction::SelectToLineStart),
+    ("select_to_line_end", EditorAction::SelectToLineEnd),
+    ("select_to_start", EditorAction::SelectToStart),
+    ("select_to_end", EditorAction::SelectToEnd),
+    ("select_page_up", EditorAction::SelectPageUp),
+    ("select_page_down", EditorAction::SelectPageDown),
+    ("select_all", EditorAction::SelectAll),
+    ("jump_to_bracket", EditorAction::JumpToBracket),
+    ("copy", EditorAction::Copy),
+    ("cut", EditorAction::Cut),
+    ("paste", EditorAction::Paste),
+    ("paste_raw", EditorAction::PasteRaw),
+    ("undo", EditorAction::Undo),
+    ("delete_backward", EditorAction::DeleteBackward),
+    ("delete_forward", EditorAction::DeleteForward),
+    ("delete_word_backward", EditorAction::DeleteWordBackward),
+    ("delete_word_forward", EditorAction::DeleteWordForward),
+    ("delete_to_line_end", EditorAction::DeleteToLineEnd),
+    ("delete_line", EditorAction::DeleteLine),
+    ("duplicate_selection", EditorAction::DuplicateSelection),
+    ("move_line_up", EditorAction::MoveLineUp),
+    ("move_line_down", EditorAction::MoveLineDown),
+    ("join_lines", EditorAction::JoinLines),
+    ("select_column_up", EditorAction::SelectColumnUp),
+    ("select_column_down", EditorAction::SelectColumnDown),
+    ("select_column_left", EditorAction::SelectColumnLeft),
+    ("select_column_right", EditorAction::SelectColumnRight),
+    ("select_next_occurrence", EditorAction::SelectNextOccurrence),
+    ("select_all_occurrences", EditorAction::SelectAllOccurrences),
+    ("uppercase", EditorAction::Uppercase),
+    ("lowercase", EditorAction::Lowercase),
+    ("title_case", EditorAction::TitleCase),
+    ("toggle_comment", EditorAction::ToggleComment),
+    ("indent", EditorAction::Indent),
+    ("outdent", EditorAction::Outdent),
+    ("newline", EditorAction::Newline),
+    ("newline_without_indent", EditorAction::NewlineWithoutIndent),
+    ("cancel", EditorAction::Cancel),
+    ("show_completions", EditorAction::ShowCompletions),
+];
+
+/// The keys every input starts with, `keymap.toml` adds to and overrides them.
+const DEFAULT_BINDINGS: &[(&str, EditorAction)] = &[
+    ("left", EditorAction::MoveLeft),
+    ("right", EditorAction::MoveRight),
+    ("up", EditorAction::MoveUp),
+    ("down", EditorAction::MoveDown),
+    ("alt-left", EditorAction::MoveWordLeft),
+    ("alt-right", EditorAction::MoveWordRight),
+    ("home", EditorAction::MoveToLineStart),
+    ("cmd-left", EditorAction::MoveToLineStart),
+    ("end", EditorAction::MoveToLineEnd),
+    ("cmd-right", EditorAction::MoveToLineEnd),
+    ("cmd-up", EditorAction::MoveToStart),
+    ("cmd-down", EditorAction::MoveToEnd),
+    ("pageup", EditorAction::PageUp),
+    ("pagedown", EditorAction::PageDown),
+    ("shift-left", EditorAction::SelectLeft),
+    ("shift-right", EditorAction::SelectRight),
+    ("shift-up", EditorAction::SelectUp),
+    ("shift-down", EditorAction::SelectDown),
+    ("alt-shift-left", EditorAction::SelectWordLeft),
+    ("alt-shift-right", EditorAction::SelectWordRight),
+    ("shift-home", EditorAction::SelectToLineStart),
+    ("shift-cmd-left", EditorAction::SelectToLineStart),
+    ("shift-end", EditorAction::SelectToLineEnd),
+    ("shift-cmd-right", EditorAction::SelectToLineEnd),
+    ("shift-cmd-up", EditorAction::SelectToStart),
+    ("shift-cmd-down", EditorAction::SelectToEnd),
+    ("shift-pageup", EditorAction::SelectPageUp),
+    ("shift-pagedown", EditorAction::SelectPageDown),
+    ("cmd-a", EditorAction::SelectAll),
+    ("shift-cmd-\\", EditorAction::JumpToBracket),
+    ("ctrl-m", EditorAction::JumpToBracket),
+    ("cmd-c", EditorAction::Copy),
+    ("cmd-x", EditorAction::Cut),
+    ("cmd-v", EditorAction::Paste),
+    ("alt-shift-cmd-v", EditorAction::PasteRaw),
+    ("cmd-z", EditorAction::Undo),
+    ("backspace", EditorAction::DeleteBackward),
+    ("shift-backspace", EditorAction::DeleteBackward),
+    ("delete", EditorAction::DeleteForward),
+    ("alt-backspace", EditorAction::DeleteWordBackward),
+    ("alt-delete", EditorAction::DeleteWordForward),
+    ("ctrl-k", EditorAction::DeleteToLineEnd),
+    ("shift-cmd-k", EditorAction::DeleteLine),
+    ("shift-cmd-d", EditorAction::DuplicateSelection),
+    ("alt-up", EditorAction::MoveLineUp),
+    ("alt-down", EditorAction::MoveLineDown),
+    ("ctrl-j", EditorAction::JoinLines),
+    ("ctrl-shift-up", EditorAction::SelectColumnUp),
+    ("ctrl-shift-down", EditorAction::SelectColumnDown),
+    ("ctrl-shift-left", EditorAction::SelectColumnLeft),
+    ("ctrl-shift-right", EditorAction::SelectColumnRight),
+    ("cmd-d", EditorAction::SelectNextOccurrence),
+    ("shift-cmd-l", EditorAction::SelectAllOccurrences),
+    ("ctrl-shift-u", EditorAction::Uppercase),
+    ("ctrl-shift-l", EditorAction::Lowercase),
+    ("ctrl-shift-t", EditorAction::TitleCase),
+    ("cmd-/", EditorAction::ToggleComment),
+    ("tab", EditorAction::Indent),
+    ("shift-tab", EditorAction::Outdent),
+    ("enter", EditorAction::Newline),
+    ("shift-enter", EditorAction::NewlineWithoutIndent),
+    ("escape", EditorAction::Cancel),
+    ("ctrl-space", EditorAction::ShowCompletions),
+];
+
+impl EditorAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTION_NAMES
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| *action)
+    }
+
+    /// Whether the action changes the text, read-only inputs ignore these.
+    pub fn edits_text(self) -> bool {
+        matches!(
+            self,
+            EditorAction::Cut
+                | EditorAction::Paste
+                | EditorAction::PasteRaw
+                | EditorAction::Undo
+                | EditorAction::DeleteBackward
+                | EditorAction::DeleteForward
+                | EditorAction::DeleteWordBackward
+                | EditorAction::DeleteWordForward
+                | EditorAction::DeleteToLineEnd
+                | EditorAction::DeleteLine
+                | EditorAction::DuplicateSelection
+                | EditorAction::MoveLineUp
+                | EditorAction::MoveLineDown
+                | EditorAction::JoinLines
+                | EditorAction::Uppercase
+                | EditorAction::Lowercase
+                | EditorAction::TitleCase
+                | EditorAction::ToggleComment
+                | EditorAction::Indent
+                | EditorAction::Outdent
+                | EditorAction::Newline
+                | EditorAction::NewlineWithoutIndent
+        )
+    }
+}
+
+/// Which key does what in inputs, a global built from `DEFAULT_BINDINGS` and
+/// `~/.config/ted-itor/keymap.toml`.
+pub struct Keymap {
+    /// Keyed by `keystroke_id`.
+    bindings: HashMap<String, EditorAction>,
+}
+
+impl Keymap {
+    /// Sets the keymap as the global. Mistakes in the user's file are shown
+    /// as toasts and skipped, the rest of it still applies.
+    pub fn init(cx: &mut AppContext) {
+        let mut keymap = Keymap::with_defaults();
+        for error in keymap.load_overrides() {
+            cx.global_mut::<ToastQueue>()
+                .push(Toast::error(format!("keymap.toml: {error:#}")));
+        }
+        cx.set_global(keymap);
+    }
+
+    /// The keymap of `DEFAULT_BINDINGS` alone.
+    fn with_defaults() -> Self {
+        Keymap {
+            bindings: DEFAULT_BINDINGS
+                .iter()
+                .map(|(keys, action)| (parse_keystroke(keys).unwrap(), *action))
+                .collect(),
+        }
+    }
+
+    /// Applies `keymap.toml`, a table of `"cmd-shift-k" = "delete_line"`
+    /// lines. Returns what was wrong with it.
+    fn load_overrides(&mut self) -> Vec<anyhow::Error> {
+        let Some(path) = config_dir().map(|dir| dir.join("keymap.toml")) else {
+            return Vec::new();
+        };
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(error) => return vec![error.into()],
+        };
+        self.apply_overrides(&source)
+    }
+
+    /// Applies the bindings of a `keymap.toml` source, see `load_overrides`.
+    fn apply_overrides(&mut self, source: &str) -> Vec<anyhow::Error> {
+        let table: toml::Table = match toml::from_str(source) {
+            Ok(table) => table,
+            Err(error) => return vec![error.into()],
+        };
+        let mut errors = Vec::new();
+        for (keys, value) in table {
+            let binding = parse_keystroke(&keys).and_then(|id| {
+                let name = value
+                    .as_str()
+                    .with_context(|| format!("{keys} should be an action name"))?;
+                let action = EditorAction::from_name(name)
+                    .with_context(|| format!("unknown action {name} for {keys}"))?;
+                Ok((id, action))
+            });
+            match binding {
+                Ok((id, action)) => {
+                    self.bindings.insert(id, action);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        errors
+    }
+
+    pub fn action_for(&self, keystroke: &Keystroke) -> Option<EditorAction> {
+        self.bindings
+            .get(&keystroke_id(&keystroke.modifiers, &keystroke.key))
+            .copied()
+    }
+}
+
+/// The modifiers and key in one fixed order, so `shift-cmd-k` and
+/// `cmd-shift-k` are the same binding.
+fn keystroke_id(modifiers: &Modifiers, key: &str) -> String {
+    let mut id = String::new();
+    for (held, name) in [
+        (modifiers.control, "ctrl-"),
+        (modifiers.alt, "alt-"),
+        (modifiers.shift, "shift-"),
+        (modifiers.command, "cmd-"),
+        (modifiers.function, "fn-"),
+    ] {
+        if held {
+            id.push_str(name);
+        }
+    }
+    id.push_str(key);
+    id
+}
+
+/// Reads keys written like `cmd-shift-k`, the key last.
+fn parse_keystroke(source: &str) -> anyhow::Result<String> {
+    // a binding of the minus key ends in "--"
+    let (modifiers, key) = match source.strip_suffix("--") {
+        Some(modifiers) => (modifiers, "-"),
+        None => source.rsplit_once('-').unwrap_or(("", source)),
+    };
+    if key.is_empty() {
+        bail!("{source:?} has no key");
+    }
+    let mut parsed = Modifiers::default();
+    for modifier in modifiers.split('-').filter(|modifier| !modifier.is_empty()) {
+        match modifier {
+            "ctrl" | "control" => parsed.control = true,
+            "alt" | "option" => parsed.alt = true,
+            "shift" => parsed.shift = true,
+            "cmd" | "command" | "super" => parsed.command = true,
+            "fn" => parsed.function = true,
+            _ => bail!("unknown modifier {modifier} in {source:?}"),
+        }
+    }
+    Ok(keystroke_id(&parsed, &key.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action_for(keymap: &Keymap, keys: &str) -> Option<EditorAction> {
+        keymap.action_for(&Keystroke::parse(keys).unwrap())
+    }
+
+    #[test]
+    fn modifiers_can_come_in_any_order() {
+        let id = parse_keystroke("cmd-shift-K").unwrap();
+        assert_eq!(id, "shift-cmd-k");
+        assert_eq!(parse_keystroke("shift-command-k").unwrap(), id);
+        let modifiers = Modifiers {
+            command: true,
+            shift: true,
+            ..Default::default()
+        };
+        assert_eq!(keystroke_id(&modifiers, "k"), id);
+        assert_eq!(
+            parse_keystroke("fn-cmd-shift-alt-ctrl-x").unwrap(),
+            "ctrl-alt-shift-cmd-fn-x"
+        );
+    }
+
+    #[test]
+    fn minus_key() {
+        assert_eq!(parse_keystroke("cmd--").unwrap(), "cmd--");
+        assert_eq!(parse_keystroke("ctrl-shift--").unwrap(), "ctrl-shift--");
+    }
+
+    #[test]
+    fn bad_keystrokes() {
+        let error = parse_keystroke("hyper-k").unwrap_err();
+        assert_eq!(error.to_string(), "unknown modifier hyper in \"hyper-k\"");
+        let error = parse_keystroke("cmd-").unwrap_err();
+        assert_eq!(error.to_string(), "\"cmd-\" has no key");
+    }
+
+    #[test]
+    fn every_default_binding_names_a_key() {
+        for (keys, _) in DEFAULT_BINDINGS {
+            assert!(parse_keystroke(keys).is_ok(), "{keys}");
+        }
+        for (name, action) in ACTION_NAMES {
+            assert_eq!(EditorAction::from_name(name), Some(*action));
+        }
+    }
+
+    #[test]
+    fn overrides_replace_defaults_and_skip_mistakes() {
+        let mut keymap = Keymap::with_defaults();
+        assert_eq!(
+            action_for(&keymap, "cmd-shift-k"),
+            Some(EditorAction::DeleteLine)
+        );
+        let errors = keymap.apply_overrides(
+            r#"
+            "cmd-shift-k" = "delete_to_line_end"
+            "alt-j" = "join_lines"
+            "ctrl-k" = "delete_lines"
+            "hyper-k" = "undo"
+            "cmd-y" = 1
+            "#,
+        );
+        let mut errors: Vec<String> = errors.iter().map(|error| format!("{error:#}")).collect();
+        errors.sort();
+        assert_eq!(
+            errors,
+            [
+                "cmd-y should be an action name",
+                "unknown action delete_lines for ctrl-k",
+                "unknown modifier hyper in \"hyper-k\"",
+            ]
+        );
+        assert_eq!(
+            action_for(&keymap, "cmd-shift-k"),
+            Some(EditorAction::DeleteToLineEnd)
+        );
+        assert_eq!(action_for(&keymap, "alt-j"), Some(EditorAction::JoinLines));
+        // a binding that failed leaves the default in place
+        assert_eq!(
+            action_for(&keymap, "ctrl-k"),
+            Some(EditorAction::DeleteToLineEnd)
+        );
+        assert_eq!(action_for(&keymap, "cmd-y"), None);
+    }
+
+    #[test]
+    fn bad_toml_is_one_error() {
+        let mut keymap = Keymap::with_defaults();
+        assert_eq!(keymap.apply_overrides("cmd-k = ").len(), 1);
+        assert_eq!(action_for(&keymap, "cmd-a"), Some(EditorAction::SelectAll));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{ops::Range, path::Path, process::Command, sync::OnceLock, time::Duration};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::process::{run_captured, split_command_line};
+
+/// How long a linter may run, `cargo clippy` builds the crate first.
+const LINT_TIMEOUT: Duration = Duration::from_secs(120);
+/// A linter printing more than this is killed.
+const MAX_LINT_OUTPUT_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Notes, hints and anything else a linter says.
+    Info,
+}
+
+impl Severity {
+    fn parse(level: &str) -> Self {
+        match level.to_ascii_lowercase().as_str() {
+            level if level.starts_with("error") || level == "fatal" => Self::Error,
+            "warning" | "warn" => Self::Warning,
+            _ => Self::Info,
+        }
+    }
+}
+
+/// Something a linter reported about the text, see `TextModel::diagnostics`.
+#[derive(Clone)]
+pub struct Diagnostic {
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Where a linter reported something, before it's placed in the text.
+pub struct LintMessage {
+    /// Zero-based, the column counted in chars.
+    pub row: usize,
+    pub column: usize,
+    /// Where it ends, when the linter says.
+    pub end: Option<(usize, usize)>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Runs `command_line` on `path`, `{file}` in it is replaced by the path, and
+/// reads what it reports about the file from its output. The error is for a
+/// toast: the linter couldn't run, or failed without printing anything this
+/// understands.
+pub fn run_linter(command_line: &str, path: &Path) -> Result<Vec<LintMessage>, String> {
+    let file = path.to_string_lossy();
+    let args: Vec<String> = split_command_line(command_line)
+        .into_iter()
+        .map(|arg| arg.replace("{file}", &file))
+        .collect();
+    let Some((program, args)) = args.split_first() else {
+        return Err("The lint command is empty".to_string());
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(dir) = path.parent() {
+        command.current_dir(dir);
+    }
+    let output = run_captured(command, String::new(), LINT_TIMEOUT, MAX_LINT_OUTPUT_LEN)
+        .map_err(|error| error.message(program))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let mut messages = Vec::new();
+    let mut understood = false;
+    for line in stdout.lines().chain(stderr.lines()) {
+        if let Some(found) = parse_json_line(line, path) {
+            understood = true;
+            messages.extend(found);
+        } else if let Some(found) = parse_text_line(line, path) {
+            understood = true;
+            messages.extend(found);
+        }
+    }
+    // linters exit with 1 when they find something, so only output nothing
+    // here could read counts as failing
+    if !output.status.success() && !understood {
+        let reason = stderr
+            .lines()
+            .chain(stdout.lines())
+            .find(|line| !line.trim().is_empty());
+        return Err(match reason {
+            Some(reason) => format!("{program} failed: {}", reason.trim()),
+            None => format!("{program} failed with {}", output.status),
+        });
+    }
+    Ok(messages)
+}
+
+/// Reads a line of rustc's or cargo's JSON messages, as printed with
+/// `--error-format=json` or `--message-format=json`. `None` for anything else,
+/// an empty list for messages about other files.
+fn parse_json_line(line: &str, path: &Path) -> Option<Vec<LintMessage>> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let value: Value = serde_json::from_str(line).ok()?;
+    let message = match value.get("reason") {
+        Some(reason) if reason == "compiler-message" => value.get("message")?,
+        // e.g. cargo's "compiler-artifact", which says nothing about the text
+        Some(_) => return Some(Vec::new()),
+        None => &value,
+    };
+    let level = message.get("level")?.as_str()?;
+    let text = message.get("message")?.as_str()?;
+    let spans = message.get("spans")?.as_array()?;
+    let number = |span: &Value, key: &str| {
+        let number = span.get(key)?.as_u64()? as usize;
+        // one-based
+        number.checked_sub(1)
+    };
+    Some(
+        spans
+            .iter()
+            .filter(|span| span.get("is_primary").and_then(Value::as_bool) == Some(true))
+            .filter(|span| {
+                span.get("file_name")
+                    .and_then(Value::as_str)
+                    .is_some_and(|file_name| is_same_file(file_name, path))
+            })
+            .filter_map(|span| {
+                let row = number(span, "line_start")?;
+                let column = number(span, "column_start")?;
+                let end = number(span, "line_end").zip(number(span, "column_end"));
+                Some(LintMessage {
+                    row,
+                    column,
+                    end,
+                    severity: Severity::parse(level),
+                    message: text.to_string(),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Reads a line like `file:line:column: severity: message`, as most linters
+/// print them, where only the line number and message are required. `None`
+/// for anything else, an empty list for a line about another file.
+fn parse_text_line(line: &str, path: &Path) -> Option<Vec<LintMessage>> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^(?:(?P<file>[^:\s][^:]*?):)?(?P<line>\d+):(?:(?P<column>\d+):)?\s*(?:(?P<severity>error|warning|warn|info|note|hint)\s*:?\s+)?(?P<message>\S.*)$",
+        )
+        .unwrap()
+    });
+    let captures = pattern.captures(line.trim_end())?;
+    if let Some(file) = captures.name("file") {
+        if !is_same_file(file.as_str(), path) {
+            return Some(Vec::new());
+        }
+    }
+    let row = captures["line"].parse::<usize>().ok()?.checked_sub(1)?;
+    let column = captures
+        .name("column")
+        .and_then(|column| column.as_str().parse::<usize>().ok())
+        .map_or(0, |column| column.saturating_sub(1));
+    let severity = captures
+        .name("severity")
+        .map_or(Severity::Warning, |severity| {
+            Severity::parse(severity.as_str())
+        });
+    Some(vec![LintMessage {
+        row,
+        column,
+        end: None,
+        severity,
+        message: captures["message"].trim().to_string(),
+    }])
+}
+
+/// Whether a linter's `file_name` is `path`, linters report them relative to
+/// where they run or the project's root.
+fn is_same_file(file_name: &str, path: &Path) -> bool {
+    let file_name = Path::new(file_name);
+    if file_name.is_absolute() {
+        file_name == path
+    } else {
+        path.ends_with(file_name.strip_prefix("./").unwrap_or(file_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/project/src/main.rs";
+
+    type Summary = (usize, usize, Option<(usize, usize)>, &'static str, String);
+
+    fn summary(messages: Vec<LintMessage>) -> Vec<Summary> {
+        messages
+            .into_iter()
+            .map(|message| {
+                let severity = match message.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Info => "info",
+                };
+                (
+                    message.row,
+                    message.column,
+                    message.end,
+                    severity,
+                    message.message,
+                )
+            })
+            .collect()
+    }
+
+    fn json(line: &str) -> Option<Vec<Summary>> {
+        parse_json_line(line, Path::new(PATH)).map(summary)
+    }
+
+    fn text(line: &str) -> Option<Vec<Summary>> {
+        parse_text_line(line, Path::new(PATH)).map(summary)
+    }
+
+    #[test]
+    fn cargo_json() {
+        let line = r#"{"reason":"compiler-message","message":{"level":"warning","message":"unused variable: `x`","spans":[{"file_name":"src/main.rs","is_primary":true,"line_start":3,"line_end":3,"column_start":9,"column_end":10},{"file_name":"src/main.rs","is_primary":false,"line_start":1,"line_end":1,"column_start":1,"column_end":2}]}}"#;
+        assert_eq!(
+            json(line).unwrap(),
+            [(
+                2,
+                8,
+                Some((2, 9)),
+                "warning",
+                "unused variable: `x`".to_string()
+            )]
+        );
+        let artifact = r#"{"reason":"compiler-artifact","target":{"name":"ted"}}"#;
+        assert_eq!(json(artifact).unwrap(), []);
+    }
+
+    #[test]
+    fn rustc_json() {
+        let line = r#"{"message":"mismatched types","level":"error","spans":[{"file_name":"/project/src/main.rs","is_primary":true,"line_start":1,"line_end":2,"column_start":1,"column_end":4}]}"#;
+        assert_eq!(
+            json(line).unwrap(),
+            [(0, 0, Some((1, 3)), "error", "mismatched types".to_string())]
+        );
+        let other_file = line.replace("/project/src/main.rs", "/project/src/lib.rs");
+        assert_eq!(json(&other_file).unwrap(), []);
+    }
+
+    #[test]
+    fn lines_that_arent_json_messages() {
+        assert!(json("   Compiling ted v0.1.0").is_none());
+        assert!(json("{not json").is_none());
+        assert!(json(r#"{"level":"warning"}"#).is_none());
+    }
+
+    #[test]
+    fn text_lines() {
+        assert_eq!(
+            text("src/main.rs:12:5: error: expected `;`").unwrap(),
+            [(11, 4, None, "error", "expected `;`".to_string())]
+        );
+        assert_eq!(
+            text("./main.rs:3: trailing whitespace ").unwrap(),
+            [(2, 0, None, "warning", "trailing whitespace".to_string())]
+        );
+        assert_eq!(
+            text("/project/src/main.rs:1:2: note: here").unwrap(),
+            [(0, 1, None, "info", "here".to_string())]
+        );
+        assert_eq!(
+            text("7: no file").unwrap(),
+            [(6, 0, None, "warning", "no file".to_string())]
+        );
+    }
+
+    #[test]
+    fn text_lines_about_other_files_or_nothing() {
+        assert_eq!(text("src/lib.rs:1:1: error: elsewhere").unwrap(), []);
+        assert_eq!(
+            text("/other/src/main.rs:1:1: error: elsewhere").unwrap(),
+            []
+        );
+        assert!(text("   Compiling ted v0.1.0").is_none());
+        assert!(text("warning: unused import").is_none());
+        assert!(text("0: line numbers start at 1").is_none());
+    }
+}
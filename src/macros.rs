@@ -0,0 +1,72 @@
+use gpui::*;
+
+use crate::{
+    keymap::EditorAction,
+    toast::{Toast, ToastQueue},
+};
+
+/// One thing a macro does when played, see `TextModel::replay`.
+#[derive(Clone)]
+pub enum MacroStep {
+    Action(EditorAction),
+    /// Typed text, replayed as text so the keyboard layout doesn't matter.
+    Insert(String),
+    /// A key that vim mode handled, see `Vim::handle_key`.
+    VimKey(Keystroke),
+}
+
+/// The macro being recorded and the last one that was, a global so the
+/// commands can reach them.
+#[derive(Default)]
+pub struct MacroRecorder {
+    /// The text the keys are recorded from and the steps so far.
+    recording: Option<(EntityId, Vec<MacroStep>)>,
+    last: Vec<MacroStep>,
+}
+
+impl MacroRecorder {
+    pub fn init(cx: &mut AppContext) {
+        cx.set_global(MacroRecorder::default())
+    }
+
+    /// Whether the keys of the text `target` are being recorded.
+    pub fn is_recording(&self, target: EntityId) -> bool {
+        self.recording.as_ref().is_some_and(|(id, _)| *id == target)
+    }
+
+    pub fn is_recording_any(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Adds `step` to the macro when it's recorded from `target`.
+    pub fn record(&mut self, target: EntityId, step: MacroStep) {
+        if let Some((id, steps)) = &mut self.recording {
+            if *id == target {
+                steps.push(step);
+            }
+        }
+    }
+
+    /// Starts recording the keys of `target`, or stops a recording and keeps
+    /// it as the macro to play.
+    pub fn toggle(target: EntityId, cx: &mut AppContext) {
+        let recorder = cx.global_mut::<MacroRecorder>();
+        let message = match recorder.recording.take() {
+            Some((_, steps)) => {
+                let message = format!("Recorded a macro of {} steps", steps.len());
+                recorder.last = steps;
+                message
+            }
+            None => {
+                recorder.recording = Some((target, Vec::new()));
+                "Recording a macro".to_string()
+            }
+        };
+        cx.global_mut::<ToastQueue>().push(Toast::info(message));
+    }
+
+    /// The macro that was recorded last.
+    pub fn last(&self) -> &[MacroStep] {
+        &self.last
+    }
+}
@@ -1,13 +1,61 @@
 use app::run_app;
+use args::Command;
 use gpui::App;
 
 mod app;
+mod args;
+mod backup;
+mod buffer;
+mod clipboard_history;
+mod command;
+mod command_palette;
+mod config;
 mod editor;
+mod editorconfig;
+mod file_tree;
+mod format;
+mod fuzzy;
+mod git;
+mod go_to_line;
+mod highlight;
+mod html;
+mod keymap;
+mod lint;
+mod macros;
+mod markdown_preview;
+mod modal;
+mod paste;
+mod process;
+mod project_search;
+mod quick_open;
+mod recent_files;
+mod recovery;
+mod search;
+mod session;
+mod shell_command;
+mod snippet;
+mod spell;
+mod statistics;
 mod theme;
+mod toast;
+#[cfg(feature = "tree-sitter")]
+mod tree_sitter_highlight;
 mod ui;
+mod vim;
 mod window;
 mod workspace;
 
 fn main() {
-    run_app(App::new())
+    let args = match Command::parse(std::env::args().skip(1)) {
+        Ok(Command::Open(args)) => args,
+        Ok(Command::Print(text)) => {
+            println!("{text}");
+            return;
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+    run_app(App::new(), args)
 }
@@ -0,0 +1,412 @@
+use std::time::Duration;
+
+use gpui::*;
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+
+use crate::{
+    theme::Theme,
+    ui::{TextEvent, TextInput, TextModel},
+};
+
+/// How long typing has to pause before the preview catches up.
+const PREVIEW_DELAY: Duration = Duration::from_millis(300);
+
+/// A run of text in a paragraph or heading.
+struct Span {
+    text: String,
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: bool,
+}
+
+enum Block {
+    Heading(HeadingLevel, Vec<Span>),
+    Paragraph(Vec<Span>),
+    Code(String),
+    /// The number of the first item for ordered lists, and the blocks of
+    /// each item.
+    List(Option<u64>, Vec<Vec<Block>>),
+    Quote(Vec<Block>),
+    Rule,
+}
+
+/// What the blocks being parsed go into.
+enum Container {
+    Blocks(Vec<Block>),
+    List(Option<u64>, Vec<Vec<Block>>),
+}
+
+/// Turns the events of `pulldown_cmark` into blocks, keeping track of the
+/// containers and inline styles it's in.
+#[derive(Default)]
+struct Builder {
+    containers: Vec<Container>,
+    spans: Vec<Span>,
+    heading: Option<HeadingLevel>,
+    code: Option<String>,
+    bold: usize,
+    italic: usize,
+    link: usize,
+}
+
+impl Builder {
+    fn push_span(&mut self, text: &str, code: bool) {
+        self.spans.push(Span {
+            text: text.to_string(),
+            bold: self.bold > 0,
+            italic: self.italic > 0,
+            code,
+            link: self.link > 0,
+        });
+    }
+
+    fn push_block(&mut self, block: Block) {
+        if let Some(Container::Blocks(blocks)) = self.containers.last_mut() {
+            blocks.push(block);
+        }
+    }
+
+    /// Ends the paragraph the spans so far make, items of tight lists and raw
+    /// HTML have text without one.
+    fn flush(&mut self) {
+        if !self.spans.is_empty() {
+            let spans = std::mem::take(&mut self.spans);
+            self.push_block(Block::Paragraph(spans));
+        }
+    }
+
+    fn pop_blocks(&mut self) -> Vec<Block> {
+        self.flush();
+        match self.containers.pop() {
+            Some(Container::Blocks(blocks)) => blocks,
+            _ => Vec::new(),
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph | Tag::HtmlBlock => self.flush(),
+                Tag::Heading { level, .. } => {
+                    self.flush();
+                    self.heading = Some(level);
+                }
+                Tag::CodeBlock(_) => {
+                    self.flush();
+                    self.code = Some(String::new());
+                }
+                Tag::BlockQuote | Tag::Item => {
+                    self.flush();
+                    self.containers.push(Container::Blocks(Vec::new()));
+                }
+                Tag::List(start) => {
+                    self.flush();
+                    self.containers.push(Container::List(start, Vec::new()));
+                }
+                Tag::Emphasis => self.italic += 1,
+                Tag::Strong => self.bold += 1,
+                Tag::Link { .. } => self.link += 1,
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph | TagEnd::HtmlBlock => self.flush(),
+                TagEnd::Heading(_) => {
+                    let spans = std::mem::take(&mut self.spans);
+                    let level = self.heading.take().unwrap_or(HeadingLevel::H1);
+                    self.push_block(Block::Heading(level, spans));
+                }
+                TagEnd::CodeBlock => {
+                    let code = self.code.take().unwrap_or_default();
+                    self.push_block(Block::Code(code.trim_end_matches('\n').to_string()));
+                }
+                TagEnd::BlockQuote => {
+                    let blocks = self.pop_blocks();
+                    self.push_block(Block::Quote(blocks));
+                }
+                TagEnd::Item => {
+                    let blocks = self.pop_blocks();
+                    if let Some(Container::List(_, items)) = self.containers.last_mut() {
+                        items.push(blocks);
+                    }
+                }
+                TagEnd::List(_) => {
+                    self.flush();
+                    if let Some(Container::List(start, items)) = self.containers.pop() {
+                        self.push_block(Block::List(start, items));
+                    }
+                }
+                TagEnd::Emphasis => self.italic = self.italic.saturating_sub(1),
+                TagEnd::Strong => self.bold = self.bold.saturating_sub(1),
+                TagEnd::Link => self.link = self.link.saturating_sub(1),
+                _ => {}
+            },
+            Event::Text(text) => match &mut self.code {
+                Some(code) => code.push_str(&text),
+                None => self.push_span(&text, false),
+            },
+            Event::Code(text) => self.push_span(&text, true),
+            // shown as it's written, never interpreted
+            Event::Html(html) | Event::InlineHtml(html) => self.push_span(&html, false),
+            Event::SoftBreak => self.push_span(" ", false),
+            Event::HardBreak => self.push_span("\n", false),
+            Event::Rule => {
+                self.flush();
+                self.push_block(Block::Rule);
+            }
+            Event::TaskListMarker(checked) => {
+                self.push_span(if checked { "☑ " } else { "☐ " }, false)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The blocks of a Markdown document, CommonMark without extensions.
+fn parse_markdown(text: &str) -> Vec<Block> {
+    let mut builder = Builder {
+        containers: vec![Container::Blocks(Vec::new())],
+        ..Default::default()
+    };
+    for event in Parser::new(text) {
+        builder.event(event);
+    }
+    builder.pop_blocks()
+}
+
+/// The editor's Markdown rendered next to it, see `Workspace::toggle_markdown_preview`.
+/// It follows edits once typing pauses, and the editor's scroll position by
+/// the share of the document scrolled past.
+pub struct MarkdownPreview {
+    model: Model<TextModel>,
+    blocks: Vec<Block>,
+    scroll_handle: ScrollHandle,
+    /// The share of the editor's text above its viewport the preview last
+    /// scrolled to, scrolling the preview by hand sticks until the editor
+    /// scrolls again.
+    synced_fraction: f32,
+    parse_task: Option<Task<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl MarkdownPreview {
+    pub fn new(input: &TextInput, cx: &mut ViewContext<Self>) -> Self {
+        let model = input.model.clone();
+        let subscriptions = vec![
+            cx.subscribe(&model, |this, _model, event, cx| {
+                if let TextEvent::Edited { .. } = event {
+                    this.schedule_parse(PREVIEW_DELAY, cx);
+                }
+            }),
+            cx.observe(&input.scroll_state(cx), |this, scroll, cx| {
+                let scroll = scroll.read(cx);
+                let (offset, content_size) = (scroll.offset, scroll.content_size);
+                this.sync_scroll(offset, content_size, cx);
+            }),
+        ];
+        let mut preview = Self {
+            model,
+            blocks: Vec::new(),
+            scroll_handle: ScrollHandle::new(),
+            synced_fraction: 0.,
+            parse_task: None,
+            _subscriptions: subscriptions,
+        };
+        preview.schedule_parse(Duration::ZERO, cx);
+        preview
+    }
+
+    /// Whether this previews `model`.
+    pub fn shows(&self, model: &Model<TextModel>) -> bool {
+        self.model.entity_id() == model.entity_id()
+    }
+
+    /// Parses the text in the background after `delay`, a new edit starts
+    /// the wait over.
+    fn schedule_parse(&mut self, delay: Duration, cx: &mut ViewContext<Self>) {
+        self.parse_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(delay).await;
+            let Ok(text) = this.update(&mut cx, |this, cx| this.model.read(cx).text()) else {
+                return;
+            };
+            let blocks = cx
+                .background_executor()
+                .spawn(async move { parse_markdown(&text) })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.blocks = blocks;
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// Scrolls as far into the preview as the editor is into its text.
+    fn sync_scroll(&mut self, offset: Pixels, content_size: Pixels, cx: &mut ViewContext<Self>) {
+        if content_size <= px(0.) {
+            return;
+        }
+        let fraction = offset / content_size;
+        // the scrollbar fading out notifies too
+        if (fraction - self.synced_fraction).abs() < f32::EPSILON {
+            return;
+        }
+        self.synced_fraction = fraction;
+        let (Some(first), Some(last)) = (
+            self.scroll_handle.bounds_for_item(0),
+            self.blocks
+                .len()
+                .checked_sub(1)
+                .and_then(|last| self.scroll_handle.bounds_for_item(last)),
+        ) else {
+            return;
+        };
+        let height = last.bottom() - first.top();
+        self.scroll_handle
+            .set_offset(point(px(0.), -(height * fraction)));
+        cx.notify();
+    }
+}
+
+/// The theme's colors for the parts of a document.
+struct PreviewColors {
+    text: Hsla,
+    muted: Hsla,
+    link: Hsla,
+    code_background: Hsla,
+    border: Hsla,
+}
+
+impl Render for MarkdownPreview {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let colors = PreviewColors {
+            text: theme.text_color,
+            muted: theme.muted_color,
+            link: theme.primary_color,
+            code_background: theme.panel_color,
+            border: theme.border_color,
+        };
+        let code_font = theme.editor_font_family.clone();
+        let background_color = theme.background_color;
+        let mut text_style = cx.text_style();
+        text_style.color = colors.text;
+
+        div()
+            .id("markdown-preview")
+            .size_full()
+            .overflow_y_scroll()
+            .track_scroll(&self.scroll_handle)
+            .bg(background_color)
+            .px_6()
+            .py_4()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .text_color(colors.text)
+            .children(
+                self.blocks
+                    .iter()
+                    .map(|block| render_block(block, &colors, &code_font, &text_style)),
+            )
+    }
+}
+
+fn render_block(
+    block: &Block,
+    colors: &PreviewColors,
+    code_font: &SharedString,
+    text_style: &TextStyle,
+) -> AnyElement {
+    let blocks = |blocks: &[Block], text_style: &TextStyle| {
+        div().flex().flex_col().gap_2().min_w_0().children(
+            blocks
+                .iter()
+                .map(|block| render_block(block, colors, code_font, text_style)),
+        )
+    };
+    match block {
+        Block::Heading(level, spans) => {
+            let heading = div().child(render_spans(spans, true, colors, text_style));
+            match level {
+                HeadingLevel::H1 => heading.text_2xl(),
+                HeadingLevel::H2 => heading.text_xl(),
+                HeadingLevel::H3 => heading.text_lg(),
+                _ => heading,
+            }
+            .into_any_element()
+        }
+        Block::Paragraph(spans) => div()
+            .child(render_spans(spans, false, colors, text_style))
+            .into_any_element(),
+        Block::Code(code) => div()
+            .p_3()
+            .rounded_md()
+            .bg(colors.code_background)
+            .font(code_font.clone())
+            .text_sm()
+            .overflow_hidden()
+            .child(code.clone())
+            .into_any_element(),
+        Block::List(start, items) => div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .children(items.iter().enumerate().map(|(i, item)| {
+                let marker = match start {
+                    Some(start) => format!("{}.", start + i as u64),
+                    None => "•".to_string(),
+                };
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(div().flex_none().text_color(colors.muted).child(marker))
+                    .child(blocks(item, text_style))
+            }))
+            .into_any_element(),
+        Block::Quote(quoted) => {
+            let mut text_style = text_style.clone();
+            text_style.color = colors.muted;
+            div()
+                .pl_3()
+                .border_l_2()
+                .border_color(colors.border)
+                .text_color(colors.muted)
+                .child(blocks(quoted, &text_style))
+                .into_any_element()
+        }
+        Block::Rule => div().h(px(1.)).bg(colors.border).into_any_element(),
+    }
+}
+
+/// The spans as one text that wraps as a whole, `bold` for headings.
+fn render_spans(
+    spans: &[Span],
+    bold: bool,
+    colors: &PreviewColors,
+    text_style: &TextStyle,
+) -> StyledText {
+    let mut text = String::new();
+    let mut highlights = Vec::new();
+    for span in spans {
+        let start = text.len();
+        text.push_str(&span.text);
+        if !(bold || span.bold || span.italic || span.code || span.link) {
+            continue;
+        }
+        let style = HighlightStyle {
+            font_weight: (bold || span.bold).then_some(FontWeight::BOLD),
+            font_style: span.italic.then_some(FontStyle::Italic),
+            color: span.link.then_some(colors.link),
+            background_color: span.code.then_some(colors.code_background),
+            underline: span.link.then_some(UnderlineStyle {
+                thickness: px(1.),
+                color: Some(colors.link),
+                wavy: false,
+            }),
+            ..Default::default()
+        };
+        highlights.push((start..text.len(), style));
+    }
+    StyledText::new(text).with_highlights(text_style, highlights)
+}
@@ -0,0 +1,292 @@
+use std::rc::Rc;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    theme::Theme,
+    ui::{Background, Button, ButtonVariant},
+};
+
+/// One of the buttons of a `Modal`.
+#[derive(Clone)]
+struct ModalAction {
+    label: SharedString,
+    variant: ButtonVariant,
+    on_click: Rc<dyn Fn(&mut WindowContext)>,
+}
+
+/// A line of a `Modal` above its buttons, e.g. a file, with its own buttons.
+#[derive(Clone)]
+struct ModalRow {
+    label: SharedString,
+    actions: Vec<ModalAction>,
+}
+
+/// A dialog in front of the whole window. While it's open the focus stays on
+/// its buttons, tab moves between them, enter clicks the focused one and
+/// escape cancels.
+#[derive(Clone)]
+pub struct Modal {
+    title: SharedString,
+    message: SharedString,
+    rows: Vec<ModalRow>,
+    actions: Vec<ModalAction>,
+    on_cancel: Rc<dyn Fn(&mut WindowContext)>,
+}
+
+/// The open modal, a global so any part of the app can open one.
+#[derive(Default)]
+pub struct ModalState {
+    modal: Option<Modal>,
+    /// One for each action of the modal, the rows' first.
+    focus_handles: Vec<FocusHandle>,
+    /// Gets the focus back when the modal closes.
+    previous_focus: Option<FocusHandle>,
+}
+
+impl ModalState {
+    pub fn init(cx: &mut AppContext) {
+        cx.set_global(ModalState::default())
+    }
+}
+
+impl Modal {
+    /// A modal without buttons yet, `on_cancel` runs when escape closes it.
+    pub fn new(
+        title: impl Into<SharedString>,
+        message: impl Into<SharedString>,
+        on_cancel: impl Fn(&mut WindowContext) + 'static,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            rows: Vec::new(),
+            actions: Vec::new(),
+            on_cancel: Rc::new(on_cancel),
+        }
+    }
+
+    /// Asks `message`, with a Cancel and an OK button.
+    pub fn confirm(
+        title: impl Into<SharedString>,
+        message: impl Into<SharedString>,
+        on_confirm: impl Fn(&mut WindowContext) + 'static,
+        on_cancel: impl Fn(&mut WindowContext) + 'static,
+    ) -> Self {
+        let on_cancel = Rc::new(on_cancel);
+        Self::new(title, message, {
+            let on_cancel = on_cancel.clone();
+            move |cx| on_cancel(cx)
+        })
+        .action("Cancel", ButtonVariant::Secondary, move |cx| on_cancel(cx))
+        .action("OK", ButtonVariant::Primary, on_confirm)
+    }
+
+    /// Adds a button after the others, clicking it closes the modal first.
+    pub fn action(
+        mut self,
+        label: impl Into<SharedString>,
+        variant: ButtonVariant,
+        on_click: impl Fn(&mut WindowContext) + 'static,
+    ) -> Self {
+        self.actions.push(ModalAction {
+            label: label.into(),
+            variant,
+            on_click: Rc::new(on_click),
+        });
+        self
+    }
+
+    /// Adds a line below the message and the other lines.
+    pub fn row(mut self, label: impl Into<SharedString>) -> Self {
+        self.rows.push(ModalRow {
+            label: label.into(),
+            actions: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a button to the end of the last line, clicking it closes the
+    /// modal first.
+    pub fn row_action(
+        mut self,
+        label: impl Into<SharedString>,
+        variant: ButtonVariant,
+        on_click: impl Fn(&mut WindowContext) + 'static,
+    ) -> Self {
+        if let Some(row) = self.rows.last_mut() {
+            row.actions.push(ModalAction {
+                label: label.into(),
+                variant,
+                on_click: Rc::new(on_click),
+            });
+        }
+        self
+    }
+
+    /// Shows the modal in place of any open one, with the focus on the first
+    /// primary button, or else the last one.
+    pub fn open(self, cx: &mut WindowContext) {
+        let row_actions: usize = self.rows.iter().map(|row| row.actions.len()).sum();
+        let focus_handles: Vec<FocusHandle> = (0..row_actions + self.actions.len())
+            .map(|_| cx.focus_handle())
+            .collect();
+        let default = self
+            .actions
+            .iter()
+            .position(|action| matches!(action.variant, ButtonVariant::Primary))
+            .or(self.actions.len().checked_sub(1))
+            .map(|i| row_actions + i);
+        // a modal replacing another one gives the focus back to what was before both
+        let previous_focus = match cx.global::<ModalState>().modal {
+            Some(_) => cx.global::<ModalState>().previous_focus.clone(),
+            None => cx.focused(),
+        };
+        if let Some(focus_handle) = default.map(|i| &focus_handles[i]) {
+            cx.focus(focus_handle);
+        }
+        cx.set_global(ModalState {
+            modal: Some(self),
+            focus_handles,
+            previous_focus,
+        });
+    }
+
+    /// Closes the open modal and gives the focus back to where it was.
+    pub fn close(cx: &mut WindowContext) {
+        let previous_focus = cx.global_mut::<ModalState>().previous_focus.take();
+        cx.set_global(ModalState::default());
+        if let Some(focus_handle) = previous_focus {
+            cx.focus(&focus_handle);
+        }
+    }
+
+    /// Moves the focus to the next button, or the previous one, and never
+    /// out of the modal.
+    fn cycle_focus(reverse: bool, cx: &mut WindowContext) {
+        let focus_handles = cx.global::<ModalState>().focus_handles.clone();
+        let count = focus_handles.len();
+        if count == 0 {
+            return;
+        }
+        let focused = focus_handles
+            .iter()
+            .position(|handle| handle.is_focused(cx))
+            .unwrap_or(count - 1);
+        let next = if reverse {
+            (focused + count - 1) % count
+        } else {
+            (focused + 1) % count
+        };
+        cx.focus(&focus_handles[next]);
+    }
+}
+
+/// Shows the open modal, if any. It goes last in the window so it covers
+/// everything else.
+#[derive(IntoElement)]
+pub struct ModalLayer;
+
+impl RenderOnce for ModalLayer {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let state = cx.global::<ModalState>();
+        let Some(modal) = state.modal.clone() else {
+            return div();
+        };
+        let mut focus_handles = state.focus_handles.clone().into_iter();
+        let theme = cx.global::<Theme>();
+        let (border_color, backdrop_color) = (theme.border_color, theme.backdrop_color);
+        let on_cancel = modal.on_cancel.clone();
+        // the rows' buttons first, in the order of `focus_handles`
+        let mut index = 0;
+        let mut button = move |action: ModalAction| {
+            let focus_handle = focus_handles.next().unwrap_or_else(|| cx.focus_handle());
+            let button = action_button(index, action, &focus_handle);
+            index += 1;
+            button
+        };
+        let rows: Vec<Div> = modal
+            .rows
+            .into_iter()
+            .map(|row| {
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(
+                        div()
+                            .flex_1()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(row.label),
+                    )
+                    .children(row.actions.into_iter().map(&mut button))
+            })
+            .collect();
+        let actions: Vec<Button> = modal.actions.into_iter().map(&mut button).collect();
+        let wide = !rows.is_empty();
+
+        // covers the whole window so nothing behind it can be clicked
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .bg(backdrop_color)
+            .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+            .on_key_down(
+                move |ev: &KeyDownEvent, cx| match ev.keystroke.key.as_str() {
+                    "tab" => Modal::cycle_focus(ev.keystroke.modifiers.shift, cx),
+                    "escape" => {
+                        Modal::close(cx);
+                        on_cancel(cx);
+                    }
+                    _ => {}
+                },
+            )
+            .child(
+                div()
+                    .w_80()
+                    .when(wide, |this| this.w(px(480.)))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .child(
+                        Background::new().child(
+                            div()
+                                .p_4()
+                                .flex()
+                                .flex_col()
+                                .gap_4()
+                                .text_sm()
+                                .child(div().font_weight(FontWeight::BOLD).child(modal.title))
+                                .child(modal.message)
+                                .when(wide, |this| {
+                                    this.child(div().flex().flex_col().gap_2().children(rows))
+                                })
+                                .child(div().flex().justify_end().gap_2().children(actions)),
+                        ),
+                    ),
+            )
+    }
+}
+
+/// A button of the modal, clicking it closes the modal first.
+fn action_button(index: usize, action: ModalAction, focus_handle: &FocusHandle) -> Button {
+    let on_click = action.on_click;
+    Button::new(
+        ("modal-action", index),
+        action.label,
+        Box::new(move |_ev, cx| {
+            Modal::close(cx);
+            on_click(cx);
+        }),
+    )
+    .variant(action.variant)
+    .focus_handle(focus_handle)
+    .h_6()
+}
@@ -0,0 +1,102 @@
+use crate::config::EditorSettings;
+
+/// Cleans up text copied from browsers and chat apps before it's pasted,
+/// each step as far as `settings` turn it on.
+pub fn normalize_pasted_text(text: &str, settings: &EditorSettings) -> String {
+    let mut text = text.to_string();
+    if settings.paste_normalize_line_endings {
+        text = normalize_line_endings(&text);
+    }
+    if settings.paste_straighten_quotes {
+        text = straighten_quotes(&text);
+    }
+    if settings.paste_replace_nbsp {
+        text = replace_nbsp(&text);
+    }
+    if settings.paste_strip_control_chars {
+        text = strip_control_chars(&text);
+    }
+    text
+}
+
+/// Turns `\r\n` and lone `\r` into `\n`.
+pub fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Turns curly quotes into the straight ones code is written with.
+pub fn straighten_quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{201c}' | '\u{201d}' => '"',
+            '\u{2018}' | '\u{2019}' => '\'',
+            c => c,
+        })
+        .collect()
+}
+
+/// Turns no-break spaces into plain ones.
+pub fn replace_nbsp(text: &str) -> String {
+    text.replace('\u{a0}', " ")
+}
+
+/// Drops the C0 control chars apart from tabs and newlines, carriage returns
+/// are left to `normalize_line_endings`.
+pub fn strip_control_chars(text: &str) -> String {
+    text.chars()
+        .filter(|c| !matches!(c, '\u{0}'..='\u{1f}') || matches!(c, '\t' | '\n' | '\r'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_line_endings() {
+        assert_eq!(normalize_line_endings("a\r\nb\rc\nd"), "a\nb\nc\nd");
+        assert_eq!(normalize_line_endings("\r\r\n"), "\n\n");
+    }
+
+    #[test]
+    fn straightens_quotes() {
+        assert_eq!(
+            straighten_quotes("\u{201c}it\u{2019}s\u{201d} \u{2018}x\u{2019}"),
+            "\"it's\" 'x'"
+        );
+    }
+
+    #[test]
+    fn replaces_nbsp() {
+        assert_eq!(replace_nbsp("a\u{a0}b\u{a0}"), "a b ");
+    }
+
+    #[test]
+    fn strips_control_chars() {
+        assert_eq!(
+            strip_control_chars("a\u{0}b\u{7}\tc\u{1b}[0m\r\n\u{1f}é"),
+            "ab\tc[0m\r\né"
+        );
+        // DEL and C1 controls aren't C0
+        assert_eq!(strip_control_chars("\u{7f}\u{85}"), "\u{7f}\u{85}");
+    }
+
+    #[test]
+    fn normalizes_as_far_as_settings_say() {
+        let text = "\u{201c}a\u{201d}\u{a0}\u{0}\r\n";
+        let mut settings = EditorSettings::default();
+        assert_eq!(
+            normalize_pasted_text(text, &settings),
+            "\u{201c}a\u{201d} \n"
+        );
+
+        settings.paste_straighten_quotes = true;
+        assert_eq!(normalize_pasted_text(text, &settings), "\"a\" \n");
+
+        settings.paste_normalize_line_endings = false;
+        settings.paste_replace_nbsp = false;
+        settings.paste_strip_control_chars = false;
+        settings.paste_straighten_quotes = false;
+        assert_eq!(normalize_pasted_text(text, &settings), text);
+    }
+}
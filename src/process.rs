@@ -0,0 +1,203 @@
+use std::{
+    io::{self, Read, Write},
+    process::{Command, ExitStatus, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often a running command is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Why `run_piped` has no output to give.
+pub enum RunError {
+    Spawn(io::Error),
+    /// It ran longer than allowed and was killed.
+    TimedOut(Duration),
+    /// It printed more than allowed and was killed.
+    TooMuchOutput(usize),
+    Failed {
+        status: ExitStatus,
+        stderr: String,
+    },
+    InvalidUtf8,
+}
+
+impl RunError {
+    /// What went wrong, for a toast. `name` is what the user knows the
+    /// command as.
+    pub fn message(&self, name: &str) -> String {
+        match self {
+            Self::Spawn(error) => format!("Couldn't run {name}: {error}"),
+            Self::TimedOut(timeout) => format!(
+                "{name} took longer than {}s and was stopped",
+                timeout.as_secs()
+            ),
+            Self::TooMuchOutput(max_len) => format!(
+                "{name} printed more than {} MB and was stopped",
+                max_len / (1024 * 1024)
+            ),
+            Self::Failed { status, stderr } if stderr.is_empty() => {
+                format!("{name} failed with {status}")
+            }
+            Self::Failed { stderr, .. } => format!("{name} failed: {stderr}"),
+            Self::InvalidUtf8 => format!("{name} printed invalid UTF-8"),
+        }
+    }
+}
+
+/// Runs `command` with `input` on its stdin and returns what it printed when
+/// it exits with 0. It's killed once it runs longer than `timeout` or prints
+/// more than `max_output_len` bytes. Blocks until it's done, so it belongs on
+/// a background task.
+pub fn run_piped(
+    command: Command,
+    input: String,
+    timeout: Duration,
+    max_output_len: usize,
+) -> Result<String, RunError> {
+    let output = run_captured(command, input, timeout, max_output_len)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(RunError::Failed {
+            status: output.status,
+            stderr,
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| RunError::InvalidUtf8)
+}
+
+/// Like `run_piped`, but returns what the command printed however it exits,
+/// e.g. for linters that exit with 1 when they found something.
+pub fn run_captured(
+    mut command: Command,
+    input: String,
+    timeout: Duration,
+    max_output_len: usize,
+) -> Result<Output, RunError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(RunError::Spawn)?;
+
+    // each pipe gets a thread, a command blocked on a full pipe while we
+    // wait on another would never finish
+    let stdin = child.stdin.take();
+    thread::spawn(move || {
+        // dropping stdin closes it, which tells the command the input ended
+        if let Some(mut stdin) = stdin {
+            stdin.write_all(input.as_bytes()).ok();
+        }
+    });
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let stdout = read_on_thread(child.stdout.take(), max_output_len, overflowed.clone());
+    let stderr = read_on_thread(child.stderr.take(), max_output_len, overflowed.clone());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if overflowed.load(Ordering::Relaxed) {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(RunError::TooMuchOutput(max_output_len));
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            Ok(None) => {
+                child.kill().ok();
+                child.wait().ok();
+                return Err(RunError::TimedOut(timeout));
+            }
+            Err(error) => return Err(RunError::Spawn(error)),
+        }
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if overflowed.load(Ordering::Relaxed) {
+        return Err(RunError::TooMuchOutput(max_output_len));
+    }
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Reads all of `pipe` on a thread of its own, up to `max_len` bytes. Sets
+/// `overflowed` when there's more.
+fn read_on_thread(
+    pipe: Option<impl Read + Send + 'static>,
+    max_len: usize,
+    overflowed: Arc<AtomicBool>,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(pipe) = pipe {
+            let limit = (max_len as u64).saturating_add(1);
+            pipe.take(limit).read_to_end(&mut output).ok();
+            if output.len() > max_len {
+                overflowed.store(true, Ordering::Relaxed);
+            }
+        }
+        output
+    })
+}
+
+/// A command that runs `command_line` with the user's shell, `sh -c` or
+/// `cmd /C` on Windows.
+pub fn shell_command(command_line: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(command_line);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    }
+}
+
+/// Splits a command line at whitespace, like a shell would without any of the
+/// expansions: quotes keep whitespace in an argument and a backslash escapes
+/// the char after it, except between single quotes.
+pub fn split_command_line(command_line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    // whether there is an argument, `""` is an empty one
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = command_line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                current.extend(chars.next());
+                in_arg = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
@@ -0,0 +1,639 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use gpui::*;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::EditorSettings,
+    theme::Theme,
+    ui::{Background, Button, ButtonVariant, List, ListEvent, TextEvent, TextInput},
+};
+
+/// The height of a file or a line in the results.
+const ROW_HEIGHT: Pixels = px(24.);
+/// More results than this scroll.
+const MAX_VISIBLE_ROWS: usize = 16;
+/// The search stops looking for files after this many.
+const MAX_SEARCHED_FILES: usize = 20_000;
+/// Bigger files are skipped, they're rarely what's searched for.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// A file with a zero byte in this many bytes at its start is taken for a
+/// binary one and skipped.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+/// How many files a background thread searches before its results are shown.
+const FILES_PER_CHUNK: usize = 64;
+/// The search stops once it found this many matches.
+const MAX_MATCHES: usize = 10_000;
+/// Longer lines are cut in the results.
+const MAX_LINE_LEN: usize = 200;
+
+pub enum ProjectSearchEvent {
+    /// A result was chosen, the owner opens its file with the cursor at the
+    /// zero-based `row` and char `column`, and hides the panel.
+    Open {
+        path: PathBuf,
+        row: usize,
+        column: usize,
+    },
+    /// Escape was pressed or the mouse went down outside. The owner hides the
+    /// panel, it keeps its results for the next time.
+    Dismissed,
+}
+
+/// A line with matches.
+struct LineMatch {
+    /// Zero-based.
+    row: usize,
+    /// The char column of the first match.
+    column: usize,
+    /// The line without its indentation, cut at `MAX_LINE_LEN`.
+    text: String,
+    /// Where the matches are in `text`.
+    ranges: Vec<Range<usize>>,
+}
+
+/// A file with matches.
+struct FileMatches {
+    path: PathBuf,
+    lines: Vec<LineMatch>,
+}
+
+/// A line of the results: a file, or one of its lines under it.
+enum ResultRow {
+    File(usize),
+    Line(usize, usize),
+}
+
+/// Searches the files in a folder for a text or a regex, a panel with an
+/// input above the results grouped by file. The files are searched on
+/// background threads and the results show up while it runs, a new query
+/// stops the search before. Up and down move through the results while the
+/// input keeps the focus, enter opens one.
+pub struct ProjectSearch {
+    root: PathBuf,
+    input: TextInput,
+    list: View<List>,
+    case_sensitive: bool,
+    /// Whether the query is a regular expression.
+    regex: bool,
+    /// Set when the query doesn't compile, nothing is searched then.
+    invalid_regex: bool,
+    /// The texts of the open files with unsaved changes, searched instead of
+    /// what's on disk.
+    unsaved_texts: Arc<HashMap<PathBuf, String>>,
+    results: Vec<FileMatches>,
+    rows: Vec<ResultRow>,
+    match_count: usize,
+    searching: bool,
+    /// Set to stop the search that's running.
+    cancelled: Arc<AtomicBool>,
+    _search: Task<()>,
+}
+
+impl EventEmitter<ProjectSearchEvent> for ProjectSearch {}
+
+impl ProjectSearch {
+    pub fn new(
+        root: PathBuf,
+        unsaved_texts: HashMap<PathBuf, String>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        let search = cx.view().downgrade();
+        let list = cx.new_view(|cx| {
+            let mut list = List::new(
+                0,
+                move |index, _highlighted, cx| {
+                    search.upgrade().map_or_else(
+                        || div().into_any_element(),
+                        |search| search.read(cx).render_row(index, cx),
+                    )
+                },
+                cx,
+            );
+            list.follow_input(&input.model, cx);
+            list
+        });
+        cx.subscribe(&input.model, |this, _model, event, cx| match event {
+            TextEvent::Edited { .. } => this.search(cx),
+            TextEvent::Cancel => cx.emit(ProjectSearchEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        cx.subscribe(&list, |this, _list, event, cx| {
+            if let ListEvent::Activate(index) = event {
+                this.open(*index, cx);
+            }
+        })
+        .detach();
+        input.focus(cx);
+        Self {
+            root,
+            input,
+            list,
+            case_sensitive: false,
+            regex: false,
+            invalid_regex: false,
+            unsaved_texts: Arc::new(unsaved_texts),
+            results: Vec::new(),
+            rows: Vec::new(),
+            match_count: 0,
+            searching: false,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            _search: Task::ready(()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether the focus is still in the panel's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+
+    /// For showing the panel again: searches once more with the texts of the
+    /// unsaved files as they are now.
+    pub fn reopen(&mut self, unsaved_texts: HashMap<PathBuf, String>, cx: &mut ViewContext<Self>) {
+        self.unsaved_texts = Arc::new(unsaved_texts);
+        self.input.focus(cx);
+        self.search(cx);
+    }
+
+    fn toggle_case_sensitive(&mut self, cx: &mut ViewContext<Self>) {
+        self.case_sensitive = !self.case_sensitive;
+        self.search(cx);
+    }
+
+    fn toggle_regex(&mut self, cx: &mut ViewContext<Self>) {
+        self.regex = !self.regex;
+        self.search(cx);
+    }
+
+    /// Stops the search that's running and starts one for the query.
+    fn search(&mut self, cx: &mut ViewContext<Self>) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.cancelled = Arc::new(AtomicBool::new(false));
+        self.results.clear();
+        self.match_count = 0;
+        self.invalid_regex = false;
+        self.searching = false;
+        self._search = Task::ready(());
+
+        let query = self.input.model.read(cx).text();
+        let pattern = if self.regex {
+            query.clone()
+        } else {
+            regex::escape(&query)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build();
+        let regex = match regex {
+            Ok(regex) if !query.is_empty() => regex,
+            Ok(_) => {
+                self.update_rows(cx);
+                return;
+            }
+            Err(_) => {
+                self.invalid_regex = true;
+                self.update_rows(cx);
+                return;
+            }
+        };
+
+        self.searching = true;
+        self.update_rows(cx);
+        let settings = cx.global::<EditorSettings>();
+        let show_hidden = settings.show_hidden_files;
+        let respect_gitignore = settings.respect_gitignore;
+        let root = self.root.clone();
+        let unsaved_texts = self.unsaved_texts.clone();
+        let cancelled = self.cancelled.clone();
+        self._search = cx.spawn(|this, mut cx| async move {
+            let executor = cx.background_executor().clone();
+            let files = executor
+                .spawn({
+                    let unsaved_texts = unsaved_texts.clone();
+                    let cancelled = cancelled.clone();
+                    async move {
+                        list_files(
+                            &root,
+                            &unsaved_texts,
+                            show_hidden,
+                            respect_gitignore,
+                            &cancelled,
+                        )
+                    }
+                })
+                .await;
+            // the chunks run side by side, their results are shown in order
+            let chunks: Vec<Task<Vec<FileMatches>>> = files
+                .chunks(FILES_PER_CHUNK)
+                .map(|chunk| {
+                    let chunk = chunk.to_vec();
+                    let regex = regex.clone();
+                    let unsaved_texts = unsaved_texts.clone();
+                    let cancelled = cancelled.clone();
+                    executor.spawn(async move {
+                        search_files(&chunk, &regex, &unsaved_texts, &cancelled)
+                    })
+                })
+                .collect();
+            for chunk in chunks {
+                let results = chunk.await;
+                let done = this
+                    .update(&mut cx, |this, cx| this.add_results(results, cx))
+                    .unwrap_or(true);
+                if done {
+                    break;
+                }
+            }
+            this.update(&mut cx, |this, cx| {
+                this.searching = false;
+                cx.notify();
+            })
+            .ok();
+        });
+    }
+
+    /// Shows the matches of more files. True once there are `MAX_MATCHES`,
+    /// the search stops there.
+    fn add_results(&mut self, results: Vec<FileMatches>, cx: &mut ViewContext<Self>) -> bool {
+        for file in results {
+            if self.match_count >= MAX_MATCHES {
+                break;
+            }
+            self.match_count += file
+                .lines
+                .iter()
+                .map(|line| line.ranges.len())
+                .sum::<usize>();
+            self.results.push(file);
+        }
+        let done = self.match_count >= MAX_MATCHES;
+        if done {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.update_rows(cx);
+        done
+    }
+
+    /// Lists the files and their lines again, keeping the highlighted row.
+    fn update_rows(&mut self, cx: &mut ViewContext<Self>) {
+        let highlighted = self.list.read(cx).highlighted();
+        let had_rows = !self.rows.is_empty();
+        self.rows.clear();
+        for (file, matches) in self.results.iter().enumerate() {
+            self.rows.push(ResultRow::File(file));
+            self.rows
+                .extend((0..matches.lines.len()).map(|line| ResultRow::Line(file, line)));
+        }
+        let count = self.rows.len();
+        self.list.update(cx, |list, cx| {
+            list.set_item_count(count, cx);
+            // results coming in don't move the highlight
+            if let Some(index) = highlighted.filter(|_| had_rows) {
+                list.highlight(index, cx);
+            }
+        });
+        cx.notify();
+    }
+
+    /// Opens the result at `index`, a file at its first match.
+    fn open(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let (file, line) = match self.rows.get(index) {
+            Some(ResultRow::File(file)) => (*file, 0),
+            Some(ResultRow::Line(file, line)) => (*file, *line),
+            None => return,
+        };
+        let file = &self.results[file];
+        let Some(line) = file.lines.get(line) else {
+            return;
+        };
+        cx.emit(ProjectSearchEvent::Open {
+            path: file.path.clone(),
+            row: line.row,
+            column: line.column,
+        });
+    }
+
+    /// How many matches were found in how many files, and whether the
+    /// search is still running.
+    fn status(&self, cx: &AppContext) -> String {
+        let files = self.results.len();
+        let found = match self.match_count {
+            0 => String::new(),
+            1 => "1 result".to_string(),
+            count if files == 1 => format!("{count} results in 1 file"),
+            count => format!("{count} results in {files} files"),
+        };
+        if self.searching {
+            format!("Searching… {found}").trim_end().to_string()
+        } else if self.input.model.read(cx).is_empty() {
+            String::new()
+        } else if self.match_count == 0 {
+            "No results".to_string()
+        } else if self.match_count >= MAX_MATCHES {
+            format!("{found}, stopped there")
+        } else {
+            found
+        }
+    }
+
+    fn render_row(&self, index: usize, cx: &WindowContext) -> AnyElement {
+        let theme = cx.global::<Theme>();
+        let muted_color = theme.muted_color;
+        let match_color = theme.match_color;
+        let row = div()
+            .h(ROW_HEIGHT)
+            .px_3()
+            .flex()
+            .items_center()
+            .gap_2()
+            .overflow_hidden()
+            .whitespace_nowrap();
+        match self.rows.get(index) {
+            Some(ResultRow::File(file)) => {
+                let file = &self.results[*file];
+                let label = file
+                    .path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(&file.path)
+                    .display()
+                    .to_string();
+                row.child(div().font_weight(FontWeight::BOLD).child(label))
+                    .child(
+                        div()
+                            .text_color(muted_color)
+                            .child(file.lines.len().to_string()),
+                    )
+                    .into_any_element()
+            }
+            Some(ResultRow::Line(file, line)) => {
+                let line = &self.results[*file].lines[*line];
+                // runs of matched and unmatched text
+                let mut runs: Vec<(&str, bool)> = Vec::new();
+                let mut offset = 0;
+                for range in &line.ranges {
+                    runs.push((&line.text[offset..range.start], false));
+                    runs.push((&line.text[range.clone()], true));
+                    offset = range.end;
+                }
+                runs.push((&line.text[offset..], false));
+                row.pl_6()
+                    .child(
+                        div()
+                            .text_color(muted_color)
+                            .child((line.row + 1).to_string()),
+                    )
+                    .child(div().flex().children(
+                        runs.into_iter().filter(|(run, _)| !run.is_empty()).map(
+                            |(run, matched)| {
+                                let run = div().child(run.to_string());
+                                if matched {
+                                    run.bg(match_color)
+                                } else {
+                                    run
+                                }
+                            },
+                        ),
+                    ))
+                    .into_any_element()
+            }
+            None => div().into_any_element(),
+        }
+    }
+}
+
+/// The files to search: the unsaved ones in `root` first, then the others
+/// in it and below. What `.gitignore` files ignore and hidden files are left
+/// out unless the settings say otherwise.
+fn list_files(
+    root: &Path,
+    unsaved_texts: &HashMap<PathBuf, String>,
+    show_hidden: bool,
+    respect_gitignore: bool,
+    cancelled: &AtomicBool,
+) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = unsaved_texts
+        .keys()
+        .filter(|path| path.starts_with(root))
+        .cloned()
+        .collect();
+    let walk = WalkBuilder::new(root)
+        .hidden(!show_hidden)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        // a folder that isn't a repository can still have a `.gitignore`
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != OsStr::new(".git"))
+        .build();
+    for entry in walk {
+        if cancelled.load(Ordering::Relaxed) || files.len() >= MAX_SEARCHED_FILES {
+            break;
+        }
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+            && !unsaved_texts.contains_key(entry.path())
+        {
+            files.push(entry.into_path());
+        }
+    }
+    files
+}
+
+/// The matches of `regex` in `files`, an unsaved file's text is searched
+/// instead of what's on disk. Files too big, binary or not UTF-8 are skipped.
+fn search_files(
+    files: &[PathBuf],
+    regex: &Regex,
+    unsaved_texts: &HashMap<PathBuf, String>,
+    cancelled: &AtomicBool,
+) -> Vec<FileMatches> {
+    let mut results = Vec::new();
+    for path in files {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+        let read;
+        let text = match unsaved_texts.get(path) {
+            Some(text) => text.as_str(),
+            None => {
+                read = read_text_file(path);
+                match &read {
+                    Some(text) => text.as_str(),
+                    None => continue,
+                }
+            }
+        };
+        let lines: Vec<LineMatch> = text
+            .lines()
+            .enumerate()
+            .filter_map(|(row, line)| line_match(row, line, regex))
+            .collect();
+        if !lines.is_empty() {
+            results.push(FileMatches {
+                path: path.clone(),
+                lines,
+            });
+        }
+    }
+    results
+}
+
+/// The text of the file at `path`, unless it's too big, binary or not UTF-8.
+fn read_text_file(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The matches of `regex` in `line`, for the results. Empty matches are left
+/// out, there'd be nothing to highlight.
+fn line_match(row: usize, line: &str, regex: &Regex) -> Option<LineMatch> {
+    let ranges: Vec<Range<usize>> = regex
+        .find_iter(line)
+        .map(|m| m.range())
+        .filter(|range| !range.is_empty())
+        .collect();
+    let first = ranges.first()?.start;
+    let column = line[..first].chars().count();
+
+    // the indentation is left out and long lines are cut
+    let indent = line.len() - line.trim_start().len();
+    let mut end = line.len().min(indent + MAX_LINE_LEN);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    let ranges = ranges
+        .into_iter()
+        .filter(|range| range.start >= indent && range.end <= end)
+        .map(|range| range.start - indent..range.end - indent)
+        .collect();
+    Some(LineMatch {
+        row,
+        column,
+        text: line[indent..end].to_string(),
+        ranges,
+    })
+}
+
+impl Render for ProjectSearch {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let border_color = theme.border_color;
+        let muted_color = theme.muted_color;
+        let rows = self.rows.len().min(MAX_VISIBLE_ROWS);
+        let status = self.status(cx);
+        let view = cx.view().downgrade();
+        let toggle = |id: &'static str,
+                      label: &'static str,
+                      on: bool,
+                      run: fn(&mut Self, &mut ViewContext<Self>)| {
+            let view = view.clone();
+            Button::new(
+                id,
+                label,
+                Box::new(move |_ev, cx| {
+                    view.update(cx, |this, cx| run(this, cx)).ok();
+                }),
+            )
+            .variant(ButtonVariant::Ghost)
+            .compact()
+            .text_sm()
+            .border_1()
+            .border_color(if on {
+                border_color
+            } else {
+                transparent_black()
+            })
+        };
+
+        // covers the window, a press outside the panel hides it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(ProjectSearchEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w(px(640.))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_2()
+                                        .pr_2()
+                                        .child(
+                                            div().flex_1().child(
+                                                self.input.clone().invalid(self.invalid_regex),
+                                            ),
+                                        )
+                                        .child(toggle(
+                                            "project-search-case-sensitive",
+                                            "Aa",
+                                            self.case_sensitive,
+                                            Self::toggle_case_sensitive,
+                                        ))
+                                        .child(toggle(
+                                            "project-search-regex",
+                                            ".*",
+                                            self.regex,
+                                            Self::toggle_regex,
+                                        ))
+                                        .child(
+                                            div()
+                                                .text_color(muted_color)
+                                                .whitespace_nowrap()
+                                                .child(status),
+                                        ),
+                                )
+                                .child(div().h(ROW_HEIGHT * rows as f32).child(self.list.clone())),
+                        ),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+};
+
+use gpui::{prelude::FluentBuilder, *};
+use ignore::WalkBuilder;
+
+use crate::{
+    config::EditorSettings,
+    fuzzy::{fuzzy_match, FuzzyMatch},
+    recent_files::RecentFiles,
+    theme::Theme,
+    ui::{Background, List, ListEvent, TextEvent, TextInput},
+};
+
+/// The height of a file in the list.
+const ROW_HEIGHT: Pixels = px(28.);
+/// More files than this scroll.
+const MAX_VISIBLE_ROWS: usize = 10;
+/// The folder scan stops after this many files, so a huge folder doesn't
+/// keep it busy.
+const MAX_SCANNED_FILES: usize = 20_000;
+/// Added to the score of a match in the file name, so it beats one spread
+/// over the folders.
+const FILE_NAME_BONUS: i32 = 100;
+
+pub enum QuickOpenEvent {
+    /// A file was chosen, the owner opens it and drops the quick open.
+    Open(PathBuf),
+    /// Escape was pressed or the mouse went down outside. The owner drops it.
+    Dismissed,
+}
+
+/// A file the quick open offers.
+struct Entry {
+    path: PathBuf,
+    /// The path relative to the folder, or in full outside it.
+    label: String,
+    /// Where the file name starts in `label`.
+    name_start: usize,
+}
+
+impl Entry {
+    fn new(path: PathBuf, folder: Option<&Path>) -> Self {
+        let label = match folder.and_then(|folder| path.strip_prefix(folder).ok()) {
+            Some(relative) => relative.display().to_string(),
+            None => match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok()) {
+                Some(in_home) => format!("~{MAIN_SEPARATOR}{}", in_home.display()),
+                None => path.display().to_string(),
+            },
+        };
+        let name_start = label.rfind(MAIN_SEPARATOR).map_or(0, |i| i + 1);
+        Self {
+            path,
+            label,
+            name_start,
+        }
+    }
+
+    /// How well `query` matches, a match in the file name beats one across
+    /// the folders.
+    fn fuzzy_match(&self, query: &str) -> Option<FuzzyMatch> {
+        let name = &self.label[self.name_start..];
+        match fuzzy_match(query, name) {
+            Some(mut name_match) => {
+                name_match.score += FILE_NAME_BONUS;
+                for position in &mut name_match.positions {
+                    *position += self.name_start;
+                }
+                Some(name_match)
+            }
+            None => fuzzy_match(query, &self.label),
+        }
+    }
+}
+
+/// An input above the recent files and the files of a folder, typing narrows
+/// them down to the ones it fuzzy matches. Enter opens one. The folder is
+/// scanned in the background, its files show up once that's done.
+pub struct QuickOpen {
+    input: TextInput,
+    list: View<List>,
+    /// The recent files first, then the folder's.
+    entries: Vec<Entry>,
+    /// The entries matching the input, the best first.
+    matches: Vec<(usize, FuzzyMatch)>,
+    /// The folder being scanned, `None` once it's done.
+    scanning: Option<PathBuf>,
+    _scan: Task<()>,
+}
+
+impl EventEmitter<QuickOpenEvent> for QuickOpen {}
+
+impl QuickOpen {
+    /// Offers the recent files, and the files in `folder` and below that
+    /// aren't ignored by a `.gitignore`.
+    pub fn new(folder: Option<PathBuf>, cx: &mut ViewContext<Self>) -> Self {
+        let folder = folder.map(|folder| folder.canonicalize().unwrap_or(folder));
+        let recent = cx.global::<RecentFiles>().paths().to_vec();
+        let entries: Vec<Entry> = recent
+            .iter()
+            .map(|path| Entry::new(path.clone(), folder.as_deref()))
+            .collect();
+        let matches: Vec<(usize, FuzzyMatch)> = (0..entries.len())
+            .map(|index| (index, FuzzyMatch::default()))
+            .collect();
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        let quick_open = cx.view().downgrade();
+        let list = cx.new_view(|cx| {
+            let mut list = List::new(
+                matches.len(),
+                move |index, _highlighted, cx| {
+                    quick_open.upgrade().map_or_else(
+                        || div().into_any_element(),
+                        |quick_open| quick_open.read(cx).render_match(index, cx),
+                    )
+                },
+                cx,
+            );
+            list.follow_input(&input.model, cx);
+            list
+        });
+        cx.subscribe(&input.model, |this, _model, event, cx| match event {
+            TextEvent::Edited { .. } => this.filter(cx),
+            TextEvent::Cancel => cx.emit(QuickOpenEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        cx.subscribe(&list, |this, _list, event, cx| {
+            if let ListEvent::Activate(index) = event {
+                this.open(*index, cx);
+            }
+        })
+        .detach();
+        input.focus(cx);
+
+        let settings = cx.global::<EditorSettings>();
+        let show_hidden = settings.show_hidden_files;
+        let respect_gitignore = settings.respect_gitignore;
+        // the recent files that are gone are found along with the folder's
+        let scan = cx.background_executor().spawn({
+            let folder = folder.clone();
+            async move {
+                let missing: Vec<PathBuf> =
+                    recent.into_iter().filter(|path| !path.is_file()).collect();
+                let files = folder
+                    .as_deref()
+                    .map(|folder| scan_folder(folder, show_hidden, respect_gitignore))
+                    .unwrap_or_default();
+                (missing, files)
+            }
+        });
+        let _scan = cx.spawn(|this, mut cx| async move {
+            let (missing, files) = scan.await;
+            this.update(&mut cx, |this, cx| this.add_scanned(missing, files, cx))
+                .ok();
+        });
+        Self {
+            input,
+            list,
+            entries,
+            matches,
+            scanning: folder,
+            _scan,
+        }
+    }
+
+    /// Whether the focus is still in the quick open's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+
+    /// Drops the `missing` recent files, here and from the list of recent
+    /// files, and adds the folder's `files` after the recent ones.
+    fn add_scanned(
+        &mut self,
+        missing: Vec<PathBuf>,
+        files: Vec<PathBuf>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.entries.retain(|entry| !missing.contains(&entry.path));
+        RecentFiles::remove(&missing, cx);
+        let known: HashSet<PathBuf> = self
+            .entries
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+        let folder = self.scanning.take();
+        self.entries.extend(
+            files
+                .into_iter()
+                .filter(|path| !known.contains(path))
+                .map(|path| Entry::new(path, folder.as_deref())),
+        );
+        self.filter(cx);
+    }
+
+    fn filter(&mut self, cx: &mut ViewContext<Self>) {
+        let query = self.input.model.read(cx).text();
+        self.matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((index, entry.fuzzy_match(&query)?)))
+            .collect();
+        // equally good matches keep the recent files first
+        self.matches
+            .sort_by_key(|(_, fuzzy_match)| -fuzzy_match.score);
+        let count = self.matches.len();
+        self.list
+            .update(cx, |list, cx| list.set_item_count(count, cx));
+        cx.notify();
+    }
+
+    fn open(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some((entry, _)) = self.matches.get(index) else {
+            return;
+        };
+        cx.emit(QuickOpenEvent::Open(self.entries[*entry].path.clone()));
+    }
+
+    /// The path of a matching file with the matched characters in bold and
+    /// the folders dimmed.
+    fn render_match(&self, index: usize, cx: &WindowContext) -> AnyElement {
+        let Some((entry, fuzzy_match)) = self.matches.get(index) else {
+            return div().into_any_element();
+        };
+        let entry = &self.entries[*entry];
+        let muted_color = cx.global::<Theme>().muted_color;
+
+        // runs of matched and unmatched characters, and whether they're in
+        // the folders
+        let mut runs: Vec<(String, bool, bool)> = Vec::new();
+        for (offset, c) in entry.label.char_indices() {
+            let matched = fuzzy_match.positions.contains(&offset);
+            let folder = offset < entry.name_start;
+            match runs.last_mut() {
+                Some((run, run_matched, run_folder))
+                    if *run_matched == matched && *run_folder == folder =>
+                {
+                    run.push(c)
+                }
+                _ => runs.push((c.to_string(), matched, folder)),
+            }
+        }
+
+        div()
+            .h(ROW_HEIGHT)
+            .px_3()
+            .flex()
+            .items_center()
+            .overflow_hidden()
+            .whitespace_nowrap()
+            .children(runs.into_iter().map(|(run, matched, folder)| {
+                div()
+                    .child(run)
+                    .when(matched, |this| this.font_weight(FontWeight::BOLD))
+                    .when(folder, |this| this.text_color(muted_color))
+            }))
+            .into_any_element()
+    }
+}
+
+/// The files in `folder` and below, without hidden ones and what `.gitignore`
+/// files ignore unless the settings say otherwise. Stops at
+/// `MAX_SCANNED_FILES`.
+fn scan_folder(folder: &Path, show_hidden: bool, respect_gitignore: bool) -> Vec<PathBuf> {
+    WalkBuilder::new(folder)
+        .hidden(!show_hidden)
+        .git_ignore(respect_gitignore)
+        .git_global(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .ignore(respect_gitignore)
+        // a folder that isn't a repository can still have a `.gitignore`
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != OsStr::new(".git"))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .take(MAX_SCANNED_FILES)
+        .collect()
+}
+
+impl Render for QuickOpen {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let border_color = theme.border_color;
+        let muted_color = theme.muted_color;
+        let rows = self.matches.len().min(MAX_VISIBLE_ROWS);
+        let status = match &self.scanning {
+            Some(folder) => Some(format!("Looking for files in {}…", folder.display())),
+            None if self.matches.is_empty() => Some("No matching files".to_string()),
+            None => None,
+        };
+
+        // covers the window, a press outside the quick open closes it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(QuickOpenEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w(px(560.))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(self.input.clone())
+                                .child(div().h(ROW_HEIGHT * rows as f32).child(self.list.clone()))
+                                .children(status.map(|status| {
+                                    div()
+                                        .h(ROW_HEIGHT)
+                                        .px_3()
+                                        .flex()
+                                        .items_center()
+                                        .overflow_hidden()
+                                        .whitespace_nowrap()
+                                        .text_color(muted_color)
+                                        .child(status)
+                                })),
+                        ),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use gpui::*;
+use serde::{Deserialize, Serialize};
+
+use crate::editor::write_atomically;
+
+/// The oldest files are forgotten past this many.
+const MAX_RECENT_FILES: usize = 100;
+
+/// The files opened last, the latest first, a global kept in the platform's
+/// data directory between runs.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    pub fn init(cx: &mut AppContext) {
+        cx.set_global(Self::load().unwrap_or_default());
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ted-itor").join("recent_files.json"))
+    }
+
+    fn load() -> Option<Self> {
+        let json = fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or(io::ErrorKind::NotFound)?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomically(&path, json.as_bytes())
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Puts `path` first, a file opened before moves up instead of showing
+    /// twice.
+    pub fn add(path: &Path, cx: &mut AppContext) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let recent = cx.global_mut::<Self>();
+        recent.paths.retain(|other| *other != path);
+        recent.paths.insert(0, path);
+        recent.paths.truncate(MAX_RECENT_FILES);
+        recent.save_or_report();
+    }
+
+    /// Forgets `paths`, e.g. files that are gone.
+    pub fn remove(paths: &[PathBuf], cx: &mut AppContext) {
+        if paths.is_empty() {
+            return;
+        }
+        let recent = cx.global_mut::<Self>();
+        recent.paths.retain(|path| !paths.contains(path));
+        recent.save_or_report();
+    }
+
+    fn save_or_report(&self) {
+        if let Err(error) = self.save() {
+            eprintln!("Couldn't save the recent files: {error}");
+        }
+    }
+}
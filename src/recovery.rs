@@ -0,0 +1,132 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::editor::write_atomically;
+
+/// How long typing has to pause before a draft is written.
+pub const DRAFT_DELAY: Duration = Duration::from_secs(2);
+/// Typing that doesn't pause still gets a draft this often.
+pub const MAX_DRAFT_DELAY: Duration = Duration::from_secs(30);
+/// Longer texts get no draft, writing them again and again would keep the
+/// disk busy.
+pub const MAX_DRAFT_LEN: usize = 8 * 1024 * 1024;
+
+/// An unsaved text kept in the platform's data directory, so a crash doesn't
+/// lose it. It's deleted when the text is saved or ted-itor exits cleanly.
+#[derive(Serialize, Deserialize)]
+pub struct Draft {
+    /// The file it's an edit of, `None` for an untitled text.
+    pub path: Option<PathBuf>,
+    pub text: String,
+    /// Where the draft itself is kept.
+    #[serde(skip)]
+    pub file: PathBuf,
+}
+
+impl Draft {
+    fn dir() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ted-itor").join("drafts"))
+    }
+
+    /// Where the draft of the text at `path` is kept, named after a hash of
+    /// the path. Untitled texts are told apart by the process and
+    /// `untitled_id`, so they don't overwrite the drafts of an earlier run.
+    pub fn file_for(path: Option<&Path>, untitled_id: u64) -> Option<PathBuf> {
+        let name = match path {
+            Some(path) => format!("{}.json", path_hash(path)),
+            None => format!("untitled-{}-{untitled_id}.json", std::process::id()),
+        };
+        Some(Self::dir()?.join(name))
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        if let Some(directory) = self.file.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let json = serde_json::to_string(self)?;
+        write_atomically(&self.file, json.as_bytes())
+    }
+
+    /// The drafts left behind by a run that didn't exit cleanly, by path.
+    /// Drafts that can't be read are skipped, ones that match their file
+    /// already are deleted.
+    pub fn load_all() -> Vec<Draft> {
+        let Some(entries) = Self::dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        let mut drafts: Vec<Draft> = entries
+            .filter_map(|entry| {
+                let file = entry.ok()?.path();
+                let json = fs::read_to_string(&file).ok()?;
+                let draft: Draft = serde_json::from_str(&json).ok()?;
+                Some(Draft { file, ..draft })
+            })
+            .filter(|draft| {
+                // a save that raced the draft's write
+                let saved = draft.path.as_ref().is_some_and(|path| {
+                    fs::read_to_string(path)
+                        .is_ok_and(|text| text.replace("\r\n", "\n") == draft.text)
+                });
+                if saved {
+                    remove_draft(&draft.file);
+                }
+                !saved
+            })
+            .collect();
+        drafts.sort_by(|a, b| a.path.cmp(&b.path));
+        drafts
+    }
+
+    /// The path to show for the draft, or "Untitled".
+    pub fn label(&self) -> String {
+        let Some(path) = &self.path else {
+            return "Untitled".to_string();
+        };
+        match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok()) {
+            Some(relative) => format!("~/{}", relative.display()),
+            None => path.display().to_string(),
+        }
+    }
+}
+
+/// A name for files kept about `path`, the same for the same path. It's a
+/// 64-bit FNV-1a hash of the path's bytes, which unlike `DefaultHasher` can't
+/// change with the Rust version and lose track of the files written before.
+pub fn path_hash(path: &Path) -> String {
+    let hash = path
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+/// Deletes the draft kept at `file`, one that's gone already is fine.
+pub fn remove_draft(file: &Path) {
+    match fs::remove_file(file) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => eprintln!("Couldn't delete the draft {}: {error}", file.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_hash_is_stable() {
+        assert_eq!(path_hash(Path::new("")), "cbf29ce484222325");
+        assert_eq!(
+            path_hash(Path::new("/home/me/notes.txt")),
+            "2b643d34cf4e6664"
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use std::ops::Range;
+
+use gpui::*;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    buffer::Buffer,
+    theme::Theme,
+    ui::{Button, ButtonVariant, TextEvent, TextInput, TextModel},
+};
+
+/// The state of a search through one `TextModel`, kept up to date while the
+/// text changes.
+pub struct SearchModel {
+    pub query: String,
+    /// Every match of `query`, in order and not overlapping.
+    pub matches: Vec<Range<usize>>,
+    /// The index into `matches` of the match jumped to last.
+    pub current: Option<usize>,
+    pub case_sensitive: bool,
+    /// Whether `query` is a regular expression.
+    pub regex: bool,
+    /// Set when `query` doesn't compile, the search falls back to matching it
+    /// literally until it does.
+    pub invalid_regex: bool,
+    /// The compiled `query` in regex mode.
+    compiled_regex: Option<Regex>,
+    /// How many matches the last replace all replaced, until the query changes.
+    pub replaced: Option<usize>,
+    /// The offset the current match is looked for from when the matches change.
+    anchor: usize,
+    editor: Option<Model<TextModel>>,
+    _editor_subscription: Option<Subscription>,
+}
+
+impl SearchModel {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            matches: Vec::new(),
+            current: None,
+            case_sensitive: false,
+            regex: false,
+            invalid_regex: false,
+            compiled_regex: None,
+            replaced: None,
+            anchor: 0,
+            editor: None,
+            _editor_subscription: None,
+        }
+    }
+
+    /// Searches `editor` from now on, or stops searching and clears the
+    /// highlights when it is `None`.
+    pub fn set_editor(&mut self, editor: Option<Model<TextModel>>, cx: &mut ModelContext<Self>) {
+        if let Some(old) = self.editor.take() {
+            old.update(cx, |editor, cx| editor.clear_highlights::<Self>(cx));
+        }
+        self._editor_subscription = editor.as_ref().map(|editor| {
+            cx.subscribe(editor, |this, _editor, event, cx| {
+                if let TextEvent::Edited { .. } = event {
+                    this.search(cx);
+                }
+            })
+        });
+        self.editor = editor;
+        self.anchor_at_cursor(cx);
+        self.search(cx);
+    }
+
+    pub fn set_query(&mut self, query: String, cx: &mut ModelContext<Self>) {
+        self.query = query;
+        self.replaced = None;
+        self.anchor_at_cursor(cx);
+        self.search(cx);
+    }
+
+    pub fn toggle_case_sensitive(&mut self, cx: &mut ModelContext<Self>) {
+        self.case_sensitive = !self.case_sensitive;
+        self.search(cx);
+    }
+
+    pub fn toggle_regex(&mut self, cx: &mut ModelContext<Self>) {
+        self.regex = !self.regex;
+        self.search(cx);
+    }
+
+    fn anchor_at_cursor(&mut self, cx: &mut ModelContext<Self>) {
+        if let Some(editor) = &self.editor {
+            self.anchor = editor.read(cx).selection.start;
+        }
+    }
+
+    /// Finds the matches again, the current one becomes the first match at or
+    /// after `anchor`.
+    fn search(&mut self, cx: &mut ModelContext<Self>) {
+        let Some(editor) = self.editor.clone() else {
+            self.matches.clear();
+            self.current = None;
+            cx.notify();
+            return;
+        };
+        self.compiled_regex = None;
+        self.invalid_regex = false;
+        if self.regex && !self.query.is_empty() {
+            match RegexBuilder::new(&self.query)
+                .case_insensitive(!self.case_sensitive)
+                .build()
+            {
+                Ok(regex) => self.compiled_regex = Some(regex),
+                Err(_) => self.invalid_regex = true,
+            }
+        }
+        let editor = editor.read(cx);
+        self.matches = match &self.compiled_regex {
+            Some(regex) => find_regex_matches(editor.buffer(), regex),
+            None => find_matches(editor.buffer(), &self.query, self.case_sensitive),
+        };
+        // past the last match the search wraps around to the first
+        self.current = if self.matches.is_empty() {
+            None
+        } else {
+            let i = self
+                .matches
+                .partition_point(|range| range.start < self.anchor);
+            Some(if i == self.matches.len() { 0 } else { i })
+        };
+        self.update_highlights(cx);
+        cx.notify();
+    }
+
+    /// Jumps to the next match, or the previous one when `reverse` is set, and
+    /// selects it in the editor.
+    pub fn select_next(&mut self, reverse: bool, cx: &mut ModelContext<Self>) {
+        let count = self.matches.len();
+        let (Some(editor), Some(current)) = (self.editor.clone(), self.current) else {
+            return;
+        };
+        let range = self.matches[current].clone();
+        // the first jump goes to the current match unless it's selected already
+        let next = editor.read(cx).selection != range;
+        let current = match (next, reverse) {
+            (true, _) => current,
+            (false, true) => (current + count - 1) % count,
+            (false, false) => (current + 1) % count,
+        };
+        self.current = Some(current);
+        let range = self.matches[current].clone();
+        self.anchor = range.start;
+        editor.update(cx, |editor, cx| {
+            editor.select(range);
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+        self.update_highlights(cx);
+        cx.notify();
+    }
+
+    /// Replaces the current match with `replacement`, selects what was
+    /// inserted and makes the match after it the current one.
+    pub fn replace_current(&mut self, replacement: &str, cx: &mut ModelContext<Self>) {
+        let (Some(editor), Some(current)) = (self.editor.clone(), self.current) else {
+            return;
+        };
+        let range = self.matches[current].clone();
+        let replacement = self.expand_replacement(editor.read(cx).buffer(), &range, replacement);
+        let inserted = range.start..range.start + replacement.len();
+        editor.update(cx, |editor, cx| {
+            editor.replace_range(range, &replacement, cx);
+            editor.select(inserted.clone());
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+        // never match inside the replacement, even when it contains the query
+        self.anchor = inserted.end;
+        self.replaced = None;
+        self.search(cx);
+    }
+
+    /// Replaces every match in a single edit and returns how many there were.
+    pub fn replace_all(&mut self, replacement: &str, cx: &mut ModelContext<Self>) -> usize {
+        let Some(editor) = self.editor.clone() else {
+            return 0;
+        };
+        let (Some(first), Some(last)) = (self.matches.first(), self.matches.last()) else {
+            return 0;
+        };
+        let span = first.start..last.end;
+        let count = self.matches.len();
+        editor.update(cx, |editor, cx| {
+            let mut text = String::new();
+            let mut offset = span.start;
+            for range in &self.matches {
+                text.push_str(&editor.text_for_range(offset..range.start));
+                text.push_str(&self.expand_replacement(editor.buffer(), range, replacement));
+                offset = range.end;
+            }
+            editor.replace_range(span, &text, cx);
+        });
+        self.replaced = Some(count);
+        self.search(cx);
+        count
+    }
+
+    /// The text a match is replaced with, in regex mode `$1` and `${name}`
+    /// refer to the groups the match captured.
+    fn expand_replacement(
+        &self,
+        buffer: &Buffer,
+        range: &Range<usize>,
+        replacement: &str,
+    ) -> String {
+        let Some(regex) = &self.compiled_regex else {
+            return replacement.to_string();
+        };
+        let row = buffer.row_for_offset(range.start);
+        let line_start = buffer.line_start(row);
+        // search from the match itself, so anchors and word boundaries see
+        // the same surroundings as when it was found
+        let Some(captures) = regex.captures_at(buffer.line(row), range.start - line_start) else {
+            return replacement.to_string();
+        };
+        let mut expanded = String::new();
+        captures.expand(replacement, &mut expanded);
+        expanded
+    }
+
+    fn update_highlights(&self, cx: &mut ModelContext<Self>) {
+        let Some(editor) = self.editor.clone() else {
+            return;
+        };
+        let theme = cx.global::<Theme>();
+        let style = |color| HighlightStyle {
+            background_color: Some(color),
+            ..Default::default()
+        };
+        let (match_style, current_style) =
+            (style(theme.match_color), style(theme.current_match_color));
+        let highlights = self
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(i, range)| {
+                let style = if Some(i) == self.current {
+                    current_style
+                } else {
+                    match_style
+                };
+                (range.clone(), style)
+            })
+            .collect();
+        editor.update(cx, |editor, cx| {
+            editor.set_highlights::<Self>(highlights, cx)
+        });
+    }
+
+    /// "3 of 17" for the bar, or a note that nothing matched or how many
+    /// matches were replaced.
+    pub fn status(&self) -> String {
+        match (self.replaced, self.current) {
+            _ if self.query.is_empty() => String::new(),
+            (Some(replaced), _) => format!("Replaced {replaced}"),
+            (None, Some(current)) => format!("{} of {}", current + 1, self.matches.len()),
+            (None, None) => "No results".to_string(),
+        }
+    }
+}
+
+/// The matches of `query` in `buffer`. Matches don't span lines, the search
+/// bar's query can't contain a newline.
+fn find_matches(buffer: &Buffer, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return matches;
+    }
+    for row in 0..buffer.line_count() {
+        let line = buffer.line(row);
+        let line_start = buffer.line_start(row);
+        let mut offset = 0;
+        while offset < line.len() {
+            match match_len_at(&line[offset..], query, case_sensitive) {
+                Some(len) => {
+                    matches.push(line_start + offset..line_start + offset + len);
+                    offset += len;
+                }
+                None => {
+                    // step to the next char boundary, never into a char
+                    offset += line[offset..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// The matches of `regex` in `buffer`, searched line by line. `find_iter`
+/// steps past empty matches, so patterns like `a*` report one match per
+/// position instead of looping.
+fn find_regex_matches(buffer: &Buffer, regex: &Regex) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+    for row in 0..buffer.line_count() {
+        let line_start = buffer.line_start(row);
+        matches.extend(
+            regex
+                .find_iter(buffer.line(row))
+                .map(|m| line_start + m.start()..line_start + m.end()),
+        );
+    }
+    matches
+}
+
+/// The length of the match of `needle` at the start of `haystack`, comparing
+/// chars one by one so a match always ends on a char boundary.
+fn match_len_at(haystack: &str, needle: &str, case_sensitive: bool) -> Option<usize> {
+    let mut chars = haystack.char_indices();
+    for n in needle.chars() {
+        let (_, c) = chars.next()?;
+        if c != n && (case_sensitive || !c.to_lowercase().eq(n.to_lowercase())) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(haystack.len(), |(i, _)| i))
+}
+
+/// The find bar shown under the title bar, a query input with a match
+/// counter and a replacement input.
+#[derive(IntoElement, Clone)]
+pub struct SearchBar {
+    pub query_input: TextInput,
+    pub replace_input: TextInput,
+    pub model: Model<SearchModel>,
+}
+
+impl SearchBar {
+    pub fn new(cx: &mut WindowContext) -> Self {
+        let query_input = TextInput::new(cx, String::new()).single_line(cx);
+        let replace_input = TextInput::new(cx, String::new()).single_line(cx);
+        let model = cx.new_model(|_cx| SearchModel::new());
+        cx.subscribe(&query_input.model, {
+            let model = model.clone();
+            move |query, event, cx| {
+                if let TextEvent::Edited { .. } = event {
+                    let query = query.read(cx).text();
+                    model.update(cx, |search, cx| search.set_query(query, cx));
+                }
+            }
+        })
+        .detach();
+        Self {
+            query_input,
+            replace_input,
+            model,
+        }
+    }
+}
+
+impl RenderOnce for SearchBar {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let search = self.model.read(cx);
+        let status = search.status();
+        let case_sensitive = search.case_sensitive;
+        let regex = search.regex;
+        let invalid_regex = search.invalid_regex;
+        // nothing to replace, e.g. while the query is empty
+        let no_matches = search.matches.is_empty();
+        let border_color = theme.border_color;
+        let replace = {
+            let model = self.model.clone();
+            let replacement = self.replace_input.model.clone();
+            move |all: bool, cx: &mut WindowContext| {
+                let replacement = replacement.read(cx).text();
+                model.update(cx, |search, cx| {
+                    if all {
+                        search.replace_all(&replacement, cx);
+                    } else {
+                        search.replace_current(&replacement, cx);
+                    }
+                });
+            }
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .px_6()
+            .py_2()
+            .bg(theme.panel_color)
+            .border_b()
+            .border_color(theme.border_color)
+            .on_key_down({
+                let model = self.model.clone();
+                let replace_input = self.replace_input.clone();
+                let replace = replace.clone();
+                move |ev, cx| {
+                    if ev.keystroke.key != "enter" {
+                        return;
+                    }
+                    // enter replaces in the replacement input and jumps in the query input
+                    if replace_input.is_focused(cx) {
+                        replace(false, cx);
+                    } else {
+                        let reverse = ev.keystroke.modifiers.shift;
+                        model.update(cx, |search, cx| search.select_next(reverse, cx));
+                    }
+                }
+            })
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(
+                        div()
+                            .flex_1()
+                            .child(self.query_input.invalid(invalid_regex)),
+                    )
+                    .child(
+                        Button::new("case-sensitive", "Aa", {
+                            let model = self.model.clone();
+                            Box::new(move |_ev, cx| {
+                                model.update(cx, |search, cx| search.toggle_case_sensitive(cx))
+                            })
+                        })
+                        .variant(ButtonVariant::Ghost)
+                        .compact()
+                        .text_sm()
+                        .border_1()
+                        .border_color(if case_sensitive {
+                            border_color
+                        } else {
+                            transparent_black()
+                        }),
+                    )
+                    .child(
+                        Button::new("regex", ".*", {
+                            let model = self.model.clone();
+                            Box::new(move |_ev, cx| {
+                                model.update(cx, |search, cx| search.toggle_regex(cx))
+                            })
+                        })
+                        .variant(ButtonVariant::Ghost)
+                        .compact()
+                        .text_sm()
+                        .border_1()
+                        .border_color(if regex {
+                            border_color
+                        } else {
+                            transparent_black()
+                        }),
+                    )
+                    .child(div().text_sm().child(status)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(div().flex_1().child(self.replace_input))
+                    .child(
+                        Button::new("replace", "Replace", {
+                            let replace = replace.clone();
+                            Box::new(move |_ev, cx| replace(false, cx))
+                        })
+                        .disabled(no_matches)
+                        .h_6()
+                        .text_sm(),
+                    )
+                    .child(
+                        Button::new(
+                            "replace-all",
+                            "Replace All",
+                            Box::new(move |_ev, cx| replace(true, cx)),
+                        )
+                        .disabled(no_matches)
+                        .h_6()
+                        .text_sm(),
+                    ),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex_matches(text: &str, pattern: &str) -> Vec<Range<usize>> {
+        find_regex_matches(&Buffer::new(text), &Regex::new(pattern).unwrap())
+    }
+
+    #[test]
+    fn empty_matches_step_forward() {
+        assert_eq!(
+            regex_matches("baaab\n\na", "a*"),
+            [0..0, 1..4, 5..5, 6..6, 7..8]
+        );
+        assert_eq!(regex_matches("", "a*"), [0..0]);
+        assert_eq!(regex_matches("x\ny", "^"), [0..0, 2..2]);
+    }
+
+    #[test]
+    fn empty_matches_stay_on_char_boundaries() {
+        let text = "é👩世";
+        let matches = regex_matches(text, "a*");
+        assert_eq!(matches, [0..0, 2..2, 6..6, 9..9]);
+        assert!(matches.iter().all(|m| text.is_char_boundary(m.start)));
+    }
+
+    #[test]
+    fn regex_matches_are_offset_by_line() {
+        assert_eq!(regex_matches("ab1\ncd22", r"\d+"), [2..3, 6..8]);
+    }
+
+    #[test]
+    fn literal_matches_ignore_case_and_dont_overlap() {
+        let buffer = Buffer::new("Ééé aaa\naA");
+        assert_eq!(find_matches(&buffer, "éé", false), [0..4]);
+        assert_eq!(find_matches(&buffer, "aa", false), [7..9, 11..13]);
+        assert_eq!(find_matches(&buffer, "aa", true), [7..9]);
+        assert!(find_matches(&buffer, "", false).is_empty());
+    }
+}
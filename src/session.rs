@@ -0,0 +1,82 @@
+use std::{fs, io, ops::Range, path::PathBuf};
+
+use gpui::*;
+use serde::{Deserialize, Serialize};
+
+use crate::editor::write_atomically;
+
+/// What was open when ted-itor was last used, restored on the next start.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Session {
+    pub files: Vec<SessionFile>,
+    /// The index into `files` of the tab that was shown.
+    pub active: usize,
+    pub window: Option<WindowGeometry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionFile {
+    pub path: PathBuf,
+    /// Byte offsets, clamped to the text when it shrank since.
+    pub selection: Range<usize>,
+    pub scroll_top: f32,
+}
+
+/// The window's position and size in screen coordinates.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl WindowGeometry {
+    /// `None` for full screen and maximized windows, they open at the default size.
+    pub fn from_bounds(bounds: WindowBounds) -> Option<Self> {
+        let WindowBounds::Fixed(bounds) = bounds else {
+            return None;
+        };
+        Some(Self {
+            x: bounds.origin.x.into(),
+            y: bounds.origin.y.into(),
+            width: bounds.size.width.into(),
+            height: bounds.size.height.into(),
+        })
+    }
+
+    pub fn bounds(self) -> WindowBounds {
+        WindowBounds::Fixed(Bounds {
+            origin: Point {
+                x: GlobalPixels::from(self.x),
+                y: GlobalPixels::from(self.y),
+            },
+            size: Size {
+                width: GlobalPixels::from(self.width),
+                height: GlobalPixels::from(self.height),
+            },
+        })
+    }
+}
+
+impl Session {
+    /// Where the session is kept, in the platform's data directory.
+    fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ted-itor").join("session.json"))
+    }
+
+    /// The last saved session, `None` when there is none or it can't be read.
+    pub fn load() -> Option<Self> {
+        let json = fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or(io::ErrorKind::NotFound)?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomically(&path, json.as_bytes())
+    }
+}
@@ -0,0 +1,196 @@
+use std::{path::PathBuf, time::Duration};
+
+use gpui::*;
+
+use crate::{
+    paste::normalize_line_endings,
+    process::{run_piped, shell_command},
+    theme::Theme,
+    toast::{Toast, ToastQueue},
+    ui::{Background, TextEvent, TextInput, TextModel},
+};
+
+/// How long a command may run before it's killed.
+const SHELL_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+/// A command printing more than this is killed, its output isn't inserted.
+const MAX_OUTPUT_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy)]
+pub enum ShellCommandMode {
+    /// Inserts what the command prints at the cursor.
+    Insert,
+    /// Pipes the selection, or the whole text without one, to the command and
+    /// replaces it with what the command prints.
+    Filter,
+}
+
+pub enum ShellCommandEvent {
+    /// Enter was pressed, the owner runs the command and drops the prompt.
+    Submitted(String),
+    /// Escape was pressed or the mouse went down outside the prompt. The
+    /// owner drops it.
+    Dismissed,
+}
+
+/// Asks for a command line to run with the shell, see `run_shell_command`.
+pub struct ShellCommandPrompt {
+    input: TextInput,
+    mode: ShellCommandMode,
+}
+
+impl EventEmitter<ShellCommandEvent> for ShellCommandPrompt {}
+
+impl ShellCommandPrompt {
+    pub fn new(mode: ShellCommandMode, cx: &mut ViewContext<Self>) -> Self {
+        let input = TextInput::new(cx, String::new()).single_line(cx);
+        cx.subscribe(&input.model, |_this, _model, event, cx| match event {
+            TextEvent::Submit { text } if !text.trim().is_empty() => {
+                cx.emit(ShellCommandEvent::Submitted(text.clone()))
+            }
+            TextEvent::Cancel => cx.emit(ShellCommandEvent::Dismissed),
+            _ => {}
+        })
+        .detach();
+        input.focus(cx);
+        Self { input, mode }
+    }
+
+    /// Whether the focus is still in the prompt's input.
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.input.is_focused(cx)
+    }
+}
+
+/// Runs `command_line` with the shell in `dir` on a background task, with a
+/// spinner toast meanwhile. Its output replaces the selection, or the piped
+/// text for `ShellCommandMode::Filter`, as one undoable edit. A failing
+/// command shows its stderr in a toast instead. Output is dropped when the
+/// text was edited before the command was done, it would land in the wrong place.
+pub fn run_shell_command(
+    editor: Model<TextModel>,
+    mode: ShellCommandMode,
+    command_line: String,
+    dir: Option<PathBuf>,
+    cx: &mut WindowContext,
+) {
+    let (range, input, version) = {
+        let editor = editor.read(cx);
+        if editor.read_only {
+            return;
+        }
+        let range = match mode {
+            ShellCommandMode::Filter if editor.selection.is_empty() => 0..editor.len(),
+            _ => editor.selection.clone(),
+        };
+        let input = match mode {
+            ShellCommandMode::Insert => String::new(),
+            ShellCommandMode::Filter => editor.text_for_range(range.clone()),
+        };
+        (range, input, editor.version)
+    };
+    // `echo` and most filters end their output with a newline, which only
+    // belongs in the text when the piped text had one
+    let keep_final_newline = input.ends_with('\n');
+    let mut command = shell_command(&command_line);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let run = cx
+        .background_executor()
+        .spawn(async move { run_piped(command, input, SHELL_COMMAND_TIMEOUT, MAX_OUTPUT_LEN) });
+    let toast = cx
+        .global_mut::<ToastQueue>()
+        .push(Toast::progress(format!("Running {command_line}")));
+
+    cx.spawn(|mut cx| async move {
+        let result = run.await;
+        cx.update(|cx| {
+            cx.global_mut::<ToastQueue>().dismiss(toast);
+            let mut output = match result {
+                Ok(output) => normalize_line_endings(&output),
+                Err(error) => {
+                    let message = error.message(&command_line);
+                    cx.global_mut::<ToastQueue>().push(Toast::error(message));
+                    return;
+                }
+            };
+            if !keep_final_newline && output.ends_with('\n') {
+                output.pop();
+            }
+            let inserted = editor.update(cx, |editor, cx| {
+                if editor.version != version {
+                    return false;
+                }
+                editor.transact(cx, |editor, cx| {
+                    editor.replace_range(range.clone(), &output, cx)
+                });
+                editor.collapse_selections();
+                let end = range.start + output.len();
+                match mode {
+                    ShellCommandMode::Insert => editor.move_to(end),
+                    ShellCommandMode::Filter => editor.select(range.start..end),
+                }
+                cx.emit(TextEvent::SelectionChanged {
+                    selection: editor.selection.clone(),
+                });
+                true
+            });
+            if !inserted {
+                let message = format!("The text changed while {command_line} ran");
+                cx.global_mut::<ToastQueue>().push(Toast::error(message));
+            }
+        })
+        .ok();
+    })
+    .detach();
+}
+
+impl Render for ShellCommandPrompt {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let (border_color, muted_color) = (theme.border_color, theme.muted_color);
+
+        // covers the window, a press outside the prompt closes it
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_start()
+            .pt_16()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _ev, cx| cx.emit(ShellCommandEvent::Dismissed)),
+            )
+            .child(
+                div()
+                    .w(px(480.))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(border_color)
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                    .child(
+                        Background::new().child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .text_sm()
+                                .child(self.input.clone())
+                                .child(div().px_4().py_2().text_color(muted_color).child(
+                                    match self.mode {
+                                        ShellCommandMode::Insert => {
+                                            "Insert the output of a shell command"
+                                        }
+                                        ShellCommandMode::Filter => {
+                                            "Filter the selection through a shell command"
+                                        }
+                                    },
+                                )),
+                        ),
+                    ),
+            )
+    }
+}
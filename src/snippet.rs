@@ -0,0 +1,211 @@
+use std::{fs, io, iter::Peekable, ops::Range, str::Chars};
+
+use gpui::*;
+use serde::Deserialize;
+
+use crate::{
+    config::config_dir,
+    toast::{Toast, ToastQueue},
+};
+
+/// The snippets every text starts with, `snippets.toml` adds to them.
+const BUILT_IN_SNIPPETS: &str = include_str!("../assets/snippets.toml");
+
+/// Text inserted by typing its prefix and pressing tab.
+#[derive(Clone, Deserialize)]
+pub struct Snippet {
+    /// A word, tab right after it expands the snippet.
+    pub prefix: String,
+    /// What's inserted. `$1`, `$2`… are where tab moves next, `${1:text}`
+    /// with `text` there and selected, `$0` where the snippet ends. A tab is
+    /// one level of indentation.
+    pub body: String,
+    /// Only expanded in texts of this language, e.g. `rust`. Unset, in all of
+    /// them.
+    pub language: Option<String>,
+    /// Shown in the command palette, the prefix when unset.
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SnippetFile {
+    #[serde(default)]
+    snippet: Vec<Snippet>,
+}
+
+/// The built-in snippets and the user's, a global.
+pub struct Snippets {
+    snippets: Vec<Snippet>,
+}
+
+impl Snippets {
+    /// Reads `~/.config/ted-itor/snippets.toml` after the built-in snippets,
+    /// a mistake in it is shown as a toast.
+    pub fn init(cx: &mut AppContext) {
+        let mut snippets = toml::from_str::<SnippetFile>(BUILT_IN_SNIPPETS)
+            .unwrap()
+            .snippet;
+        match load_user_snippets() {
+            Ok(user_snippets) => {
+                for snippet in user_snippets {
+                    let same = snippets.iter_mut().find(|other| {
+                        other.prefix == snippet.prefix && other.language == snippet.language
+                    });
+                    match same {
+                        Some(other) => *other = snippet,
+                        None => snippets.push(snippet),
+                    }
+                }
+            }
+            Err(error) => {
+                cx.global_mut::<ToastQueue>()
+                    .push(Toast::error(format!("snippets.toml: {error:#}")));
+            }
+        }
+        cx.set_global(Self { snippets });
+    }
+
+    pub fn all(&self) -> &[Snippet] {
+        &self.snippets
+    }
+
+    /// The snippet `prefix` expands to in a text of `language`, one for that
+    /// language over one for all of them.
+    pub fn find(&self, prefix: &str, language: Option<&str>) -> Option<&Snippet> {
+        self.snippets
+            .iter()
+            .filter(|snippet| {
+                snippet.prefix == prefix
+                    && snippet
+                        .language
+                        .as_deref()
+                        .map_or(true, |only| Some(only) == language)
+            })
+            .max_by_key(|snippet| snippet.language.is_some())
+    }
+}
+
+fn load_user_snippets() -> anyhow::Result<Vec<Snippet>> {
+    let Some(path) = config_dir().map(|dir| dir.join("snippets.toml")) else {
+        return Ok(Vec::new());
+    };
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    Ok(toml::from_str::<SnippetFile>(&source)?.snippet)
+}
+
+/// A snippet's body ready to be inserted.
+pub struct ExpandedSnippet {
+    pub text: String,
+    /// Offsets into `text`, in the order tab visits them, `$0` last.
+    pub stops: Vec<Range<usize>>,
+}
+
+/// Replaces the tab stops in `body` by their text and indents it: every
+/// line after the first starts with `indent`, and each tab is an `indent_unit`.
+/// A stop used twice is only visited where it's first. `\$` is a dollar sign.
+pub fn expand_snippet(body: &str, indent: &str, indent_unit: &str) -> ExpandedSnippet {
+    let push = |text: &mut String, c: char| match c {
+        '\n' => {
+            text.push('\n');
+            text.push_str(indent);
+        }
+        '\t' => text.push_str(indent_unit),
+        c => text.push(c),
+    };
+    let mut text = String::new();
+    let mut stops: Vec<(usize, Range<usize>)> = Vec::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('$' | '}' | '\\')) => {
+                push(&mut text, chars.next().unwrap());
+            }
+            '$' => match parse_tab_stop(&mut chars) {
+                Some((index, placeholder)) => {
+                    let start = text.len();
+                    for c in placeholder.chars() {
+                        push(&mut text, c);
+                    }
+                    if !stops.iter().any(|(other, _)| *other == index) {
+                        stops.push((index, start..text.len()));
+                    }
+                }
+                None => text.push('$'),
+            },
+            c => push(&mut text, c),
+        }
+    }
+    // tab visits 1, 2… and ends at 0, or at the end without one
+    stops.sort_by_key(|(index, _)| if *index == 0 { usize::MAX } else { *index });
+    if stops.last().map_or(true, |(index, _)| *index != 0) {
+        stops.push((0, text.len()..text.len()));
+    }
+    ExpandedSnippet {
+        text,
+        stops: stops.into_iter().map(|(_, range)| range).collect(),
+    }
+}
+
+/// Reads the `1` or `{1:text}` after a dollar sign. Nothing is consumed when
+/// it isn't a tab stop.
+fn parse_tab_stop(chars: &mut Peekable<Chars>) -> Option<(usize, String)> {
+    let mut ahead = chars.clone();
+    let braced = ahead.next_if_eq(&'{').is_some();
+    let mut digits = String::new();
+    while let Some(digit) = ahead.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    let index = digits.parse().ok()?;
+    let mut placeholder = String::new();
+    if braced {
+        if ahead.next_if_eq(&':').is_some() {
+            loop {
+                match ahead.next()? {
+                    '\\' if ahead.peek() == Some(&'}') => placeholder.push(ahead.next()?),
+                    '}' => break,
+                    c => placeholder.push(c),
+                }
+            }
+        } else if ahead.next()? != '}' {
+            return None;
+        }
+    }
+    *chars = ahead;
+    Some((index, placeholder))
+}
+
+/// The tab stops of an expanded snippet, while tab moves between them. See
+/// `TextModel::insert_snippet`.
+pub struct TabStops {
+    pub stops: Vec<Range<usize>>,
+    /// The stop tab moved to last.
+    pub current: usize,
+}
+
+impl TabStops {
+    /// Follows an edit of `range` into `new_len` bytes. Text typed at the
+    /// edges of the current stop becomes part of it, the other stops move out
+    /// of its way.
+    pub fn edit(&mut self, range: Range<usize>, new_len: usize) {
+        let map = |offset: usize, after: bool| {
+            if offset < range.start {
+                offset
+            } else if offset > range.end {
+                offset - range.len() + new_len
+            } else if after {
+                range.start + new_len
+            } else {
+                range.start
+            }
+        };
+        for (i, stop) in self.stops.iter_mut().enumerate() {
+            let current = i == self.current;
+            let start = map(stop.start, !current);
+            *stop = start..map(stop.end, current).max(start);
+        }
+    }
+}
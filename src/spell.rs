@@ -0,0 +1,267 @@
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    ops::Range,
+    path::PathBuf,
+};
+
+use gpui::*;
+
+use crate::{
+    config::{config_dir, EditorSettings},
+    ui::TextModel,
+};
+
+/// The words spell checking knows without a `spell_check_dictionary`.
+const BUNDLED_DICTIONARY: &str = include_str!("../assets/dictionary-en.txt");
+/// How many corrections the context menu offers for a word.
+const MAX_SUGGESTIONS: usize = 5;
+/// How many chars a correction may add, remove, replace or swap.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+/// Endings of inflected words and what the word ended with before, e.g.
+/// "tried" is "try" with "ied".
+const SUFFIXES: &[(&str, &[&str])] = &[
+    ("'s", &[""]),
+    ("s", &[""]),
+    ("es", &[""]),
+    ("ies", &["y"]),
+    ("ed", &["", "e"]),
+    ("ied", &["y"]),
+    ("ing", &["", "e"]),
+    ("er", &["", "e"]),
+    ("ier", &["y"]),
+    ("est", &["", "e"]),
+    ("iest", &["y"]),
+    ("ly", &["", "le"]),
+    ("ily", &["y"]),
+    ("ally", &["", "al"]),
+    ("ness", &[""]),
+    ("iness", &["y"]),
+    ("ment", &[""]),
+    ("ful", &[""]),
+    ("less", &[""]),
+    ("able", &["", "e"]),
+    ("ation", &["", "e"]),
+    ("ion", &["", "e"]),
+    ("ity", &["", "e"]),
+    ("ize", &["", "e"]),
+    ("ise", &["", "e"]),
+    ("ist", &["", "e"]),
+    ("ism", &["", "e"]),
+    ("al", &[""]),
+    ("ive", &["", "e"]),
+    ("ous", &["", "e"]),
+];
+/// Beginnings that make another word out of one the dictionary knows.
+const PREFIXES: &[&str] = &[
+    "un", "re", "non", "pre", "dis", "mis", "over", "under", "out", "sub", "super", "anti", "co",
+    "multi", "inter",
+];
+
+/// The words spell checking accepts, a global: the bundled English ones or
+/// the `spell_check_dictionary`, and the ones the user added.
+#[derive(Default)]
+pub struct Dictionary {
+    /// Lowercase, apostrophes straight.
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Reads the dictionary the settings ask for, a file that can't be read
+    /// is reported and the bundled one is used instead.
+    pub fn init(settings: &EditorSettings, cx: &mut AppContext) {
+        let custom = settings.spell_check_dictionary.as_ref().and_then(|path| {
+            fs::read_to_string(path)
+                .map_err(|error| eprintln!("Couldn't read {}: {error}", path.display()))
+                .ok()
+        });
+        let mut dictionary = Self::default();
+        dictionary.extend(custom.as_deref().unwrap_or(BUNDLED_DICTIONARY));
+        if let Some(text) = user_dictionary_path().and_then(|path| fs::read_to_string(path).ok()) {
+            dictionary.extend(&text);
+        }
+        cx.set_global(dictionary);
+    }
+
+    /// Adds a word from each line of `text`. Hunspell's flags after a slash
+    /// are ignored, so its `.dic` files work too.
+    fn extend(&mut self, text: &str) {
+        for line in text.lines() {
+            let word = line.split('/').next().unwrap_or_default().trim();
+            if !word.is_empty() && !word.starts_with('#') {
+                self.words.insert(normalize(word));
+            }
+        }
+    }
+
+    /// Accepts `word` from now on, in every editor, and appends it to the
+    /// user's dictionary so it stays accepted.
+    pub fn add_word(word: &str, cx: &mut AppContext) -> io::Result<()> {
+        let path = user_dictionary_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{word}")?;
+        // editors observe the dictionary and check their text again
+        cx.global_mut::<Dictionary>().words.insert(normalize(word));
+        Ok(())
+    }
+
+    /// Whether `word` is in the dictionary, ignoring case, or an inflection
+    /// of a word that is.
+    pub fn contains(&self, word: &str) -> bool {
+        let word = normalize(word);
+        self.knows_form(&word, 2)
+            || PREFIXES.iter().any(|prefix| {
+                word.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.len() >= 3 && self.knows_form(rest, 2))
+            })
+    }
+
+    /// Whether `word` or what it could be inflected from is in the
+    /// dictionary, looking through up to `depth` endings, e.g. "carefully".
+    fn knows_form(&self, word: &str, depth: usize) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+        if depth == 0 {
+            return false;
+        }
+        SUFFIXES.iter().any(|(suffix, endings)| {
+            let Some(stem) = word.strip_suffix(suffix).filter(|stem| stem.len() >= 2) else {
+                return false;
+            };
+            // "stopped" is "stop" with its last letter doubled
+            let undoubled = matches!(*suffix, "ed" | "ing" | "er" | "est")
+                .then(|| {
+                    let mut chars = stem.chars().rev();
+                    let last = chars.next()?;
+                    (chars.next()? == last).then(|| &stem[..stem.len() - last.len_utf8()])
+                })
+                .flatten();
+            endings
+                .iter()
+                .any(|ending| self.knows_form(&format!("{stem}{ending}"), depth - 1))
+                || undoubled.is_some_and(|stem| self.words.contains(stem))
+        })
+    }
+
+    /// Up to `MAX_SUGGESTIONS` words of the dictionary close to `word`, the
+    /// closest first, in the case `word` is written in.
+    pub fn suggestions(&self, word: &str) -> Vec<String> {
+        let normalized = normalize(word);
+        let chars: Vec<char> = normalized.chars().collect();
+        let mut candidates: Vec<(usize, &str)> = self
+            .words
+            .iter()
+            .filter_map(|candidate| {
+                let candidate_chars: Vec<char> = candidate.chars().collect();
+                if candidate_chars.len().abs_diff(chars.len()) > MAX_SUGGESTION_DISTANCE {
+                    return None;
+                }
+                let distance = edit_distance(&chars, &candidate_chars);
+                (distance <= MAX_SUGGESTION_DISTANCE).then_some((distance, candidate.as_str()))
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| match_case(word, candidate))
+            .collect()
+    }
+
+    /// The words of `line` that aren't in the dictionary. Words that are
+    /// probably not prose are skipped: ones in all caps or camel case, with
+    /// digits or underscores, and single letters.
+    pub fn misspelled_words(&self, line: &str) -> Vec<Range<usize>> {
+        let words = TextModel::compute_word_ranges(line);
+        let mut misspelled = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let mut range = words[i].clone();
+            // contractions and possessives, "don't" is one word
+            while let Some(next) = words.get(i + 1) {
+                let between = &line[range.end..next.start];
+                if between != "'" && between != "’" {
+                    break;
+                }
+                range.end = next.end;
+                i += 1;
+            }
+            i += 1;
+            let word = &line[range.clone()];
+            if is_prose(word) && !self.contains(word) {
+                misspelled.push(range);
+            }
+        }
+        misspelled
+    }
+}
+
+/// Whether `line` opens or closes a Markdown code block, the code in it
+/// isn't spell checked.
+pub fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// `~/.config/ted-itor/dictionary.txt`, one word per line.
+fn user_dictionary_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("dictionary.txt"))
+}
+
+/// How words are kept in the dictionary, lowercase with straight apostrophes.
+fn normalize(word: &str) -> String {
+    word.to_lowercase().replace('’', "'")
+}
+
+fn is_prose(word: &str) -> bool {
+    word.chars().filter(|c| c.is_alphabetic()).count() > 1
+        && !word.chars().any(|c| c.is_numeric() || c == '_')
+        && !word.chars().skip(1).any(char::is_uppercase)
+}
+
+/// `candidate` written like `word`: all caps, capitalized or as it is.
+fn match_case(word: &str, candidate: &str) -> String {
+    let mut chars = word.chars();
+    let first_upper = chars.next().is_some_and(char::is_uppercase);
+    if first_upper && word.chars().count() > 1 && chars.all(|c| !c.is_lowercase()) {
+        candidate.to_uppercase()
+    } else if first_upper {
+        let mut candidate_chars = candidate.chars();
+        candidate_chars
+            .next()
+            .map(|first| first.to_uppercase().chain(candidate_chars).collect())
+            .unwrap_or_default()
+    } else {
+        candidate.to_string()
+    }
+}
+
+/// The number of chars inserted, deleted, replaced or swapped with their
+/// neighbor that turn `a` into `b`.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // three rows of the table are all the swaps need
+    let mut before_previous: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,122 @@
+use gpui::*;
+
+use crate::{
+    modal::Modal,
+    toast::{Toast, ToastQueue},
+    ui::{ButtonVariant, TextModel},
+};
+
+/// The reading speed the reading time is estimated with.
+const WORDS_PER_MINUTE: usize = 200;
+/// Texts longer than this are counted on a background task, with a spinner
+/// toast meanwhile.
+const MAX_INLINE_LEN: usize = 1024 * 1024;
+
+/// Counts of a text, see `Statistics::of`.
+pub struct Statistics {
+    pub chars: usize,
+    pub non_whitespace_chars: usize,
+    /// Counted like the editor finds words, see `TextModel::compute_word_ranges`.
+    pub words: usize,
+    pub lines: usize,
+    /// Runs of lines that aren't blank.
+    pub paragraphs: usize,
+}
+
+impl Statistics {
+    pub fn of(text: &str) -> Self {
+        let mut statistics = Self {
+            chars: 0,
+            non_whitespace_chars: 0,
+            words: 0,
+            lines: 0,
+            paragraphs: 0,
+        };
+        let mut in_paragraph = false;
+        for line in text.split('\n') {
+            statistics.lines += 1;
+            statistics.words += TextModel::compute_word_ranges(line).len();
+            for c in line.chars() {
+                statistics.chars += 1;
+                if !c.is_whitespace() {
+                    statistics.non_whitespace_chars += 1;
+                }
+            }
+            let blank = line.trim().is_empty();
+            if !blank && !in_paragraph {
+                statistics.paragraphs += 1;
+            }
+            in_paragraph = !blank;
+        }
+        // the newlines between the lines
+        statistics.chars += statistics.lines - 1;
+        statistics
+    }
+
+    /// How long reading the words takes, rounded up to whole minutes.
+    pub fn reading_time(&self) -> String {
+        match self.words.div_ceil(WORDS_PER_MINUTE) {
+            0 => "none".to_string(),
+            1 => "about 1 minute".to_string(),
+            minutes if minutes < 60 => format!("about {minutes} minutes"),
+            minutes => format!("about {} h {} min", minutes / 60, minutes % 60),
+        }
+    }
+
+    /// One count per line, for the statistics modal.
+    pub fn summary(&self) -> String {
+        format!(
+            "Characters: {}\n\
+             Characters without whitespace: {}\n\
+             Words: {}\n\
+             Lines: {}\n\
+             Paragraphs: {}\n\
+             Reading time: {}",
+            self.chars,
+            self.non_whitespace_chars,
+            self.words,
+            self.lines,
+            self.paragraphs,
+            self.reading_time()
+        )
+    }
+}
+
+/// Counts the selection of `model`, or the whole text without one, and shows
+/// the result in a modal. Long texts are counted in the background.
+pub fn show_statistics(model: &Model<TextModel>, cx: &mut WindowContext) {
+    let (text, title) = {
+        let model = model.read(cx);
+        if model.selection.is_empty() {
+            (model.text(), "Document Statistics")
+        } else {
+            (model.selected_text(), "Selection Statistics")
+        }
+    };
+    if text.len() <= MAX_INLINE_LEN {
+        open_modal(title, Statistics::of(&text), cx);
+        return;
+    }
+    let count = cx
+        .background_executor()
+        .spawn(async move { Statistics::of(&text) });
+    let toast = cx
+        .global_mut::<ToastQueue>()
+        .push(Toast::progress("Counting words"));
+    cx.spawn(|mut cx| async move {
+        let statistics = count.await;
+        cx.update(|cx| {
+            cx.global_mut::<ToastQueue>().dismiss(toast);
+            open_modal(title, statistics, cx);
+        })
+        .ok();
+    })
+    .detach();
+}
+
+fn open_modal(title: &'static str, statistics: Statistics, cx: &mut WindowContext) {
+    Modal::new(title, statistics.summary(), |_cx| {})
+        .action("OK", ButtonVariant::Primary, |_cx| {})
+        .open(cx);
+    cx.refresh();
+}
@@ -1,28 +1,593 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context};
 use gpui::*;
 
+use crate::{
+    config::{config_dir, Config},
+    highlight::TokenKind,
+    toast::{Toast, ToastQueue},
+};
+
+/// How much one zoom step grows or shrinks the editor font.
+const ZOOM_STEP: f32 = 1.;
+const MIN_FONT_SIZE: f32 = 6.;
+const MAX_FONT_SIZE: f32 = 72.;
+
+/// Whether a theme is light text on dark or the other way around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+impl From<WindowAppearance> for Appearance {
+    fn from(appearance: WindowAppearance) -> Self {
+        match appearance {
+            WindowAppearance::Light | WindowAppearance::VibrantLight => Appearance::Light,
+            WindowAppearance::Dark | WindowAppearance::VibrantDark => Appearance::Dark,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Theme {
+    /// What `Theme::load` knows it by, the file name without `.toml`.
+    pub name: SharedString,
+    pub appearance: Appearance,
     pub background_color: Hsla,
     pub primary_color: Hsla,
     pub danger_color: Hsla,
+    /// The edge of info and success toasts, errors use `danger_color`.
+    pub info_color: Hsla,
+    pub success_color: Hsla,
     pub panel_color: Hsla,
     pub border_color: Hsla,
     pub text_color: Hsla,
+    pub cursor_color: Hsla,
+    pub selection_color: Hsla,
+    /// The text color of a selection, `None` keeps its syntax colors.
+    pub selection_text_color: Option<Hsla>,
+    pub placeholder_color: Hsla,
+    /// Laid over a button's color while the mouse is on it, see `blend`.
+    pub button_hover_overlay: Hsla,
+    /// Dims the window behind dialogs and the drop target.
+    pub backdrop_color: Hsla,
+    /// The circles of `WindowControls` on macOS, they turn `muted_color` while
+    /// the window isn't active.
+    pub close_button_color: Hsla,
+    pub minimize_button_color: Hsla,
+    pub zoom_button_color: Hsla,
+    pub muted_color: Hsla,
+    pub line_number_color: Hsla,
+    pub active_line_color: Hsla,
+    pub match_color: Hsla,
+    pub current_match_color: Hsla,
+    pub occurrence_color: Hsla,
+    pub bracket_color: Hsla,
+    /// Behind whitespace at the end of a line.
+    pub trailing_whitespace_color: Hsla,
+    pub keyword_color: Hsla,
+    pub string_color: Hsla,
+    pub comment_color: Hsla,
+    pub number_color: Hsla,
+    /// The handle of a `Scrollbar` and the track it slides in.
+    pub scrollbar_thumb_color: Hsla,
+    pub scrollbar_track_color: Hsla,
+    /// The marks for spaces, tabs and line ends while whitespace is rendered.
+    pub whitespace_color: Hsla,
+    /// The indent guides and the ruler.
+    pub guide_color: Hsla,
+    /// The boxed codes invisible chars like zero width spaces are shown as.
+    pub control_char_color: Hsla,
+    /// The gutter markers of lines added, changed and removed since the last
+    /// git commit.
+    pub diff_added_color: Hsla,
+    pub diff_modified_color: Hsla,
+    pub diff_removed_color: Hsla,
+    /// The underlines of what a linter reported, by severity.
+    pub diagnostic_error_color: Hsla,
+    pub diagnostic_warning_color: Hsla,
+    pub diagnostic_info_color: Hsla,
+    /// The editor's font, monospace so columns line up.
+    pub editor_font_family: SharedString,
+    pub editor_font_size: Pixels,
+    /// A multiple of the font size.
+    pub editor_line_height: f32,
+    /// The font of everything around the editor.
+    pub ui_font_family: SharedString,
+    pub ui_font_size: Pixels,
+    /// Steps of `ZOOM_STEP` added to `editor_font_size`, kept when the theme
+    /// changes.
+    pub zoom: i32,
 }
 
 impl Theme {
-    pub fn init(cx: &mut AppContext) {
-        cx.set_global(Theme::new())
+    /// Sets the theme picked in the config as the global, or else the built-in
+    /// one matching the system's light or dark mode.
+    pub fn init(config: &Config, cx: &mut WindowContext) {
+        let system = Theme::builtin(cx.appearance().into());
+        let theme = match &config.theme {
+            Some(name) => Theme::load(&name).unwrap_or_else(|error| {
+                cx.global_mut::<ToastQueue>().push(Toast::error(format!(
+                    "Couldn't load the theme {name}: {error:#}"
+                )));
+                system
+            }),
+            None => system,
+        };
+        cx.set_global(Theme {
+            zoom: config.zoom,
+            ..theme
+        })
+    }
+
+    /// Makes `theme` the global with the current zoom, and repaints.
+    pub fn replace(theme: Theme, cx: &mut WindowContext) {
+        let zoom = cx.global::<Theme>().zoom;
+        cx.set_global(Theme { zoom, ..theme });
+        // every element reads the theme when it renders
+        cx.refresh();
+    }
+
+    /// Zooms the editor font in or out by `steps`, or back to the theme's size
+    /// with `None`. The zoom is saved in the config for the next start.
+    pub fn zoom(steps: Option<i32>, cx: &mut WindowContext) {
+        let theme = cx.global_mut::<Theme>();
+        let zoom = steps.map_or(0, |steps| theme.zoom + steps);
+        let size = theme.editor_font_size + px(ZOOM_STEP * zoom as f32);
+        if zoom == theme.zoom || size < px(MIN_FONT_SIZE) || size > px(MAX_FONT_SIZE) {
+            return;
+        }
+        theme.zoom = zoom;
+        cx.refresh();
+        cx.background_executor()
+            .spawn(async move {
+                if let Err(error) = Config::save_zoom(zoom) {
+                    eprintln!("Couldn't save the zoom: {error}");
+                }
+            })
+            .detach();
+    }
+
+    /// The editor's font size with the zoom applied.
+    pub fn zoomed_font_size(&self) -> Pixels {
+        self.editor_font_size + px(ZOOM_STEP * self.zoom as f32)
+    }
+
+    /// The editor's line height in pixels.
+    pub fn editor_line_height_in_pixels(&self) -> Pixels {
+        self.zoomed_font_size() * self.editor_line_height
+    }
+
+    /// The color of syntax tokens of `kind`.
+    pub fn token_color(&self, kind: TokenKind) -> Hsla {
+        match kind {
+            TokenKind::Keyword => self.keyword_color,
+            TokenKind::String => self.string_color,
+            TokenKind::Comment => self.comment_color,
+            TokenKind::Number => self.number_color,
+        }
+    }
+
+    /// Swaps the global theme for the built-in one of the other appearance.
+    pub fn toggle(cx: &mut WindowContext) {
+        let appearance = match cx.global::<Theme>().appearance {
+            Appearance::Light => Appearance::Dark,
+            Appearance::Dark => Appearance::Light,
+        };
+        Theme::replace(Theme::builtin(appearance), cx);
+    }
+
+    pub fn builtin(appearance: Appearance) -> Self {
+        match appearance {
+            Appearance::Light => Self::light(),
+            Appearance::Dark => Self::dark(),
+        }
+    }
+
+    /// Loads `~/.config/ted-itor/themes/<name>.toml`, the colors it leaves out
+    /// are the built-in ones. Without such a file `light` and `dark` are the
+    /// built-in themes.
+    pub fn load(name: &str) -> anyhow::Result<Theme> {
+        let path = Self::path(name).context("no home directory")?;
+        match name {
+            "light" if !path.exists() => Ok(Self::light()),
+            "dark" if !path.exists() => Ok(Self::dark()),
+            _ => Self::load_file(&path),
+        }
+    }
+
+    /// The built-in themes, then the ones in the themes folder by name.
+    pub fn names() -> Vec<SharedString> {
+        let mut files: Vec<String> = config_dir()
+            .and_then(|dir| fs::read_dir(dir.join("themes")).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_owned())
+            })
+            .filter(|name| name != "light" && name != "dark")
+            .collect();
+        files.sort();
+        ["light".to_owned(), "dark".to_owned()]
+            .into_iter()
+            .chain(files)
+            .map(SharedString::from)
+            .collect()
+    }
+
+    /// Switches to the theme called `name` and makes it the one to start with.
+    pub fn select(name: &str, cx: &mut WindowContext) {
+        match Theme::load(name) {
+            Ok(theme) => Theme::replace(theme, cx),
+            Err(error) => {
+                cx.global_mut::<ToastQueue>().push(Toast::error(format!(
+                    "Couldn't load the theme {name}: {error:#}"
+                )));
+                return;
+            }
+        }
+        let name = name.to_owned();
+        cx.background_executor()
+            .spawn(async move {
+                if let Err(error) = Config::save_theme(&name) {
+                    eprintln!("Couldn't save the theme: {error}");
+                }
+            })
+            .detach();
+    }
+
+    /// Where the theme called `name` is kept.
+    pub fn path(name: &str) -> Option<PathBuf> {
+        Some(config_dir()?.join("themes").join(format!("{name}.toml")))
     }
 
-    fn new() -> Self {
+    pub fn load_file(path: &Path) -> anyhow::Result<Theme> {
+        let source =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let theme = Self::parse(&source).with_context(|| format!("in {}", path.display()))?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        Ok(Theme {
+            name: name.into_owned().into(),
+            ..theme
+        })
+    }
+
+    /// Reads a theme of `key = "#rrggbb"` lines, `#rrggbbaa` for a translucent
+    /// color, and the fonts. It starts from the light theme, or the dark one
+    /// with `appearance = "dark"`. Keys it doesn't know are reported and skipped.
+    pub fn parse(source: &str) -> anyhow::Result<Theme> {
+        let mut table: toml::Table = toml::from_str(source).map_err(|error| {
+            // the error's own text is several lines with a snippet of the file
+            let line = error
+                .span()
+                .map_or(1, |span| source[..span.start].matches('\n').count() + 1);
+            anyhow!("line {line}: {}", error.message())
+        })?;
+        let appearance = match table.remove("appearance") {
+            None => Appearance::Light,
+            Some(value) => match value.as_str() {
+                Some("light") => Appearance::Light,
+                Some("dark") => Appearance::Dark,
+                _ => bail!("appearance should be \"light\" or \"dark\""),
+            },
+        };
+        let mut theme = Theme::builtin(appearance);
+        for (key, value) in table {
+            if theme.set_font(&key, &value)? {
+                continue;
+            }
+            let Some(color) = theme.color_mut(&key) else {
+                eprintln!("Unknown theme key {key}, it's ignored");
+                continue;
+            };
+            let hex = value
+                .as_str()
+                .ok_or_else(|| anyhow!("{key} should be a string like \"#rrggbb\""))?;
+            *color = parse_color(hex).with_context(|| format!("invalid color for {key}"))?;
+        }
+        Ok(theme)
+    }
+
+    /// Sets the font setting called `key`, false when `key` isn't one.
+    fn set_font(&mut self, key: &str, value: &toml::Value) -> anyhow::Result<bool> {
+        let family = || {
+            let family = value
+                .as_str()
+                .with_context(|| format!("{key} should be a string"))?;
+            anyhow::Ok(SharedString::from(family.to_owned()))
+        };
+        let number = || {
+            let number = value
+                .as_float()
+                .or_else(|| value.as_integer().map(|number| number as f64))
+                .filter(|number| *number > 0.)
+                .with_context(|| format!("{key} should be a positive number"))?;
+            anyhow::Ok(number as f32)
+        };
+        match key {
+            "editor_font_family" => self.editor_font_family = family()?,
+            "editor_font_size" => self.editor_font_size = px(number()?),
+            "editor_line_height" => self.editor_line_height = number()?,
+            "ui_font_family" => self.ui_font_family = family()?,
+            "ui_font_size" => self.ui_font_size = px(number()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The color a theme file sets with `key`, the field name without `_color`.
+    fn color_mut(&mut self, key: &str) -> Option<&mut Hsla> {
+        Some(match key {
+            "background" => &mut self.background_color,
+            "primary" => &mut self.primary_color,
+            "danger" => &mut self.danger_color,
+            "info" => &mut self.info_color,
+            "success" => &mut self.success_color,
+            "panel" => &mut self.panel_color,
+            "border" => &mut self.border_color,
+            "text" => &mut self.text_color,
+            "cursor" => &mut self.cursor_color,
+            "selection" => &mut self.selection_color,
+            "selection_text" => self.selection_text_color.insert(self.text_color),
+            "placeholder" => &mut self.placeholder_color,
+            "button_hover_overlay" => &mut self.button_hover_overlay,
+            "backdrop" => &mut self.backdrop_color,
+            "close_button" => &mut self.close_button_color,
+            "minimize_button" => &mut self.minimize_button_color,
+            "zoom_button" => &mut self.zoom_button_color,
+            "muted" => &mut self.muted_color,
+            "line_number" => &mut self.line_number_color,
+            "active_line" => &mut self.active_line_color,
+            "match" => &mut self.match_color,
+            "current_match" => &mut self.current_match_color,
+            "occurrence" => &mut self.occurrence_color,
+            "bracket" => &mut self.bracket_color,
+            "trailing_whitespace" => &mut self.trailing_whitespace_color,
+            "keyword" => &mut self.keyword_color,
+            "string" => &mut self.string_color,
+            "comment" => &mut self.comment_color,
+            "number" => &mut self.number_color,
+            "scrollbar_thumb" => &mut self.scrollbar_thumb_color,
+            "scrollbar_track" => &mut self.scrollbar_track_color,
+            "whitespace" => &mut self.whitespace_color,
+            "guide" => &mut self.guide_color,
+            "control_char" => &mut self.control_char_color,
+            "diff_added" => &mut self.diff_added_color,
+            "diff_modified" => &mut self.diff_modified_color,
+            "diff_removed" => &mut self.diff_removed_color,
+            "diagnostic_error" => &mut self.diagnostic_error_color,
+            "diagnostic_warning" => &mut self.diagnostic_warning_color,
+            "diagnostic_info" => &mut self.diagnostic_info_color,
+            _ => return None,
+        })
+    }
+
+    /// Dark text on pale green.
+    pub fn light() -> Self {
         Self {
+            name: "light".into(),
+            appearance: Appearance::Light,
             primary_color: white(),
             background_color: hsla(129. / 360., 0.47, 0.96, 1.),
             danger_color: hsla(3. / 360., 100., 0.69, 1.),
+            info_color: hsla(210. / 360., 0.7, 0.55, 1.),
+            success_color: hsla(129. / 360., 0.5, 0.4, 1.),
             panel_color: hsla(123. / 360., 0.37, 0.89, 1.),
             border_color: hsla(120. / 360., 0.13, 0.61, 1.),
             text_color: black(),
+            cursor_color: hsla(123. / 360., 0.6, 0.3, 1.),
+            selection_color: hsla(0., 0., 0.9, 1.),
+            selection_text_color: None,
+            placeholder_color: hsla(120. / 360., 0.05, 0.5, 1.),
+            // darkens
+            button_hover_overlay: hsla(0., 0., 0., 0.08),
+            backdrop_color: hsla(0., 0., 0., 0.3),
+            close_button_color: hsla(3. / 360., 0.96, 0.64, 1.),
+            minimize_button_color: hsla(40. / 360., 0.98, 0.58, 1.),
+            zoom_button_color: hsla(125. / 360., 0.66, 0.48, 1.),
+            muted_color: hsla(120. / 360., 0.13, 0.8, 1.),
+            line_number_color: hsla(120. / 360., 0.13, 0.61, 1.),
+            active_line_color: hsla(129. / 360., 0.47, 0.99, 1.),
+            match_color: hsla(50. / 360., 0.9, 0.8, 1.),
+            current_match_color: hsla(35. / 360., 0.95, 0.6, 1.),
+            occurrence_color: hsla(129. / 360., 0.3, 0.88, 1.),
+            bracket_color: hsla(200. / 360., 0.6, 0.85, 1.),
+            trailing_whitespace_color: hsla(3. / 360., 0.9, 0.93, 1.),
+            keyword_color: hsla(280. / 360., 0.6, 0.45, 1.),
+            string_color: hsla(123. / 360., 0.6, 0.3, 1.),
+            comment_color: hsla(120. / 360., 0.05, 0.5, 1.),
+            number_color: hsla(25. / 360., 0.8, 0.45, 1.),
+            scrollbar_thumb_color: hsla(120. / 360., 0.13, 0.61, 0.6),
+            scrollbar_track_color: hsla(0., 0., 0., 0.04),
+            whitespace_color: hsla(120. / 360., 0.13, 0.8, 1.),
+            guide_color: hsla(120. / 360., 0.13, 0.9, 1.),
+            control_char_color: hsla(3. / 360., 0.6, 0.5, 1.),
+            diff_added_color: hsla(129. / 360., 0.5, 0.45, 1.),
+            diff_modified_color: hsla(210. / 360., 0.7, 0.55, 1.),
+            diff_removed_color: hsla(3. / 360., 0.8, 0.6, 1.),
+            diagnostic_error_color: hsla(3. / 360., 0.8, 0.55, 1.),
+            diagnostic_warning_color: hsla(40. / 360., 0.9, 0.45, 1.),
+            diagnostic_info_color: hsla(210. / 360., 0.7, 0.55, 1.),
+            editor_font_family: "Menlo".into(),
+            editor_font_size: px(14.),
+            editor_line_height: 1.5,
+            ui_font_family: ".SystemUIFont".into(),
+            ui_font_size: px(14.),
+            zoom: 0,
+        }
+    }
+
+    /// Light text on dark green-gray.
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".into(),
+            appearance: Appearance::Dark,
+            primary_color: hsla(125. / 360., 0.12, 0.2, 1.),
+            background_color: hsla(125. / 360., 0.12, 0.12, 1.),
+            danger_color: hsla(3. / 360., 0.8, 0.6, 1.),
+            info_color: hsla(210. / 360., 0.6, 0.6, 1.),
+            success_color: hsla(129. / 360., 0.45, 0.55, 1.),
+            panel_color: hsla(125. / 360., 0.12, 0.17, 1.),
+            border_color: hsla(120. / 360., 0.08, 0.32, 1.),
+            text_color: hsla(0., 0., 0.9, 1.),
+            cursor_color: hsla(123. / 360., 0.5, 0.6, 1.),
+            selection_color: hsla(123. / 360., 0.15, 0.28, 1.),
+            selection_text_color: Some(hsla(0., 0., 1., 1.)),
+            placeholder_color: hsla(120. / 360., 0.05, 0.5, 1.),
+            // lightens, darker buttons would disappear into the background
+            button_hover_overlay: hsla(0., 0., 1., 0.08),
+            backdrop_color: hsla(0., 0., 0., 0.5),
+            close_button_color: hsla(3. / 360., 0.96, 0.64, 1.),
+            minimize_button_color: hsla(40. / 360., 0.98, 0.58, 1.),
+            zoom_button_color: hsla(125. / 360., 0.66, 0.48, 1.),
+            muted_color: hsla(120. / 360., 0.08, 0.25, 1.),
+            line_number_color: hsla(120. / 360., 0.08, 0.45, 1.),
+            active_line_color: hsla(125. / 360., 0.12, 0.15, 1.),
+            match_color: hsla(50. / 360., 0.5, 0.3, 1.),
+            current_match_color: hsla(35. / 360., 0.7, 0.4, 1.),
+            occurrence_color: hsla(129. / 360., 0.2, 0.22, 1.),
+            bracket_color: hsla(200. / 360., 0.4, 0.3, 1.),
+            trailing_whitespace_color: hsla(3. / 360., 0.4, 0.22, 1.),
+            keyword_color: hsla(280. / 360., 0.6, 0.75, 1.),
+            string_color: hsla(123. / 360., 0.45, 0.6, 1.),
+            comment_color: hsla(120. / 360., 0.05, 0.55, 1.),
+            number_color: hsla(25. / 360., 0.8, 0.65, 1.),
+            scrollbar_thumb_color: hsla(120. / 360., 0.08, 0.45, 0.6),
+            scrollbar_track_color: hsla(0., 0., 1., 0.04),
+            whitespace_color: hsla(120. / 360., 0.08, 0.3, 1.),
+            guide_color: hsla(120. / 360., 0.08, 0.2, 1.),
+            control_char_color: hsla(3. / 360., 0.6, 0.65, 1.),
+            diff_added_color: hsla(129. / 360., 0.45, 0.5, 1.),
+            diff_modified_color: hsla(210. / 360., 0.6, 0.6, 1.),
+            diff_removed_color: hsla(3. / 360., 0.8, 0.6, 1.),
+            diagnostic_error_color: hsla(3. / 360., 0.8, 0.6, 1.),
+            diagnostic_warning_color: hsla(40. / 360., 0.9, 0.55, 1.),
+            diagnostic_info_color: hsla(210. / 360., 0.6, 0.6, 1.),
+            editor_font_family: "Menlo".into(),
+            editor_font_size: px(14.),
+            editor_line_height: 1.5,
+            ui_font_family: ".SystemUIFont".into(),
+            ui_font_size: px(14.),
+            zoom: 0,
+        }
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa`.
+fn parse_color(hex: &str) -> anyhow::Result<Hsla> {
+    let digits = hex
+        .strip_prefix('#')
+        .ok_or_else(|| anyhow!("{hex:?} doesn't start with #"))?;
+    if !matches!(digits.len(), 6 | 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("{hex:?} isn't #rrggbb or #rrggbbaa");
+    }
+    let value = u32::from_str_radix(digits, 16)?;
+    let rgba = if digits.len() == 6 {
+        rgb(value)
+    } else {
+        rgba(value)
+    };
+    Ok(rgba.into())
+}
+
+/// `overlay` painted over `base`, as if they were two layers. Either may be
+/// translucent, a transparent `base` gives `overlay` itself.
+pub fn blend(base: Hsla, overlay: Hsla) -> Hsla {
+    let base = Rgba::from(base);
+    let overlay = Rgba::from(overlay);
+    let a = overlay.a + base.a * (1. - overlay.a);
+    if a == 0. {
+        return transparent_black();
+    }
+    let mix = |base_value: f32, overlay_value: f32| {
+        (overlay_value * overlay.a + base_value * base.a * (1. - overlay.a)) / a
+    };
+    Rgba {
+        r: mix(base.r, overlay.r),
+        g: mix(base.g, overlay.g),
+        b: mix(base.b, overlay.b),
+        a,
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_fixture(name: &str, source: &str) -> anyhow::Result<Theme> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(format!("{name}.toml"));
+        fs::write(&path, source)?;
+        Theme::load_file(&path)
+    }
+
+    #[test]
+    fn loads_a_theme_file() {
+        let theme = load_fixture(
+            "dusk",
+            r##"
+            appearance = "dark"
+            background = "#102030"
+            selection = "#ffffff80"
+            editor_font_size = 15
+            editor_font_family = "Iosevka"
+            "##,
+        )
+        .unwrap();
+        let dark = Theme::dark();
+        assert_eq!(theme.name.to_string(), "dusk");
+        assert_eq!(theme.appearance, Appearance::Dark);
+        assert_eq!(theme.background_color, Hsla::from(rgb(0x102030)));
+        assert_eq!(theme.selection_color, Hsla::from(rgba(0xffffff80)));
+        assert_eq!(theme.editor_font_size, px(15.));
+        assert_eq!(theme.editor_font_family.to_string(), "Iosevka");
+        // what the file leaves out is the built-in dark theme's
+        assert_eq!(theme.text_color, dark.text_color);
+        assert_eq!(theme.ui_font_size, dark.ui_font_size);
+    }
+
+    #[test]
+    fn skips_unknown_keys() {
+        let theme = load_fixture("odd", "sparkle = \"#ffffff\"\ntext = \"#000000\"\n").unwrap();
+        assert_eq!(theme.appearance, Appearance::Light);
+        assert_eq!(theme.text_color, Hsla::from(rgb(0x000000)));
+        assert_eq!(theme.background_color, Theme::light().background_color);
+    }
+
+    #[test]
+    fn reports_the_key_of_a_bad_color() {
+        for color in ["\"#12345\"", "\"102030\"", "\"#gggggg\"", "12"] {
+            let error = load_fixture("bad", &format!("border = {color}\n")).unwrap_err();
+            let message = format!("{error:#}");
+            assert!(message.contains("border"), "{message}");
+            assert!(message.contains("bad.toml"), "{message}");
         }
     }
+
+    #[test]
+    fn reports_the_line_of_invalid_toml() {
+        let error = load_fixture("broken", "text = \"#000000\"\nborder = \n").unwrap_err();
+        assert!(format!("{error:#}").contains("line 2"), "{error:#}");
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#ff0000").unwrap(), Hsla::from(rgb(0xff0000)));
+        assert_eq!(
+            parse_color("#ff000080").unwrap(),
+            Hsla::from(rgba(0xff000080))
+        );
+        assert!(parse_color("ff0000").is_err());
+        assert!(parse_color("#ff00").is_err());
+    }
 }
@@ -0,0 +1,242 @@
+use std::time::Duration;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::theme::Theme;
+
+/// How long a toast stays before it goes away by itself.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// The newest toasts shown in full, older ones collapse into a single line.
+const MAX_VISIBLE_TOASTS: usize = 3;
+/// The frames a spinner goes through, one per `SPINNER_INTERVAL`.
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+
+#[derive(Clone, Copy)]
+pub enum Severity {
+    Info,
+    Success,
+    Error,
+}
+
+/// A short message in a corner of the window.
+#[derive(Clone)]
+pub struct Toast {
+    id: usize,
+    severity: Severity,
+    message: SharedString,
+    /// Shows a spinner and stays until whoever pushed it dismisses it, for
+    /// something still running.
+    spinner: bool,
+}
+
+impl Toast {
+    pub fn new(severity: Severity, message: impl Into<SharedString>) -> Self {
+        Self {
+            id: 0,
+            severity,
+            message: message.into(),
+            spinner: false,
+        }
+    }
+
+    /// An info toast with a spinner, see `spinner`.
+    pub fn progress(message: impl Into<SharedString>) -> Self {
+        Self {
+            spinner: true,
+            ..Self::info(message)
+        }
+    }
+
+    pub fn info(message: impl Into<SharedString>) -> Self {
+        Self::new(Severity::Info, message)
+    }
+
+    pub fn success(message: impl Into<SharedString>) -> Self {
+        Self::new(Severity::Success, message)
+    }
+
+    pub fn error(message: impl Into<SharedString>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+}
+
+/// The toasts on screen, oldest first. A global so any code can push one,
+/// `Toasts` shows them.
+#[derive(Default)]
+pub struct ToastQueue {
+    toasts: Vec<Toast>,
+    next_id: usize,
+}
+
+impl ToastQueue {
+    pub fn init(cx: &mut AppContext) {
+        cx.set_global(ToastQueue::default())
+    }
+
+    /// Shows `toast` and returns its id, to dismiss it with.
+    pub fn push(&mut self, mut toast: Toast) -> usize {
+        let id = self.next_id;
+        toast.id = id;
+        self.next_id += 1;
+        self.toasts.push(toast);
+        id
+    }
+
+    pub fn dismiss(&mut self, id: usize) {
+        self.toasts.retain(|toast| toast.id != id);
+    }
+
+    /// Dismisses every toast older than the ones shown in full.
+    fn dismiss_collapsed(&mut self) {
+        let collapsed = self.toasts.len().saturating_sub(MAX_VISIBLE_TOASTS);
+        self.toasts.drain(..collapsed);
+    }
+}
+
+/// Shows the `ToastQueue` in the bottom right corner of the window and
+/// dismisses each toast once its time is up.
+pub struct Toasts {
+    /// Toasts with an id below it have their timer running.
+    scheduled: usize,
+    /// Counts up while there are spinners, see `SPINNER_FRAMES`.
+    spinner_frame: usize,
+    spinner_task: Option<Task<()>>,
+}
+
+impl Toasts {
+    pub fn new(cx: &mut ViewContext<Self>) -> Self {
+        cx.observe_global::<ToastQueue>(|this, cx| {
+            this.schedule_dismissals(cx);
+            this.animate_spinners(cx);
+            cx.notify();
+        })
+        .detach();
+        let mut toasts = Self {
+            scheduled: 0,
+            spinner_frame: 0,
+            spinner_task: None,
+        };
+        // toasts pushed before the window opened
+        toasts.schedule_dismissals(cx);
+        toasts.animate_spinners(cx);
+        toasts
+    }
+
+    /// Starts a timer for each toast that doesn't have one yet, toasts with
+    /// a spinner are dismissed by whoever pushed them.
+    fn schedule_dismissals(&mut self, cx: &mut ViewContext<Self>) {
+        let ids: Vec<usize> = cx
+            .global::<ToastQueue>()
+            .toasts
+            .iter()
+            .filter(|toast| !toast.spinner)
+            .map(|toast| toast.id)
+            .filter(|id| *id >= self.scheduled)
+            .collect();
+        for id in ids {
+            self.scheduled = id + 1;
+            cx.spawn(|this, mut cx| async move {
+                cx.background_executor().timer(TOAST_DURATION).await;
+                this.update(&mut cx, |_this, cx| {
+                    cx.global_mut::<ToastQueue>().dismiss(id);
+                })
+                .ok();
+            })
+            .detach();
+        }
+    }
+
+    /// Turns the spinners while there are any.
+    fn animate_spinners(&mut self, cx: &mut ViewContext<Self>) {
+        let spinning = cx
+            .global::<ToastQueue>()
+            .toasts
+            .iter()
+            .any(|toast| toast.spinner);
+        if !spinning {
+            self.spinner_task = None;
+            return;
+        }
+        if self.spinner_task.is_some() {
+            return;
+        }
+        self.spinner_task = Some(cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(SPINNER_INTERVAL).await;
+                let updated = this.update(&mut cx, |this, cx| {
+                    this.spinner_frame += 1;
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+}
+
+impl Render for Toasts {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let toasts = &cx.global::<ToastQueue>().toasts;
+        let collapsed = toasts.len().saturating_sub(MAX_VISIBLE_TOASTS);
+        let spinner = SPINNER_FRAMES[self.spinner_frame % SPINNER_FRAMES.len()];
+        let toast_style = || {
+            div()
+                .w_72()
+                .px_3()
+                .py_2()
+                .rounded_md()
+                .border_1()
+                .border_l_4()
+                .bg(theme.panel_color)
+                .text_color(theme.text_color)
+                .text_sm()
+                .cursor_pointer()
+                // keeps the click from reaching the editor underneath
+                .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+        };
+
+        div()
+            .absolute()
+            .bottom_0()
+            .right_0()
+            .p_4()
+            .flex()
+            .flex_col()
+            .items_end()
+            .gap_2()
+            .when(collapsed > 0, |this| {
+                this.child(
+                    toast_style()
+                        .id("collapsed-toasts")
+                        .border_color(theme.border_color)
+                        .text_color(theme.muted_color)
+                        .child(match collapsed {
+                            1 => "1 older message".to_string(),
+                            _ => format!("{collapsed} older messages"),
+                        })
+                        .on_click(|_ev, cx| cx.global_mut::<ToastQueue>().dismiss_collapsed()),
+                )
+            })
+            .children(toasts[collapsed..].iter().map(|toast| {
+                let color = match toast.severity {
+                    Severity::Info => theme.info_color,
+                    Severity::Success => theme.success_color,
+                    Severity::Error => theme.danger_color,
+                };
+                let id = toast.id;
+                toast_style()
+                    .id(("toast", id))
+                    .border_color(color)
+                    .flex()
+                    .gap_2()
+                    .when(toast.spinner, |this| {
+                        this.child(div().flex_none().text_color(color).child(spinner))
+                    })
+                    .child(toast.message.clone())
+                    .on_click(move |_ev, cx| cx.global_mut::<ToastQueue>().dismiss(id))
+            }))
+    }
+}
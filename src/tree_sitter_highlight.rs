@@ -0,0 +1,120 @@
+use std::{ops::Range, sync::Mutex};
+
+use tree_sitter::{InputEdit, Language, Parser, Point, Query, QueryCursor, Tree};
+
+use crate::highlight::{Highlighter, TextEdit, TokenKind};
+
+/// Highlights with a tree-sitter grammar and its highlight query. The tree of
+/// the last text is kept, so after an edit only the changed part is parsed.
+pub struct TreeSitterHighlighter {
+    parser: Mutex<Parser>,
+    query: Query,
+    /// The tree of the last text and that text's length after the edits since.
+    tree: Mutex<Option<(Tree, usize)>>,
+}
+
+impl TreeSitterHighlighter {
+    fn new(language: Language, highlight_query: &str) -> Option<Self> {
+        let mut parser = Parser::new();
+        parser.set_language(language).ok()?;
+        let query = Query::new(language, highlight_query).ok()?;
+        Some(Self {
+            parser: Mutex::new(parser),
+            query,
+            tree: Mutex::new(None),
+        })
+    }
+
+    pub fn for_language(language: &str) -> Option<Self> {
+        match language {
+            "rust" => Self::new(
+                tree_sitter_rust::language(),
+                tree_sitter_rust::HIGHLIGHT_QUERY,
+            ),
+            "markdown" => Self::new(
+                tree_sitter_md::language(),
+                tree_sitter_md::HIGHLIGHT_QUERY_BLOCK,
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// The `TokenKind` of a capture in a highlight query, e.g. `@keyword.control`.
+fn token_kind(capture: &str) -> Option<TokenKind> {
+    let prefix = |name: &str| capture == name || capture.starts_with(&format!("{name}."));
+    if prefix("keyword") || prefix("text.title") {
+        Some(TokenKind::Keyword)
+    } else if prefix("string") || prefix("text.literal") {
+        Some(TokenKind::String)
+    } else if prefix("comment") {
+        Some(TokenKind::Comment)
+    } else if prefix("number") || prefix("constant.numeric") || prefix("text.uri") {
+        Some(TokenKind::Number)
+    } else {
+        None
+    }
+}
+
+fn point((row, column): (usize, usize)) -> Point {
+    Point { row, column }
+}
+
+impl Highlighter for TreeSitterHighlighter {
+    fn highlight(&self, text: &str) -> Vec<(Range<usize>, TokenKind)> {
+        let mut tree = self.tree.lock().unwrap();
+        // a tree of a different text, e.g. one that was truncated, can't be reused
+        let old_tree = tree
+            .take()
+            .filter(|(_, len)| *len == text.len())
+            .map(|(tree, _)| tree);
+        let Some(new_tree) = self.parser.lock().unwrap().parse(text, old_tree.as_ref()) else {
+            return Vec::new();
+        };
+
+        let mut tokens: Vec<(Range<usize>, TokenKind)> = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let captures = cursor.captures(&self.query, new_tree.root_node(), text.as_bytes());
+        for (query_match, index) in captures {
+            let capture = query_match.captures[index];
+            let Some(kind) = token_kind(&self.query.capture_names()[capture.index as usize]) else {
+                continue;
+            };
+            tokens.push((capture.node.byte_range(), kind));
+        }
+        // captures can nest, the outermost one wins
+        tokens.sort_by_key(|(range, _)| (range.start, usize::MAX - range.end));
+        let mut end = 0;
+        tokens.retain(|(range, _)| {
+            let keep = range.start >= end;
+            if keep {
+                end = range.end;
+            }
+            keep
+        });
+
+        *tree = Some((new_tree, text.len()));
+        tokens
+    }
+
+    fn edit(&self, edit: &TextEdit) {
+        let mut tree = self.tree.lock().unwrap();
+        let Some((old_tree, len)) = tree.as_mut() else {
+            return;
+        };
+        if edit.range.end > *len {
+            // the edit is past what was parsed, start over
+            *tree = None;
+            return;
+        }
+        old_tree.edit(&InputEdit {
+            start_byte: edit.range.start,
+            old_end_byte: edit.range.end,
+            new_end_byte: edit.range.start + edit.new_len,
+            start_position: point(edit.start),
+            old_end_position: point(edit.old_end),
+            new_end_position: point(edit.new_end),
+        });
+        *len = *len + edit.new_len - edit.range.len();
+    }
+}
@@ -1,13 +1,38 @@
 use std::{
+    any::TypeId,
+    cell::OnceCell,
+    cmp::Reverse,
+    collections::HashMap,
     ops::Range,
+    rc::Rc,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use gpui::*;
 
 use smallvec::SmallVec;
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::theme::Theme;
+use crate::{
+    buffer::Buffer,
+    clipboard_history::copy_to_clipboard,
+    config::{BufferSettings, EditorSettings},
+    git::{diff_stats, shift_hunks, DiffHunk, DiffKind},
+    highlight::{
+        highlighter_for_language, line_comment_for_language, Highlighter, TextEdit, TokenKind,
+        DEFAULT_LINE_COMMENT,
+    },
+    keymap::{EditorAction, Keymap},
+    lint::{Diagnostic, LintMessage, Severity},
+    macros::{MacroRecorder, MacroStep},
+    paste::normalize_pasted_text,
+    snippet::{expand_snippet, Snippets, TabStops},
+    spell::{is_fence, Dictionary},
+    theme::{blend, Theme},
+    toast::{Toast, ToastQueue},
+    vim::Vim,
+};
 use gpui::prelude::FluentBuilder;
 
 #[derive(IntoElement)]
@@ -30,6 +55,8 @@ impl RenderOnce for Background {
         div()
             .bg(theme.background_color)
             .text_color(theme.text_color)
+            .font(theme.ui_font_family.clone())
+            .text_size(theme.ui_font_size)
             .size_full()
             .when(self.children.len() > 0, |this| this.children(self.children))
     }
@@ -44,14 +71,26 @@ impl ParentElement for Background {
 #[derive(IntoElement)]
 pub struct Layout {
     title_bar: AnyElement,
+    progress: Option<f32>,
+    tab_bar: Option<AnyElement>,
+    banner: Option<AnyElement>,
+    search_bar: Option<AnyElement>,
+    sidebar: Option<AnyElement>,
     body: AnyElement,
+    status_bar: Option<AnyElement>,
 }
 
 impl Layout {
     pub fn new() -> Self {
         Self {
             title_bar: TitleBar::new().into_any_element(),
+            progress: None,
+            tab_bar: None,
+            banner: None,
+            search_bar: None,
+            sidebar: None,
             body: div().into_any_element(),
+            status_bar: None,
         }
     }
 
@@ -60,35 +99,129 @@ impl Layout {
         self
     }
 
+    /// Shows a thin bar under the title bar, filled from 0 to 1.
+    pub fn progress(mut self, progress: Option<f32>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Shown under the title bar and its progress.
+    pub fn tab_bar(mut self, tab_bar: impl IntoElement) -> Self {
+        self.tab_bar = Some(tab_bar.into_any_element());
+        self
+    }
+
+    /// A notice about the file, shown above the search bar.
+    pub fn banner(mut self, banner: impl IntoElement) -> Self {
+        self.banner = Some(banner.into_any_element());
+        self
+    }
+
+    /// Left of the body and the search bar, e.g. a file tree.
+    pub fn sidebar(mut self, sidebar: impl IntoElement) -> Self {
+        self.sidebar = Some(sidebar.into_any_element());
+        self
+    }
+
+    /// Shown between the title bar and the body.
+    pub fn search_bar(mut self, search_bar: impl IntoElement) -> Self {
+        self.search_bar = Some(search_bar.into_any_element());
+        self
+    }
+
     pub fn body(mut self, body: impl IntoElement) -> Self {
         self.body = body.into_any_element();
         self
     }
+
+    /// Pinned to the bottom, under the body.
+    pub fn status_bar(mut self, status_bar: impl IntoElement) -> Self {
+        self.status_bar = Some(status_bar.into_any_element());
+        self
+    }
 }
 
 impl RenderOnce for Layout {
-    fn render(self, _cx: &mut WindowContext) -> impl IntoElement {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let bar_color = cx.global::<Theme>().cursor_color;
         div()
+            .size_full()
+            .flex()
+            .flex_col()
             .child(self.title_bar)
-            .child(div().p_6().child(self.body))
+            .children(self.progress.map(|progress| {
+                div()
+                    .h(px(2.))
+                    .w_full()
+                    .child(div().h_full().w(relative(progress)).bg(bar_color))
+            }))
+            .children(self.tab_bar)
+            .children(self.banner)
+            .child(
+                div()
+                    .flex_1()
+                    .min_h_0()
+                    .flex()
+                    .children(self.sidebar)
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .flex()
+                            .flex_col()
+                            .children(self.search_bar)
+                            .child(div().flex_1().min_h_0().p_6().child(self.body)),
+                    ),
+            )
+            .children(self.status_bar)
     }
 }
 
+/// How many chars of a title fit before its middle is left out.
+const MAX_TITLE_LEN: usize = 60;
+
+/// The top of the window, it moves the window when dragged. The children are
+/// controls on the right, they have to stop the mouse down from reaching the bar.
 #[derive(IntoElement)]
 pub struct TitleBar {
+    title: Option<SharedString>,
+    subtitle: Option<AnyElement>,
+    controls: Option<WindowControls>,
     children: SmallVec<[AnyElement; 2]>,
 }
 
 impl TitleBar {
     pub fn new() -> Self {
         TitleBar {
+            title: None,
+            subtitle: None,
+            controls: None,
             children: SmallVec::new(),
         }
     }
+
+    /// Centered in the bar, a long one loses its middle so the file name at
+    /// the end of a path stays visible.
+    pub fn title(mut self, title: impl Into<SharedString>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Shown after the title, e.g. whether the file has unsaved changes.
+    pub fn subtitle(mut self, subtitle: impl IntoElement) -> Self {
+        self.subtitle = Some(subtitle.into_any_element());
+        self
+    }
+
+    /// On the left on macOS and on the right elsewhere, like native ones.
+    pub fn window_controls(mut self, controls: WindowControls) -> Self {
+        self.controls = Some(controls);
+        self
+    }
 }
 
 impl RenderOnce for TitleBar {
-    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+    fn render(mut self, cx: &mut WindowContext) -> impl IntoElement {
         let theme = cx.global::<Theme>();
 
         div()
@@ -98,40 +231,518 @@ impl RenderOnce for TitleBar {
             .bg(theme.panel_color)
             .border_color(theme.border_color)
             .border_b()
-            .when(self.children.len() > 0, |this| this.children(self.children))
+            .on_mouse_down(MouseButton::Left, |ev, cx| {
+                if ev.click_count == 2 {
+                    cx.zoom_window();
+                } else {
+                    cx.start_window_move();
+                }
+            })
+            // both sides grow alike so the title stays centered
+            .child(
+                div()
+                    .flex_1()
+                    .flex()
+                    .items_center()
+                    .pl_3()
+                    .when(cfg!(target_os = "macos"), |this| {
+                        this.children(self.controls.take())
+                    }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_none()
+                    .items_center()
+                    .gap_2()
+                    .text_sm()
+                    .children(
+                        self.title
+                            .map(|title| truncate_middle(&title, MAX_TITLE_LEN)),
+                    )
+                    .children(self.subtitle),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .flex()
+                    .justify_end()
+                    .items_center()
+                    .gap_2()
+                    .text_sm()
+                    .children(self.children)
+                    .map(|this| match self.controls {
+                        Some(controls) => this.child(controls),
+                        None => this.mr_2(),
+                    }),
+            )
+    }
+}
+
+/// Close, minimize and zoom buttons for the `TitleBar`, circles on macOS and
+/// glyphs elsewhere. They dim while the window isn't active.
+#[derive(IntoElement)]
+pub struct WindowControls {
+    on_close: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>,
+}
+
+impl WindowControls {
+    /// Closing is left to `on_close`, so it can ask about unsaved changes first.
+    pub fn new(on_close: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>) -> Self {
+        Self { on_close }
+    }
+}
+
+impl RenderOnce for WindowControls {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let active = cx.is_window_active();
+        let on_close = self.on_close;
+        let buttons: [(&str, Hsla, Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>); 3] = [
+            ("✕", theme.close_button_color, on_close),
+            (
+                "—",
+                theme.minimize_button_color,
+                Box::new(|_ev: &MouseDownEvent, cx: &mut WindowContext| cx.minimize_window()),
+            ),
+            (
+                "□",
+                theme.zoom_button_color,
+                Box::new(|_ev: &MouseDownEvent, cx: &mut WindowContext| cx.zoom_window()),
+            ),
+        ];
+        let muted_color = theme.muted_color;
+        let hover_overlay = theme.button_hover_overlay;
+        let close_hover_color = theme.danger_color;
+        let panel_color = theme.panel_color;
+        let macos = cfg!(target_os = "macos");
+
+        div()
+            .flex()
+            .items_center()
+            .when(macos, |this| this.gap_2())
+            .children(
+                buttons
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (glyph, color, on_click))| {
+                        let button = div().on_mouse_down(MouseButton::Left, move |ev, cx| {
+                            // the title bar would move the window otherwise
+                            cx.stop_propagation();
+                            on_click(ev, cx);
+                        });
+                        if macos {
+                            let color = if active { color } else { muted_color };
+                            button
+                                .size_3()
+                                .rounded_full()
+                                .bg(color)
+                                .hover(|style| style.bg(blend(color, hover_overlay)))
+                        } else {
+                            let hover_color = if i == 0 {
+                                close_hover_color
+                            } else {
+                                blend(panel_color, hover_overlay)
+                            };
+                            button
+                                .w_10()
+                                .h_7()
+                                .flex()
+                                .items_center()
+                                .justify_center()
+                                .when(!active, |this| this.text_color(muted_color))
+                                .hover(|style| style.bg(hover_color))
+                                .child(glyph)
+                        }
+                    }),
+            )
     }
 }
 
+/// `text` with the middle replaced by "…" when it's longer than `max_len` chars.
+fn truncate_middle(text: &str, max_len: usize) -> SharedString {
+    let len = text.chars().count();
+    if len <= max_len {
+        return text.to_owned().into();
+    }
+    // more of the end stays, that's where a path has the file name
+    let tail = max_len * 2 / 3;
+    let head = max_len - 1 - tail;
+    let start: String = text.chars().take(head).collect();
+    let end: String = text.chars().skip(len - tail).collect();
+    format!("{start}…{end}").into()
+}
+
 impl ParentElement for TitleBar {
     fn extend(&mut self, elements: impl Iterator<Item = AnyElement>) {
         self.children.extend(elements);
     }
 }
 
+/// A row of `Tab`s, shown under the `TitleBar`.
+#[derive(IntoElement)]
+pub struct TabBar {
+    children: SmallVec<[AnyElement; 2]>,
+}
+
+impl TabBar {
+    pub fn new() -> Self {
+        TabBar {
+            children: SmallVec::new(),
+        }
+    }
+}
+
+impl RenderOnce for TabBar {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .h_8()
+            .flex()
+            .items_end()
+            .px_2()
+            .gap_1()
+            .bg(theme.panel_color)
+            .border_color(theme.border_color)
+            .border_b()
+            .when(self.children.len() > 0, |this| this.children(self.children))
+    }
+}
+
+impl ParentElement for TabBar {
+    fn extend(&mut self, elements: impl Iterator<Item = AnyElement>) {
+        self.children.extend(elements);
+    }
+}
+
+/// Diagnostics in the status bar are cut after this many chars.
+const MAX_STATUS_MESSAGE_LEN: usize = 80;
+
+/// The first line of a diagnostic's message, cut to fit the status bar.
+fn status_message(message: &str) -> String {
+    let line = message.lines().next().unwrap_or_default();
+    if line.chars().count() <= MAX_STATUS_MESSAGE_LEN {
+        return line.to_string();
+    }
+    let mut line: String = line.chars().take(MAX_STATUS_MESSAGE_LEN - 1).collect();
+    line.push('…');
+    line
+}
+
+/// The cursor's position and the size of a text, shown under the editor. It's
+/// a view of its own so it can follow the text without the workspace.
+pub struct StatusBar {
+    model: Option<Model<TextModel>>,
+    _subscriptions: Vec<Subscription>,
+    /// Picks the theme, on the left.
+    theme_select: View<Select<SharedString>>,
+    /// Picks tabs or spaces and how many for the text, on the right.
+    indent_select: View<Select<(bool, usize)>>,
+}
+
+/// How the status bar shows an indentation, e.g. "Spaces: 4".
+fn indent_label(hard_tabs: bool, tab_size: usize) -> String {
+    if hard_tabs {
+        format!("Tab Size: {tab_size}")
+    } else {
+        format!("Spaces: {tab_size}")
+    }
+}
+
+impl StatusBar {
+    pub fn new(cx: &mut ViewContext<Self>) -> Self {
+        let theme_select = cx.new_view(|cx| {
+            // a watched theme file may live outside the themes folder
+            let current = cx.global::<Theme>().name.clone();
+            let mut names = Theme::names();
+            if !names.contains(&current) {
+                names.push(current.clone());
+            }
+            let selected = names.iter().position(|name| *name == current).unwrap_or(0);
+            let options = names.into_iter().map(|name| (name.clone(), name)).collect();
+            Select::new(options, selected, cx)
+                .on_change(|name: &SharedString, cx| Theme::select(name, cx))
+        });
+        let status_bar = cx.view().downgrade();
+        let indent_select = cx.new_view(|cx| {
+            let default = cx.global::<EditorSettings>().buffer_settings();
+            let mut sizes = vec![2, 4, 8];
+            if !sizes.contains(&default.tab_size) {
+                sizes.push(default.tab_size);
+                sizes.sort();
+            }
+            let options: Vec<(SharedString, (bool, usize))> = [false, true]
+                .into_iter()
+                .flat_map(|hard_tabs| sizes.iter().map(move |size| (hard_tabs, *size)))
+                .map(|(hard_tabs, size)| (indent_label(hard_tabs, size).into(), (hard_tabs, size)))
+                .collect();
+            let selected = options
+                .iter()
+                .position(|(_, value)| *value == (default.hard_tabs, default.tab_size))
+                .unwrap_or(0);
+            Select::new(options, selected, cx).on_change(move |&(hard_tabs, tab_size), cx| {
+                // picked by hand, so it sticks
+                status_bar
+                    .update(cx, |this, cx| {
+                        if let Some(model) = &this.model {
+                            model.update(cx, |model, cx| {
+                                model.set_indentation(hard_tabs, tab_size, cx)
+                            });
+                        }
+                    })
+                    .ok();
+            })
+        });
+        cx.observe_global::<MacroRecorder>(|_this, cx| cx.notify())
+            .detach();
+        // the theme also changes by shortcut and when its file is edited
+        cx.observe_global::<Theme>(|this, cx| {
+            let name = cx.global::<Theme>().name.clone();
+            this.theme_select
+                .update(cx, |select, cx| select.set_selected(&name, cx));
+        })
+        .detach();
+        StatusBar {
+            model: None,
+            _subscriptions: Vec::new(),
+            theme_select,
+            indent_select,
+        }
+    }
+
+    /// Shows the indentation of the text in the select, e.g. after it was
+    /// detected in a file that was opened.
+    fn sync_indent_select(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(model) = &self.model else {
+            return;
+        };
+        let settings = &model.read(cx).settings;
+        let value = (settings.hard_tabs, settings.tab_size);
+        if *self.indent_select.read(cx).selected() != value {
+            self.indent_select
+                .update(cx, |select, cx| select.set_selected(&value, cx));
+        }
+    }
+
+    /// Shows the numbers of `model`, updated on each edit and cursor movement.
+    pub fn set_model(&mut self, model: Model<TextModel>, cx: &mut ViewContext<Self>) {
+        if self
+            .model
+            .as_ref()
+            .is_some_and(|current| current.entity_id() == model.entity_id())
+        {
+            return;
+        }
+        self._subscriptions = vec![
+            cx.subscribe(&model, |_this, _model, event, cx| {
+                if let TextEvent::Edited { .. } | TextEvent::SelectionChanged { .. } = event {
+                    cx.notify();
+                }
+            }),
+            // e.g. the vim mode or the indentation changing
+            cx.observe(&model, |this, _model, cx| {
+                this.sync_indent_select(cx);
+                cx.notify();
+            }),
+        ];
+        self.model = Some(model);
+        self.sync_indent_select(cx);
+        cx.notify();
+    }
+}
+
+impl Render for StatusBar {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let mut items: SmallVec<[String; 7]> = SmallVec::new();
+        if let Some(model) = &self.model {
+            let model = model.read(cx);
+            if let Some(vim) = &model.vim {
+                items.push(vim.mode.label().to_string());
+            }
+            if cx.global::<MacroRecorder>().is_recording_any() {
+                items.push("Recording macro".to_string());
+            }
+            if let Some(diagnostic) = model.diagnostic_at(model.head()) {
+                items.push(status_message(&diagnostic.message));
+            }
+            let (row, column) = model.cursor_position();
+            items.push(format!("Ln {}, Col {}", row + 1, column + 1));
+            if !model.selection.is_empty() {
+                items.push(format!("{} selected", model.selected_char_count()));
+            }
+            if let Some(hunks) = model
+                .diff_hunks
+                .as_deref()
+                .filter(|hunks| !hunks.is_empty())
+            {
+                let (added, modified, removed) = diff_stats(hunks);
+                items.push(format!("+{added} ~{modified} −{removed}"));
+            }
+            items.push(format!(
+                "{} lines, {} words, {} chars",
+                model.buffer().line_count(),
+                model.word_ranges().len(),
+                model.char_count()
+            ));
+        }
+
+        div()
+            .h_6()
+            .flex()
+            .items_center()
+            .justify_between()
+            .gap_4()
+            .px_4()
+            .text_xs()
+            .text_color(theme.line_number_color)
+            .bg(theme.panel_color)
+            .border_color(theme.border_color)
+            .border_t()
+            .child(self.theme_select.clone())
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_4()
+                    .children(items)
+                    .when(self.model.is_some(), |this| {
+                        this.child(self.indent_select.clone())
+                    }),
+            )
+    }
+}
+
+/// One file in a `TabBar`, with a dot while it has unsaved changes and a
+/// button that closes it.
+#[derive(IntoElement)]
+pub struct Tab {
+    title: SharedString,
+    active: bool,
+    dirty: bool,
+    on_click: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>,
+    on_close: Option<Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>>,
+}
+
+impl Tab {
+    pub fn new(
+        title: impl Into<SharedString>,
+        on_click: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            active: false,
+            dirty: false,
+            on_click,
+            on_close: None,
+        }
+    }
+
+    pub fn active(mut self, active: bool) -> Self {
+        self.active = active;
+        self
+    }
+
+    pub fn dirty(mut self, dirty: bool) -> Self {
+        self.dirty = dirty;
+        self
+    }
+
+    pub fn on_close(mut self, on_close: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>) -> Self {
+        self.on_close = Some(on_close);
+        self
+    }
+}
+
+impl RenderOnce for Tab {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let muted_color = theme.muted_color;
+
+        div()
+            .h_7()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_3()
+            .rounded_t_md()
+            .border_1()
+            .border_b_0()
+            .text_sm()
+            .cursor_pointer()
+            .map(|this| {
+                if self.active {
+                    this.bg(theme.background_color)
+                        .border_color(theme.border_color)
+                } else {
+                    this.border_color(transparent_black())
+                        .text_color(theme.line_number_color)
+                }
+            })
+            .on_mouse_down(MouseButton::Left, self.on_click)
+            .child(self.title)
+            .when(self.dirty, |this| this.child("●"))
+            .children(self.on_close.map(|on_close| {
+                div()
+                    .px_1()
+                    .rounded_sm()
+                    .hover(|style| style.bg(muted_color))
+                    .on_mouse_down(MouseButton::Left, move |ev, cx| {
+                        // the tab itself would be activated too
+                        cx.stop_propagation();
+                        on_close(ev, cx);
+                    })
+                    .child("×")
+            }))
+    }
+}
+
+#[derive(Clone, Copy)]
 pub enum ButtonVariant {
     Primary,
+    /// For the quieter choice next to a primary or danger button.
+    Secondary,
+    /// Only shows a background while hovered, e.g. for toolbar toggles.
+    Ghost,
     Danger,
 }
 
+/// Passed to a `Button`'s `on_click`, whether the mouse or enter and space
+/// while it has focus clicked it.
+pub struct ButtonEvent;
+
+/// Clicks when the mouse is pressed and released on it, releasing it
+/// elsewhere cancels.
 #[derive(IntoElement)]
 pub struct Button {
+    id: ElementId,
     base: Div,
     child: AnyElement,
-    on_click: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>,
+    on_click: Box<dyn Fn(&ButtonEvent, &mut WindowContext)>,
     variant: ButtonVariant,
+    disabled: bool,
+    compact: bool,
+    focus_handle: Option<FocusHandle>,
 }
 
 impl Button {
-    #[allow(dead_code)]
+    /// `id` tells the button apart from the others in its parent, it keeps
+    /// track of the press between renders.
     pub fn new(
+        id: impl Into<ElementId>,
         child: impl IntoElement,
-        on_click: Box<dyn Fn(&MouseDownEvent, &mut WindowContext)>,
+        on_click: Box<dyn Fn(&ButtonEvent, &mut WindowContext)>,
     ) -> Self {
         Self {
+            id: id.into(),
             base: div(),
             child: child.into_any_element(),
             on_click,
             variant: ButtonVariant::Primary,
+            disabled: false,
+            compact: false,
+            focus_handle: None,
         }
     }
 
@@ -140,10 +751,34 @@ impl Button {
         self
     }
 
-    pub fn color(&self, theme: &Theme) -> Hsla {
+    /// Fades the button and ignores clicks and keys.
+    pub fn disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    /// A small square around a single glyph, e.g. in the title bar.
+    pub fn compact(mut self) -> Self {
+        self.compact = true;
+        self
+    }
+
+    /// Lets the button take focus, then enter and space click it. The owner
+    /// keeps the handle, the button is built anew on every render.
+    pub fn focus_handle(mut self, focus_handle: &FocusHandle) -> Self {
+        self.focus_handle = Some(focus_handle.clone());
+        self
+    }
+
+    /// The background, text and border colors of the variant. Hovering and
+    /// pressing lay `Theme::button_hover_overlay` over the background once
+    /// and twice, so every variant works in light and dark themes.
+    pub fn variant_style(&self, theme: &Theme) -> (Hsla, Hsla, Hsla) {
         match self.variant {
-            ButtonVariant::Primary => theme.primary_color,
-            ButtonVariant::Danger => theme.danger_color,
+            ButtonVariant::Primary => (theme.primary_color, theme.text_color, transparent_black()),
+            ButtonVariant::Secondary => (theme.panel_color, theme.text_color, theme.border_color),
+            ButtonVariant::Ghost => (transparent_black(), theme.text_color, transparent_black()),
+            ButtonVariant::Danger => (theme.danger_color, theme.text_color, transparent_black()),
         }
     }
 }
@@ -152,19 +787,63 @@ impl RenderOnce for Button {
     fn render(self, cx: &mut WindowContext) -> impl IntoElement {
         let theme = cx.global::<Theme>();
 
-        let color = self.color(theme);
-        let hover_color = hsla(color.h, color.s, (color.l - 0.08).clamp(0., 1.), color.a);
+        let (mut color, mut text_color, border_color) = self.variant_style(theme);
+        let hover_color = blend(color, theme.button_hover_overlay);
+        let active_color = blend(hover_color, theme.button_hover_overlay);
+        let focus_color = theme.primary_color;
+        let disabled = self.disabled;
+        if disabled {
+            color.a *= 0.5;
+            text_color.a *= 0.5;
+        }
+        let bordered = matches!(self.variant, ButtonVariant::Secondary);
+        let compact = self.compact;
+        let on_click: Rc<dyn Fn(&ButtonEvent, &mut WindowContext)> = self.on_click.into();
+        let on_key = on_click.clone();
 
-        self.base
-            .p_2()
+        let button = self
+            .base
+            .id(self.id)
+            .map(|this| {
+                if compact {
+                    this.size_6().p_1()
+                } else {
+                    this.p_2()
+                }
+            })
             .rounded_md()
-            .hover(|style| style.bg(hover_color))
+            // the caller's border stays unless the variant has its own
+            .when(bordered, |this| this.border_1().border_color(border_color))
             .flex()
             .justify_center()
             .items_center()
             .bg(color)
-            .on_mouse_down(MouseButton::Left, self.on_click)
-            .child(self.child)
+            .text_color(text_color)
+            // a press never reaches what's behind, e.g. a `TitleBar` would
+            // move the window
+            .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+            .when(!disabled, |this| {
+                this.hover(|style| style.bg(hover_color))
+                    .active(|style| style.bg(active_color))
+                    .on_click(move |_ev, cx| on_click(&ButtonEvent, cx))
+            })
+            .child(self.child);
+        match self.focus_handle {
+            Some(focus_handle) => button
+                .track_focus(&focus_handle)
+                .border_2()
+                .border_color(transparent_black())
+                .focus(|style| style.border_color(focus_color))
+                .on_key_down(move |ev: &KeyDownEvent, cx| {
+                    let key = ev.keystroke.key.as_str();
+                    if !disabled && (key == "enter" || key == "space") {
+                        cx.stop_propagation();
+                        on_key(&ButtonEvent, cx);
+                    }
+                })
+                .into_any_element(),
+            None => button.into_any_element(),
+        }
     }
 }
 
@@ -174,292 +853,4849 @@ impl Styled for Button {
     }
 }
 
-#[derive(IntoElement, Clone)]
-pub struct TextInput {
-    focus_handle: FocusHandle,
-    view: View<TextDisplay>,
-    pub model: Model<TextModel>,
+/// An entry of a `ContextMenu`.
+pub struct ContextMenuItem {
+    label: SharedString,
+    shortcut: Option<SharedString>,
+    disabled: bool,
+    on_select: Rc<dyn Fn(&mut WindowContext)>,
 }
 
-impl TextInput {
-    pub fn new(cx: &mut WindowContext, initial_text: String) -> Self {
-        let model = TextModel::init(initial_text.clone(), cx);
-        let clone = model.clone();
-        let view = cx.new_view(move |cx| {
-            let view = TextDisplay {
-                model: clone.clone(),
-            };
-            cx.subscribe(&clone, |_subscriber, _emitter, event, cx| match event {
-                TextEvent::Input { text: _ } => {
-                    cx.notify();
-                }
-                _ => {}
-            })
-            .detach();
-            view
-        });
+impl ContextMenuItem {
+    pub fn new(
+        label: impl Into<SharedString>,
+        on_select: impl Fn(&mut WindowContext) + 'static,
+    ) -> Self {
         Self {
-            focus_handle: cx.focus_handle(),
-            view,
-            model,
+            label: label.into(),
+            shortcut: None,
+            disabled: false,
+            on_select: Rc::new(on_select),
         }
     }
+
+    /// The keys doing the same, shown on the right.
+    pub fn shortcut(mut self, shortcut: impl Into<SharedString>) -> Self {
+        self.shortcut = Some(shortcut.into());
+        self
+    }
+
+    /// Greys the item out, it can't be selected.
+    pub fn disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
 }
 
-pub struct TextModel {
-    pub text: String,
-    pub selection: Range<usize>,
-    pub word_click: (usize, u16),
+pub enum ContextMenuEvent {
+    /// An item was selected, or the menu was dismissed. The owner drops it.
+    Dismissed,
 }
 
-impl TextModel {
-    pub fn init(text: String, cx: &mut WindowContext) -> Model<Self> {
-        let i = text.len();
-        let m = Self {
-            text,
-            selection: i..i,
-            word_click: (0, 0),
-        };
-        let model = cx.new_model(|_cx| m);
-        cx.subscribe(
-            &model,
-            |subscriber, emitter: &TextEvent, cx| match emitter {
-                TextEvent::Input { text: _ } => {
-                    subscriber.update(cx, |editor, _cx| {
-                        editor.word_click = (0, 0);
-                    });
-                }
-                _ => {}
-            },
-        )
-        .detach();
-        model
+/// A list of items at the mouse. It takes the focus while open, up and down
+/// move between the items and enter selects one. Escape or a click anywhere
+/// else dismisses it.
+pub struct ContextMenu {
+    /// Where the menu's top left corner goes, in window coordinates.
+    position: Point<Pixels>,
+    items: Vec<ContextMenuItem>,
+    /// The item enter selects.
+    highlighted: Option<usize>,
+    focus_handle: FocusHandle,
+}
+
+impl EventEmitter<ContextMenuEvent> for ContextMenu {}
+
+impl ContextMenu {
+    pub fn new(
+        position: Point<Pixels>,
+        items: Vec<ContextMenuItem>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let focus_handle = cx.focus_handle();
+        cx.focus(&focus_handle);
+        Self {
+            position,
+            items,
+            highlighted: None,
+            focus_handle,
+        }
     }
-    pub fn reset(&mut self, cx: &mut ModelContext<Self>) {
-        self.text = "".to_string();
-        self.selection = 0..0;
+
+    /// Highlights the next enabled item below, or above, wrapping around.
+    fn move_highlight(&mut self, up: bool, cx: &mut ViewContext<Self>) {
+        let count = self.items.len();
+        let start = match (self.highlighted, up) {
+            (Some(index), _) => index,
+            // the first step lands on the first or the last item
+            (None, false) => count.saturating_sub(1),
+            (None, true) => 0,
+        };
+        self.highlighted = (1..=count)
+            .map(|step| {
+                if up {
+                    (start + count - step % count) % count
+                } else {
+                    (start + step) % count
+                }
+            })
+            .find(|index| !self.items[*index].disabled)
+            .or(self.highlighted);
         cx.notify();
-        cx.emit(TextEvent::Input {
-            text: self.text.clone(),
-        });
     }
-    pub fn word_ranges(&self) -> Vec<Range<usize>> {
-        let mut words = Vec::new();
-        let mut last_was_boundary = true;
-        let mut word_start = 0;
-        let s = self.text.clone();
 
-        for (i, c) in s.char_indices() {
-            if c.is_alphanumeric() || c == '_' {
-                if last_was_boundary {
-                    word_start = i;
-                }
-                last_was_boundary = false;
-            } else {
-                if !last_was_boundary {
-                    words.push(word_start..i);
+    fn select(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some(item) = self.items.get(index) else {
+            return;
+        };
+        if item.disabled {
+            return;
+        }
+        let on_select = item.on_select.clone();
+        cx.emit(ContextMenuEvent::Dismissed);
+        on_select(cx);
+    }
+
+    fn on_key_down(&mut self, ev: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        // the text underneath doesn't get keys while the menu is open
+        cx.stop_propagation();
+        match ev.keystroke.key.as_str() {
+            "up" => self.move_highlight(true, cx),
+            "down" => self.move_highlight(false, cx),
+            "enter" => {
+                if let Some(index) = self.highlighted {
+                    self.select(index, cx);
                 }
-                last_was_boundary = true;
             }
+            "escape" => cx.emit(ContextMenuEvent::Dismissed),
+            _ => {}
         }
+    }
+}
 
-        // Check if the last characters form a word and push it if so
-        if !last_was_boundary {
-            words.push(word_start..s.len());
+impl Render for ContextMenu {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let highlight_color = blend(theme.panel_color, theme.button_hover_overlay);
+        let (panel_color, border_color) = (theme.panel_color, theme.border_color);
+        let (text_color, muted_color) = (theme.text_color, theme.muted_color);
+
+        overlay().position(self.position).child(
+            div()
+                .track_focus(&self.focus_handle)
+                .on_key_down(cx.listener(Self::on_key_down))
+                .on_mouse_down_out(
+                    cx.listener(|_this, _ev, cx| cx.emit(ContextMenuEvent::Dismissed)),
+                )
+                // keeps presses from moving the cursor of the text underneath
+                .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                .on_mouse_down(MouseButton::Right, |_ev, cx| cx.stop_propagation())
+                .min_w_48()
+                .py_1()
+                .rounded_md()
+                .border_1()
+                .border_color(border_color)
+                .bg(panel_color)
+                .shadow_md()
+                .text_sm()
+                .children(self.items.iter().enumerate().map(|(index, item)| {
+                    div()
+                        .id(("context-menu-item", index))
+                        .flex()
+                        .justify_between()
+                        .gap_4()
+                        .px_3()
+                        .py_1()
+                        .text_color(if item.disabled {
+                            muted_color
+                        } else {
+                            text_color
+                        })
+                        .when(self.highlighted == Some(index), |this| {
+                            this.bg(highlight_color)
+                        })
+                        .when(!item.disabled, |this| {
+                            this.hover(|style| style.bg(highlight_color))
+                                .on_click(cx.listener(move |this, _ev, cx| this.select(index, cx)))
+                        })
+                        .child(item.label.clone())
+                        .children(
+                            item.shortcut
+                                .clone()
+                                .map(|shortcut| div().text_color(muted_color).child(shortcut)),
+                        )
+                })),
+        )
+    }
+}
+
+/// The height of one option in a `Select`'s popup.
+const SELECT_OPTION_HEIGHT: Pixels = px(24.);
+/// More options than this scroll inside the popup.
+const MAX_VISIBLE_OPTIONS: usize = 8;
+/// Keys typed within this long of each other jump to an option together.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A button showing the current option that opens a list of all of them.
+/// While the list is open up and down move through it, typing jumps to the
+/// first option starting with the typed text, enter picks the highlighted
+/// option and escape closes the list without changing anything.
+pub struct Select<T> {
+    /// Each value with the text it's shown as.
+    options: Vec<(SharedString, T)>,
+    selected: usize,
+    open: bool,
+    /// The option enter picks while the list is open.
+    highlighted: usize,
+    /// What was typed to jump to an option, and when the last key came.
+    typed: String,
+    typed_at: Option<Instant>,
+    focus_handle: FocusHandle,
+    scroll_handle: ScrollHandle,
+    /// Where the button was last painted, the list opens below or above it.
+    trigger_bounds: Option<Bounds<Pixels>>,
+    on_change: Option<Box<dyn Fn(&T, &mut WindowContext)>>,
+}
+
+impl<T: 'static> Select<T> {
+    pub fn new(
+        options: Vec<(SharedString, T)>,
+        selected: usize,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self {
+            options,
+            selected,
+            open: false,
+            highlighted: selected,
+            typed: String::new(),
+            typed_at: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: ScrollHandle::new(),
+            trigger_bounds: None,
+            on_change: None,
         }
+    }
 
-        words
+    /// Called when an option other than the current one is picked.
+    pub fn on_change(mut self, on_change: impl Fn(&T, &mut WindowContext) + 'static) -> Self {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
+    pub fn selected(&self) -> &T {
+        &self.options[self.selected].1
+    }
+
+    /// Shows the option with `value` as the current one without calling
+    /// `on_change`, e.g. when the value changed elsewhere.
+    pub fn set_selected(&mut self, value: &T, cx: &mut ViewContext<Self>)
+    where
+        T: PartialEq,
+    {
+        if let Some(index) = self.options.iter().position(|(_, option)| option == value) {
+            self.selected = index;
+            cx.notify();
+        }
+    }
+
+    fn open(&mut self, cx: &mut ViewContext<Self>) {
+        self.open = true;
+        self.typed.clear();
+        self.highlight(self.selected, cx);
+    }
+
+    fn close(&mut self, cx: &mut ViewContext<Self>) {
+        self.open = false;
+        cx.notify();
+    }
+
+    fn highlight(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        self.highlighted = index;
+        self.scroll_handle.scroll_to_item(index);
+        cx.notify();
+    }
+
+    /// Makes the option at `index` the current one and closes the list.
+    fn confirm(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        self.close(cx);
+        if index == self.selected {
+            return;
+        }
+        self.selected = index;
+        if let Some(on_change) = &self.on_change {
+            on_change(&self.options[index].1, cx);
+        }
+    }
+
+    /// Adds `text` to what was typed and highlights the first option starting
+    /// with it. When none does, the option after the highlighted one starting
+    /// with `text` alone is tried, so typing a letter again cycles.
+    fn type_ahead(&mut self, text: &str, cx: &mut ViewContext<Self>) {
+        let now = Instant::now();
+        if self
+            .typed_at
+            .map_or(true, |typed_at| now - typed_at > TYPE_AHEAD_TIMEOUT)
+        {
+            self.typed.clear();
+        }
+        self.typed_at = Some(now);
+        self.typed.push_str(&text.to_lowercase());
+        let starts_with =
+            |index: &usize, prefix: &str| self.options[*index].0.to_lowercase().starts_with(prefix);
+        let count = self.options.len();
+        let found = (0..count)
+            .find(|index| starts_with(index, &self.typed))
+            .or_else(|| {
+                let text = text.to_lowercase();
+                (1..=count)
+                    .map(|step| (self.highlighted + step) % count)
+                    .find(|index| starts_with(index, &text))
+            });
+        if let Some(index) = found {
+            self.highlight(index, cx);
+        }
+    }
+
+    fn on_key_down(&mut self, ev: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let count = self.options.len();
+        if count == 0 {
+            return;
+        }
+        let key = ev.keystroke.key.as_str();
+        if !self.open {
+            if matches!(key, "enter" | "space" | "up" | "down") {
+                cx.stop_propagation();
+                self.open(cx);
+            }
+            return;
+        }
+        cx.stop_propagation();
+        match key {
+            "up" => self.highlight((self.highlighted + count - 1) % count, cx),
+            "down" => self.highlight((self.highlighted + 1) % count, cx),
+            "enter" => self.confirm(self.highlighted, cx),
+            "escape" => self.close(cx),
+            _ => {
+                if let Some(text) = &ev.keystroke.ime_key {
+                    self.type_ahead(text, cx);
+                }
+            }
+        }
+    }
+
+    /// The list of options, below the button or above it when there's more
+    /// room there.
+    fn render_options(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let highlight_color = blend(theme.panel_color, theme.button_hover_overlay);
+        let (panel_color, border_color) = (theme.panel_color, theme.border_color);
+
+        let rows = self.options.len().min(MAX_VISIBLE_OPTIONS);
+        // the options, the vertical padding and the border
+        let height = SELECT_OPTION_HEIGHT * rows as f32 + px(10.);
+        let trigger = self.trigger_bounds.unwrap_or_default();
+        let room_below = cx.viewport_size().height - trigger.bottom();
+        let room_above = trigger.top();
+        let (position, anchor) = if room_below < height && room_above > room_below {
+            (trigger.origin, AnchorCorner::BottomLeft)
+        } else {
+            (trigger.lower_left(), AnchorCorner::TopLeft)
+        };
+
+        overlay().position(position).anchor(anchor).child(
+            div()
+                .id("select-options")
+                .track_scroll(&self.scroll_handle)
+                .overflow_y_scroll()
+                .max_h(SELECT_OPTION_HEIGHT * MAX_VISIBLE_OPTIONS as f32 + px(10.))
+                .min_w(trigger.size.width)
+                .py_1()
+                .rounded_md()
+                .border_1()
+                .border_color(border_color)
+                .bg(panel_color)
+                .shadow_md()
+                .on_mouse_down_out(cx.listener(|this, ev: &MouseDownEvent, cx| {
+                    // a click on the button closes the list itself
+                    let on_trigger = this
+                        .trigger_bounds
+                        .is_some_and(|bounds| bounds.contains(&ev.position));
+                    if !on_trigger {
+                        this.close(cx);
+                    }
+                }))
+                .children(self.options.iter().enumerate().map(|(index, (label, _))| {
+                    div()
+                        .id(("select-option", index))
+                        .h(SELECT_OPTION_HEIGHT)
+                        .flex()
+                        .items_center()
+                        .px_2()
+                        .when(index == self.highlighted, |this| this.bg(highlight_color))
+                        .on_mouse_move(cx.listener(move |this, _ev, cx| {
+                            if this.highlighted != index {
+                                this.highlight(index, cx);
+                            }
+                        }))
+                        .on_click(cx.listener(move |this, _ev, cx| this.confirm(index, cx)))
+                        .child(label.clone())
+                })),
+        )
     }
 }
 
-pub enum TextEvent {
-    Input { text: String },
+impl<T: 'static> Render for Select<T> {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let hover_color = blend(theme.panel_color, theme.button_hover_overlay);
+        let (panel_color, border_color, focus_color) =
+            (theme.panel_color, theme.border_color, theme.primary_color);
+        let label = self
+            .options
+            .get(self.selected)
+            .map(|(label, _)| label.clone())
+            .unwrap_or_default();
+        let view = cx.view().clone();
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .child(
+                div()
+                    .id("select-trigger")
+                    .relative()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .px_2()
+                    .rounded_md()
+                    .border_1()
+                    .border_color(if self.focus_handle.is_focused(cx) {
+                        focus_color
+                    } else {
+                        border_color
+                    })
+                    .bg(panel_color)
+                    .hover(|style| style.bg(hover_color))
+                    .cursor_pointer()
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _ev, cx| {
+                            cx.stop_propagation();
+                            this.focus_handle.focus(cx);
+                            if this.open {
+                                this.close(cx);
+                            } else {
+                                this.open(cx);
+                            }
+                        }),
+                    )
+                    .child(label)
+                    .child("▾")
+                    .child(
+                        canvas(move |bounds, cx| {
+                            view.update(cx, |this, _cx| this.trigger_bounds = Some(bounds));
+                        })
+                        .absolute()
+                        .top_0()
+                        .left_0()
+                        .size_full(),
+                    ),
+            )
+            .when(self.open, |this| this.child(self.render_options(cx)))
+    }
+}
+
+pub enum ListEvent {
+    /// Enter was pressed on the highlighted item, or the item was clicked
+    /// unless `List::set_activate_on_click` turned that off.
+    Activate(usize),
+    /// An item was clicked, `click_count` is 2 for a double click.
+    Click { index: usize, click_count: usize },
+    /// A move up from the first item or down from the last, the owner may
+    /// take the focus elsewhere.
     Movement(TextMovement),
 }
-pub enum TextMovement {
-    Up,
-    Down,
+
+/// Items drawn by a closure, only the visible ones are rendered so long lists
+/// stay fast. One item is highlighted: the mouse and up, down, home and end
+/// move it, enter or a click activates it. An input can steer the list while
+/// keeping the focus, see `List::follow_input`.
+pub struct List {
+    item_count: usize,
+    highlighted: Option<usize>,
+    /// Renders the item at an index, told whether it's highlighted.
+    render_item: Box<dyn Fn(usize, bool, &mut WindowContext) -> AnyElement>,
+    activate_on_click: bool,
+    focus_handle: FocusHandle,
+    scroll_handle: UniformListScrollHandle,
+    _input_subscription: Option<Subscription>,
 }
 
-impl EventEmitter<TextEvent> for TextModel {}
+impl EventEmitter<ListEvent> for List {}
 
-impl RenderOnce for TextInput {
-    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+impl List {
+    pub fn new(
+        item_count: usize,
+        render_item: impl Fn(usize, bool, &mut WindowContext) -> AnyElement + 'static,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self {
+            item_count,
+            highlighted: (item_count > 0).then_some(0),
+            render_item: Box::new(render_item),
+            activate_on_click: true,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: UniformListScrollHandle::new(),
+            _input_subscription: None,
+        }
+    }
+
+    /// For when the items changed, the first one is highlighted again.
+    pub fn set_item_count(&mut self, item_count: usize, cx: &mut ViewContext<Self>) {
+        self.item_count = item_count;
+        self.highlighted = None;
+        if item_count > 0 {
+            self.highlight(0, cx);
+        }
+        cx.notify();
+    }
+
+    /// Off, a click only sends `ListEvent::Click` and the owner decides what
+    /// it does.
+    pub fn set_activate_on_click(&mut self, activate_on_click: bool) {
+        self.activate_on_click = activate_on_click;
+    }
+
+    pub fn highlighted(&self) -> Option<usize> {
+        self.highlighted
+    }
+
+    /// Highlights the item at `index` and scrolls it into view.
+    pub fn highlight(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        if index >= self.item_count {
+            return;
+        }
+        self.highlighted = Some(index);
+        self.scroll_handle.scroll_to_item(index);
+        cx.notify();
+    }
+
+    /// Moves the highlight one item up or down. Past either end the movement
+    /// is passed on as `ListEvent::Movement`.
+    pub fn move_highlight(&mut self, movement: TextMovement, cx: &mut ViewContext<Self>) {
+        let next = match (movement, self.highlighted) {
+            (_, None) => Some(0),
+            (TextMovement::Up, Some(index)) => index.checked_sub(1),
+            (TextMovement::Down, Some(index)) => Some(index + 1),
+        };
+        match next.filter(|next| *next < self.item_count) {
+            Some(index) => self.highlight(index, cx),
+            None => cx.emit(ListEvent::Movement(movement)),
+        }
+    }
+
+    pub fn activate(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(index) = self.highlighted {
+            cx.emit(ListEvent::Activate(index));
+        }
+    }
+
+    /// Lets the input of `model` steer the list while it has the focus: its
+    /// up and down move the highlight and submitting activates the item.
+    pub fn follow_input(&mut self, model: &Model<TextModel>, cx: &mut ViewContext<Self>) {
+        self._input_subscription =
+            Some(cx.subscribe(model, |this, _model, event, cx| match event {
+                TextEvent::Movement(movement) => this.move_highlight(*movement, cx),
+                TextEvent::Submit { .. } => this.activate(cx),
+                _ => {}
+            }));
+    }
+
+    pub fn focus(&self, cx: &mut WindowContext) {
         cx.focus(&self.focus_handle);
+    }
+
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.focus_handle.is_focused(cx)
+    }
+
+    fn on_key_down(&mut self, ev: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        match ev.keystroke.key.as_str() {
+            "up" => self.move_highlight(TextMovement::Up, cx),
+            "down" => self.move_highlight(TextMovement::Down, cx),
+            "home" => self.highlight(0, cx),
+            "end" => self.highlight(self.item_count.saturating_sub(1), cx),
+            "enter" => self.activate(cx),
+            _ => return,
+        }
+        cx.stop_propagation();
+    }
+}
 
+impl Render for List {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
         let theme = cx.global::<Theme>();
+        let highlight_color = blend(theme.panel_color, theme.button_hover_overlay);
 
         div()
             .track_focus(&self.focus_handle)
-            .on_key_down(move |ev, cx| {
-                self.model.update(cx, |editor, cx| {
-                    let keystroke = &ev.keystroke.key;
-                    if ev.keystroke.modifiers.command {
-                        match keystroke.as_str() {
-                            "a" => {
-                                editor.selection = 0..editor.text.len();
-                            }
-                            "c" => {
-                                let selected_text =
-                                    editor.text[editor.selection.clone()].to_string();
-                                cx.write_to_clipboard(ClipboardItem::new(selected_text));
-                            }
-                            "v" => {
-                                let clipboard = cx.read_from_clipboard();
-                                if let Some(clipboard) = clipboard {
-                                    let text = clipboard.text();
-                                    editor.text.replace_range(editor.selection.clone(), &text);
-                                    let i = editor.selection.start + text.len();
-                                    editor.selection = i..i;
-                                }
-                            }
-                            "x" => {
-                                let selected_text =
-                                    editor.text[editor.selection.clone()].to_string();
-                                cx.write_to_clipboard(ClipboardItem::new(selected_text));
-                                editor.text.replace_range(editor.selection.clone(), "");
-                                editor.selection.end = editor.selection.start;
-                            }
-                            _ => {}
-                        }
-                    } else if let Some(ime_key) = &ev.keystroke.ime_key {
-                        editor.text.replace_range(editor.selection.clone(), ime_key);
-                        let i = editor.selection.start + ime_key.len();
-                        editor.selection = i..i;
-                    } else {
-                        match keystroke.as_str() {
-                            "up" => {
-                                cx.emit(TextEvent::Movement(TextMovement::Up));
-                                return;
-                            }
-                            "down" => {
-                                cx.emit(TextEvent::Movement(TextMovement::Down));
-                                return;
-                            }
-                            "left" => {
-                                if editor.selection.start > 0 {
-                                    let i = if editor.selection.start == editor.selection.end {
-                                        editor.selection.start - 1
-                                    } else {
-                                        editor.selection.start
-                                    };
-                                    editor.selection = i..i;
-                                }
-                            }
-                            "right" => {
-                                if editor.selection.end < editor.text.len() {
-                                    let i = if editor.selection.start == editor.selection.end {
-                                        editor.selection.end + 1
-                                    } else {
-                                        editor.selection.end
-                                    };
-                                    editor.selection = i..i;
-                                }
-                            }
-                            "backspace" => {
-                                if editor.selection.start == editor.selection.end
-                                    && editor.selection.start > 0
-                                {
-                                    // necessary for non-ascii characters
-                                    let mut start = editor.text[..editor.selection.start].chars();
-                                    start.next_back();
-                                    let start = start.as_str();
-                                    let i = start.len();
-                                    editor.text =
-                                        start.to_owned() + &editor.text[editor.selection.end..];
-                                    editor.selection = i..i;
-                                } else {
-                                    editor.text.replace_range(editor.selection.clone(), "");
-                                    editor.selection.end = editor.selection.start;
-                                }
-                            }
-                            "enter" => {
-                                editor.text.insert(editor.selection.start, '\n');
-                                let i = editor.selection.start + 1;
-                                editor.selection = i..i;
-                            }
-                            "escape" => {
-                                cx.hide();
-                            }
-                            keystroke_str => {
-                                eprintln!("Unhandled keystroke {keystroke_str}")
-                            }
-                        };
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .child(
+                uniform_list(
+                    cx.view().clone(),
+                    "list",
+                    self.item_count,
+                    move |this, range, cx| {
+                        range
+                            .map(|index| {
+                                let highlighted = this.highlighted == Some(index);
+                                div()
+                                    .id(("list-item", index))
+                                    .when(highlighted, |this| this.bg(highlight_color))
+                                    .on_mouse_move(cx.listener(move |this, _ev, cx| {
+                                        if this.highlighted != Some(index) {
+                                            this.highlighted = Some(index);
+                                            cx.notify();
+                                        }
+                                    }))
+                                    .on_click(cx.listener(move |this, ev: &ClickEvent, cx| {
+                                        cx.emit(ListEvent::Click {
+                                            index,
+                                            click_count: ev.up.click_count,
+                                        });
+                                        if this.activate_on_click {
+                                            cx.emit(ListEvent::Activate(index));
+                                        }
+                                    }))
+                                    .child((this.render_item)(index, highlighted, cx))
+                            })
+                            .collect()
+                    },
+                )
+                .track_scroll(self.scroll_handle.clone())
+                .size_full(),
+            )
+    }
+}
+
+#[derive(IntoElement, Clone)]
+pub struct TextInput {
+    focus_handle: FocusHandle,
+    view: View<TextDisplay>,
+    pub model: Model<TextModel>,
+    invalid: bool,
+}
+
+impl TextInput {
+    pub fn new(cx: &mut WindowContext, initial_text: String) -> Self {
+        let model = TextModel::init(initial_text.clone(), cx);
+        Self::with_model(model, cx)
+    }
+
+    /// Another input showing `model`, edits made in either show up in both.
+    pub fn with_model(model: Model<TextModel>, cx: &mut WindowContext) -> Self {
+        let focus_handle = cx.focus_handle();
+        let view = cx.new_view({
+            let model = model.clone();
+            let focus_handle = focus_handle.clone();
+            move |cx| TextDisplay::new(model, focus_handle, cx)
+        });
+        Self {
+            focus_handle,
+            view,
+            model,
+            invalid: false,
+        }
+    }
+
+    /// Turns the input into a one-line field where enter emits `TextEvent::Submit`
+    /// instead of inserting a newline.
+    pub fn single_line(self, cx: &mut WindowContext) -> Self {
+        self.model
+            .update(cx, |editor, _cx| editor.single_line = true);
+        self
+    }
+
+    /// Draws the border in `Theme::danger_color`, e.g. for a query that doesn't parse.
+    pub fn invalid(mut self, invalid: bool) -> Self {
+        self.invalid = invalid;
+        self
+    }
+
+    pub fn focus(&self, cx: &mut WindowContext) {
+        cx.focus(&self.focus_handle);
+    }
+
+    /// How far the text is scrolled up out of view.
+    pub fn scroll_top(&self, cx: &AppContext) -> Pixels {
+        self.view.read(cx).scroll.read(cx).offset
+    }
+
+    /// Where the text is scrolled and how tall it is, observe it to follow
+    /// the scrolling.
+    pub fn scroll_state(&self, cx: &AppContext) -> Model<ScrollState> {
+        self.view.read(cx).scroll.clone()
+    }
+
+    /// Scrolls the text, a position past its end is clamped on the next scroll.
+    pub fn set_scroll_top(&self, scroll_top: Pixels, cx: &mut WindowContext) {
+        let scroll = self.view.read(cx).scroll.clone();
+        scroll.update(cx, |scroll, cx| {
+            scroll.offset = scroll_top;
+            cx.notify();
+        });
+    }
+
+    pub fn is_focused(&self, cx: &WindowContext) -> bool {
+        self.focus_handle.is_focused(cx)
+    }
+
+    /// Plays the last recorded macro `count` times from the cursor, one undo
+    /// reverts all of it.
+    pub fn play_macro(&self, count: usize, cx: &mut WindowContext) {
+        let steps = cx.global::<MacroRecorder>().last().to_vec();
+        if steps.is_empty() {
+            cx.global_mut::<ToastQueue>()
+                .push(Toast::info("No macro was recorded yet"));
+            return;
+        }
+        let page_rows = self.view.read(cx).page_rows();
+        self.model.update(cx, |editor, cx| {
+            let version = editor.version;
+            let selection = editor.selection.clone();
+            editor.transact(cx, |editor, cx| {
+                for _ in 0..count {
+                    for step in &steps {
+                        editor.replay(step, page_rows, cx);
                     }
-                    cx.emit(TextEvent::Input {
-                        text: editor.text.clone(),
-                    });
+                }
+            });
+            if editor.version == version && editor.selection != selection {
+                cx.emit(TextEvent::SelectionChanged {
+                    selection: editor.selection.clone(),
                 });
-            })
-            .p_4()
-            .w_full()
-            .border_1()
-            .border_color(theme.border_color)
-            .text_color(theme.text_color)
-            .focus(|style| style.border_color(theme.primary_color))
-            .child(self.view)
+            }
+        });
     }
 }
 
-pub struct TextDisplay {
-    model: Model<TextModel>,
+#[derive(PartialEq)]
+enum CharKind {
+    Whitespace,
+    Punctuation,
+    Word,
 }
 
-impl Render for TextDisplay {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        let theme = cx.global::<Theme>();
+impl CharKind {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharKind::Whitespace
+        } else if c.is_alphanumeric() || c == '_' {
+            CharKind::Word
+        } else {
+            CharKind::Punctuation
+        }
+    }
+}
+
+pub struct TextModel {
+    buffer: Buffer,
+    pub selection: Range<usize>,
+    /// Whether the cursor sits at `selection.start` instead of `selection.end`.
+    pub selection_reversed: bool,
+    /// The column up/down movement tries to stay in, so passing through a
+    /// short line doesn't lose it.
+    pub goal_column: Option<usize>,
+    /// Bumped on every change to the text, so edits can be told apart from
+    /// cursor movement.
+    pub version: usize,
+    /// Enter submits instead of inserting a newline, see `TextInput::single_line`.
+    pub single_line: bool,
+    /// Filled by `word_ranges` and emptied whenever the text changes.
+    cached_word_ranges: OnceCell<Vec<Range<usize>>>,
+    /// Filled by `char_count` and emptied whenever the text changes.
+    cached_char_count: OnceCell<usize>,
+    /// Filled by `invisible_chars` and emptied whenever the text changes.
+    cached_invisible_chars: OnceCell<Vec<(usize, char)>>,
+    /// Keystrokes that would edit the text are ignored, it can still be
+    /// selected and copied.
+    pub read_only: bool,
+    /// The text is displayed as one bullet per character and can't be copied,
+    /// for passwords and other secrets.
+    pub masked: bool,
+    /// Whether `TextDisplay` draws a gutter with line numbers, see
+    /// `Editor::show_line_numbers`.
+    pub show_line_numbers: bool,
+    /// Backgrounds painted behind the text, grouped by the feature that set them.
+    highlights: HashMap<TypeId, Vec<(Range<usize>, HighlightStyle)>>,
+    /// Colors the syntax of the text, see `TextModel::set_highlighter`.
+    highlighter: Option<Arc<dyn Highlighter>>,
+    /// Filled by `tokens` and emptied whenever the text changes.
+    cached_tokens: OnceCell<Vec<(Range<usize>, TokenKind)>>,
+    /// The edits `undo` reverts, the latest last.
+    undo_stack: Vec<UndoEntry>,
+    /// Given to the next edits, see `break_undo_group`.
+    undo_group: usize,
+    /// Set while `transact` runs.
+    in_transaction: bool,
+    /// Set when the input is edited the vim way, see `Editor::set_vim_mode`.
+    pub vim: Option<Vim>,
+    /// What `toggle_comment` puts in front of lines, depends on the language.
+    pub line_comment: &'static str,
+    /// The indentation and language of the text, see `set_language` and
+    /// `detect_indentation`.
+    pub settings: BufferSettings,
+    /// The line `split_selection_into_lines` selected last, after the ones it
+    /// selects next. Emptied whenever the text changes.
+    split_lines: Vec<Range<usize>>,
+    /// The cursors besides `selection`, each with whether it is reversed. Edits
+    /// shift them like `selection`, see `for_each_selection`.
+    extra_selections: Vec<(Range<usize>, bool)>,
+    /// The (row, column) corners of the rectangle `select_columns` selected
+    /// last, the anchor first, and the `selection` it left.
+    column_selection: Option<((usize, usize), (usize, usize), Range<usize>)>,
+    /// Long lines are wrapped at `wrap_column` instead of running off the
+    /// right edge, see `set_soft_wrap`.
+    soft_wrap: bool,
+    /// How many chars fit the width of the `TextDisplay`, set when it's painted.
+    wrap_column: Option<usize>,
+    /// Filled by `visual_row_starts` and emptied whenever the text or its
+    /// wrapping changes.
+    cached_visual_rows: OnceCell<Vec<usize>>,
+    /// The lines that differ from the file's last git commit, drawn in the
+    /// gutter. `None` when the file isn't committed. Edits shift them until
+    /// the `Editor` diffs the text again.
+    pub diff_hunks: Option<Vec<DiffHunk>>,
+    /// What the linter reported, ordered by where it starts. Edits shift the
+    /// ones after them and drop the ones they touch, see `set_diagnostics`.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The words the dictionary doesn't know, ordered, `None` while spell
+    /// checking is off. Edits check the lines they touch again, see
+    /// `sync_spell_check`.
+    misspellings: Option<Vec<Range<usize>>>,
+    /// The rows of the Markdown code fences, the code between them isn't
+    /// spell checked.
+    fence_rows: Vec<usize>,
+    /// The words offered to finish the one being typed, shown in a popup at
+    /// it. See `update_completion`.
+    pub completion: Option<Completion>,
+    /// The tab stops of the snippet being filled in, see `insert_snippet`.
+    snippet: Option<TabStops>,
+}
+
+/// Words of the text that start like the one at the cursor.
+pub struct Completion {
+    /// The word up to the cursor, what accepting a completion replaces.
+    pub range: Range<usize>,
+    pub words: Vec<String>,
+    /// The word tab and enter accept.
+    pub selected: usize,
+}
+
+/// One edit to the text, with what it replaced.
+struct UndoEntry {
+    /// Where the new text is.
+    range: Range<usize>,
+    old_text: String,
+    /// The selection from before the edit, restored by undoing it.
+    selection: Range<usize>,
+    /// Edits of the same group are undone together.
+    group: usize,
+}
+
+impl TextModel {
+    pub fn init(text: String, cx: &mut WindowContext) -> Model<Self> {
+        let settings = cx.global::<EditorSettings>().buffer_settings();
+        cx.new_model(|_cx| Self::new(text, settings))
+    }
+    /// A model of `text` with the cursor at its end.
+    fn new(text: String, settings: BufferSettings) -> Self {
+        let i = text.len();
+        Self {
+            buffer: Buffer::new(&text),
+            selection: i..i,
+            selection_reversed: false,
+            goal_column: None,
+            version: 0,
+            single_line: false,
+            cached_word_ranges: OnceCell::new(),
+            cached_char_count: OnceCell::new(),
+            cached_invisible_chars: OnceCell::new(),
+            read_only: false,
+            masked: false,
+            show_line_numbers: false,
+            highlights: HashMap::new(),
+            highlighter: None,
+            cached_tokens: OnceCell::new(),
+            undo_stack: Vec::new(),
+            undo_group: 0,
+            in_transaction: false,
+            vim: None,
+            line_comment: DEFAULT_LINE_COMMENT,
+            settings,
+            split_lines: Vec::new(),
+            extra_selections: Vec::new(),
+            column_selection: None,
+            soft_wrap: false,
+            wrap_column: None,
+            cached_visual_rows: OnceCell::new(),
+            diff_hunks: None,
+            diagnostics: Vec::new(),
+            misspellings: None,
+            fence_rows: Vec::new(),
+            completion: None,
+            snippet: None,
+        }
+    }
+    pub fn reset(&mut self, cx: &mut ModelContext<Self>) {
+        self.set_text(String::new(), cx);
+    }
+    /// The words of the text, computed once per change to the text.
+    pub fn word_ranges(&self) -> &[Range<usize>] {
+        self.cached_word_ranges.get_or_init(|| {
+            let mut words = Vec::new();
+            for row in 0..self.buffer.line_count() {
+                let line_start = self.buffer.line_start(row);
+                words.extend(
+                    Self::compute_word_ranges(self.buffer.line(row))
+                        .into_iter()
+                        .map(|word| line_start + word.start..line_start + word.end),
+                );
+            }
+            words
+        })
+    }
+    /// The number of chars in the text, counted once per change to the text.
+    pub fn char_count(&self) -> usize {
+        *self.cached_char_count.get_or_init(|| {
+            let chars: usize = (0..self.buffer.line_count())
+                .map(|row| self.buffer.line(row).chars().count())
+                .sum();
+            // the newlines between the lines
+            chars + self.buffer.line_count() - 1
+        })
+    }
+    /// The number of chars in the selection, counted without copying it.
+    /// Everything selected takes the cached `char_count`.
+    pub fn selected_char_count(&self) -> usize {
+        if self.selection == (0..self.len()) {
+            return self.char_count();
+        }
+        self.buffer
+            .chars_at(self.selection.start)
+            .take_while(|(i, _)| *i < self.selection.end)
+            .count()
+    }
+    /// The chars that would take no room or look like a space, with their
+    /// offsets, computed once per change to the text. See `is_invisible`.
+    pub fn invisible_chars(&self) -> &[(usize, char)] {
+        self.cached_invisible_chars.get_or_init(|| {
+            let mut chars = Vec::new();
+            for row in 0..self.buffer.line_count() {
+                let line_start = self.buffer.line_start(row);
+                chars.extend(
+                    self.buffer
+                        .line(row)
+                        .char_indices()
+                        .filter(|(_, c)| is_invisible(*c))
+                        .map(|(i, c)| (line_start + i, c)),
+                );
+            }
+            chars
+        })
+    }
+    pub fn soft_wrap(&self) -> bool {
+        self.soft_wrap
+    }
+    /// Turns soft wrap on or off. The offsets stay as they are, only the rows
+    /// the text is displayed on change.
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool, cx: &mut ModelContext<Self>) {
+        if self.soft_wrap != soft_wrap {
+            self.soft_wrap = soft_wrap;
+            self.cached_visual_rows.take();
+            self.goal_column = None;
+            cx.notify();
+        }
+    }
+    /// Called by `TextDisplay` when the number of chars that fit its width
+    /// changes.
+    fn set_wrap_column(&mut self, column: usize, cx: &mut ModelContext<Self>) {
+        if self.wrap_column != Some(column) {
+            self.wrap_column = Some(column);
+            self.cached_visual_rows.take();
+            self.goal_column = None;
+            cx.notify();
+        }
+    }
+    /// The column lines are wrapped at, `None` when they aren't.
+    fn wrap_width(&self) -> Option<usize> {
+        self.wrap_column
+            .filter(|_| self.soft_wrap && !self.single_line && !self.masked)
+    }
+    /// The parts of line `row` that are displayed on a row each, as ranges of
+    /// the text. A line wraps after the last whitespace that fits, or within
+    /// a word that doesn't fit on its own.
+    pub fn wrapped_rows(&self, row: usize) -> Vec<Range<usize>> {
+        let line_start = self.buffer.line_start(row);
+        let line = self.buffer.line(row);
+        let Some(width) = self.wrap_width() else {
+            return vec![line_start..line_start + line.len()];
+        };
+        let mut parts = Vec::new();
+        let mut part_start = 0;
+        let mut column = 0;
+        let mut after_space = None;
+        for (i, c) in line.char_indices() {
+            if column == width.max(1) {
+                let end = after_space.filter(|end| *end > part_start).unwrap_or(i);
+                parts.push(line_start + part_start..line_start + end);
+                // the chars after the space move along to the next row
+                column = line[end..i].chars().count();
+                part_start = end;
+                after_space = None;
+            }
+            column += 1;
+            if c.is_whitespace() {
+                after_space = Some(i + c.len_utf8());
+            }
+        }
+        parts.push(line_start + part_start..line_start + line.len());
+        parts
+    }
+    /// The first visual row of each line followed by the number of visual
+    /// rows, computed once per change to the text or its wrapping.
+    fn visual_row_starts(&self) -> &[usize] {
+        self.cached_visual_rows.get_or_init(|| {
+            let mut starts = Vec::with_capacity(self.buffer.line_count() + 1);
+            let mut visual_row = 0;
+            for row in 0..self.buffer.line_count() {
+                starts.push(visual_row);
+                visual_row += self.wrapped_rows(row).len();
+            }
+            starts.push(visual_row);
+            starts
+        })
+    }
+    /// The number of rows the text is displayed on, the lines when it
+    /// isn't wrapped.
+    pub fn visual_row_count(&self) -> usize {
+        if self.wrap_width().is_none() {
+            return self.buffer.line_count();
+        }
+        self.visual_row_starts().last().copied().unwrap_or(1)
+    }
+    /// The visual row `offset` is displayed on and the range of the text on
+    /// it. An offset where a line wraps is displayed at the start of the next
+    /// row.
+    pub fn visual_row_for_offset(&self, offset: usize) -> (usize, Range<usize>) {
+        let row = self.buffer.row_for_offset(offset);
+        if self.wrap_width().is_none() {
+            return (row, self.buffer.line_start(row)..self.buffer.line_end(row));
+        }
+        let parts = self.wrapped_rows(row);
+        let i = parts
+            .iter()
+            .rposition(|part| part.start <= offset)
+            .unwrap_or(0);
+        (self.visual_row_starts()[row] + i, parts[i].clone())
+    }
+    /// The range of the text displayed on `visual_row`, clamped to the last row.
+    pub fn visual_row_range(&self, visual_row: usize) -> Range<usize> {
+        if self.wrap_width().is_none() {
+            let row = visual_row.min(self.buffer.line_count() - 1);
+            return self.buffer.line_start(row)..self.buffer.line_end(row);
+        }
+        let starts = self.visual_row_starts();
+        let visual_row = visual_row.min(starts[starts.len() - 1].saturating_sub(1));
+        let row = starts.partition_point(|start| *start <= visual_row) - 1;
+        self.wrapped_rows(row).swap_remove(visual_row - starts[row])
+    }
+    /// The offset `column` chars into `visual_row`, clamped to the end of the
+    /// row. That is before the last char of a row that wraps, the offset
+    /// after it is displayed on the next row.
+    fn offset_in_visual_row(&self, visual_row: usize, column: usize) -> usize {
+        let range = self.visual_row_range(visual_row);
+        let end = if range.end < self.line_end(range.start) {
+            self.prev_boundary(range.end)
+        } else {
+            range.end
+        };
+        self.text_for_range(range.clone())
+            .char_indices()
+            .nth(column)
+            .map_or(end, |(i, _)| (range.start + i).min(end))
+    }
+    /// Where home moves the cursor: the smart line start on the first row
+    /// of a line, the start of the row on the ones it wraps onto.
+    pub fn visual_line_start(&self, offset: usize) -> usize {
+        let (_, range) = self.visual_row_for_offset(offset);
+        if range.start == self.line_start(offset) {
+            self.smart_line_start(offset)
+        } else {
+            range.start
+        }
+    }
+    /// Where end moves the cursor: the end of the line on its last row, the
+    /// last char of the row on the ones it wraps from.
+    pub fn visual_line_end(&self, offset: usize) -> usize {
+        let (_, range) = self.visual_row_for_offset(offset);
+        if range.end == self.line_end(offset) {
+            range.end
+        } else {
+            self.prev_boundary(range.end)
+        }
+    }
+    pub fn compute_word_ranges(s: &str) -> Vec<Range<usize>> {
+        let mut words = Vec::new();
+        let mut last_was_boundary = true;
+        let mut word_start = 0;
 
-        let mut text = self.model.read(cx).text.clone();
-        let mut selection_style = HighlightStyle::default();
+        for (i, c) in s.char_indices() {
+            if CharKind::of(c) == CharKind::Word {
+                if last_was_boundary {
+                    word_start = i;
+                }
+                last_was_boundary = false;
+            } else {
+                if !last_was_boundary {
+                    words.push(word_start..i);
+                }
+                last_was_boundary = true;
+            }
+        }
+
+        // Check if the last characters form a word and push it if so
+        if !last_was_boundary {
+            words.push(word_start..s.len());
+        }
+
+        words
+    }
+    /// The bracket right after `offset`, or else right before it, and the
+    /// offset of its partner. The partner is `None` when the nesting between
+    /// them doesn't line up or it isn't found within `MAX_BRACKET_SCAN` chars.
+    pub fn bracket_pair(&self, offset: usize) -> Option<(usize, Option<usize>)> {
+        let after = self.buffer.chars_at(offset).next();
+        let before = self.buffer.reversed_chars_at(offset).next();
+        let (bracket, c) = [after, before]
+            .into_iter()
+            .flatten()
+            .find(|(_, c)| "()[]{}".contains(*c))?;
+        let partner = |c| match c {
+            '(' => ')',
+            ')' => '(',
+            '[' => ']',
+            ']' => '[',
+            '{' => '}',
+            _ => '{',
+        };
+        let opening = "([{".contains(c);
+        let chars: Box<dyn Iterator<Item = (usize, char)>> = if opening {
+            Box::new(self.buffer.chars_at(bracket + 1))
+        } else {
+            Box::new(self.buffer.reversed_chars_at(bracket))
+        };
+        // brackets opened (or closed, going backwards) since `bracket`
+        let mut stack = Vec::new();
+        for (i, next) in chars.take(MAX_BRACKET_SCAN) {
+            if !"()[]{}".contains(next) {
+                continue;
+            }
+            if "([{".contains(next) == opening {
+                stack.push(next);
+            } else if let Some(inner) = stack.pop() {
+                if partner(inner) != next {
+                    return Some((bracket, None));
+                }
+            } else if next == partner(c) {
+                return Some((bracket, Some(i)));
+            } else {
+                return Some((bracket, None));
+            }
+        }
+        Some((bracket, None))
+    }
+    /// Moves the cursor to the partner of the bracket next to it, keeping it on
+    /// the same side of the bracket.
+    pub fn jump_to_bracket(&mut self) {
+        let head = self.head();
+        if let Some((bracket, Some(partner))) = self.bracket_pair(head) {
+            if head == bracket {
+                self.move_to(partner);
+            } else {
+                self.move_to(partner + 1);
+            }
+        }
+    }
+    /// The word containing or ending at `offset`.
+    pub fn word_range_at(&self, offset: usize) -> Option<Range<usize>> {
+        let words = self.word_ranges();
+        let i = words.partition_point(|word| word.end < offset);
+        words.get(i).filter(|word| word.start <= offset).cloned()
+    }
+    /// The end of the selection the cursor is drawn at.
+    pub fn head(&self) -> usize {
+        if self.selection_reversed {
+            self.selection.start
+        } else {
+            self.selection.end
+        }
+    }
+    /// The end of the selection that stays put while the selection is extended.
+    pub fn anchor(&self) -> usize {
+        if self.selection_reversed {
+            self.selection.end
+        } else {
+            self.selection.start
+        }
+    }
+    /// Every assignment to `selection` goes through here, keeping both ends
+    /// inside the text and on char boundaries.
+    fn set_selection(&mut self, range: Range<usize>, reversed: bool) {
+        let start = self.clamp_offset(range.start);
+        let end = self.clamp_offset(range.end).max(start);
+        self.selection = start..end;
+        self.selection_reversed = reversed;
+    }
+    /// Puts a selection that went stale, e.g. after the text was replaced
+    /// from the outside, back into the text.
+    pub fn clamp_selection(&mut self) {
+        self.set_selection(self.selection.clone(), self.selection_reversed);
+    }
+    pub fn select(&mut self, range: Range<usize>) {
+        self.set_selection(range, false);
+        self.goal_column = None;
+    }
+    pub fn select_all(&mut self) {
+        self.select(0..self.len());
+    }
+    pub fn move_to(&mut self, offset: usize) {
+        self.select(offset..offset);
+    }
+    /// Moves the head of the selection to `offset`, keeping the anchor in place.
+    pub fn select_to(&mut self, offset: usize) {
+        let anchor = self.anchor();
+        self.goal_column = None;
+        if offset < anchor {
+            self.set_selection(offset..anchor, true);
+        } else {
+            self.set_selection(anchor..offset, false);
+        }
+    }
+    /// All selections, `selection` and the extra cursors, in the order of
+    /// the text.
+    pub fn selections(&self) -> Vec<Range<usize>> {
+        let mut selections: Vec<Range<usize>> = self
+            .extra_selections
+            .iter()
+            .map(|(selection, _)| selection.clone())
+            .chain([self.selection.clone()])
+            .collect();
+        selections.sort_by_key(|selection| selection.start);
+        selections
+    }
+    /// The heads of the extra cursors, where their carets are drawn.
+    fn extra_heads(&self) -> impl Iterator<Item = usize> + '_ {
+        self.extra_selections.iter().map(|(selection, reversed)| {
+            if *reversed {
+                selection.start
+            } else {
+                selection.end
+            }
+        })
+    }
+    /// Drops the extra cursors, keeping only `selection`.
+    pub fn collapse_selections(&mut self) {
+        self.extra_selections.clear();
+        self.column_selection = None;
+    }
+    /// Selects the rectangle between the (row, column) points `anchor` and
+    /// `head`, columns counted in chars, as one selection per row. A row
+    /// shorter than the rectangle gets a cursor at its end, the head's row
+    /// holds the primary selection.
+    pub fn select_columns(&mut self, anchor: (usize, usize), head: (usize, usize)) {
+        let last_row = self.buffer.line_count() - 1;
+        let (anchor, head) = (
+            (anchor.0.min(last_row), anchor.1),
+            (head.0.min(last_row), head.1),
+        );
+        let rows = anchor.0.min(head.0)..anchor.0.max(head.0) + 1;
+        let columns = anchor.1.min(head.1)..anchor.1.max(head.1);
+        let reversed = head.1 < anchor.1;
+        self.extra_selections = rows
+            .filter(|row| *row != head.0)
+            .map(|row| {
+                let selection =
+                    self.offset_in_row(row, columns.start)..self.offset_in_row(row, columns.end);
+                (selection, reversed)
+            })
+            .collect();
+        let selection =
+            self.offset_in_row(head.0, columns.start)..self.offset_in_row(head.0, columns.end);
+        self.select(selection);
+        self.selection_reversed = reversed;
+        self.column_selection = Some((anchor, head, self.selection.clone()));
+    }
+    /// Moves the head corner of the rectangle `select_columns` selected by
+    /// `rows` and `columns`, starting one at the cursor when the selection
+    /// changed since.
+    pub fn move_column_head(&mut self, rows: isize, columns: isize) {
+        let (anchor, head) = match &self.column_selection {
+            Some((anchor, head, selection)) if *selection == self.selection => (*anchor, *head),
+            _ => {
+                let head = self.head();
+                let point = (self.buffer.row_for_offset(head), self.column(head));
+                (point, point)
+            }
+        };
+        let row = head
+            .0
+            .saturating_add_signed(rows)
+            .min(self.buffer.line_count() - 1);
+        // no further right than the longest of the rows
+        let max_column = (anchor.0.min(row)..anchor.0.max(row) + 1)
+            .map(|row| self.buffer.line(row).chars().count())
+            .max()
+            .unwrap_or(0);
+        let column = head
+            .1
+            .saturating_add_signed(columns)
+            .min(max_column.max(head.1));
+        self.select_columns(anchor, (row, column));
+    }
+    /// Extends the rectangle `select_columns` selected last to `offset`,
+    /// e.g. while dragging. Returns false when there is none.
+    pub fn select_columns_to(&mut self, offset: usize) -> bool {
+        let anchor = match &self.column_selection {
+            Some((anchor, _, selection)) if *selection == self.selection => *anchor,
+            _ => return false,
+        };
+        let head = (self.buffer.row_for_offset(offset), self.column(offset));
+        self.select_columns(anchor, head);
+        true
+    }
+    /// Runs `f` once for each selection, with it as `selection`, in the order
+    /// of the text. Edits shift the other selections along, and all of them
+    /// are undone together. Selections that end up overlapping are merged.
+    pub fn for_each_selection(
+        &mut self,
+        cx: &mut ModelContext<Self>,
+        mut f: impl FnMut(&mut Self, &mut ModelContext<Self>),
+    ) {
+        if self.extra_selections.is_empty() {
+            f(self, cx);
+            return;
+        }
+        self.transact(cx, |this, cx| {
+            // the primary joins the others, so edits shift it the same way
+            let primary = (this.selection.clone(), this.selection_reversed);
+            this.extra_selections.push(primary.clone());
+            this.extra_selections
+                .sort_by_key(|(selection, _)| selection.start);
+            let primary_index = this
+                .extra_selections
+                .iter()
+                .position(|selection| *selection == primary)
+                .unwrap_or(0);
+            for i in 0..this.extra_selections.len() {
+                let (selection, reversed) = this.extra_selections[i].clone();
+                this.set_selection(selection, reversed);
+                // one goal column can't serve cursors in different columns
+                this.goal_column = None;
+                f(this, cx);
+                this.extra_selections[i] = (this.selection.clone(), this.selection_reversed);
+            }
+            let (selection, reversed) = this.extra_selections.remove(primary_index);
+            this.set_selection(selection, reversed);
+            this.goal_column = None;
+            this.merge_selections();
+        });
+    }
+    /// Merges selections that overlap or whose cursors meet, the merged one
+    /// stays primary if either was.
+    fn merge_selections(&mut self) {
+        let mut selections: Vec<(Range<usize>, bool, bool)> = self
+            .extra_selections
+            .drain(..)
+            .map(|(selection, reversed)| (selection, reversed, false))
+            .chain([(self.selection.clone(), self.selection_reversed, true)])
+            .collect();
+        selections.sort_by_key(|(selection, _, _)| selection.start);
+        let mut merged: Vec<(Range<usize>, bool, bool)> = Vec::new();
+        for (selection, reversed, primary) in selections {
+            match merged.last_mut() {
+                Some(last) if selection.start < last.0.end || selection.start == last.0.start => {
+                    last.0.end = last.0.end.max(selection.end);
+                    last.2 |= primary;
+                }
+                _ => merged.push((selection, reversed, primary)),
+            }
+        }
+        for (selection, reversed, primary) in merged {
+            if primary {
+                self.set_selection(selection, reversed);
+            } else {
+                self.extra_selections.push((selection, reversed));
+            }
+        }
+    }
+    /// Selects the next place after `selection` where the selected text is,
+    /// wrapping around, that isn't selected yet, as a new cursor. Without a selection the
+    /// word at the cursor is selected instead.
+    pub fn select_next_occurrence(&mut self) {
+        if self.single_line || self.masked {
+            return;
+        }
+        if self.selection.is_empty() {
+            if let Some(word) = self.word_range_at(self.head()) {
+                self.select(word);
+            }
+            return;
+        }
+        let needle = self.selected_text();
+        let selections = self.selections();
+        let from = self.selection.end;
+        let from_row = self.buffer.row_for_offset(from);
+        let after = (from_row..self.buffer.line_count())
+            .flat_map(|row| self.occurrences_in_row(&needle, row))
+            .filter(|start| *start >= from);
+        let before = (0..=from_row)
+            .flat_map(|row| self.occurrences_in_row(&needle, row))
+            .filter(|start| *start < from);
+        let next = after
+            .chain(before)
+            .find(|start| !selections.iter().any(|selection| selection.start == *start));
+        if let Some(start) = next {
+            // the new cursor becomes the primary one, so it's scrolled to
+            let previous = (self.selection.clone(), self.selection_reversed);
+            self.extra_selections.push(previous);
+            self.select(start..start + needle.len());
+        }
+    }
+    /// Adds a cursor at each other place the selected text, or the word at the
+    /// cursor, is.
+    pub fn select_all_occurrences(&mut self) {
+        if self.single_line || self.masked {
+            return;
+        }
+        if self.selection.is_empty() {
+            match self.word_range_at(self.head()) {
+                Some(word) => self.select(word),
+                None => return,
+            }
+        }
+        let needle = self.selected_text();
+        self.extra_selections = (0..self.buffer.line_count())
+            .flat_map(|row| self.occurrences_in_row(&needle, row))
+            .map(|start| start..start + needle.len())
+            .filter(|selection| *selection != self.selection)
+            .map(|selection| (selection, false))
+            .collect();
+        self.merge_selections();
+    }
+    /// The offsets `needle` occurs at starting in `row`, searched in the lines
+    /// themselves so nothing is copied. A needle spanning lines starts at most
+    /// once per row, at its end.
+    fn occurrences_in_row(&self, needle: &str, row: usize) -> Vec<usize> {
+        let line = self.buffer.line(row);
+        let line_start = self.buffer.line_start(row);
+        let Some((first, rest)) = needle.split_once('\n') else {
+            return line
+                .match_indices(needle)
+                .map(|(i, _)| line_start + i)
+                .collect();
+        };
+        let mut parts = rest.split('\n');
+        let last = parts.next_back().unwrap_or_default();
+        let middle_rows = parts.clone().count();
+        let last_row = row + middle_rows + 1;
+        if !line.ends_with(first) || last_row >= self.buffer.line_count() {
+            return Vec::new();
+        }
+        let matches = parts
+            .enumerate()
+            .all(|(i, part)| &self.buffer.line(row + 1 + i)[..] == part)
+            && self.buffer.line(last_row).starts_with(last);
+        if matches {
+            vec![line_start + line.len() - first.len()]
+        } else {
+            Vec::new()
+        }
+    }
+    /// Clamps `offset` to the text and moves it back onto a char boundary.
+    pub fn clamp_offset(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.len());
+        while !self.buffer.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+    /// The start of the grapheme cluster before `offset`.
+    pub fn prev_boundary(&self, offset: usize) -> usize {
+        let row = self.buffer.row_for_offset(offset);
+        let line_start = self.buffer.line_start(row);
+        if offset == line_start {
+            // the newline ending the previous line
+            return offset.saturating_sub(1);
+        }
+        self.buffer.line(row)[..offset - line_start]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(line_start, |(i, _)| line_start + i)
+    }
+    /// The end of the grapheme cluster after `offset`.
+    pub fn next_boundary(&self, offset: usize) -> usize {
+        let row = self.buffer.row_for_offset(offset);
+        if offset == self.buffer.line_end(row) {
+            return (offset + 1).min(self.len());
+        }
+        self.buffer.line(row)[offset - self.buffer.line_start(row)..]
+            .graphemes(true)
+            .next()
+            .map_or(offset, |grapheme| offset + grapheme.len())
+    }
+    /// The start of the word before `offset`, skipping whitespace. A run of
+    /// punctuation counts as a word of its own.
+    pub fn word_boundary_before(&self, offset: usize) -> usize {
+        let mut chars = self
+            .buffer
+            .reversed_chars_at(offset)
+            .skip_while(|(_, c)| c.is_whitespace())
+            .peekable();
+        let Some(&(_, first)) = chars.peek() else {
+            return 0;
+        };
+        let kind = CharKind::of(first);
+        for (i, c) in chars {
+            if CharKind::of(c) != kind {
+                return i + c.len_utf8();
+            }
+        }
+        0
+    }
+    /// The end of the word after `offset`, skipping whitespace. A run of
+    /// punctuation counts as a word of its own.
+    pub fn word_boundary_after(&self, offset: usize) -> usize {
+        let mut chars = self
+            .buffer
+            .chars_at(offset)
+            .skip_while(|(_, c)| c.is_whitespace())
+            .peekable();
+        let Some(&(_, first)) = chars.peek() else {
+            return self.len();
+        };
+        let kind = CharKind::of(first);
+        for (i, c) in chars {
+            if CharKind::of(c) != kind {
+                return i;
+            }
+        }
+        self.len()
+    }
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+    /// Materializes the whole text, prefer `text_for_range` or `buffer` on
+    /// hot paths.
+    pub fn text(&self) -> String {
+        self.buffer.to_string()
+    }
+    pub fn text_for_range(&self, range: Range<usize>) -> String {
+        self.buffer.text_for_range(range)
+    }
+    /// Replaces the highlights set under `T`, e.g. the matches of a search.
+    /// They aren't moved by edits, whoever sets them recomputes them.
+    pub fn set_highlights<T: 'static>(
+        &mut self,
+        highlights: Vec<(Range<usize>, HighlightStyle)>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.highlights.insert(TypeId::of::<T>(), highlights);
+        cx.notify();
+    }
+    pub fn clear_highlights<T: 'static>(&mut self, cx: &mut ModelContext<Self>) {
+        if self.highlights.remove(&TypeId::of::<T>()).is_some() {
+            cx.notify();
+        }
+    }
+    pub fn highlights(&self) -> impl Iterator<Item = &(Range<usize>, HighlightStyle)> {
+        self.highlights.values().flatten()
+    }
+    pub fn set_highlighter(
+        &mut self,
+        highlighter: Option<Arc<dyn Highlighter>>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.highlighter = highlighter;
+        self.cached_tokens.take();
+        cx.notify();
+    }
+    /// Sets the language of the text, which picks its highlighter and what
+    /// starts a line comment.
+    pub fn set_language(&mut self, language: Option<&'static str>, cx: &mut ModelContext<Self>) {
+        self.settings.language = language;
+        self.line_comment = language.map_or(DEFAULT_LINE_COMMENT, line_comment_for_language);
+        self.set_highlighter(language.and_then(highlighter_for_language), cx);
+        self.sync_spell_check(cx);
+    }
+    /// Indents the way the text already is, see
+    /// `BufferSettings::detect_indentation`.
+    pub fn detect_indentation(&mut self, cx: &mut ModelContext<Self>) {
+        let lines = (0..self.buffer.line_count()).map(|row| self.buffer.line(row).as_ref());
+        self.settings.detect_indentation(lines);
+        cx.notify();
+    }
+    /// Indents with tabs or `tab_size` spaces from now on, whatever the text
+    /// looks like.
+    pub fn set_indentation(
+        &mut self,
+        hard_tabs: bool,
+        tab_size: usize,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.settings.hard_tabs = hard_tabs;
+        self.settings.tab_size = tab_size;
+        self.settings.indent_overridden = true;
+        cx.notify();
+    }
+    /// The syntax tokens of the text, computed once per change to the text.
+    /// Only the first `MAX_HIGHLIGHT_LEN` bytes are highlighted.
+    pub fn tokens(&self) -> &[(Range<usize>, TokenKind)] {
+        self.cached_tokens.get_or_init(|| {
+            let Some(highlighter) = &self.highlighter else {
+                return Vec::new();
+            };
+            let end = self.clamp_offset(MAX_HIGHLIGHT_LEN);
+            highlighter.highlight(&self.text_for_range(0..end))
+        })
+    }
+    pub fn selected_text(&self) -> String {
+        self.text_for_range(self.selection.clone())
+    }
+    /// Replaces the whole text, keeping as much of the selection as still fits.
+    pub fn set_text(&mut self, text: String, cx: &mut ModelContext<Self>) {
+        let range = 0..self.len();
+        let old_end = self.buffer.point_for_offset(range.end);
+        self.buffer = Buffer::new(&text);
+        if let Some(highlighter) = &self.highlighter {
+            highlighter.edit(&TextEdit {
+                range: range.clone(),
+                new_len: text.len(),
+                start: (0, 0),
+                old_end,
+                new_end: self.buffer.point_for_offset(text.len()),
+            });
+        }
+        self.version += 1;
+        self.cached_word_ranges.take();
+        self.cached_char_count.take();
+        self.cached_invisible_chars.take();
+        self.cached_tokens.take();
+        self.cached_visual_rows.take();
+        self.goal_column = None;
+        self.split_lines.clear();
+        self.extra_selections.clear();
+        self.completion = None;
+        self.snippet = None;
+        self.clear_undo_history();
+        self.clamp_selection();
+        self.recheck_spelling(cx);
+        cx.notify();
+        cx.emit(TextEvent::Edited {
+            range,
+            new_text_len: text.len(),
+        });
+    }
+    /// Places what a linter reported in the text. A message without an end
+    /// covers the word it points at, or else one char.
+    pub fn set_diagnostics(&mut self, messages: Vec<LintMessage>, cx: &mut ModelContext<Self>) {
+        self.diagnostics = messages
+            .into_iter()
+            .map(|message| {
+                let mut start = self.offset_for_point(message.row, message.column);
+                let end = match message.end {
+                    Some((row, column)) => self.offset_for_point(row, column).max(start),
+                    None => self.word_range_at(start).map_or_else(
+                        || self.next_boundary(start).min(self.line_end(start)),
+                        |word| word.end,
+                    ),
+                };
+                // past the end of the line, the last char of it
+                if start == end && start > self.line_start(start) {
+                    start = self.prev_boundary(start);
+                }
+                Diagnostic {
+                    range: start..end,
+                    severity: message.severity,
+                    message: message.message,
+                }
+            })
+            .collect();
+        self.diagnostics
+            .sort_by_key(|diagnostic| diagnostic.range.start);
+        cx.notify();
+    }
+    /// The most severe diagnostic the cursor at `offset` is in.
+    pub fn diagnostic_at(&self, offset: usize) -> Option<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.range.start <= offset && offset <= diagnostic.range.end)
+            .min_by_key(|diagnostic| match diagnostic.severity {
+                Severity::Error => 0,
+                Severity::Warning => 1,
+                Severity::Info => 2,
+            })
+    }
+    /// Turns spell checking on or off as the settings say. Only plain text
+    /// and Markdown are checked, and never single line or masked inputs.
+    pub fn sync_spell_check(&mut self, cx: &mut ModelContext<Self>) {
+        let enabled = cx.global::<EditorSettings>().spell_check
+            && !self.single_line
+            && !self.masked
+            && matches!(self.settings.language, None | Some("markdown"));
+        if enabled {
+            self.check_all_spelling(cx);
+        } else if self.misspellings.take().is_some() {
+            self.fence_rows.clear();
+            cx.notify();
+        }
+    }
+    /// Checks the whole text again if spell checking is on, e.g. after a word
+    /// was added to the dictionary.
+    pub fn recheck_spelling(&mut self, cx: &mut ModelContext<Self>) {
+        if self.misspellings.is_some() {
+            self.check_all_spelling(cx);
+        }
+    }
+    fn check_all_spelling(&mut self, cx: &mut ModelContext<Self>) {
+        let rows = 0..self.buffer.line_count();
+        self.fence_rows = if self.settings.language == Some("markdown") {
+            rows.clone()
+                .filter(|row| is_fence(self.buffer.line(*row)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.misspellings = Some(self.misspelled_in_rows(rows, cx));
+        cx.notify();
+    }
+    /// The misspelled words of `rows` outside code fences, as offsets into
+    /// the text.
+    fn misspelled_in_rows(&self, rows: Range<usize>, cx: &AppContext) -> Vec<Range<usize>> {
+        let dictionary = cx.global::<Dictionary>();
+        let mut misspelled = Vec::new();
+        for row in rows {
+            // an odd number of fences at or above it opened a code block
+            if self.fence_rows.partition_point(|fence| *fence <= row) % 2 == 1 {
+                continue;
+            }
+            let line_start = self.buffer.line_start(row);
+            misspelled.extend(
+                dictionary
+                    .misspelled_words(self.buffer.line(row))
+                    .into_iter()
+                    .map(|word| line_start + word.start..line_start + word.end),
+            );
+        }
+        misspelled
+    }
+    /// Follows an edit of `range` that turned the rows from `start_row` to
+    /// `old_end_row` into the ones up to `new_end_row`: moves the misspellings
+    /// after it and checks the edited rows again. Adding or removing a code
+    /// fence changes what's code below it, so the whole text is checked then.
+    fn update_spelling(
+        &mut self,
+        range: Range<usize>,
+        new_len: usize,
+        rows: (usize, usize, usize),
+        fences_changed: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let (start_row, old_end_row, new_end_row) = rows;
+        if fences_changed {
+            self.check_all_spelling(cx);
+            return;
+        }
+        for fence in &mut self.fence_rows {
+            if *fence > old_end_row {
+                *fence = *fence + new_end_row - old_end_row;
+            }
+        }
+        let checked = self.buffer.line_start(start_row)..self.buffer.line_end(new_end_row);
+        let found = self.misspelled_in_rows(start_row..new_end_row + 1, cx);
+        let Some(misspellings) = &mut self.misspellings else {
+            return;
+        };
+        misspellings.retain_mut(|word| {
+            if word.end <= range.start {
+                true
+            } else if word.start >= range.end {
+                word.start = word.start - range.len() + new_len;
+                word.end = word.end - range.len() + new_len;
+                true
+            } else {
+                false
+            }
+        });
+        misspellings.retain(|word| word.end < checked.start || word.start > checked.end);
+        let at = misspellings.partition_point(|word| word.start < checked.start);
+        misspellings.splice(at..at, found);
+    }
+    /// The misspelled word at `offset`, if spell checking is on.
+    pub fn misspelling_at(&self, offset: usize) -> Option<Range<usize>> {
+        let misspellings = self.misspellings.as_ref()?;
+        let i = misspellings.partition_point(|word| word.end < offset);
+        misspellings
+            .get(i)
+            .filter(|word| word.start <= offset)
+            .cloned()
+    }
+    pub fn misspellings(&self) -> &[Range<usize>] {
+        self.misspellings.as_deref().unwrap_or_default()
+    }
+    /// Offers the words that finish the one at the cursor, however short it
+    /// is so far.
+    pub fn show_completions(&mut self, cx: &mut ModelContext<Self>) {
+        self.complete(1, cx);
+    }
+    /// Keeps the completions in step with the word at the cursor, closing
+    /// them once nothing matches it. With `typed`, a word that just became
+    /// `auto_complete_min_chars` long opens them.
+    pub fn update_completion(&mut self, typed: bool, cx: &mut ModelContext<Self>) {
+        if self.completion.is_some() {
+            self.complete(1, cx);
+        } else if typed {
+            let settings = cx.global::<EditorSettings>();
+            if settings.auto_complete {
+                self.complete(settings.auto_complete_min_chars.max(1), cx);
+            }
+        }
+    }
+    fn complete(&mut self, min_chars: usize, cx: &mut ModelContext<Self>) {
+        // the highlight stays on its word while the list changes around it
+        let selected = self
+            .completion
+            .take()
+            .and_then(|completion| completion.words.into_iter().nth(completion.selected));
+        self.completion = self
+            .completion_prefix()
+            .filter(|prefix| self.text_for_range(prefix.clone()).chars().count() >= min_chars)
+            .and_then(|range| {
+                let words = self.completion_words(range.clone());
+                let selected = selected
+                    .and_then(|selected| words.iter().position(|word| *word == selected))
+                    .unwrap_or(0);
+                (!words.is_empty()).then_some(Completion {
+                    range,
+                    words,
+                    selected,
+                })
+            });
+        cx.notify();
+    }
+    /// The word the cursor is at the end of, `None` when there's a selection
+    /// or more than one cursor.
+    fn completion_prefix(&self) -> Option<Range<usize>> {
+        if !self.selection.is_empty()
+            || !self.extra_selections.is_empty()
+            || self.single_line
+            || self.masked
+            || self.read_only
+        {
+            return None;
+        }
+        let head = self.head();
+        self.word_range_at(head)
+            .filter(|word| word.end == head && word.start < head)
+    }
+    /// The words of the text that start like the one in `prefix`, the ones
+    /// with the same case first and then the most used.
+    fn completion_words(&self, prefix: Range<usize>) -> Vec<String> {
+        let prefix = self.text_for_range(prefix);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        let mut row = 0;
+        for word in self.word_ranges() {
+            // words don't span lines, so each is a slice of one
+            while self.buffer.line_end(row) < word.start {
+                row += 1;
+            }
+            let line_start = self.buffer.line_start(row);
+            let text = &self.buffer.line(row)[word.start - line_start..word.end - line_start];
+            if text != prefix && starts_with_ignoring_case(text, &prefix) {
+                *counts.entry(text).or_default() += 1;
+            }
+        }
+        let mut words: Vec<(&str, usize)> = counts.into_iter().collect();
+        words.sort_by_key(|(word, count)| (!word.starts_with(&prefix), Reverse(*count), *word));
+        words
+            .into_iter()
+            .take(MAX_COMPLETIONS)
+            .map(|(word, _)| word.to_string())
+            .collect()
+    }
+    /// Moves the highlight to the completion above or below, wrapping around.
+    pub fn move_completion_highlight(
+        &mut self,
+        movement: TextMovement,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+        let count = completion.words.len();
+        completion.selected = match movement {
+            TextMovement::Up => (completion.selected + count - 1) % count,
+            TextMovement::Down => (completion.selected + 1) % count,
+        };
+        cx.notify();
+    }
+    /// Replaces the word being typed with the completion at `index`, as one
+    /// undo step.
+    pub fn accept_completion(&mut self, index: usize, cx: &mut ModelContext<Self>) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        if let Some(word) = completion.words.get(index) {
+            self.transact(cx, |this, cx| {
+                this.replace_range(completion.range.clone(), word, cx);
+            });
+        }
+        cx.notify();
+    }
+    /// Expands the snippet whose prefix is the word before the cursor.
+    /// Returns false when there's none.
+    fn expand_snippet_at_cursor(&mut self, cx: &mut ModelContext<Self>) -> bool {
+        let Some(word) = self.completion_prefix() else {
+            return false;
+        };
+        let prefix = self.text_for_range(word.clone());
+        let Some(snippet) = cx
+            .global::<Snippets>()
+            .find(&prefix, self.settings.language)
+        else {
+            return false;
+        };
+        let body = snippet.body.clone();
+        self.insert_snippet(word, &body, cx);
+        true
+    }
+    /// Replaces `range` with a snippet's `body`, indented like the line it's
+    /// on, and selects its first tab stop. Tab and shift-tab move between
+    /// the stops until the last one, escape leaves them.
+    pub fn insert_snippet(&mut self, range: Range<usize>, body: &str, cx: &mut ModelContext<Self>) {
+        let row = self.buffer.row_for_offset(self.clamp_offset(range.start));
+        let indent: String = self
+            .buffer
+            .line(row)
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        let expanded = expand_snippet(body, &indent, &self.settings.indent());
+        self.collapse_selections();
+        self.completion = None;
+        self.snippet = None;
+        self.transact(cx, |this, cx| {
+            this.replace_range(range.clone(), &expanded.text, cx);
+        });
+        let start = self.clamp_offset(range.start);
+        self.snippet = Some(TabStops {
+            stops: expanded
+                .stops
+                .into_iter()
+                .map(|stop| start + stop.start..start + stop.end)
+                .collect(),
+            current: 0,
+        });
+        self.select_tab_stop(0);
+        cx.notify();
+    }
+    /// Selects the snippet's stop at `index`, the last one ends the snippet.
+    fn select_tab_stop(&mut self, index: usize) {
+        let Some(snippet) = &mut self.snippet else {
+            return;
+        };
+        snippet.current = index.min(snippet.stops.len() - 1);
+        let stop = snippet.stops[snippet.current].clone();
+        if snippet.current + 1 == snippet.stops.len() {
+            self.snippet = None;
+        }
+        let stop = self.clamp_offset(stop.start)..self.clamp_offset(stop.end);
+        self.set_selection(stop, false);
+    }
+    pub fn set_diff_hunks(&mut self, hunks: Option<Vec<DiffHunk>>, cx: &mut ModelContext<Self>) {
+        self.diff_hunks = hunks;
+        cx.notify();
+    }
+    /// Replaces the whole text as one undoable edit, unlike `set_text`. Only
+    /// the part between what the old and new text start and end with is
+    /// touched, the cursor keeps its line and column as far as they still exist.
+    pub fn replace_text(&mut self, text: &str, cx: &mut ModelContext<Self>) {
+        let old_text = self.text();
+        if old_text == text {
+            return;
+        }
+        let (row, column) = self.cursor_position();
+        let mut prefix = old_text
+            .bytes()
+            .zip(text.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !text.is_char_boundary(prefix) || !old_text.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let max_suffix = old_text.len().min(text.len()) - prefix;
+        let mut suffix = old_text
+            .bytes()
+            .rev()
+            .zip(text.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !text.is_char_boundary(text.len() - suffix)
+            || !old_text.is_char_boundary(old_text.len() - suffix)
+        {
+            suffix -= 1;
+        }
+        self.transact(cx, |editor, cx| {
+            editor.replace_range(
+                prefix..old_text.len() - suffix,
+                &text[prefix..text.len() - suffix],
+                cx,
+            );
+        });
+        self.extra_selections.clear();
+        self.move_to(self.offset_for_point(row, column));
+    }
+    /// Every change to the text goes through here. The range is snapped to char
+    /// boundaries and the selection is shifted along with the text after it.
+    pub fn replace_range(
+        &mut self,
+        range: Range<usize>,
+        new_text: &str,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let start = self.clamp_offset(range.start);
+        let range = start..self.clamp_offset(range.end).max(start);
+        let start_point = self.buffer.point_for_offset(range.start);
+        let old_end = self.buffer.point_for_offset(range.end);
+        let checks_fences =
+            self.misspellings.is_some() && self.settings.language == Some("markdown");
+        let touches_fence = |model: &Self, end_row: usize| {
+            checks_fences && (start_point.0..=end_row).any(|row| is_fence(model.buffer.line(row)))
+        };
+        let removes_fence = touches_fence(self, old_end.0);
+        if self.undo_stack.len() == MAX_UNDO_ENTRIES {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(UndoEntry {
+            range: range.start..range.start + new_text.len(),
+            old_text: self.buffer.text_for_range(range.clone()),
+            selection: self.selection.clone(),
+            group: self.undo_group,
+        });
+        self.buffer.replace(range.clone(), new_text);
+        let new_end = self.buffer.point_for_offset(range.start + new_text.len());
+        if let Some(highlighter) = &self.highlighter {
+            highlighter.edit(&TextEdit {
+                range: range.clone(),
+                new_len: new_text.len(),
+                start: start_point,
+                old_end,
+                new_end,
+            });
+        }
+        if let Some(hunks) = &mut self.diff_hunks {
+            shift_hunks(hunks, start_point.0, old_end.0, new_end.0);
+        }
+        self.version += 1;
+        self.cached_word_ranges.take();
+        self.cached_char_count.take();
+        self.cached_invisible_chars.take();
+        self.cached_tokens.take();
+        self.cached_visual_rows.take();
+        self.goal_column = None;
+        self.split_lines.clear();
+        self.column_selection = None;
+
+        let shift = |offset: usize| {
+            if offset <= range.start {
+                offset
+            } else if offset >= range.end {
+                offset - range.len() + new_text.len()
+            } else {
+                range.start + new_text.len()
+            }
+        };
+        let selection = shift(self.selection.start)..shift(self.selection.end);
+        self.set_selection(selection, self.selection_reversed);
+        // what a diagnostic was about changed, it may not apply anymore
+        self.diagnostics.retain_mut(|diagnostic| {
+            let diagnostic = &mut diagnostic.range;
+            if diagnostic.end <= range.start {
+                true
+            } else if diagnostic.start >= range.end {
+                diagnostic.start = diagnostic.start - range.len() + new_text.len();
+                diagnostic.end = diagnostic.end - range.len() + new_text.len();
+                true
+            } else {
+                false
+            }
+        });
+        for (selection, _) in &mut self.extra_selections {
+            *selection = shift(selection.start)..shift(selection.end);
+        }
+        if let Some(snippet) = &mut self.snippet {
+            snippet.edit(range.clone(), new_text.len());
+        }
+        if self.misspellings.is_some() {
+            let fences_changed = removes_fence || touches_fence(self, new_end.0);
+            self.update_spelling(
+                range.clone(),
+                new_text.len(),
+                (start_point.0, old_end.0, new_end.0),
+                fences_changed,
+                cx,
+            );
+        }
+
+        cx.notify();
+        cx.emit(TextEvent::Edited {
+            range,
+            new_text_len: new_text.len(),
+        });
+    }
+    /// Makes the next edit start a new undo step, the edits since the last
+    /// call are undone together.
+    pub fn break_undo_group(&mut self) {
+        if !self.in_transaction {
+            self.undo_group += 1;
+        }
+    }
+    /// Runs `f` with all its edits in one undo group, even where it would
+    /// break the group itself.
+    pub fn transact<R>(
+        &mut self,
+        cx: &mut ModelContext<Self>,
+        f: impl FnOnce(&mut Self, &mut ModelContext<Self>) -> R,
+    ) -> R {
+        self.break_undo_group();
+        self.in_transaction = true;
+        let result = f(self, cx);
+        self.in_transaction = false;
+        self.break_undo_group();
+        result
+    }
+    /// Forgets all edits so far, e.g. the chunks of a file being loaded.
+    pub fn clear_undo_history(&mut self) {
+        self.undo_stack.clear();
+    }
+    /// Reverts the last group of edits and restores the selection from
+    /// before them. Returns false when there is nothing to undo.
+    pub fn undo(&mut self, cx: &mut ModelContext<Self>) -> bool {
+        let Some(group) = self.undo_stack.last().map(|entry| entry.group) else {
+            return false;
+        };
+        let start = self
+            .undo_stack
+            .iter()
+            .rposition(|entry| entry.group != group)
+            .map_or(0, |i| i + 1);
+        let entries = self.undo_stack.split_off(start);
+        for entry in entries.iter().rev() {
+            self.replace_range(entry.range.clone(), &entry.old_text, cx);
+        }
+        // reverting records edits of its own, which aren't undone again
+        self.undo_stack.truncate(start);
+        self.break_undo_group();
+        self.extra_selections.clear();
+        if let Some(entry) = entries.first() {
+            self.select(entry.selection.clone());
+        }
+        true
+    }
+    pub fn insert_at(&mut self, offset: usize, text: &str, cx: &mut ModelContext<Self>) {
+        self.replace_range(offset..offset, text, cx);
+    }
+    pub fn delete_range(&mut self, range: Range<usize>, cx: &mut ModelContext<Self>) {
+        self.replace_range(range, "", cx);
+    }
+    /// Replaces the selection with `text` and puts the cursor after it.
+    pub fn insert(&mut self, text: &str, cx: &mut ModelContext<Self>) {
+        let start = self.selection.start;
+        self.replace_range(self.selection.clone(), text, cx);
+        self.move_to(start + text.len());
+    }
+    /// Whether there is something to copy, the selection or else the
+    /// cursor's line. Masked text stays off the clipboard.
+    pub fn can_copy(&self) -> bool {
+        !self.masked && (!self.selection.is_empty() || !self.single_line)
+    }
+    /// The cursor's line with the newline after it, or the one before it on
+    /// the last line.
+    fn line_range_at(&self, offset: usize) -> Range<usize> {
+        let start = self.line_start(offset);
+        let end = self.line_end(offset);
+        if end < self.len() {
+            start..end + 1
+        } else {
+            start.saturating_sub(1)..end
+        }
+    }
+    /// Copies the selection, or the cursor's line ending in a newline so it
+    /// pastes as a line.
+    pub fn copy(&self, cx: &mut ModelContext<Self>) {
+        if !self.can_copy() {
+            return;
+        }
+        let text = if self.selection.is_empty() {
+            let head = self.head();
+            format!(
+                "{}\n",
+                self.text_for_range(self.line_start(head)..self.line_end(head))
+            )
+        } else {
+            self.selected_text()
+        };
+        copy_to_clipboard(text, cx);
+    }
+    /// Cuts the selection, or the cursor's whole line.
+    pub fn cut(&mut self, cx: &mut ModelContext<Self>) {
+        if self.can_copy() && !self.read_only {
+            self.copy(cx);
+            let range = if self.selection.is_empty() {
+                self.line_range_at(self.head())
+            } else {
+                self.selection.clone()
+            };
+            self.delete_range(range, cx);
+        }
+    }
+    /// Deletes to the end of the line, or the newline when the cursor is at
+    /// the end already, like emacs' kill-line.
+    pub fn delete_to_line_end(&mut self, cx: &mut ModelContext<Self>) {
+        let head = self.head();
+        let line_end = self.line_end(head);
+        let end = if head < line_end {
+            line_end
+        } else {
+            self.next_boundary(head)
+        };
+        self.delete_range(head..end, cx);
+    }
+    /// Deletes the lines the selection touches with the newline after them,
+    /// or the one before them at the end of the text.
+    pub fn delete_lines(&mut self, cx: &mut ModelContext<Self>) {
+        let rows = self.selected_rows();
+        let start = self.buffer.line_start(rows.start);
+        let end = self.buffer.line_end(rows.end - 1);
+        let range = if end < self.len() {
+            start..end + 1
+        } else {
+            start.saturating_sub(1)..end
+        };
+        self.delete_range(range.clone(), cx);
+        self.move_to(self.line_start(range.start));
+    }
+    /// Puts a copy of the selection after it and selects the copy. Without a
+    /// selection the cursor's line is copied below itself, and the cursor
+    /// moves along to the copy.
+    pub fn duplicate_selection(&mut self, cx: &mut ModelContext<Self>) {
+        if !self.selection.is_empty() {
+            let text = self.selected_text();
+            let end = self.selection.end;
+            self.insert_at(end, &text, cx);
+            self.set_selection(end..end + text.len(), self.selection_reversed);
+        } else if !self.single_line {
+            let head = self.head();
+            let line_end = self.line_end(head);
+            let line = format!("\n{}", self.text_for_range(self.line_start(head)..line_end));
+            self.insert_at(line_end, &line, cx);
+            self.move_to(head + line.len());
+        }
+    }
+    /// The rows the selection touches. A selection ending at the start of a
+    /// line doesn't include that line.
+    pub fn selected_rows(&self) -> Range<usize> {
+        let first_row = self.buffer.row_for_offset(self.selection.start);
+        let mut last_row = self.buffer.row_for_offset(self.selection.end);
+        if last_row > first_row && self.selection.end == self.buffer.line_start(last_row) {
+            last_row -= 1;
+        }
+        first_row..last_row + 1
+    }
+    /// Adds `indent` to the start of each line the selection spans, leaving
+    /// out blank ones. A cursor or a selection inside one line is replaced by
+    /// `indent` instead.
+    pub fn indent(&mut self, indent: &str, cx: &mut ModelContext<Self>) {
+        let rows = self.selected_rows();
+        let whole_line = self.selection.start == self.line_start(self.selection.start)
+            && self.selection.end == self.line_end(self.selection.start);
+        if self.selection.is_empty() || (rows.len() == 1 && !whole_line) {
+            self.insert(indent, cx);
+            return;
+        }
+        // from the bottom up, so the rows above keep their offsets
+        for row in rows.rev() {
+            if !self.buffer.line(row).trim().is_empty() {
+                self.insert_at(self.buffer.line_start(row), indent, cx);
+            }
+        }
+    }
+    /// Inserts typed text. With `auto_pairs` set a bracket or quote gets its
+    /// closing partner or wraps the selection, and a closing one that is next
+    /// already is typed over. Nothing is paired in front of a word.
+    pub fn type_text(&mut self, text: &str, cx: &mut ModelContext<Self>) {
+        let mut chars = text.chars();
+        let c = chars.next().filter(|_| chars.next().is_none());
+        let Some(c) = c.filter(|_| cx.global::<EditorSettings>().auto_pairs && !self.single_line)
+        else {
+            self.insert(text, cx);
+            return;
+        };
+        let start = self.selection.start;
+        let next = self
+            .buffer
+            .chars_at(self.selection.end)
+            .next()
+            .map(|(_, c)| c);
+        let prev = self.buffer.reversed_chars_at(start).next().map(|(_, c)| c);
+        // a closing bracket that is next already is typed over
+        let closes = PAIRS.iter().any(|(open, close)| *close == c && *open != c);
+        if closes && self.selection.is_empty() && next == Some(c) {
+            self.move_to(start + c.len_utf8());
+            return;
+        }
+        let Some(close) = closing_pair(c) else {
+            self.insert(text, cx);
+            return;
+        };
+        let before_word = next.is_some_and(|next| CharKind::of(next) == CharKind::Word);
+        if self.selection.is_empty() && before_word {
+            self.insert(text, cx);
+            return;
+        }
+        let is_quote = c == close;
+        if is_quote && self.selection.is_empty() {
+            // the closing quote typed over, or an apostrophe in a word
+            if next == Some(c) {
+                self.move_to(start + c.len_utf8());
+                return;
+            }
+            if prev.is_some_and(char::is_alphanumeric) {
+                self.insert(text, cx);
+                return;
+            }
+        }
+        let selected = self.selected_text();
+        let end = start + c.len_utf8() + selected.len();
+        self.replace_range(self.selection.clone(), &format!("{c}{selected}{close}"), cx);
+        if selected.is_empty() {
+            self.move_to(end);
+        } else {
+            self.select(start + c.len_utf8()..end);
+        }
+    }
+    /// Deletes both brackets or quotes of an empty pair around the cursor.
+    /// Returns false when the cursor isn't in one.
+    pub fn delete_pair(&mut self, cx: &mut ModelContext<Self>) -> bool {
+        if !self.selection.is_empty()
+            || self.single_line
+            || !cx.global::<EditorSettings>().auto_pairs
+        {
+            return false;
+        }
+        let head = self.head();
+        let prev = self.buffer.reversed_chars_at(head).next();
+        let next = self.buffer.chars_at(head).next().map(|(_, c)| c);
+        match prev {
+            Some((start, open)) if next.is_some() && closing_pair(open) == next => {
+                self.delete_range(start..head + open.len_utf8(), cx);
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Replaces the selection with a newline and the indentation of the line
+    /// it was on, one `indent` deeper after an opening bracket. A closing
+    /// bracket right after the cursor moves to a line of its own.
+    pub fn newline_and_indent(&mut self, indent: &str, cx: &mut ModelContext<Self>) {
+        let start = self.selection.start;
+        let line_start = self.line_start(start);
+        let line = &self.buffer.line(self.buffer.row_for_offset(start))[..start - line_start];
+        let current_indent = &line[..line.len() - line.trim_start().len()];
+        let mut text = format!("\n{current_indent}");
+        let open = line.chars().next_back();
+        let close = self
+            .buffer
+            .chars_at(self.selection.end)
+            .next()
+            .map(|(_, c)| c);
+        if let Some(open) = open.filter(|c| matches!(c, '{' | '(' | '[')) {
+            text.push_str(indent);
+            let cursor = start + text.len();
+            let partner = match open {
+                '{' => '}',
+                '(' => ')',
+                _ => ']',
+            };
+            if close == Some(partner) {
+                text.push_str(&format!("\n{current_indent}"));
+            }
+            self.replace_range(self.selection.clone(), &text, cx);
+            self.move_to(cursor);
+        } else {
+            self.insert(&text, cx);
+        }
+    }
+    /// Removes up to one level of indentation, a tab or `tab_size` spaces,
+    /// from each line the selection touches.
+    pub fn outdent(&mut self, tab_size: usize, cx: &mut ModelContext<Self>) {
+        for row in self.selected_rows().rev() {
+            let line = self.buffer.line(row);
+            let len = if line.starts_with('\t') {
+                1
+            } else {
+                line.len() - line.trim_start_matches(' ').len()
+            };
+            if len > 0 {
+                let start = self.buffer.line_start(row);
+                self.delete_range(start..start + len.min(tab_size.max(1)), cx);
+            }
+        }
+    }
+    /// Replaces the lines the selection touches, or all lines when nothing is
+    /// selected, with what `transform` makes of them, in one edit. A blank
+    /// last line, i.e. the text's trailing newline, is left out. Returns the
+    /// number of lines before and after, `None` for a read-only text.
+    pub fn transform_lines(
+        &mut self,
+        transform: impl FnOnce(&mut Vec<String>),
+        cx: &mut ModelContext<Self>,
+    ) -> Option<(usize, usize)> {
+        if self.read_only {
+            return None;
+        }
+        let mut rows = if self.selection.is_empty() {
+            0..self.buffer.line_count()
+        } else {
+            self.selected_rows()
+        };
+        if rows.len() > 1 && self.buffer.line(rows.end - 1).is_empty() {
+            rows.end -= 1;
+        }
+        let range = self.buffer.line_start(rows.start)..self.buffer.line_end(rows.end - 1);
+        let mut lines: Vec<String> = rows.map(|row| self.buffer.line(row).to_string()).collect();
+        let before = lines.len();
+        transform(&mut lines);
+        let selection = self.selection.clone();
+        let reversed = self.selection_reversed;
+        self.replace_range(range, &lines.join("\n"), cx);
+        self.set_selection(selection, reversed);
+        Some((before, lines.len()))
+    }
+    /// Replaces the selected text with what `transform` makes of it and
+    /// selects the result, whose length may differ. Does nothing without a
+    /// selection.
+    pub fn transform_selection(
+        &mut self,
+        transform: impl FnOnce(&Self, Range<usize>) -> String,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if self.selection.is_empty() {
+            return;
+        }
+        let range = self.selection.clone();
+        let reversed = self.selection_reversed;
+        let text = transform(self, range.clone());
+        self.replace_range(range.clone(), &text, cx);
+        self.set_selection(range.start..range.start + text.len(), reversed);
+    }
+    /// `range` with the first letter of each word uppercase and the others
+    /// lowercase, the text between words stays as it is.
+    pub fn title_case(&self, range: Range<usize>) -> String {
+        let mut result = String::with_capacity(range.len());
+        let mut offset = range.start;
+        for word in self.word_ranges() {
+            if word.end <= range.start || word.start >= range.end {
+                continue;
+            }
+            let start = word.start.max(range.start);
+            let end = word.end.min(range.end);
+            result.push_str(&self.text_for_range(offset..start));
+            for (i, c) in self.text_for_range(start..end).char_indices() {
+                // a word cut off by the selection has its first letter outside of it
+                if i == 0 && start == word.start {
+                    result.extend(c.to_uppercase());
+                } else {
+                    result.extend(c.to_lowercase());
+                }
+            }
+            offset = end;
+        }
+        result.push_str(&self.text_for_range(offset..range.end));
+        result
+    }
+    /// Joins the lines the selection touches, or the cursor's line with the
+    /// next. Each newline and the indentation after it become one space,
+    /// none next to a blank line. Without a selection the cursor goes to
+    /// where the lines were joined.
+    pub fn join_lines(&mut self, cx: &mut ModelContext<Self>) {
+        let mut rows = self.selected_rows();
+        if rows.len() == 1 {
+            rows.end += 1;
+        }
+        if rows.end > self.buffer.line_count() {
+            return;
+        }
+        let collapsed = self.selection.is_empty();
+        let mut join_point = None;
+        for row in (rows.start..rows.end - 1).rev() {
+            let line = self.buffer.line(row);
+            let next = self.buffer.line(row + 1);
+            let indent = next.len() - next.trim_start().len();
+            let separator = if line.trim().is_empty() || next.trim().is_empty() {
+                ""
+            } else {
+                " "
+            };
+            let start = self.buffer.line_end(row) - (line.len() - line.trim_end().len());
+            let end = self.buffer.line_start(row + 1) + indent;
+            self.replace_range(start..end, separator, cx);
+            join_point = Some(start);
+        }
+        if let Some(join_point) = join_point.filter(|_| collapsed) {
+            self.move_to(join_point);
+        }
+    }
+    /// Removes the whitespace at the end of every line. A cursor in removed
+    /// whitespace ends up at the end of its line.
+    pub fn trim_trailing_whitespace(&mut self, cx: &mut ModelContext<Self>) {
+        for row in (0..self.buffer.line_count()).rev() {
+            let line = self.buffer.line(row);
+            let trailing = line.len() - line.trim_end().len();
+            if trailing > 0 {
+                let end = self.buffer.line_end(row);
+                self.replace_range(end - trailing..end, "", cx);
+            }
+        }
+    }
+    /// Makes the text end in exactly one newline, empty lines at the end are
+    /// removed. Text without anything but newlines ends up empty.
+    pub fn ensure_final_newline(&mut self, cx: &mut ModelContext<Self>) {
+        let last = (0..self.buffer.line_count()).rfind(|row| !self.buffer.line(*row).is_empty());
+        let (end, newline) = match last {
+            Some(row) => (self.buffer.line_end(row), "\n"),
+            None => (0, ""),
+        };
+        if self.text_for_range(end..self.len()) != newline {
+            self.replace_range(end..self.len(), newline, cx);
+        }
+    }
+    /// Selects the content of each line the selection touches, one line per
+    /// call: the first call selects the first line, the following ones the
+    /// next line as long as the text and selection stay as they were left.
+    pub fn split_selection_into_lines(&mut self) {
+        if self.split_lines.last() == Some(&self.selection) {
+            self.split_lines.pop();
+        } else {
+            self.split_lines = self
+                .selected_rows()
+                .rev()
+                .map(|row| self.buffer.line_start(row)..self.buffer.line_end(row))
+                .collect();
+        }
+        if let Some(line) = self.split_lines.last() {
+            self.select(line.clone());
+        }
+    }
+    /// Comments out the lines the selection touches, or uncomments them when
+    /// they all are comments already. The comment markers line up at the
+    /// smallest indentation, blank lines stay as they are.
+    pub fn toggle_comment(&mut self, cx: &mut ModelContext<Self>) {
+        let marker = self.line_comment;
+        let rows: Vec<usize> = self
+            .selected_rows()
+            .filter(|row| !self.buffer.line(*row).trim().is_empty())
+            .collect();
+        let indent_len = |line: &str| line.len() - line.trim_start().len();
+        let commented = rows.iter().all(|row| {
+            let line = self.buffer.line(*row);
+            line.trim_start().starts_with(marker)
+        });
+        if commented {
+            for &row in rows.iter().rev() {
+                let line = self.buffer.line(row);
+                let start = self.buffer.line_start(row) + indent_len(line);
+                // the space after the marker goes too
+                let mut end = start + marker.len();
+                if line[end - self.buffer.line_start(row)..].starts_with(' ') {
+                    end += 1;
+                }
+                self.delete_range(start..end, cx);
+            }
+        } else {
+            let column = rows
+                .iter()
+                .map(|row| indent_len(self.buffer.line(*row)))
+                .min()
+                .unwrap_or(0);
+            for &row in rows.iter().rev() {
+                let at = self.buffer.line_start(row) + column;
+                self.insert_at(at, &format!("{marker} "), cx);
+            }
+        }
+    }
+    /// Swaps the lines the selection touches with the one above them, or
+    /// below. The selection moves along, a block at the first or last line
+    /// stays where it is.
+    pub fn move_lines(&mut self, up: bool, cx: &mut ModelContext<Self>) {
+        let rows = self.selected_rows();
+        let (first_row, last_row) = (rows.start, rows.end - 1);
+        let block_start = self.buffer.line_start(first_row);
+        let block = self.text_for_range(block_start..self.buffer.line_end(last_row));
+        let (range, new_text, shift) = if up {
+            if first_row == 0 {
+                return;
+            }
+            let above = self.buffer.line(first_row - 1).to_string();
+            let range = self.buffer.line_start(first_row - 1)..self.buffer.line_end(last_row);
+            (
+                range,
+                format!("{block}\n{above}"),
+                -(above.len() as isize + 1),
+            )
+        } else {
+            if last_row + 1 == self.buffer.line_count() {
+                return;
+            }
+            let below = self.buffer.line(last_row + 1).to_string();
+            let range = block_start..self.buffer.line_end(last_row + 1);
+            (range, format!("{below}\n{block}"), below.len() as isize + 1)
+        };
+        let selection = self.selection.clone();
+        let reversed = self.selection_reversed;
+        self.replace_range(range, &new_text, cx);
+        let moved = |offset: usize| offset.saturating_add_signed(shift);
+        self.set_selection(moved(selection.start)..moved(selection.end), reversed);
+    }
+    /// The clipboard's text, cleaned up by `normalize_pasted_text` unless `raw`.
+    fn clipboard_text(raw: bool, cx: &mut ModelContext<Self>) -> Option<String> {
+        let text = cx.read_from_clipboard()?.text().clone();
+        Some(if raw {
+            text
+        } else {
+            normalize_pasted_text(&text, cx.global::<EditorSettings>())
+        })
+    }
+    /// Inserts the clipboard's text, on one line when the input is single line.
+    /// Text ending in a newline, e.g. a line copied without a selection, goes
+    /// above the cursor's line when nothing is selected.
+    pub fn paste(&mut self, raw: bool, cx: &mut ModelContext<Self>) {
+        let Some(text) = Self::clipboard_text(raw, cx) else {
+            return;
+        };
+        if self.read_only {
+            return;
+        }
+        let text = if self.single_line {
+            text.replace("\r\n", " ")
+                .replace(|c| c == '\r' || c == '\n', " ")
+        } else {
+            text
+        };
+        if self.selection.is_empty() && text.ends_with('\n') {
+            let head = self.head();
+            self.insert_at(self.line_start(head), &text, cx);
+            // the cursor moves down with its line
+            self.move_to(head + text.len());
+        } else {
+            self.insert(&text, cx);
+        }
+    }
+    /// Deletes the selection, or the grapheme before the cursor.
+    pub fn delete_backward(&mut self, cx: &mut ModelContext<Self>) {
+        if self.selection.is_empty() {
+            // removes the whole grapheme, e.g. an emoji ZWJ sequence
+            let start = self.prev_boundary(self.selection.start);
+            self.delete_range(start..self.selection.end, cx);
+        } else {
+            self.delete_range(self.selection.clone(), cx);
+        }
+    }
+    /// Deletes the selection, or the grapheme after the cursor.
+    pub fn delete_forward(&mut self, cx: &mut ModelContext<Self>) {
+        if self.selection.is_empty() {
+            let end = self.next_boundary(self.selection.end);
+            self.delete_range(self.selection.start..end, cx);
+        } else {
+            self.delete_range(self.selection.clone(), cx);
+        }
+    }
+    /// Moves the cursor to `offset`, or only the head of the selection when
+    /// `extend` is set.
+    pub fn move_head_to(&mut self, offset: usize, extend: bool) {
+        if extend {
+            self.select_to(offset);
+        } else {
+            self.move_to(offset);
+        }
+    }
+    /// The first non-whitespace character of the line, or the start of the line
+    /// when `offset` is already there.
+    pub fn smart_line_start(&self, offset: usize) -> usize {
+        let row = self.buffer.row_for_offset(offset);
+        let line_start = self.buffer.line_start(row);
+        let line = self.buffer.line(row);
+        let indent_end = line_start + line.len() - line.trim_start().len();
+        if offset == indent_end {
+            line_start
+        } else {
+            indent_end
+        }
+    }
+    pub fn line_start(&self, offset: usize) -> usize {
+        self.buffer.line_start(self.buffer.row_for_offset(offset))
+    }
+    pub fn line_end(&self, offset: usize) -> usize {
+        self.buffer.line_end(self.buffer.row_for_offset(offset))
+    }
+    /// The offset `column` characters into `row`, clamped to the end of the line.
+    fn offset_in_row(&self, row: usize, column: usize) -> usize {
+        let line_start = self.buffer.line_start(row);
+        self.buffer
+            .line(row)
+            .char_indices()
+            .nth(column)
+            .map_or(self.buffer.line_end(row), |(i, _)| line_start + i)
+    }
+    /// The offset of the zero-based `row` and char `column`, clamped to the
+    /// end of the line and the last row.
+    pub fn offset_for_point(&self, row: usize, column: usize) -> usize {
+        self.offset_in_row(row.min(self.buffer.line_count() - 1), column)
+    }
+    /// The zero-based line and column of the cursor, the column counted in chars.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let head = self.head();
+        (self.buffer.row_for_offset(head), self.column(head))
+    }
+    /// The number of characters between the start of the line and `offset`.
+    pub fn column(&self, offset: usize) -> usize {
+        let row = self.buffer.row_for_offset(offset);
+        self.buffer.line(row)[..offset - self.buffer.line_start(row)]
+            .chars()
+            .count()
+    }
+    /// Moves the head to the goal column on the previous visual row. Returns
+    /// false when it is already on the first row.
+    pub fn move_up(&mut self, extend: bool) -> bool {
+        let head = self.head();
+        let (row, range) = self.visual_row_for_offset(head);
+        if row == 0 {
+            return false;
+        }
+        let column = self
+            .goal_column
+            .unwrap_or_else(|| self.text_for_range(range.start..head).chars().count());
+        let offset = self.offset_in_visual_row(row - 1, column);
+        self.move_head_to(offset, extend);
+        self.goal_column = Some(column);
+        true
+    }
+    /// Moves the head to the goal column on the next visual row. Returns
+    /// false when it is already on the last row.
+    pub fn move_down(&mut self, extend: bool) -> bool {
+        let head = self.head();
+        let (row, range) = self.visual_row_for_offset(head);
+        if row + 1 == self.visual_row_count() {
+            return false;
+        }
+        let column = self
+            .goal_column
+            .unwrap_or_else(|| self.text_for_range(range.start..head).chars().count());
+        let offset = self.offset_in_visual_row(row + 1, column);
+        self.move_head_to(offset, extend);
+        self.goal_column = Some(column);
+        true
+    }
+
+    /// Runs one step of a macro, the way the key it was recorded from ran.
+    /// Edits are skipped while the text is read-only.
+    pub fn replay(&mut self, step: &MacroStep, page_rows: usize, cx: &mut ModelContext<Self>) {
+        match step {
+            MacroStep::Action(action) => {
+                if !self.read_only || !action.edits_text() {
+                    self.perform(*action, page_rows, cx);
+                }
+            }
+            MacroStep::Insert(text) => {
+                if !self.read_only {
+                    self.for_each_selection(cx, |this, cx| this.type_text(text, cx));
+                }
+            }
+            MacroStep::VimKey(keystroke) => {
+                let mut vim = self.vim.take();
+                if let Some(vim) = &mut vim {
+                    vim.handle_key(keystroke, self, cx);
+                }
+                self.vim = vim;
+            }
+        }
+    }
+    /// Does what a key bound to `action` does, `page_rows` is how far the
+    /// page actions move. With extra cursors most actions happen at each of
+    /// them, the ones working on whole lines only at `selection`.
+    pub fn perform(&mut self, action: EditorAction, page_rows: usize, cx: &mut ModelContext<Self>) {
+        use EditorAction::*;
+        // tab right after a snippet's prefix expands it
+        if action == Indent && self.extra_selections.is_empty() && self.expand_snippet_at_cursor(cx)
+        {
+            return;
+        }
+        // the open completions take the keys for choosing one, up and down
+        // are passed on like past the first and last line
+        if let Some(selected) = self
+            .completion
+            .as_ref()
+            .map(|completion| completion.selected)
+        {
+            match action {
+                MoveUp | MoveDown => {
+                    let movement = if action == MoveUp {
+                        TextMovement::Up
+                    } else {
+                        TextMovement::Down
+                    };
+                    cx.emit(TextEvent::Movement(movement));
+                    return;
+                }
+                Indent | Newline => {
+                    self.accept_completion(selected, cx);
+                    return;
+                }
+                Cancel => {
+                    self.completion = None;
+                    cx.notify();
+                    return;
+                }
+                _ => {}
+            }
+        }
+        // tab and shift-tab move between a snippet's stops
+        if let Some(current) = self.snippet.as_ref().map(|snippet| snippet.current) {
+            match action {
+                Indent => {
+                    self.select_tab_stop(current + 1);
+                    return;
+                }
+                Outdent => {
+                    self.select_tab_stop(current.saturating_sub(1));
+                    return;
+                }
+                Cancel => {
+                    self.snippet = None;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        if self.extra_selections.is_empty() {
+            self.perform_at_selection(action, page_rows, cx);
+            return;
+        }
+        match action {
+            Cancel => self.collapse_selections(),
+            SelectAll | Undo => {
+                self.collapse_selections();
+                self.perform_at_selection(action, page_rows, cx);
+            }
+            Copy => self.copy_selections(cx),
+            Cut => {
+                self.copy_selections(cx);
+                if !self.read_only && !self.masked {
+                    self.for_each_selection(cx, |this, cx| {
+                        this.delete_range(this.selection.clone(), cx)
+                    });
+                }
+            }
+            Paste => self.paste_at_selections(false, cx),
+            PasteRaw => self.paste_at_selections(true, cx),
+            MoveLineUp | MoveLineDown | DeleteLine | ToggleComment | JoinLines | Outdent
+            | SelectNextOccurrence | SelectAllOccurrences | SelectColumnUp | SelectColumnDown
+            | SelectColumnLeft | SelectColumnRight => {
+                self.perform_at_selection(action, page_rows, cx)
+            }
+            _ => self.for_each_selection(cx, |this, cx| {
+                this.perform_at_selection(action, page_rows, cx)
+            }),
+        }
+    }
+    /// Copies the selected texts one per line, in the order of the text.
+    fn copy_selections(&self, cx: &mut ModelContext<Self>) {
+        if self.masked {
+            return;
+        }
+        let text = self
+            .selections()
+            .into_iter()
+            .map(|selection| self.text_for_range(selection))
+            .collect::<Vec<_>>()
+            .join("\n");
+        copy_to_clipboard(text, cx);
+    }
+    /// Pastes one line of the clipboard at each cursor when there are as many
+    /// lines as cursors, or else all of it at each.
+    fn paste_at_selections(&mut self, raw: bool, cx: &mut ModelContext<Self>) {
+        let Some(text) = Self::clipboard_text(raw, cx) else {
+            return;
+        };
+        if self.read_only {
+            return;
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        let mut lines = lines.into_iter();
+        let split = lines.len() == self.extra_selections.len() + 1;
+        self.for_each_selection(cx, |this, cx| {
+            let text = if split {
+                lines.next().unwrap_or_default()
+            } else {
+                text.as_str()
+            };
+            this.insert(text, cx);
+        });
+    }
+    /// `perform` for the one cursor at `selection`.
+    fn perform_at_selection(
+        &mut self,
+        action: EditorAction,
+        page_rows: usize,
+        cx: &mut ModelContext<Self>,
+    ) {
+        use EditorAction::*;
+        let head = self.head();
+        match action {
+            MoveLeft => {
+                let i = if self.selection.is_empty() {
+                    self.prev_boundary(self.selection.start)
+                } else {
+                    self.selection.start
+                };
+                self.move_to(i);
+            }
+            MoveRight => {
+                let i = if self.selection.is_empty() {
+                    self.next_boundary(self.selection.end)
+                } else {
+                    self.selection.end
+                };
+                self.move_to(i);
+            }
+            SelectLeft => self.select_to(self.prev_boundary(head)),
+            SelectRight => self.select_to(self.next_boundary(head)),
+            MoveUp | MoveDown | SelectUp | SelectDown if self.single_line => {
+                let movement = if matches!(action, MoveUp | SelectUp) {
+                    TextMovement::Up
+                } else {
+                    TextMovement::Down
+                };
+                cx.emit(TextEvent::Movement(movement));
+            }
+            // let whoever embeds the input move past it
+            MoveUp => {
+                if !self.move_up(false) {
+                    cx.emit(TextEvent::Movement(TextMovement::Up));
+                }
+            }
+            MoveDown => {
+                if !self.move_down(false) {
+                    cx.emit(TextEvent::Movement(TextMovement::Down));
+                }
+            }
+            SelectUp => {
+                if !self.move_up(true) {
+                    self.select_to(0);
+                }
+            }
+            SelectDown => {
+                if !self.move_down(true) {
+                    self.select_to(self.len());
+                }
+            }
+            MoveWordLeft | SelectWordLeft => {
+                self.move_head_to(self.word_boundary_before(head), action == SelectWordLeft);
+            }
+            MoveWordRight | SelectWordRight => {
+                self.move_head_to(self.word_boundary_after(head), action == SelectWordRight);
+            }
+            MoveToLineStart | SelectToLineStart => {
+                self.move_head_to(self.visual_line_start(head), action == SelectToLineStart);
+            }
+            MoveToLineEnd | SelectToLineEnd => {
+                self.move_head_to(self.visual_line_end(head), action == SelectToLineEnd);
+            }
+            MoveToStart | SelectToStart => self.move_head_to(0, action == SelectToStart),
+            MoveToEnd | SelectToEnd => self.move_head_to(self.len(), action == SelectToEnd),
+            PageUp | SelectPageUp => {
+                let extend = action == SelectPageUp;
+                for _ in 0..page_rows {
+                    if !self.move_up(extend) {
+                        self.move_head_to(0, extend);
+                        break;
+                    }
+                }
+            }
+            PageDown | SelectPageDown => {
+                let extend = action == SelectPageDown;
+                for _ in 0..page_rows {
+                    if !self.move_down(extend) {
+                        self.move_head_to(self.len(), extend);
+                        break;
+                    }
+                }
+            }
+            SelectAll => self.select_all(),
+            JumpToBracket => self.jump_to_bracket(),
+            Copy => self.copy(cx),
+            Cut => self.cut(cx),
+            Paste => self.paste(false, cx),
+            PasteRaw => self.paste(true, cx),
+            DeleteToLineEnd => self.delete_to_line_end(cx),
+            DeleteLine => self.delete_lines(cx),
+            DuplicateSelection => self.duplicate_selection(cx),
+            MoveLineUp => self.move_lines(true, cx),
+            MoveLineDown => self.move_lines(false, cx),
+            SelectColumnUp => self.move_column_head(-1, 0),
+            SelectColumnDown => self.move_column_head(1, 0),
+            SelectColumnLeft => self.move_column_head(0, -1),
+            SelectColumnRight => self.move_column_head(0, 1),
+            SelectNextOccurrence => self.select_next_occurrence(),
+            SelectAllOccurrences => self.select_all_occurrences(),
+            ToggleComment => self.toggle_comment(cx),
+            JoinLines => self.join_lines(cx),
+            Uppercase => self.transform_selection(
+                |editor, range| editor.text_for_range(range).to_uppercase(),
+                cx,
+            ),
+            Lowercase => self.transform_selection(
+                |editor, range| editor.text_for_range(range).to_lowercase(),
+                cx,
+            ),
+            TitleCase => self.transform_selection(Self::title_case, cx),
+            Indent => {
+                let indent = self.settings.indent();
+                self.indent(&indent, cx);
+            }
+            Outdent => self.outdent(self.settings.tab_size, cx),
+            Undo => {
+                self.undo(cx);
+            }
+            DeleteBackward => {
+                if !self.delete_pair(cx) {
+                    self.delete_backward(cx);
+                }
+            }
+            DeleteForward => self.delete_forward(cx),
+            DeleteWordBackward => {
+                let range = if self.selection.is_empty() {
+                    self.word_boundary_before(self.selection.start)..self.selection.start
+                } else {
+                    self.selection.clone()
+                };
+                self.delete_range(range, cx);
+            }
+            DeleteWordForward => {
+                let range = if self.selection.is_empty() {
+                    self.selection.end..self.word_boundary_after(self.selection.end)
+                } else {
+                    self.selection.clone()
+                };
+                self.delete_range(range, cx);
+            }
+            Newline | NewlineWithoutIndent if self.single_line => {
+                cx.emit(TextEvent::Submit { text: self.text() })
+            }
+            Newline => {
+                let indent = self.settings.indent();
+                self.newline_and_indent(&indent, cx);
+            }
+            NewlineWithoutIndent => self.insert("\n", cx),
+            Cancel => cx.emit(TextEvent::Cancel),
+            ShowCompletions => self.show_completions(cx),
+        }
+    }
+}
+
+pub enum TextEvent {
+    /// The text in `range` was replaced by `new_text_len` bytes, read the
+    /// result through `TextModel::text`.
+    Edited {
+        range: Range<usize>,
+        new_text_len: usize,
+    },
+    SelectionChanged {
+        selection: Range<usize>,
+    },
+    Movement(TextMovement),
+    Submit {
+        text: String,
+    },
+    /// Escape was pressed, the owner decides what that closes.
+    Cancel,
+    Focus,
+    Blur,
+}
+#[derive(Clone, Copy)]
+pub enum TextMovement {
+    Up,
+    Down,
+}
+
+impl EventEmitter<TextEvent> for TextModel {}
+
+/// `value` kept between zero and `max`.
+fn clamp_pixels(value: Pixels, max: Pixels) -> Pixels {
+    if value < px(0.) {
+        px(0.)
+    } else if value > max {
+        max
+    } else {
+        value
+    }
+}
+
+/// Whether `word` starts with `prefix`, in any case.
+fn starts_with_ignoring_case(word: &str, prefix: &str) -> bool {
+    let mut chars = word.chars();
+    prefix.chars().all(|p| {
+        chars
+            .next()
+            .is_some_and(|c| c == p || c.to_lowercase().eq(p.to_lowercase()))
+    })
+}
+
+/// The partner that closes `open`, if it is one of the `PAIRS`.
+fn closing_pair(open: char) -> Option<char> {
+    PAIRS
+        .iter()
+        .find(|(pair_open, _)| *pair_open == open)
+        .map(|(_, close)| *close)
+}
+
+/// Paints `range` of a line's token colors in `color`, over whatever they were.
+fn recolor(colors: &mut Vec<(Range<usize>, Hsla)>, range: Range<usize>, color: Hsla) {
+    let mut recolored = Vec::with_capacity(colors.len() + 2);
+    for (token, token_color) in colors.drain(..) {
+        if token.start < range.start {
+            recolored.push((token.start..token.end.min(range.start), token_color));
+        }
+        if token.end > range.end {
+            recolored.push((token.start.max(range.end)..token.end, token_color));
+        }
+    }
+    recolored.push((range, color));
+    recolored.sort_by_key(|(range, _)| range.start);
+    *colors = recolored;
+}
+
+impl RenderOnce for TextInput {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let read_only = self.model.read(cx).read_only;
+        let single_line = self.model.read(cx).single_line;
+        let (border_color, focus_border_color) = if self.invalid {
+            (theme.danger_color, theme.danger_color)
+        } else if read_only {
+            (theme.muted_color, theme.border_color)
+        } else {
+            (theme.border_color, theme.primary_color)
+        };
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(move |ev, cx| {
+                let page_rows = self.view.read(cx).page_rows();
+                let action = cx.global::<Keymap>().action_for(&ev.keystroke);
+                self.model.update(cx, |editor, cx| {
+                    editor.clamp_selection();
+                    let version = editor.version;
+                    let selection = editor.selection.clone();
+                    // the key that starts a recording isn't part of it
+                    let recording = cx.global::<MacroRecorder>().is_recording(cx.entity_id());
+                    let mut vim = editor.vim.take();
+                    let handled_by_vim = vim
+                        .as_mut()
+                        .is_some_and(|vim| vim.handle_key(&ev.keystroke, editor, cx));
+                    editor.vim = vim;
+                    let step = if handled_by_vim {
+                        Some(MacroStep::VimKey(ev.keystroke.clone()))
+                    } else if let Some(action) = action {
+                        editor.break_undo_group();
+                        Some(MacroStep::Action(action))
+                    } else if let Some(ime_key) = &ev.keystroke.ime_key {
+                        // typing is undone a word at a time
+                        if ime_key.starts_with(char::is_whitespace) {
+                            editor.break_undo_group();
+                        }
+                        Some(MacroStep::Insert(ime_key.clone()))
+                    } else {
+                        // shortcuts are left to the workspace
+                        if !ev.keystroke.modifiers.command && !ev.keystroke.modifiers.control {
+                            cx.global_mut::<ToastQueue>().push(Toast::info(format!(
+                                "Unhandled keystroke {}",
+                                ev.keystroke.key
+                            )));
+                        }
+                        None
+                    };
+                    let typed = matches!(step, Some(MacroStep::Insert(_)));
+                    if let Some(step) = step {
+                        if !handled_by_vim {
+                            editor.replay(&step, page_rows, cx);
+                        }
+                        if recording {
+                            cx.global_mut::<MacroRecorder>()
+                                .record(cx.entity_id(), step);
+                        }
+                    }
+                    // typing a word offers the ones finishing it
+                    if typed {
+                        editor.update_completion(true, cx);
+                    }
+                    // edits emit their own `TextEvent::Edited`
+                    if editor.version == version && editor.selection != selection {
+                        cx.emit(TextEvent::SelectionChanged {
+                            selection: editor.selection.clone(),
+                        });
+                    }
+                });
+                match action {
+                    Some(EditorAction::PageUp | EditorAction::SelectPageUp) => self
+                        .view
+                        .update(cx, |display, cx| display.scroll_page(true, cx)),
+                    Some(EditorAction::PageDown | EditorAction::SelectPageDown) => self
+                        .view
+                        .update(cx, |display, cx| display.scroll_page(false, cx)),
+                    _ => {}
+                }
+            })
+            .flex()
+            .flex_col()
+            .when(!single_line, |this| this.flex_1().min_h_0())
+            .p_4()
+            .w_full()
+            .border_1()
+            .border_color(border_color)
+            .text_color(theme.text_color)
+            .focus(|style| style.border_color(focus_border_color))
+            .child(self.view)
+    }
+}
+
+const CURSOR_WIDTH: Pixels = px(2.);
+const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+/// How many lines scrolling the cursor into view keeps above and below it.
+const AUTOSCROLL_MARGIN: f32 = 2.;
+/// The room scrolling the cursor into view keeps left and right of it.
+const AUTOSCROLL_MARGIN_X: Pixels = px(32.);
+/// The height of the bar that shows how far the text is scrolled sideways.
+const SCROLLBAR_WIDTH: Pixels = px(4.);
+/// The space between the line numbers and the text.
+const GUTTER_PADDING: Pixels = px(12.);
+/// Room left of the line numbers for the diff markers, see `TextModel::diff_hunks`.
+const DIFF_GUTTER_WIDTH: Pixels = px(8.);
+const DIFF_MARKER_INSET: Pixels = px(2.);
+const DIFF_MARKER_WIDTH: Pixels = px(3.);
+/// How far the wedge for removed lines reaches out from the line between rows.
+const DIFF_REMOVED_SIZE: Pixels = px(4.);
+/// How far a diagnostic's wavy underline goes up and down, and along the line
+/// for each of those.
+const SQUIGGLE_HEIGHT: Pixels = px(2.);
+const SQUIGGLE_THICKNESS: Pixels = px(1.);
+/// The size of the dots under misspelled words, and the gap between them.
+const SPELLING_DOT_SIZE: Pixels = px(1.5);
+/// How far `TextModel::bracket_pair` looks for a partner before giving up.
+const MAX_BRACKET_SCAN: usize = 100_000;
+/// Only this much of a text is syntax highlighted, the rest stays plain.
+const MAX_HIGHLIGHT_LEN: usize = 1_000_000;
+/// How many words the completion popup offers at most.
+const MAX_COMPLETIONS: usize = 8;
+/// Selected words longer than this don't get their other occurrences highlighted.
+const MAX_OCCURRENCE_LEN: usize = 256;
+/// How far a blank line looks up and down for the indentation it continues.
+const MAX_GUIDE_SCAN: usize = 1_000;
+/// The brackets and quotes `TextModel::type_text` closes, a quote closes itself.
+const PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
+/// The oldest edits are forgotten past this many.
+const MAX_UNDO_ENTRIES: usize = 10_000;
+
+const MASK_CHAR: char = '•';
+/// How many bytes the code of an invisible char takes on screen, four hex
+/// digits.
+const INVISIBLE_CODE_LEN: usize = 4;
+
+/// Chars that take no room or pass for a space, which is how confusing text
+/// hides in plain sight: controls, no-break and zero width spaces, bidi
+/// overrides and the like. Tabs and newlines are plain whitespace.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0}'..='\u{8}'
+            | '\u{b}'..='\u{1f}'
+            | '\u{7f}'..='\u{a0}'
+            | '\u{ad}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{2028}'..='\u{202f}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{feff}'
+    )
+}
+
+/// Where the indent guides of `line` go: the byte index and column of every
+/// multiple of `tab_size` inside its leading whitespace, where a tab reaches
+/// up to the next multiple.
+fn indent_stops(line: &str, tab_size: usize) -> Vec<(usize, usize)> {
+    let tab_size = tab_size.max(1);
+    let mut stops = Vec::new();
+    let mut column = 0;
+    for (i, c) in line.char_indices() {
+        let width = match c {
+            ' ' => 1,
+            '\t' => tab_size - column % tab_size,
+            _ => break,
+        };
+        if column % tab_size == 0 {
+            stops.push((i, column));
+        }
+        column += width;
+    }
+    stops
+}
+
+/// The hex code an invisible char is displayed as, `INVISIBLE_CODE_LEN` long
+/// for every char `is_invisible` matches.
+fn invisible_code(c: char) -> String {
+    format!("{:04X}", c as u32)
+}
+
+/// Maps offsets in the model's text to offsets in the displayed text, they
+/// only differ when the input is masked.
+enum DisplayMap {
+    Identity,
+    /// The start of every grapheme cluster followed by the end of the text,
+    /// each cluster is displayed as one `MASK_CHAR`.
+    Masked(Vec<usize>),
+    /// The offset, length and display offset of every invisible char, each
+    /// is displayed as its code, see `invisible_code`.
+    Invisibles(Vec<(usize, usize, usize)>),
+}
+
+impl DisplayMap {
+    fn masked(text: &str) -> Self {
+        let mut boundaries: Vec<usize> = text.grapheme_indices(true).map(|(i, _)| i).collect();
+        boundaries.push(text.len());
+        DisplayMap::Masked(boundaries)
+    }
+
+    /// The text displayed as it is, apart from the `chars` found by
+    /// `TextModel::invisible_chars`.
+    fn invisibles(chars: &[(usize, char)]) -> Self {
+        if chars.is_empty() {
+            return DisplayMap::Identity;
+        }
+        let mut shift = 0;
+        DisplayMap::Invisibles(
+            chars
+                .iter()
+                .map(|(offset, c)| {
+                    let display_offset = offset + shift;
+                    shift += INVISIBLE_CODE_LEN - c.len_utf8();
+                    (*offset, c.len_utf8(), display_offset)
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether the text is displayed line by line, as opposed to masked.
+    fn shows_lines(&self) -> bool {
+        !matches!(self, DisplayMap::Masked(_))
+    }
+
+    fn display_text(&self, text: &str) -> String {
+        match self {
+            DisplayMap::Identity | DisplayMap::Invisibles(_) => text.to_string(),
+            DisplayMap::Masked(boundaries) => MASK_CHAR.to_string().repeat(boundaries.len() - 1),
+        }
+    }
+
+    fn to_display(&self, offset: usize) -> usize {
+        match self {
+            DisplayMap::Identity => offset,
+            DisplayMap::Masked(boundaries) => {
+                let grapheme = boundaries.binary_search(&offset).unwrap_or_else(|i| i);
+                grapheme * MASK_CHAR.len_utf8()
+            }
+            DisplayMap::Invisibles(chars) => {
+                let i = chars.partition_point(|(start, _, _)| *start < offset);
+                match i.checked_sub(1).map(|i| chars[i]) {
+                    Some((start, len, display_start)) => {
+                        display_start + INVISIBLE_CODE_LEN + offset.saturating_sub(start + len)
+                    }
+                    None => offset,
+                }
+            }
+        }
+    }
+
+    fn to_model(&self, display_offset: usize) -> usize {
+        match self {
+            DisplayMap::Identity => display_offset,
+            DisplayMap::Masked(boundaries) => {
+                let grapheme = display_offset / MASK_CHAR.len_utf8();
+                boundaries[grapheme.min(boundaries.len() - 1)]
+            }
+            DisplayMap::Invisibles(chars) => {
+                let i = chars.partition_point(|(_, _, start)| *start <= display_offset);
+                match i.checked_sub(1).map(|i| chars[i]) {
+                    // inside a code, the closer side of the char
+                    Some((start, len, display_start))
+                        if display_offset < display_start + INVISIBLE_CODE_LEN =>
+                    {
+                        if display_offset - display_start < INVISIBLE_CODE_LEN / 2 {
+                            start
+                        } else {
+                            start + len
+                        }
+                    }
+                    Some((start, len, display_start)) => {
+                        start + len + display_offset - display_start - INVISIBLE_CODE_LEN
+                    }
+                    None => display_offset,
+                }
+            }
+        }
+    }
+}
+
+/// The lines a `TextDisplay` painted last, used to map between offsets and
+/// window positions. Every visual row of the text, a line or a part of a
+/// wrapped one, is one row of `line_height`, only the visible ones are shaped.
+struct TextLayout {
+    /// Where the first line starts, above `viewport` when scrolled down.
+    origin: Point<Pixels>,
+    viewport: Bounds<Pixels>,
+    /// The width of the line numbers left of the text, zero without them.
+    gutter_width: Pixels,
+    line_height: Pixels,
+    /// The number of visual rows in the whole text.
+    row_count: usize,
+    /// The row of the first entry in `lines`.
+    first_row: usize,
+    /// The visible rows and the display offsets they start at.
+    lines: Vec<(usize, ShapedLine)>,
+    display_map: DisplayMap,
+    /// How far the visible lines can be scrolled sideways, zero when they fit.
+    max_scroll_left: Pixels,
+}
+
+impl TextLayout {
+    /// Like `index_for_position`, but in terms of the model's text.
+    fn offset_for_position(&self, position: Point<Pixels>) -> usize {
+        self.display_map.to_model(self.index_for_position(position))
+    }
+
+    /// The top left of the row, relative to the viewport's coordinates.
+    fn row_origin(&self, row: usize) -> Point<Pixels> {
+        self.origin + point(px(0.), self.line_height * row as f32)
+    }
+
+    /// Where `index` is drawn, `None` when its line isn't visible.
+    fn position_for_index(&self, index: usize) -> Option<Point<Pixels>> {
+        let i = self
+            .lines
+            .iter()
+            .rposition(|(start, line)| *start <= index && index <= start + line.len())?;
+        let (start, line) = &self.lines[i];
+        Some(self.row_origin(self.first_row + i) + point(line.x_for_index(index - start), px(0.)))
+    }
+
+    /// Fills the part of `range` on the visible lines.
+    fn paint_background(&self, range: Range<usize>, color: Hsla, cx: &mut ElementContext) {
+        for (i, (start, line)) in self.lines.iter().enumerate() {
+            let end = start + line.len();
+            let range_start = range.start.clamp(*start, end);
+            let range_end = range.end.clamp(*start, end);
+            if range_start < range_end {
+                let left = line.x_for_index(range_start - start);
+                let right = line.x_for_index(range_end - start);
+                cx.paint_quad(fill(
+                    Bounds::new(
+                        self.row_origin(self.first_row + i) + point(left, px(0.)),
+                        size(right - left, self.line_height),
+                    ),
+                    color,
+                ));
+            }
+        }
+    }
+
+    /// Draws a wavy line under the part of `range` on the visible lines.
+    fn paint_squiggle(&self, range: Range<usize>, color: Hsla, cx: &mut ElementContext) {
+        for (i, (start, line)) in self.lines.iter().enumerate() {
+            let end = start + line.len();
+            let range_start = range.start.clamp(*start, end);
+            let range_end = range.end.clamp(*start, end);
+            if range_start >= range_end {
+                continue;
+            }
+            let left = self.origin.x + line.x_for_index(range_start - start);
+            let right = self.origin.x + line.x_for_index(range_end - start);
+            let bottom = self.row_origin(self.first_row + i).y + self.line_height - px(1.);
+            let top = bottom - SQUIGGLE_HEIGHT - SQUIGGLE_THICKNESS;
+            let mut peaks = vec![point(left, top)];
+            let mut x = left;
+            while x < right {
+                x = (x + SQUIGGLE_HEIGHT).min(right);
+                let y = if peaks.len() % 2 == 0 {
+                    top
+                } else {
+                    top + SQUIGGLE_HEIGHT
+                };
+                peaks.push(point(x, y));
+            }
+            // the zigzag along the top edge and back along the bottom one
+            let mut squiggle = Path::new(peaks[0]);
+            for peak in &peaks[1..] {
+                squiggle.line_to(*peak);
+            }
+            for peak in peaks.iter().rev() {
+                squiggle.line_to(*peak + point(px(0.), SQUIGGLE_THICKNESS));
+            }
+            squiggle.line_to(peaks[0]);
+            cx.paint_path(squiggle, color);
+        }
+    }
+
+    /// Draws a dotted line under the part of `range` on the visible lines.
+    fn paint_dots(&self, range: Range<usize>, color: Hsla, cx: &mut ElementContext) {
+        for (i, (start, line)) in self.lines.iter().enumerate() {
+            let end = start + line.len();
+            let range_start = range.start.clamp(*start, end);
+            let range_end = range.end.clamp(*start, end);
+            if range_start >= range_end {
+                continue;
+            }
+            let right = self.origin.x + line.x_for_index(range_end - start);
+            let y = self.row_origin(self.first_row + i).y + self.line_height
+                - px(1.)
+                - SPELLING_DOT_SIZE;
+            let mut x = self.origin.x + line.x_for_index(range_start - start);
+            while x + SPELLING_DOT_SIZE <= right {
+                cx.paint_quad(fill(
+                    Bounds::new(point(x, y), size(SPELLING_DOT_SIZE, SPELLING_DOT_SIZE)),
+                    color,
+                ));
+                x += SPELLING_DOT_SIZE * 2.;
+            }
+        }
+    }
+
+    fn in_gutter(&self, position: Point<Pixels>) -> bool {
+        position.x < self.viewport.origin.x + self.gutter_width
+    }
+
+    fn content_height(&self) -> Pixels {
+        self.line_height * self.row_count as f32
+    }
+
+    fn max_scroll_top(&self) -> Pixels {
+        let max = self.content_height() - self.viewport.size.height;
+        if max > px(0.) {
+            max
+        } else {
+            px(0.)
+        }
+    }
+
+    fn clamp_scroll_top(&self, scroll_top: Pixels) -> Pixels {
+        let max = self.max_scroll_top();
+        if scroll_top < px(0.) {
+            px(0.)
+        } else if scroll_top > max {
+            max
+        } else {
+            scroll_top
+        }
+    }
+
+    fn clamp_scroll_left(&self, scroll_left: Pixels) -> Pixels {
+        clamp_pixels(scroll_left, self.max_scroll_left)
+    }
+
+    /// The scroll position closest to `scroll_top` that keeps `row` visible,
+    /// with `AUTOSCROLL_MARGIN` lines around it when they fit.
+    fn scroll_top_for_row(&self, row: usize, scroll_top: Pixels) -> Pixels {
+        let top = self.line_height * row as f32;
+        let margin = self.line_height * AUTOSCROLL_MARGIN;
+        let height = self.viewport.size.height;
+        let scroll_top = if height < self.line_height + margin * 2. {
+            // too small for the margin, just keep the line itself visible
+            if top < scroll_top {
+                top
+            } else if top + self.line_height > scroll_top + height {
+                top + self.line_height - height
+            } else {
+                scroll_top
+            }
+        } else if top - margin < scroll_top {
+            top - margin
+        } else if top + self.line_height + margin > scroll_top + height {
+            top + self.line_height + margin - height
+        } else {
+            scroll_top
+        };
+        self.clamp_scroll_top(scroll_top)
+    }
+
+    /// The offset closest to `position`, positions below the text map to its
+    /// end. Rows that aren't visible map to the closest visible one.
+    fn index_for_position(&self, position: Point<Pixels>) -> usize {
+        if position.y < self.origin.y {
+            return 0;
+        }
+        let row = ((position.y - self.origin.y) / self.line_height) as usize;
+        if row >= self.row_count {
+            return usize::MAX;
+        }
+        let i = row
+            .saturating_sub(self.first_row)
+            .min(self.lines.len().saturating_sub(1));
+        let Some((start, line)) = self.lines.get(i) else {
+            return 0;
+        };
+        let index = start + line.closest_index_for_x(position.x - self.origin.x);
+        // the end of a row that wraps is displayed on the next one, the
+        // offset is moved back onto the last char when it's clamped
+        let wraps = self
+            .lines
+            .get(i + 1)
+            .is_some_and(|(next, _)| *next == start + line.len());
+        if wraps && index == start + line.len() {
+            index.saturating_sub(1)
+        } else {
+            index
+        }
+    }
+}
+
+pub struct TextDisplay {
+    model: Model<TextModel>,
+    focus_handle: FocusHandle,
+    layout: Arc<Mutex<Option<TextLayout>>>,
+    /// Whether the left mouse button went down on the text and a drag extends
+    /// the selection.
+    selecting: bool,
+    /// How far the text is scrolled up out of view, shared with its `Scrollbar`.
+    scroll: Model<ScrollState>,
+    /// How far the text is scrolled left out of view, always zero while it's
+    /// soft wrapped.
+    scroll_left: Pixels,
+    /// Set when the cursor moved by keyboard, the next paint scrolls it into view.
+    autoscroll: bool,
+    cursor_visible: bool,
+    _blink_task: Task<()>,
+    /// Open after a right click.
+    context_menu: Option<View<ContextMenu>>,
+    /// Where the completion popup goes, below the word it finishes. Set when
+    /// the text is painted, `None` while there are no completions.
+    completion_origin: Option<Point<Pixels>>,
+}
+
+impl TextDisplay {
+    fn new(model: Model<TextModel>, focus_handle: FocusHandle, cx: &mut ViewContext<Self>) -> Self {
+        cx.observe(&model, |_this, _model, cx| cx.notify()).detach();
+        let scroll = cx.new_model(|_cx| ScrollState::new());
+        cx.observe(&scroll, |_this, _scroll, cx| cx.notify())
+            .detach();
+        cx.on_focus(&focus_handle, |this, cx| {
+            this.restart_blinking(cx);
+            this.model
+                .update(cx, |_editor, cx| cx.emit(TextEvent::Focus));
+        })
+        .detach();
+        cx.on_blur(&focus_handle, |this, cx| {
+            this.selecting = false;
+            this.model
+                .update(cx, |_editor, cx| cx.emit(TextEvent::Blur));
+            cx.notify();
+        })
+        .detach();
+        cx.subscribe(&model, |this, _emitter, event, cx| match event {
+            TextEvent::Edited { .. } | TextEvent::SelectionChanged { .. } => {
+                // the viewport stays put while the mouse is selecting
+                if !this.selecting {
+                    this.autoscroll = true;
+                }
+                this.restart_blinking(cx);
+                // the completions follow the word at the cursor, or close
+                if this.model.read(cx).completion.is_some() {
+                    this.model
+                        .update(cx, |editor, cx| editor.update_completion(false, cx));
+                }
+                cx.notify();
+            }
+            TextEvent::Movement(movement) => {
+                let movement = *movement;
+                this.model.update(cx, |editor, cx| {
+                    editor.move_completion_highlight(movement, cx)
+                });
+            }
+            _ => {}
+        })
+        .detach();
+        Self {
+            model,
+            focus_handle,
+            layout: Arc::new(Mutex::new(None)),
+            selecting: false,
+            scroll,
+            scroll_left: px(0.),
+            autoscroll: false,
+            cursor_visible: true,
+            _blink_task: Self::blink(cx),
+            context_menu: None,
+            completion_origin: None,
+        }
+    }
+
+    fn blink(cx: &mut ViewContext<Self>) -> Task<()> {
+        cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(CURSOR_BLINK_INTERVAL).await;
+                let result = this.update(&mut cx, |this, cx| {
+                    this.cursor_visible = !this.cursor_visible;
+                    cx.notify();
+                });
+                if result.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Keeps the cursor solid while typing, the blink starts over afterwards.
+    fn restart_blinking(&mut self, cx: &mut ViewContext<Self>) {
+        self.cursor_visible = true;
+        self._blink_task = Self::blink(cx);
+    }
+
+    fn index_for_position(&self, position: Point<Pixels>) -> Option<usize> {
+        let layout = self.layout.lock().unwrap();
+        layout
+            .as_ref()
+            .map(|layout| layout.offset_for_position(position))
+    }
+
+    /// The number of lines that fit the viewport, at least one.
+    fn page_rows(&self) -> usize {
+        let layout = self.layout.lock().unwrap();
+        layout.as_ref().map_or(1, |layout| {
+            ((layout.viewport.size.height / layout.line_height) as usize).max(1)
+        })
+    }
+
+    /// Scrolls a viewport height up or down, the cursor is moved separately.
+    fn scroll_page(&mut self, up: bool, cx: &mut ViewContext<Self>) {
+        let layout = self.layout.lock().unwrap();
+        let Some(layout) = layout.as_ref() else {
+            return;
+        };
+        let height = layout.viewport.size.height;
+        let scroll_top = self.scroll.read(cx).offset;
+        let scroll_top = if up {
+            scroll_top - height
+        } else {
+            scroll_top + height
+        };
+        let scroll_top = layout.clamp_scroll_top(scroll_top);
+        self.scroll
+            .update(cx, |scroll, cx| scroll.scroll_to(scroll_top, cx));
+    }
+
+    fn on_scroll_wheel(&mut self, ev: &ScrollWheelEvent, cx: &mut ViewContext<Self>) {
+        let layout = self.layout.lock().unwrap();
+        let Some(layout) = layout.as_ref() else {
+            return;
+        };
+        let delta = ev.delta.pixel_delta(layout.line_height);
+        // a mouse wheel scrolls sideways with shift held
+        let (dx, dy) = if ev.modifiers.shift {
+            (delta.y, px(0.))
+        } else {
+            (delta.x, delta.y)
+        };
+        self.scroll_left = layout.clamp_scroll_left(self.scroll_left - dx);
+        let scroll_top = layout.clamp_scroll_top(self.scroll.read(cx).offset - dy);
+        self.scroll
+            .update(cx, |scroll, cx| scroll.scroll_to(scroll_top, cx));
+        cx.notify();
+    }
+
+    fn on_mouse_down(&mut self, ev: &MouseDownEvent, cx: &mut ViewContext<Self>) {
+        let Some(index) = self.index_for_position(ev.position) else {
+            return;
+        };
+        let in_gutter = self
+            .layout
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(false, |layout| layout.in_gutter(ev.position));
+        self.selecting = true;
+        self.model.update(cx, |editor, cx| {
+            let offset = editor.clamp_offset(index);
+            editor.collapse_selections();
+            match ev.click_count {
+                // alt starts a rectangle, the drag sizes it
+                1 if ev.modifiers.alt && !editor.single_line => {
+                    let point = (
+                        editor.buffer().row_for_offset(offset),
+                        editor.column(offset),
+                    );
+                    editor.select_columns(point, point);
+                }
+                // a line number selects its line, including the newline
+                _ if in_gutter => {
+                    let end = editor.next_boundary(editor.line_end(offset));
+                    editor.select(editor.line_start(offset)..end);
+                }
+                1 if ev.modifiers.shift => editor.select_to(offset),
+                1 => editor.move_to(offset),
+                // words are meaningless in masked text, select all of it
+                2 if editor.masked => editor.select_all(),
+                2 => {
+                    let word = editor.word_range_at(offset).unwrap_or(offset..offset);
+                    editor.select(word);
+                }
+                3 => editor.select(editor.line_start(offset)..editor.line_end(offset)),
+                _ => editor.select_all(),
+            }
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+    }
+
+    /// Moves the cursor to the click unless it's on the selection, then opens
+    /// the context menu there.
+    fn on_right_mouse_down(&mut self, ev: &MouseDownEvent, cx: &mut ViewContext<Self>) {
+        let Some(index) = self.index_for_position(ev.position) else {
+            return;
+        };
+        self.focus_handle.focus(cx);
+        let misspelling = self.model.update(cx, |editor, cx| {
+            let offset = editor.clamp_offset(index);
+            if !(editor.selection.start..=editor.selection.end).contains(&offset) {
+                editor.move_to(offset);
+                cx.emit(TextEvent::SelectionChanged {
+                    selection: editor.selection.clone(),
+                });
+            }
+            editor.misspelling_at(offset)
+        });
+        self.deploy_context_menu(ev.position, misspelling, cx);
+    }
+
+    /// Opens the context menu at `position`, its items do what their shortcut
+    /// does in `TextInput`. Over a `misspelling`, corrections for it and
+    /// adding it to the dictionary come first.
+    fn deploy_context_menu(
+        &mut self,
+        position: Point<Pixels>,
+        misspelling: Option<Range<usize>>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let editor = self.model.read(cx);
+        let can_copy = editor.can_copy();
+        let read_only = editor.read_only;
+        let has_clipboard = cx.read_from_clipboard().is_some();
+        let item = |label: &'static str, shortcut: &'static str, action: EditorAction| {
+            let model = self.model.clone();
+            ContextMenuItem::new(label, move |cx| {
+                model.update(cx, |editor, cx| {
+                    let version = editor.version;
+                    let selection = editor.selection.clone();
+                    editor.perform(action, 0, cx);
+                    // edits emit their own `TextEvent::Edited`
+                    if editor.version == version && editor.selection != selection {
+                        cx.emit(TextEvent::SelectionChanged {
+                            selection: editor.selection.clone(),
+                        });
+                    }
+                });
+            })
+            .shortcut(shortcut)
+        };
+        let mut items = Vec::new();
+        if let Some(range) = misspelling {
+            let word = editor.text_for_range(range.clone());
+            let suggestions = cx.global::<Dictionary>().suggestions(&word);
+            if suggestions.is_empty() {
+                items.push(ContextMenuItem::new("No Suggestions", |_cx| {}).disabled(true));
+            }
+            for suggestion in suggestions {
+                let model = self.model.clone();
+                let range = range.clone();
+                items.push(
+                    ContextMenuItem::new(suggestion.clone(), move |cx| {
+                        model.update(cx, |editor, cx| {
+                            editor.transact(cx, |editor, cx| {
+                                editor.replace_range(range.clone(), &suggestion, cx);
+                            });
+                            editor.move_to(range.start + suggestion.len());
+                            cx.emit(TextEvent::SelectionChanged {
+                                selection: editor.selection.clone(),
+                            });
+                        });
+                    })
+                    .disabled(read_only),
+                );
+            }
+            items.push(ContextMenuItem::new("Add to Dictionary", move |cx| {
+                if let Err(error) = Dictionary::add_word(&word, cx) {
+                    cx.global_mut::<ToastQueue>().push(Toast::error(format!(
+                        "Couldn't add \"{word}\" to the dictionary: {error}"
+                    )));
+                }
+            }));
+        }
+        items.extend([
+            item("Cut", "⌘X", EditorAction::Cut).disabled(!can_copy || read_only),
+            item("Copy", "⌘C", EditorAction::Copy).disabled(!can_copy),
+            item("Paste", "⌘V", EditorAction::Paste).disabled(!has_clipboard || read_only),
+            item("Select All", "⌘A", EditorAction::SelectAll),
+        ]);
+        let menu = cx.new_view(|cx| ContextMenu::new(position, items, cx));
+        cx.subscribe(&menu, |this, menu, event, cx| match event {
+            ContextMenuEvent::Dismissed => {
+                // a right click elsewhere may have opened another menu already
+                if this.context_menu.as_ref() != Some(&menu) {
+                    return;
+                }
+                // a click that dismissed it keeps the focus where it went
+                if menu.read(cx).focus_handle.is_focused(cx) {
+                    this.focus_handle.focus(cx);
+                }
+                this.context_menu = None;
+                cx.notify();
+            }
+        })
+        .detach();
+        self.context_menu = Some(menu);
+        cx.notify();
+    }
+
+    fn on_mouse_drag(&mut self, ev: &MouseMoveEvent, cx: &mut ViewContext<Self>) {
+        if !self.selecting {
+            return;
+        }
+        let Some(index) = self.index_for_position(ev.position) else {
+            return;
+        };
+        self.model.update(cx, |editor, cx| {
+            let head = editor.clamp_offset(index);
+            if head != editor.head() {
+                if !editor.select_columns_to(head) {
+                    editor.select_to(head);
+                }
+                cx.emit(TextEvent::SelectionChanged {
+                    selection: editor.selection.clone(),
+                });
+            }
+        });
+    }
+
+    fn on_mouse_up(&mut self, _ev: &MouseUpEvent, _cx: &mut ViewContext<Self>) {
+        self.selecting = false;
+    }
+}
+
+impl Render for TextDisplay {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        // the metrics for hit testing come from the same font, so clicks land
+        // on the right char at any zoom
+        let font_family = theme.editor_font_family.clone();
+        let font_size = theme.zoomed_font_size();
+        let line_height = theme.editor_line_height_in_pixels();
+
+        let model = self.model.read(cx);
+        let display_map = if model.masked {
+            DisplayMap::masked(&model.text())
+        } else {
+            DisplayMap::invisibles(model.invisible_chars())
+        };
+        // the visual row of the cursor, and its line for the line numbers
+        let (cursor_row, cursor_line) = match display_map {
+            DisplayMap::Identity | DisplayMap::Invisibles(_) => {
+                let head = model.clamp_offset(model.head());
+                (
+                    model.visual_row_for_offset(head).0,
+                    model.buffer().row_for_offset(head),
+                )
+            }
+            DisplayMap::Masked(_) => (0, 0),
+        };
+
+        // before the first paint the window is the best guess for the viewport
+        let viewport_height = self
+            .layout
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(cx.viewport_size().height, |layout| {
+                layout.viewport.size.height
+            });
+        let first_row = (self.scroll.read(cx).offset / line_height) as usize;
+        let visible_rows = (viewport_height / line_height).ceil() as usize + 1;
+
+        // the visible lines are shared with the buffer, only masked text is copied
+        let (row_count, first_row, lines): (usize, usize, Vec<(usize, SharedString)>) =
+            if model.is_empty() {
+                (1, 0, vec![(0, SharedString::from("Type here..."))])
+            } else {
+                match &display_map {
+                    DisplayMap::Identity | DisplayMap::Invisibles(_) => {
+                        let buffer = model.buffer();
+                        let row_count = model.visual_row_count();
+                        let first_row = first_row.min(row_count);
+                        let rows = first_row..(first_row + visible_rows).min(row_count);
+                        (
+                            row_count,
+                            first_row,
+                            rows.map(|row| {
+                                let range = model.visual_row_range(row);
+                                let line_row = buffer.row_for_offset(range.start);
+                                let line = buffer.line(line_row);
+                                // only the rows of a wrapped line are copied
+                                let text = if range.len() == line.len() {
+                                    line.clone()
+                                } else {
+                                    let start = buffer.line_start(line_row);
+                                    line[range.start - start..range.end - start]
+                                        .to_string()
+                                        .into()
+                                };
+                                (range.start, text)
+                            })
+                            .collect(),
+                        )
+                    }
+                    DisplayMap::Masked(_) => (
+                        1,
+                        0,
+                        vec![(0, display_map.display_text(&model.text()).into())],
+                    ),
+                }
+            };
+        let placeholder = model.is_empty();
+        // the number of each row that starts a line, wrapped rows have none
+        let line_numbers: Vec<Option<usize>> = lines
+            .iter()
+            .map(|(start, _)| {
+                let row = model.buffer().row_for_offset(*start);
+                (model.buffer().line_start(row) == *start).then_some(row)
+            })
+            .collect();
+        let line_count = model.buffer().line_count();
+        // the diff marker of each row: a bar beside added and modified lines,
+        // and whether lines were removed above it or, at the end, below it
+        let diff_marks: Vec<(Option<DiffKind>, bool, bool)> = match &model.diff_hunks {
+            Some(hunks) => lines
+                .iter()
+                .map(|(start, line)| {
+                    let row = model.buffer().row_for_offset(*start);
+                    let starts_line = model.buffer().line_start(row) == *start;
+                    let ends_text =
+                        row + 1 == line_count && model.buffer().line_end(row) == start + line.len();
+                    let bar = hunks
+                        .iter()
+                        .find(|hunk| hunk.rows.contains(&row))
+                        .map(|hunk| hunk.kind);
+                    let removed = |row: usize| {
+                        hunks
+                            .iter()
+                            .any(|hunk| hunk.kind == DiffKind::Removed && hunk.rows.start == row)
+                    };
+                    (
+                        bar,
+                        starts_line && removed(row),
+                        ends_text && removed(line_count),
+                    )
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let diff_gutter = model.diff_hunks.is_some();
+        // rows that end in a newline, the rows of a wrapped line go on in the next
+        let ends_line: Vec<bool> = lines
+            .iter()
+            .map(|(start, line)| {
+                let row = model.buffer().row_for_offset(*start);
+                display_map.shows_lines()
+                    && row + 1 < line_count
+                    && model.buffer().line_end(row) == start + line.len()
+            })
+            .collect();
+
+        // the syntax colors of each visible line, relative to its start
+        let tokens: &[(Range<usize>, TokenKind)] = if placeholder || model.masked {
+            &[]
+        } else {
+            model.tokens()
+        };
+        let mut token_colors: Vec<Vec<(Range<usize>, Hsla)>> = lines
+            .iter()
+            .map(|(start, line)| {
+                let end = start + line.len();
+                let first = tokens.partition_point(|(range, _)| range.end <= *start);
+                tokens[first..]
+                    .iter()
+                    .take_while(|(range, _)| range.start < end)
+                    .map(|(range, kind)| {
+                        (
+                            range.start.max(*start) - start..range.end.min(end) - start,
+                            theme.token_color(*kind),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        if placeholder {
+            token_colors = lines
+                .iter()
+                .map(|(_, line)| vec![(0..line.len(), theme.placeholder_color)])
+                .collect();
+        }
+
+        // the index in each visible line of its indent guides, `None` on blank
+        // lines, which take theirs from the deeper of the lines around them
+        let settings = cx.global::<EditorSettings>();
+        let guides: Vec<Vec<(Option<usize>, usize)>> = if settings.indent_guides
+            && !placeholder
+            && display_map.shows_lines()
+            && !model.single_line
+        {
+            let buffer = model.buffer();
+            let tab_size = model.settings.tab_size;
+            let through_blank_lines = settings.indent_guides_through_blank_lines;
+            let is_blank = |row: usize| buffer.line(row).trim().is_empty();
+            let depth_of = |row: usize| {
+                indent_stops(buffer.line(row), tab_size)
+                    .last()
+                    .map_or(0, |(_, column)| column + 1)
+            };
+            line_numbers
+                .iter()
+                .map(|row| match *row {
+                    Some(row) if !is_blank(row) => indent_stops(buffer.line(row), tab_size)
+                        .into_iter()
+                        .map(|(i, column)| (Some(i), column))
+                        .collect(),
+                    Some(row) if through_blank_lines => {
+                        let above = (row.saturating_sub(MAX_GUIDE_SCAN)..row)
+                            .rev()
+                            .find(|row| !is_blank(*row));
+                        let below = (row + 1..(row + MAX_GUIDE_SCAN).min(line_count))
+                            .find(|row| !is_blank(*row));
+                        let depth = above.map_or(0, depth_of).max(below.map_or(0, depth_of));
+                        (0..depth)
+                            .step_by(tab_size.max(1))
+                            .map(|column| (None, column))
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let ruler_column = settings
+            .show_ruler
+            .then_some(settings.ruler_column)
+            .filter(|_| !placeholder && display_map.shows_lines() && !model.single_line);
+
+        // invisible chars are displayed as their codes, boxed in when painted,
+        // which moves everything after them on the line
+        let mut code_ranges: Vec<Vec<Range<usize>>> = vec![Vec::new(); lines.len()];
+        if let DisplayMap::Invisibles(_) = display_map {
+            for (((start, line), colors), codes) in lines
+                .iter_mut()
+                .zip(&mut token_colors)
+                .zip(&mut code_ranges)
+            {
+                let model_start = *start;
+                *start = display_map.to_display(model_start);
+                for (range, _) in colors.iter_mut() {
+                    *range = display_map.to_display(model_start + range.start) - *start
+                        ..display_map.to_display(model_start + range.end) - *start;
+                }
+                if !line.chars().any(is_invisible) {
+                    continue;
+                }
+                let mut text = String::with_capacity(line.len());
+                for c in line.chars() {
+                    if is_invisible(c) {
+                        codes.push(text.len()..text.len() + INVISIBLE_CODE_LEN);
+                        text.push_str(&invisible_code(c));
+                    } else {
+                        text.push(c);
+                    }
+                }
+                for code in codes.iter() {
+                    recolor(colors, code.clone(), theme.control_char_color);
+                }
+                *line = text.into();
+            }
+        }
+
+        // the selection may not have been clamped yet when the text was replaced
+        let sels: Vec<Range<usize>> = model
+            .selections()
+            .into_iter()
+            .map(|sel| {
+                display_map.to_display(model.clamp_offset(sel.start))
+                    ..display_map.to_display(model.clamp_offset(sel.end))
+            })
+            .collect();
+        // the primary cursor first, it's the one scrolled into view
+        let cursors: Vec<usize> = [model.head()]
+            .into_iter()
+            .chain(model.extra_heads())
+            .map(|head| display_map.to_display(model.clamp_offset(head)))
+            .collect();
+        let selection_color = theme.selection_color;
+        // selected text in its own color, where the theme has one
+        if let Some(color) = theme.selection_text_color.filter(|_| !placeholder) {
+            for ((start, line), colors) in lines.iter().zip(&mut token_colors) {
+                let end = start + line.len();
+                for sel in &sels {
+                    if sel.start < end && sel.end > *start {
+                        recolor(
+                            colors,
+                            sel.start.max(*start) - start..sel.end.min(end) - start,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+        let visible_range = lines.first().map_or(0, |(start, _)| *start)
+            ..lines.last().map_or(0, |(start, line)| start + line.len());
+        let to_display = |range: Range<usize>| {
+            display_map.to_display(range.start)..display_map.to_display(range.end)
+        };
+        let mut highlights: Vec<(Range<usize>, Hsla)> = if placeholder {
+            vec![]
+        } else {
+            model
+                .highlights()
+                .filter_map(|(range, style)| {
+                    let range = display_map.to_display(model.clamp_offset(range.start))
+                        ..display_map.to_display(model.clamp_offset(range.end));
+                    if range.end < visible_range.start || range.start > visible_range.end {
+                        return None;
+                    }
+                    Some((range, style.background_color?))
+                })
+                .collect()
+        };
+
+        let diagnostic_colors = (
+            theme.diagnostic_error_color,
+            theme.diagnostic_warning_color,
+            theme.diagnostic_info_color,
+        );
+        let squiggles: Vec<(Range<usize>, Hsla)> = model
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| {
+                let range = to_display(
+                    model.clamp_offset(diagnostic.range.start)
+                        ..model.clamp_offset(diagnostic.range.end),
+                );
+                if range.end < visible_range.start || range.start > visible_range.end {
+                    return None;
+                }
+                let color = match diagnostic.severity {
+                    Severity::Error => diagnostic_colors.0,
+                    Severity::Warning => diagnostic_colors.1,
+                    Severity::Info => diagnostic_colors.2,
+                };
+                Some((range, color))
+            })
+            .collect();
+        let misspellings = model.misspellings();
+        let first_visible =
+            misspellings.partition_point(|word| to_display(word.clone()).end < visible_range.start);
+        let misspelled: Vec<Range<usize>> = misspellings[first_visible..]
+            .iter()
+            .map(|word| to_display(word.clone()))
+            .take_while(|word| word.start <= visible_range.end)
+            .collect();
+        let misspelling_color = theme.danger_color;
+
+        // a selected word lights up wherever else it is used, only the visible
+        // words are compared
+        let selection = model.selection.clone();
+        let selected_word = !placeholder
+            && !model.masked
+            && selection.len() <= MAX_OCCURRENCE_LEN
+            && model.word_range_at(selection.start) == Some(selection.clone());
+        if selected_word {
+            let word = model.selected_text();
+            let words = model.word_ranges();
+            let visible_start = display_map.to_model(visible_range.start);
+            let visible_end = display_map.to_model(visible_range.end);
+            let first = words.partition_point(|range| range.end < visible_start);
+            highlights.extend(
+                words[first..]
+                    .iter()
+                    .take_while(|range| range.start <= visible_end)
+                    .filter(|range| {
+                        **range != selection
+                            && range.len() == word.len()
+                            && model.text_for_range((*range).clone()) == word
+                    })
+                    .map(|range| (to_display(range.clone()), theme.occurrence_color)),
+            );
+        }
+
+        // both brackets of a pair next to the cursor, or a lone one in red
+        if !placeholder && !model.masked && selection.is_empty() {
+            if let Some((bracket, partner)) = model.bracket_pair(selection.start) {
+                match partner {
+                    Some(partner) => highlights.extend([
+                        (to_display(bracket..bracket + 1), theme.bracket_color),
+                        (to_display(partner..partner + 1), theme.bracket_color),
+                    ]),
+                    None => highlights.push((to_display(bracket..bracket + 1), theme.danger_color)),
+                }
+            }
+        }
+
+        // whitespace at the end of lines, but not on the cursor's line where
+        // it's still being typed
+        if !placeholder && !model.masked && !model.single_line {
+            let buffer = model.buffer();
+            let first_line = buffer.row_for_offset(display_map.to_model(visible_range.start));
+            let last_line = buffer.row_for_offset(display_map.to_model(visible_range.end));
+            for row in (first_line..=last_line).filter(|row| *row != cursor_line) {
+                let line = buffer.line(row);
+                let trailing = line.len() - line.trim_end().len();
+                if trailing > 0 {
+                    let end = buffer.line_end(row);
+                    highlights.push((
+                        to_display(end - trailing..end),
+                        theme.trailing_whitespace_color,
+                    ));
+                }
+            }
+        }
+
+        // the marks for whitespace by their index in each visible line, drawn
+        // over the text so the offsets stay those of the real chars
+        let whitespace_marks: Vec<Vec<(usize, char)>> =
+            if settings.render_whitespace && !placeholder {
+                let in_selection = settings.whitespace_in_selection;
+                lines
+                    .iter()
+                    .zip(&ends_line)
+                    .map(|((start, line), ends_line)| {
+                        line.char_indices()
+                            .filter_map(|(i, c)| match c {
+                                ' ' => Some((i, '·')),
+                                '\t' => Some((i, '→')),
+                                _ => None,
+                            })
+                            .chain(ends_line.then_some((line.len(), '¶')))
+                            .filter(|(i, _)| {
+                                !in_selection || sels.iter().any(|sel| sel.contains(&(start + i)))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+        let whitespace_color = theme.whitespace_color;
+        let guide_color = theme.guide_color;
+        let control_char_color = theme.control_char_color;
+        let cursor_color = theme.cursor_color;
+        let text_color = theme.text_color;
+        let line_number_color = theme.line_number_color;
+        let diff_colors = (
+            theme.diff_added_color,
+            theme.diff_modified_color,
+            theme.diff_removed_color,
+        );
+        let show_line_numbers = model.show_line_numbers;
+        let active_line_color = theme.active_line_color;
+        // a selection already marks the lines it covers
+        let active_row = match display_map {
+            DisplayMap::Identity | DisplayMap::Invisibles(_) => {
+                model
+                    .visual_row_for_offset(model.clamp_offset(model.selection.start))
+                    .0
+            }
+            DisplayMap::Masked(_) => 0,
+        };
+        let single_line = model.single_line;
+        let wraps = model.soft_wrap && !single_line && !model.masked;
+        let wrap_column = model.wrap_column;
+        let model_handle = self.model.clone();
+        let show_active_line = self.focus_handle.is_focused(cx) && model.selection.is_empty();
+        let cursor_visible = self.cursor_visible && self.focus_handle.is_focused(cx);
+        let layout = self.layout.clone();
+        let view = cx.view().clone();
+        let scroll = self.scroll.clone();
+        let scroll_top = scroll.read(cx).offset;
+        let scroll_left = self.scroll_left;
+        let scrollbar_color = theme.scrollbar_thumb_color;
+        let autoscroll = self.autoscroll;
+        let completion_anchor = model
+            .completion
+            .as_ref()
+            .map(|completion| display_map.to_display(model.clamp_offset(completion.range.start)));
+        // it doesn't take the focus, the keys for it go through the model
+        let completion_popup =
+            self.completion_origin
+                .zip(model.completion.as_ref())
+                .map(|(origin, completion)| {
+                    let highlight_color = blend(theme.panel_color, theme.button_hover_overlay);
+                    overlay().position(origin).child(
+                        div()
+                            // keeps presses from moving the cursor of the text underneath
+                            .on_mouse_down(MouseButton::Left, |_ev, cx| cx.stop_propagation())
+                            .min_w_32()
+                            .py_1()
+                            .rounded_md()
+                            .border_1()
+                            .border_color(theme.border_color)
+                            .bg(theme.panel_color)
+                            .shadow_md()
+                            .font(theme.editor_font_family.clone())
+                            .text_sm()
+                            .children(completion.words.iter().enumerate().map(|(index, word)| {
+                                let model = self.model.clone();
+                                div()
+                                    .id(("completion", index))
+                                    .px_2()
+                                    .when(index == completion.selected, |this| {
+                                        this.bg(highlight_color)
+                                    })
+                                    .hover(|style| style.bg(highlight_color))
+                                    .on_mouse_down(MouseButton::Left, move |_ev, cx| {
+                                        cx.stop_propagation();
+                                        model.update(cx, |editor, cx| {
+                                            editor.accept_completion(index, cx)
+                                        });
+                                    })
+                                    .child(word.clone())
+                            })),
+                    )
+                });
+
+        div()
+            .relative()
+            .flex_1()
+            .min_h_0()
+            .overflow_hidden()
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_mouse_down))
+            .on_mouse_down(MouseButton::Right, cx.listener(Self::on_right_mouse_down))
+            .on_scroll_wheel(cx.listener(Self::on_scroll_wheel))
+            // as tall as the whole text, so the element sizes to it when
+            // nothing constrains its height
+            .child(div().h(line_height * row_count as f32))
+            .child(
+                // spans the viewport rather than the text, its bounds are
+                // what's visible
+                canvas(move |bounds, cx| {
+                    let mut style = cx.text_style();
+                    style.font_family = font_family;
+                    style.font_size = font_size.into();
+
+                    // wide enough for the number of the last line, so the text
+                    // only moves when the document gains a digit
+                    let mut gutter_width = if show_line_numbers {
+                        let digits = "0".repeat(line_count.to_string().len());
+                        let run = style.to_run(digits.len());
+                        cx.text_system()
+                            .shape_line(digits.into(), font_size, &[run])
+                            .map_or(px(0.), |line| line.width + GUTTER_PADDING)
+                    } else {
+                        px(0.)
+                    };
+                    // the diff markers go left of the line numbers
+                    if diff_gutter {
+                        gutter_width += DIFF_GUTTER_WIDTH;
+                    }
+
+                    // the width of one column, assuming the editor font is monospaced
+                    let char_width = cx
+                        .text_system()
+                        .shape_line("0".into(), font_size, &[style.to_run(1)])
+                        .map_or(px(0.), |zero| zero.width);
+
+                    // how many chars fit
+                    if wraps && char_width > px(0.) {
+                        let width = bounds.size.width - gutter_width - CURSOR_WIDTH;
+                        let column = ((width / char_width) as usize).max(1);
+                        if wrap_column != Some(column) {
+                            // the text is wrapped to it in the next frame
+                            model_handle.update(cx, |model, cx| model.set_wrap_column(column, cx));
+                        }
+                    }
+
+                    let mut shaped_lines = Vec::with_capacity(lines.len());
+                    for ((start, line), colors) in lines.into_iter().zip(token_colors) {
+                        // one run per token, the text between them in the default color
+                        let mut runs = Vec::new();
+                        let mut offset = 0;
+                        for (range, color) in colors {
+                            if range.start > offset {
+                                runs.push(style.to_run(range.start - offset));
+                            }
+                            let mut run = style.to_run(range.len());
+                            run.color = color;
+                            runs.push(run);
+                            offset = range.end;
+                        }
+                        if offset < line.len() || runs.is_empty() {
+                            runs.push(style.to_run(line.len() - offset));
+                        }
+                        let Ok(shaped) = cx.text_system().shape_line(line, font_size, &runs) else {
+                            return;
+                        };
+                        shaped_lines.push((start, shaped));
+                    }
+
+                    // only the visible lines count for how far it scrolls sideways
+                    let text_width = bounds.size.width - gutter_width;
+                    let content_width = shaped_lines
+                        .iter()
+                        .map(|(_, line)| line.width)
+                        .fold(px(0.), |max, width| if width > max { width } else { max });
+                    let max_scroll_left = if wraps {
+                        px(0.)
+                    } else {
+                        clamp_pixels(
+                            content_width + AUTOSCROLL_MARGIN_X - text_width,
+                            px(f32::MAX),
+                        )
+                    };
+                    let mut scroll_left = clamp_pixels(scroll_left, max_scroll_left);
+                    let primary_cursor = cursors.first().copied();
+                    if let Some(cursor) = primary_cursor.filter(|_| autoscroll && !wraps) {
+                        let cursor_line = shaped_lines.iter().rfind(|(start, line)| {
+                            *start <= cursor && cursor <= start + line.len()
+                        });
+                        if let Some((start, line)) = cursor_line {
+                            let x = line.x_for_index(cursor - start);
+                            if x - AUTOSCROLL_MARGIN_X < scroll_left {
+                                scroll_left = x - AUTOSCROLL_MARGIN_X;
+                            } else if x + AUTOSCROLL_MARGIN_X > scroll_left + text_width {
+                                scroll_left = x + AUTOSCROLL_MARGIN_X - text_width;
+                            }
+                            scroll_left = clamp_pixels(scroll_left, max_scroll_left);
+                        }
+                    }
+
+                    let text_layout = TextLayout {
+                        origin: bounds.origin + point(gutter_width - scroll_left, -scroll_top),
+                        viewport: bounds,
+                        gutter_width,
+                        line_height,
+                        row_count,
+                        first_row,
+                        lines: shaped_lines,
+                        display_map,
+                        max_scroll_left,
+                    };
+
+                    if show_active_line {
+                        let origin = text_layout.row_origin(active_row);
+                        cx.paint_quad(fill(
+                            Bounds::new(
+                                point(bounds.origin.x, origin.y),
+                                size(bounds.size.width, line_height),
+                            ),
+                            active_line_color,
+                        ));
+                    }
+
+                    // text scrolled sideways is hidden behind the gutter
+                    let text_bounds = Bounds::new(
+                        point(bounds.origin.x + gutter_width, bounds.origin.y),
+                        size(text_width, bounds.size.height),
+                    );
+                    cx.with_content_mask(
+                        Some(ContentMask {
+                            bounds: text_bounds,
+                        }),
+                        |cx| {
+                            if let Some(column) = ruler_column {
+                                let x = text_layout.origin.x + char_width * column as f32;
+                                cx.paint_quad(fill(
+                                    Bounds::new(
+                                        point(x, bounds.origin.y),
+                                        size(px(1.), bounds.size.height),
+                                    ),
+                                    guide_color,
+                                ));
+                            }
+                            for (i, stops) in guides.iter().enumerate() {
+                                let Some((_, line)) = text_layout.lines.get(i) else {
+                                    break;
+                                };
+                                let origin = text_layout.row_origin(first_row + i);
+                                for (index, column) in stops {
+                                    let x = match index {
+                                        Some(index) => line.x_for_index(*index),
+                                        None => char_width * *column as f32,
+                                    };
+                                    cx.paint_quad(fill(
+                                        Bounds::new(
+                                            point(origin.x + x, origin.y),
+                                            size(px(1.), line_height),
+                                        ),
+                                        guide_color,
+                                    ));
+                                }
+                            }
+                            for (range, color) in highlights {
+                                text_layout.paint_background(range, color, cx);
+                            }
+                            if !placeholder {
+                                for sel in sels {
+                                    text_layout.paint_background(sel, selection_color, cx);
+                                }
+                            }
+                            for (i, (_, line)) in text_layout.lines.iter().enumerate() {
+                                let origin = text_layout.row_origin(first_row + i);
+                                line.paint(origin, line_height, cx).ok();
+                                for code in code_ranges.get(i).into_iter().flatten() {
+                                    let left = line.x_for_index(code.start);
+                                    let right = line.x_for_index(code.end);
+                                    cx.paint_quad(outline(
+                                        Bounds::new(
+                                            point(origin.x + left, origin.y + px(2.)),
+                                            size(right - left, line_height - px(4.)),
+                                        ),
+                                        control_char_color,
+                                    ));
+                                }
+                            }
+                            for (range, color) in squiggles {
+                                text_layout.paint_squiggle(range, color, cx);
+                            }
+                            for range in misspelled {
+                                text_layout.paint_dots(range, misspelling_color, cx);
+                            }
+                            // each mark is shaped once and painted wherever it's needed
+                            let mut shaped_marks: Vec<(char, ShapedLine)> = Vec::new();
+                            for (i, marks) in whitespace_marks.iter().enumerate() {
+                                let Some((_, line)) = text_layout.lines.get(i) else {
+                                    break;
+                                };
+                                let origin = text_layout.row_origin(first_row + i);
+                                for (index, mark) in marks {
+                                    if !shaped_marks.iter().any(|(c, _)| c == mark) {
+                                        let mut run = style.to_run(mark.len_utf8());
+                                        run.color = whitespace_color;
+                                        let Ok(shaped) = cx.text_system().shape_line(
+                                            mark.to_string().into(),
+                                            font_size,
+                                            &[run],
+                                        ) else {
+                                            continue;
+                                        };
+                                        shaped_marks.push((*mark, shaped));
+                                    }
+                                    let Some((_, shaped)) =
+                                        shaped_marks.iter().find(|(c, _)| c == mark)
+                                    else {
+                                        continue;
+                                    };
+                                    let mut x = line.x_for_index(*index);
+                                    // a dot in the middle of its space
+                                    if *mark == '·' {
+                                        let space = line.x_for_index(index + 1) - x;
+                                        x += (space - shaped.width) * 0.5;
+                                    }
+                                    shaped
+                                        .paint(point(origin.x + x, origin.y), line_height, cx)
+                                        .ok();
+                                }
+                            }
+                            if cursor_visible {
+                                for cursor in cursors {
+                                    if let Some(position) = text_layout.position_for_index(cursor) {
+                                        cx.paint_quad(fill(
+                                            Bounds::new(position, size(CURSOR_WIDTH, line_height)),
+                                            cursor_color,
+                                        ));
+                                    }
+                                }
+                            }
+                        },
+                    );
 
-        selection_style.background_color = Some(hsla(0., 0., 0.9, 1.));
+                    // a thin bar along the bottom while lines are wider than the view
+                    if max_scroll_left > px(0.) {
+                        let total = text_width + max_scroll_left;
+                        let thumb_width = text_width * (text_width / total);
+                        let thumb_left = text_width * (scroll_left / total);
+                        cx.paint_quad(fill(
+                            Bounds::new(
+                                point(
+                                    text_bounds.origin.x + thumb_left,
+                                    bounds.origin.y + bounds.size.height - SCROLLBAR_WIDTH,
+                                ),
+                                size(thumb_width, SCROLLBAR_WIDTH),
+                            ),
+                            scrollbar_color,
+                        ));
+                    }
 
-        let sel = self.model.read(cx).selection.clone();
-        let mut highlights = vec![(sel, selection_style)];
+                    for i in 0..text_layout.lines.len() {
+                        let origin = text_layout.row_origin(first_row + i);
+                        let number = line_numbers.get(i).copied().flatten();
+                        if let Some(row) = number.filter(|_| show_line_numbers) {
+                            let mut run = style.to_run(0);
+                            // the current line stands out from the rest
+                            run.color = if row == cursor_line {
+                                text_color
+                            } else {
+                                line_number_color
+                            };
+                            let number = (row + 1).to_string();
+                            run.len = number.len();
+                            if let Ok(number) =
+                                cx.text_system()
+                                    .shape_line(number.into(), font_size, &[run])
+                            {
+                                let x = gutter_width - GUTTER_PADDING - number.width;
+                                let origin = point(bounds.origin.x + x, origin.y);
+                                number.paint(origin, line_height, cx).ok();
+                            }
+                        }
+                    }
 
-        let mut style = TextStyle::default();
-        style.color = theme.text_color;
-        if text.len() == 0 {
-            text = "Type here...".to_string();
-            style.color = theme.text_color;
-            highlights = vec![];
-        }
+                    let (added_color, modified_color, removed_color) = diff_colors;
+                    for (i, (bar, removed_above, removed_below)) in diff_marks.iter().enumerate() {
+                        let origin = text_layout.row_origin(first_row + i);
+                        let x = bounds.origin.x + DIFF_MARKER_INSET;
+                        if let Some(kind) = bar {
+                            let color = match kind {
+                                DiffKind::Added => added_color,
+                                DiffKind::Modified => modified_color,
+                                DiffKind::Removed => removed_color,
+                            };
+                            cx.paint_quad(fill(
+                                Bounds::new(
+                                    point(x, origin.y),
+                                    size(DIFF_MARKER_WIDTH, line_height),
+                                ),
+                                color,
+                            ));
+                        }
+                        // a wedge pointing at the line between the rows
+                        let edges = [
+                            (*removed_above, origin.y),
+                            (*removed_below, origin.y + line_height),
+                        ];
+                        for (_, y) in edges.into_iter().filter(|(removed, _)| *removed) {
+                            let reach = DIFF_REMOVED_SIZE;
+                            let mut wedge = Path::new(point(x, y - reach));
+                            wedge.line_to(point(x + reach, y));
+                            wedge.line_to(point(x, y + reach));
+                            wedge.line_to(point(x, y - reach));
+                            cx.paint_path(wedge, removed_color);
+                        }
+                    }
 
-        let styled_text = StyledText::new(text + " ").with_highlights(&style, highlights);
-        let clone = self.model.clone();
-        InteractiveText::new("text", styled_text).on_click(
-            self.model.read(cx).word_ranges(),
-            move |ev, cx| {
-                clone.update(cx, |editor, cx| {
-                    let (index, mut count) = editor.word_click;
-                    if index == ev {
-                        count += 1;
-                    } else {
-                        count = 1;
+                    if autoscroll {
+                        // the text is already painted for this frame, so a new
+                        // scroll position shows up in the next one
+                        let new_scroll_top = text_layout.scroll_top_for_row(cursor_row, scroll_top);
+                        view.update(cx, |this, _cx| this.autoscroll = false);
+                        if new_scroll_top != scroll_top {
+                            scroll.update(cx, |scroll, cx| scroll.scroll_to(new_scroll_top, cx));
+                        }
                     }
-                    match count {
-                        2 => {
-                            let word_ranges = editor.word_ranges();
-                            editor.selection = word_ranges.get(ev).unwrap().clone();
+                    // keeps the clamped or autoscrolled position painted above
+                    view.update(cx, |this, _cx| this.scroll_left = scroll_left);
+                    // what the scrollbar sizes its thumb by
+                    let content_height = text_layout.content_height();
+                    scroll.update(cx, |scroll, _cx| {
+                        scroll.content_size = content_height;
+                        scroll.viewport_size = bounds.size.height;
+                    });
+                    // the completions open below the word they finish, where
+                    // it was just painted
+                    let completion_origin = completion_anchor
+                        .and_then(|index| text_layout.position_for_index(index))
+                        .map(|position| position + point(px(0.), line_height));
+                    view.update(cx, |this, cx| {
+                        if this.completion_origin != completion_origin {
+                            this.completion_origin = completion_origin;
+                            cx.notify();
                         }
-                        3 => {
-                            // Should select the line
+                    });
+                    *layout.lock().unwrap() = Some(text_layout);
+
+                    // listen on the whole window, a drag keeps selecting when
+                    // the mouse leaves the text
+                    cx.on_mouse_event({
+                        let view = view.clone();
+                        move |ev: &MouseMoveEvent, phase, cx| {
+                            if phase == DispatchPhase::Bubble
+                                && ev.pressed_button == Some(MouseButton::Left)
+                            {
+                                view.update(cx, |this, cx| this.on_mouse_drag(ev, cx));
+                            }
                         }
-                        4 => {
-                            count = 0;
-                            editor.selection = 0..editor.text.len();
+                    });
+                    cx.on_mouse_event(move |ev: &MouseUpEvent, phase, cx| {
+                        if phase == DispatchPhase::Bubble && ev.button == MouseButton::Left {
+                            view.update(cx, |this, cx| this.on_mouse_up(ev, cx));
                         }
-                        _ => {}
-                    }
-                    editor.word_click = (ev, count);
-                    cx.notify();
-                });
-            },
-        )
+                    });
+                })
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full(),
+            )
+            .when(!single_line, |this| {
+                this.child(Scrollbar::new(self.scroll.clone()))
+            })
+            // last, so it's drawn over the text
+            .children(self.context_menu.clone())
+            .children(completion_popup)
     }
 }
 
-#[allow(dead_code)]
 enum Orientation {
     Horizontal,
     Vertical,
@@ -477,7 +5713,6 @@ impl Divider {
         }
     }
 
-    #[allow(dead_code)]
     pub fn vertical() -> Self {
         Self {
             orientation: Orientation::Vertical,
@@ -495,7 +5730,952 @@ impl RenderOnce for Divider {
             .border_color(theme.border_color);
         match self.orientation {
             Orientation::Horizontal => base.h_0(),
-            Orientation::Vertical => div().w_0(),
+            Orientation::Vertical => base.w_0().h_full(),
+        }
+    }
+}
+
+/// Neither side of a `SplitPane` gets smaller than this while dragging.
+const MIN_PANE_SIZE: Pixels = px(120.);
+
+/// How a `SplitPane` divides its space, kept by the view that renders it so
+/// the ratio survives re-renders.
+pub struct SplitState {
+    /// The share of the space the first child gets, from 0 to 1.
+    pub ratio: f32,
+    /// Whether the divider is being dragged.
+    dragging: bool,
+}
+
+impl SplitState {
+    pub fn new() -> Self {
+        Self {
+            ratio: 0.5,
+            dragging: false,
+        }
+    }
+}
+
+/// Two children side by side, or stacked, with a divider between them that
+/// can be dragged to resize them.
+#[derive(IntoElement)]
+pub struct SplitPane {
+    state: Model<SplitState>,
+    orientation: Orientation,
+    first: AnyElement,
+    second: AnyElement,
+}
+
+impl SplitPane {
+    pub fn new(
+        state: Model<SplitState>,
+        first: impl IntoElement,
+        second: impl IntoElement,
+    ) -> Self {
+        Self {
+            state,
+            orientation: Orientation::Vertical,
+            first: first.into_any_element(),
+            second: second.into_any_element(),
+        }
+    }
+
+    /// Puts the second child under the first instead of right of it.
+    pub fn stacked(mut self) -> Self {
+        self.orientation = Orientation::Horizontal;
+        self
+    }
+}
+
+impl RenderOnce for SplitPane {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let ratio = self.state.read(cx).ratio;
+        let stacked = matches!(self.orientation, Orientation::Horizontal);
+        let state = self.state.clone();
+        let pane = || {
+            div()
+                .flex()
+                .flex_col()
+                .min_w_0()
+                .min_h_0()
+                .overflow_hidden()
+        };
+
+        div()
+            .relative()
+            .size_full()
+            .flex()
+            .when(stacked, |this| this.flex_col())
+            .child(
+                pane()
+                    .map(|this| {
+                        if stacked {
+                            this.w_full().h(relative(ratio))
+                        } else {
+                            this.h_full().w(relative(ratio))
+                        }
+                    })
+                    .child(self.first),
+            )
+            .child(
+                // wider than the line it draws, so it's easy to grab
+                div()
+                    .flex()
+                    .justify_center()
+                    .map(|this| {
+                        if stacked {
+                            this.py_1().cursor_row_resize()
+                        } else {
+                            this.px_1().cursor_col_resize()
+                        }
+                    })
+                    .on_mouse_down(MouseButton::Left, {
+                        let state = self.state.clone();
+                        move |_ev, cx| {
+                            cx.stop_propagation();
+                            state.update(cx, |state, _cx| state.dragging = true);
+                        }
+                    })
+                    .child(match self.orientation {
+                        Orientation::Horizontal => Divider::horizontal(),
+                        Orientation::Vertical => Divider::vertical(),
+                    }),
+            )
+            .child(pane().flex_1().child(self.second))
+            .child(
+                // the pane's bounds turn mouse positions into ratios
+                canvas(move |bounds, cx| {
+                    cx.on_mouse_event({
+                        let state = state.clone();
+                        move |ev: &MouseMoveEvent, phase, cx| {
+                            if phase != DispatchPhase::Bubble || !state.read(cx).dragging {
+                                return;
+                            }
+                            let (position, origin, size) = if stacked {
+                                (ev.position.y, bounds.origin.y, bounds.size.height)
+                            } else {
+                                (ev.position.x, bounds.origin.x, bounds.size.width)
+                            };
+                            if size <= px(0.) {
+                                return;
+                            }
+                            // too small for two minimum panes, both get half
+                            let min = (MIN_PANE_SIZE / size).min(0.5);
+                            let ratio = ((position - origin) / size).clamp(min, 1. - min);
+                            state.update(cx, |state, cx| {
+                                state.ratio = ratio;
+                                cx.notify();
+                            });
+                        }
+                    });
+                    cx.on_mouse_event(move |ev: &MouseUpEvent, phase, cx| {
+                        if phase == DispatchPhase::Bubble && ev.button == MouseButton::Left {
+                            state.update(cx, |state, _cx| state.dragging = false);
+                        }
+                    });
+                })
+                .absolute()
+                .top_0()
+                .left_0()
+                .size_full(),
+            )
+    }
+}
+
+/// How long a `Scrollbar` stays after the last scroll before it hides.
+const SCROLLBAR_FADE_DELAY: Duration = Duration::from_secs(1);
+/// The width of a `Scrollbar`, which is also where it can be grabbed.
+const SCROLLBAR_TRACK_WIDTH: Pixels = px(10.);
+/// The thumb of a `Scrollbar` doesn't get shorter than this for long content.
+const MIN_THUMB_SIZE: Pixels = px(24.);
+
+/// How far some content is scrolled, shared between whoever paints the
+/// content and its `Scrollbar`. The painter keeps the sizes up to date.
+pub struct ScrollState {
+    /// How far the content is scrolled up out of view.
+    pub offset: Pixels,
+    /// The height of all of the content.
+    pub content_size: Pixels,
+    /// The height of the part of the content that's visible.
+    pub viewport_size: Pixels,
+    /// Where the thumb was grabbed, measured from its top, while it's dragged.
+    drag_offset: Option<Pixels>,
+    hovered: bool,
+    /// The scrollbar shows from a scroll until `SCROLLBAR_FADE_DELAY` after
+    /// it, or while the mouse is on it.
+    visible: bool,
+    _fade_task: Option<Task<()>>,
+}
+
+impl ScrollState {
+    pub fn new() -> Self {
+        Self {
+            offset: px(0.),
+            content_size: px(0.),
+            viewport_size: px(0.),
+            drag_offset: None,
+            hovered: false,
+            visible: false,
+            _fade_task: None,
+        }
+    }
+
+    fn max_offset(&self) -> Pixels {
+        clamp_pixels(self.content_size - self.viewport_size, px(f32::MAX))
+    }
+
+    /// Scrolls to `offset`, clamped to the content, and shows the scrollbar.
+    pub fn scroll_to(&mut self, offset: Pixels, cx: &mut ModelContext<Self>) {
+        self.offset = clamp_pixels(offset, self.max_offset());
+        self.show(cx);
+        cx.notify();
+    }
+
+    /// Shows the scrollbar and hides it again after `SCROLLBAR_FADE_DELAY`,
+    /// unless the mouse is on it by then.
+    fn show(&mut self, cx: &mut ModelContext<Self>) {
+        self.visible = true;
+        self._fade_task = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(SCROLLBAR_FADE_DELAY).await;
+            this.update(&mut cx, |this, cx| {
+                if !this.hovered && this.drag_offset.is_none() {
+                    this.visible = false;
+                    cx.notify();
+                }
+            })
+            .ok();
+        }));
+    }
+
+    /// The top and height of the thumb in a track of `track_height`, `None`
+    /// when the content fits and there is nothing to scroll.
+    fn thumb(&self, track_height: Pixels) -> Option<(Pixels, Pixels)> {
+        let max_offset = self.max_offset();
+        if max_offset <= px(0.) || self.content_size <= px(0.) {
+            return None;
+        }
+        let height = track_height * (self.viewport_size / self.content_size);
+        let height = clamp_pixels(height, track_height);
+        let height = if height < MIN_THUMB_SIZE {
+            clamp_pixels(MIN_THUMB_SIZE, track_height)
+        } else {
+            height
+        };
+        let top = (track_height - height) * (self.offset / max_offset);
+        Some((top, height))
+    }
+
+    /// The offset that puts the top of the thumb at `thumb_top`.
+    fn offset_for_thumb(&self, thumb_top: Pixels, track_height: Pixels) -> Pixels {
+        let Some((_, height)) = self.thumb(track_height) else {
+            return px(0.);
+        };
+        let room = track_height - height;
+        if room <= px(0.) {
+            return px(0.);
+        }
+        self.max_offset() * (thumb_top / room)
+    }
+
+    /// A press at `y` in the track: on the thumb it starts a drag, elsewhere
+    /// it scrolls a page toward `y`, or right to it when `jump` is set.
+    fn press(&mut self, track: Bounds<Pixels>, y: Pixels, jump: bool, cx: &mut ModelContext<Self>) {
+        let track_height = track.size.height;
+        let Some((top, height)) = self.thumb(track_height) else {
+            return;
+        };
+        let y = y - track.origin.y;
+        if y >= top && y <= top + height {
+            self.drag_offset = Some(y - top);
+            self.show(cx);
+            cx.notify();
+        } else if jump {
+            let offset = self.offset_for_thumb(y - height / 2., track_height);
+            self.scroll_to(offset, cx);
+        } else if y < top {
+            self.scroll_to(self.offset - self.viewport_size, cx);
+        } else {
+            self.scroll_to(self.offset + self.viewport_size, cx);
+        }
+    }
+
+    /// Drags the thumb along with the mouse, and tracks whether it's on the track.
+    fn mouse_moved(
+        &mut self,
+        track: Bounds<Pixels>,
+        position: Point<Pixels>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if let Some(drag_offset) = self.drag_offset {
+            let thumb_top = position.y - track.origin.y - drag_offset;
+            let offset = self.offset_for_thumb(thumb_top, track.size.height);
+            self.scroll_to(offset, cx);
+        }
+        let hovered = track.contains(&position);
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            // leaving starts the countdown to hiding
+            self.show(cx);
+            cx.notify();
+        }
+    }
+
+    fn release(&mut self, cx: &mut ModelContext<Self>) {
+        if self.drag_offset.take().is_some() {
+            self.show(cx);
+        }
+    }
+}
+
+/// A vertical bar along the right edge of its parent, which has to be
+/// `relative`. The thumb's size is the share of the content that's visible,
+/// dragging it scrolls, and a click on the track scrolls a page, or right
+/// there with alt held.
+#[derive(IntoElement)]
+pub struct Scrollbar {
+    state: Model<ScrollState>,
+}
+
+impl Scrollbar {
+    pub fn new(state: Model<ScrollState>) -> Self {
+        Self { state }
+    }
+}
+
+impl RenderOnce for Scrollbar {
+    fn render(self, cx: &mut WindowContext) -> impl IntoElement {
+        let theme = cx.global::<Theme>();
+        let thumb_color = theme.scrollbar_thumb_color;
+        let track_color = theme.scrollbar_track_color;
+        let state = self.state;
+
+        div()
+            .absolute()
+            .top_0()
+            .right_0()
+            .h_full()
+            .w(SCROLLBAR_TRACK_WIDTH)
+            .child(
+                canvas(move |bounds, cx| {
+                    let scroll = state.read(cx);
+                    let shown = scroll.visible || scroll.hovered || scroll.drag_offset.is_some();
+                    let Some((top, height)) = scroll.thumb(bounds.size.height) else {
+                        return;
+                    };
+                    if shown {
+                        cx.paint_quad(fill(bounds, track_color));
+                        cx.paint_quad(fill(
+                            Bounds::new(
+                                bounds.origin + point(px(2.), top),
+                                size(bounds.size.width - px(4.), height),
+                            ),
+                            thumb_color,
+                        ));
+                    }
+                    cx.on_mouse_event({
+                        let state = state.clone();
+                        move |ev: &MouseDownEvent, phase, cx| {
+                            if phase != DispatchPhase::Bubble
+                                || ev.button != MouseButton::Left
+                                || !bounds.contains(&ev.position)
+                            {
+                                return;
+                            }
+                            // the content under the bar doesn't see the click
+                            cx.stop_propagation();
+                            state.update(cx, |state, cx| {
+                                state.press(bounds, ev.position.y, ev.modifiers.alt, cx)
+                            });
+                        }
+                    });
+                    cx.on_mouse_event({
+                        let state = state.clone();
+                        move |ev: &MouseMoveEvent, phase, cx| {
+                            if phase == DispatchPhase::Bubble {
+                                state.update(cx, |state, cx| {
+                                    state.mouse_moved(bounds, ev.position, cx)
+                                });
+                            }
+                        }
+                    });
+                    cx.on_mouse_event(move |ev: &MouseUpEvent, phase, cx| {
+                        if phase == DispatchPhase::Bubble && ev.button == MouseButton::Left {
+                            state.update(cx, |state, cx| state.release(cx));
+                        }
+                    });
+                })
+                .size_full(),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard_history::ClipboardHistory;
+
+    const TEXT: &str = "héllo 👩‍👩‍👧 世界";
+    const FAMILY: &str = "👩‍👩‍👧";
+
+    fn model(text: &str, cx: &mut TestAppContext) -> Model<TextModel> {
+        cx.update(|cx| {
+            cx.set_global(EditorSettings::default());
+            ClipboardHistory::init(cx);
+        });
+        let settings = EditorSettings::default().buffer_settings();
+        cx.new_model(|_| TextModel::new(text.to_string(), settings))
+    }
+
+    fn perform(model: &Model<TextModel>, action: EditorAction, cx: &mut TestAppContext) {
+        model.update(cx, |model, cx| model.perform(action, 10, cx));
+    }
+
+    fn text(model: &Model<TextModel>, cx: &mut TestAppContext) -> String {
+        model.read_with(cx, |model, _| model.text())
+    }
+
+    fn selection(model: &Model<TextModel>, cx: &mut TestAppContext) -> Range<usize> {
+        model.read_with(cx, |model, _| model.selection.clone())
+    }
+
+    #[gpui::test]
+    fn moves_by_grapheme(cx: &mut TestAppContext) {
+        let model = model(TEXT, cx);
+        perform(&model, EditorAction::MoveToStart, cx);
+        let mut offsets = Vec::new();
+        for _ in 0..12 {
+            perform(&model, EditorAction::MoveRight, cx);
+            offsets.push(selection(&model, cx).start);
+        }
+        let family_end = 7 + FAMILY.len();
+        assert_eq!(
+            offsets,
+            [
+                1,
+                3,
+                4,
+                5,
+                6,
+                7,
+                family_end,
+                family_end + 1,
+                family_end + 4,
+                TEXT.len(),
+                TEXT.len(),
+                TEXT.len()
+            ]
+        );
+
+        let mut offsets = Vec::new();
+        for _ in 0..11 {
+            perform(&model, EditorAction::MoveLeft, cx);
+            offsets.push(selection(&model, cx).start);
+        }
+        assert_eq!(
+            offsets,
+            [
+                family_end + 4,
+                family_end + 1,
+                family_end,
+                7,
+                6,
+                5,
+                4,
+                3,
+                1,
+                0,
+                0
+            ]
+        );
+    }
+
+    #[gpui::test]
+    fn types_next_to_multi_byte_chars(cx: &mut TestAppContext) {
+        let model = model(TEXT, cx);
+        model.update(cx, |model, cx| {
+            model.move_to(1);
+            model.type_text("é", cx);
+            model.move_to(10 + FAMILY.len());
+            model.type_text("!", cx);
+        });
+        assert_eq!(text(&model, cx), "hééllo 👩‍👩‍👧 !世界");
+        assert_eq!(selection(&model, cx), 11 + FAMILY.len()..11 + FAMILY.len());
+
+        perform(&model, EditorAction::MoveRight, cx);
+        model.update(cx, |model, cx| model.type_text("👍", cx));
+        assert_eq!(text(&model, cx), "hééllo 👩‍👩‍👧 !世👍界");
+    }
+
+    #[gpui::test]
+    fn pastes_next_to_multi_byte_chars(cx: &mut TestAppContext) {
+        let model = model(TEXT, cx);
+        cx.write_to_clipboard(ClipboardItem::new(FAMILY.to_string()));
+        perform(&model, EditorAction::Paste, cx);
+        assert_eq!(text(&model, cx), format!("{TEXT}{FAMILY}"));
+        let end = TEXT.len() + FAMILY.len();
+        assert_eq!(selection(&model, cx), end..end);
+
+        // a selection ending inside the emoji is clamped to its start
+        model.update(cx, |model, _| model.select(3..8));
+        perform(&model, EditorAction::Paste, cx);
+        assert_eq!(text(&model, cx), format!("hé{FAMILY}{FAMILY} 世界{FAMILY}"));
+    }
+
+    #[gpui::test]
+    fn backspace_deletes_whole_zwj_sequence(cx: &mut TestAppContext) {
+        let model = model(TEXT, cx);
+        model.update(cx, |model, _| model.move_to(7 + FAMILY.len()));
+        perform(&model, EditorAction::DeleteBackward, cx);
+        assert_eq!(text(&model, cx), "héllo  世界");
+        assert_eq!(selection(&model, cx), 7..7);
+
+        perform(&model, EditorAction::MoveToEnd, cx);
+        perform(&model, EditorAction::DeleteBackward, cx);
+        perform(&model, EditorAction::MoveToStart, cx);
+        perform(&model, EditorAction::MoveRight, cx);
+        perform(&model, EditorAction::MoveRight, cx);
+        perform(&model, EditorAction::DeleteBackward, cx);
+        assert_eq!(text(&model, cx), "hllo  世");
+    }
+
+    #[gpui::test]
+    fn delete_forward_at_end(cx: &mut TestAppContext) {
+        let model = model("abc", cx);
+        perform(&model, EditorAction::DeleteForward, cx);
+        assert_eq!(text(&model, cx), "abc");
+        assert_eq!(selection(&model, cx), 3..3);
+
+        model.update(cx, |model, _| model.select(1..3));
+        perform(&model, EditorAction::DeleteForward, cx);
+        assert_eq!(text(&model, cx), "a");
+        assert_eq!(selection(&model, cx), 1..1);
+        perform(&model, EditorAction::DeleteForward, cx);
+        assert_eq!(text(&model, cx), "a");
+    }
+
+    #[gpui::test]
+    fn delete_forward_over_newline_and_multi_byte_char(cx: &mut TestAppContext) {
+        let model = model("a\n世", cx);
+        model.update(cx, |model, _| model.move_to(1));
+        perform(&model, EditorAction::DeleteForward, cx);
+        assert_eq!(text(&model, cx), "a世");
+        perform(&model, EditorAction::DeleteForward, cx);
+        assert_eq!(text(&model, cx), "a");
+    }
+
+    #[gpui::test]
+    fn cursor_position_counts_chars(cx: &mut TestAppContext) {
+        let model = model("héllo\n世界!\n", cx);
+        let position = |offset: usize, cx: &mut TestAppContext| {
+            model.update(cx, |model, _| {
+                model.move_to(offset);
+                model.cursor_position()
+            })
+        };
+        assert_eq!(position(0, cx), (0, 0));
+        assert_eq!(position(3, cx), (0, 2));
+        assert_eq!(position(6, cx), (0, 5));
+        assert_eq!(position(7, cx), (1, 0));
+        assert_eq!(position(13, cx), (1, 2));
+        assert_eq!(position(14, cx), (1, 3));
+        assert_eq!(position(15, cx), (2, 0));
+
+        model.read_with(cx, |model, _| {
+            assert_eq!(model.offset_for_point(0, 2), 3);
+            assert_eq!(model.offset_for_point(1, 2), 13);
+            // past the end of the line and the last row
+            assert_eq!(model.offset_for_point(1, 99), 14);
+            assert_eq!(model.offset_for_point(9, 0), 15);
+        });
+    }
+
+    #[gpui::test]
+    fn cursor_position_at_reversed_selection_head(cx: &mut TestAppContext) {
+        let model = model("ab\n世界", cx);
+        model.update(cx, |model, _| {
+            model.move_to(model.len());
+            model.select_to(1);
+        });
+        assert_eq!(
+            model.read_with(cx, |model, _| model.cursor_position()),
+            (0, 1)
+        );
+    }
+
+    #[gpui::test]
+    fn single_line_paste_flattens_lines(cx: &mut TestAppContext) {
+        let model = model("find: ", cx);
+        model.update(cx, |model, _| model.single_line = true);
+        cx.write_to_clipboard(ClipboardItem::new("one\ntwo\r\nthree\n".to_string()));
+        perform(&model, EditorAction::Paste, cx);
+        assert_eq!(text(&model, cx), "find: one two three ");
+        assert_eq!(selection(&model, cx), 20..20);
+
+        perform(&model, EditorAction::PasteRaw, cx);
+        assert_eq!(text(&model, cx), "find: one two three one two three ");
+    }
+
+    #[gpui::test]
+    fn enter_replaces_selection(cx: &mut TestAppContext) {
+        let model = model("    let x = 1;", cx);
+        model.update(cx, |model, _| model.select(8..9));
+        perform(&model, EditorAction::Newline, cx);
+        assert_eq!(text(&model, cx), "    let \n     = 1;");
+        assert_eq!(selection(&model, cx), 13..13);
+    }
+
+    #[gpui::test]
+    fn enter_replaces_reversed_selection(cx: &mut TestAppContext) {
+        let model = model("    let x = 1;", cx);
+        model.update(cx, |model, _| model.select_to(8));
+        assert!(model.read_with(cx, |model, _| model.selection_reversed));
+        perform(&model, EditorAction::Newline, cx);
+        assert_eq!(text(&model, cx), "    let \n    ");
+        assert_eq!(selection(&model, cx), 13..13);
+
+        model.update(cx, |model, _| {
+            model.move_to(8);
+            model.select_to(4);
+        });
+        perform(&model, EditorAction::NewlineWithoutIndent, cx);
+        assert_eq!(text(&model, cx), "    \n\n    ");
+        assert_eq!(selection(&model, cx), 5..5);
+    }
+
+    #[gpui::test]
+    fn typing_replaces_reversed_selection(cx: &mut TestAppContext) {
+        let model = model("abc def", cx);
+        model.update(cx, |model, cx| {
+            model.select_to(4);
+            model.type_text("x", cx);
+        });
+        assert_eq!(text(&model, cx), "abc x");
+        assert_eq!(selection(&model, cx), 5..5);
+    }
+
+    #[gpui::test]
+    fn selection_is_clamped_to_shorter_text(cx: &mut TestAppContext) {
+        let model = model("hello world", cx);
+        model.update(cx, |model, cx| {
+            model.select(6..11);
+            model.set_text("hé".to_string(), cx);
+        });
+        assert_eq!(selection(&model, cx), 3..3);
+
+        // onto char boundaries, the end never before the start
+        model.update(cx, |model, _| model.select(2..99));
+        assert_eq!(selection(&model, cx), 1..3);
+        model.update(cx, |model, _| {
+            model.selection = 5..2;
+            model.clamp_selection();
+        });
+        assert_eq!(selection(&model, cx), 3..3);
+    }
+
+    #[gpui::test]
+    fn editing_keys_after_text_shrank_under_selection(cx: &mut TestAppContext) {
+        use EditorAction::*;
+        cx.write_to_clipboard(ClipboardItem::new("pasted".to_string()));
+        let actions = [
+            MoveLeft,
+            MoveRight,
+            MoveUp,
+            MoveDown,
+            SelectWordLeft,
+            SelectToEnd,
+            Copy,
+            Cut,
+            Paste,
+            Undo,
+            DeleteBackward,
+            DeleteForward,
+            DeleteWordBackward,
+            DeleteWordForward,
+            DeleteToLineEnd,
+            DeleteLine,
+            DuplicateSelection,
+            MoveLineUp,
+            MoveLineDown,
+            JoinLines,
+            SelectNextOccurrence,
+            SelectAllOccurrences,
+            Uppercase,
+            TitleCase,
+            ToggleComment,
+            Indent,
+            Outdent,
+            Newline,
+        ];
+        for action in actions {
+            for selection in [1..15, 8..15] {
+                let input = model("hello\nworld\nagain", cx);
+                input.update(cx, |input, cx| {
+                    input.select(selection.clone());
+                    input.set_text("wö".to_string(), cx);
+                    input.perform(action, 10, cx);
+                    let selection = input.selection.clone();
+                    assert!(selection.start <= selection.end, "{action:?}");
+                    assert!(selection.end <= input.len(), "{action:?}");
+                    assert!(input.text().is_char_boundary(selection.start), "{action:?}");
+                    assert!(input.text().is_char_boundary(selection.end), "{action:?}");
+                });
+            }
         }
     }
+
+    #[gpui::test]
+    fn word_ranges_are_cached_until_edited(cx: &mut TestAppContext) {
+        let line = "lorem ipsum dolor sit amet\n";
+        let text = line.repeat(1024 * 1024 / line.len());
+        let words = 5 * text.lines().count();
+        let model = model(&text, cx);
+        model.update(cx, |model, cx| {
+            let first = model.word_ranges().as_ptr();
+            assert_eq!(model.word_ranges().len(), words);
+            assert_eq!(model.word_ranges().as_ptr(), first);
+
+            model.replace_range(0..5, "hello there", cx);
+            assert!(model.cached_word_ranges.get().is_none());
+            assert_eq!(model.word_ranges().len(), words + 1);
+            assert_eq!(model.word_ranges()[..2], [0..5, 6..11]);
+            let second = model.word_ranges().as_ptr();
+            assert_eq!(model.word_ranges().as_ptr(), second);
+
+            // moving the cursor keeps them
+            model.move_to(3);
+            assert_eq!(model.word_ranges().as_ptr(), second);
+        });
+    }
+
+    #[gpui::test]
+    fn auto_pairs_open_and_skip_over(cx: &mut TestAppContext) {
+        let model = model("", cx);
+        model.update(cx, |model, cx| {
+            for c in ["f", "(", "[", "\""] {
+                model.type_text(c, cx);
+            }
+        });
+        assert_eq!(text(&model, cx), "f([\"\"])");
+        assert_eq!(selection(&model, cx), 4..4);
+
+        model.update(cx, |model, cx| {
+            for c in ["x", "\"", "]", ")"] {
+                model.type_text(c, cx);
+            }
+        });
+        assert_eq!(text(&model, cx), "f([\"x\"])");
+        assert_eq!(selection(&model, cx), 8..8);
+    }
+
+    #[gpui::test]
+    fn auto_pairs_wrap_selection(cx: &mut TestAppContext) {
+        let model = model("a word", cx);
+        model.update(cx, |model, cx| {
+            model.select_to(2);
+            model.type_text("(", cx);
+        });
+        assert_eq!(text(&model, cx), "a (word)");
+        assert_eq!(selection(&model, cx), 3..7);
+    }
+
+    #[gpui::test]
+    fn backspace_deletes_empty_pair(cx: &mut TestAppContext) {
+        let typed = model("", cx);
+        typed.update(cx, |model, cx| model.type_text("{", cx));
+        perform(&typed, EditorAction::DeleteBackward, cx);
+        assert_eq!(text(&typed, cx), "");
+
+        // only an empty one
+        let filled = model("(a)", cx);
+        filled.update(cx, |model, _| model.move_to(2));
+        perform(&filled, EditorAction::DeleteBackward, cx);
+        assert_eq!(text(&filled, cx), "()");
+        perform(&filled, EditorAction::DeleteBackward, cx);
+        assert_eq!(text(&filled, cx), "");
+    }
+
+    #[gpui::test]
+    fn no_pairs_next_to_words(cx: &mut TestAppContext) {
+        let before = model("foo", cx);
+        before.update(cx, |model, cx| {
+            model.move_to(0);
+            model.type_text("(", cx);
+            model.type_text("\"", cx);
+        });
+        assert_eq!(text(&before, cx), "(\"foo");
+
+        // an apostrophe in a word
+        let after = model("don", cx);
+        after.update(cx, |model, cx| model.type_text("'", cx));
+        assert_eq!(text(&after, cx), "don'");
+    }
+
+    #[gpui::test]
+    fn no_pairs_without_setting(cx: &mut TestAppContext) {
+        let model = model("", cx);
+        cx.update(|cx| cx.global_mut::<EditorSettings>().auto_pairs = false);
+        model.update(cx, |model, cx| {
+            model.type_text("(", cx);
+            model.type_text(")", cx);
+        });
+        assert_eq!(text(&model, cx), "()");
+        assert_eq!(selection(&model, cx), 2..2);
+    }
+
+    fn transform_lines(
+        model: &Model<TextModel>,
+        transform: impl FnOnce(&mut Vec<String>),
+        cx: &mut TestAppContext,
+    ) -> Option<(usize, usize)> {
+        model.update(cx, |model, cx| model.transform_lines(transform, cx))
+    }
+
+    fn dedupe(lines: &mut Vec<String>) {
+        let mut seen = std::collections::HashSet::new();
+        lines.retain(|line| seen.insert(line.clone()));
+    }
+
+    #[gpui::test]
+    fn transform_lines_keeps_trailing_newline(cx: &mut TestAppContext) {
+        let model = model("b\nA\na\nc\n", cx);
+        assert_eq!(
+            transform_lines(&model, |lines| lines.sort(), cx),
+            Some((4, 4))
+        );
+        assert_eq!(text(&model, cx), "A\na\nb\nc\n");
+        assert_eq!(
+            transform_lines(&model, |lines| lines.reverse(), cx),
+            Some((4, 4))
+        );
+        assert_eq!(text(&model, cx), "c\nb\na\nA\n");
+        let sort_case_insensitive =
+            |lines: &mut Vec<String>| lines.sort_by_key(|line| line.to_lowercase());
+        assert_eq!(
+            transform_lines(&model, sort_case_insensitive, cx),
+            Some((4, 4))
+        );
+        assert_eq!(text(&model, cx), "a\nA\nb\nc\n");
+    }
+
+    #[gpui::test]
+    fn transform_lines_without_trailing_newline(cx: &mut TestAppContext) {
+        let model = model("b\nc\nb\na", cx);
+        assert_eq!(transform_lines(&model, dedupe, cx), Some((4, 3)));
+        assert_eq!(text(&model, cx), "b\nc\na");
+        assert_eq!(
+            transform_lines(&model, |lines| lines.sort(), cx),
+            Some((3, 3))
+        );
+        assert_eq!(text(&model, cx), "a\nb\nc");
+    }
+
+    #[gpui::test]
+    fn transform_lines_of_selection(cx: &mut TestAppContext) {
+        let model = model("d\nc\nb\nc\na\n", cx);
+        // from the end of the second line to the start of the fifth
+        model.update(cx, |model, _| model.select(3..8));
+        assert_eq!(
+            transform_lines(&model, |lines| lines.sort(), cx),
+            Some((3, 3))
+        );
+        assert_eq!(text(&model, cx), "d\nb\nc\nc\na\n");
+        assert_eq!(selection(&model, cx), 3..8);
+
+        model.update(cx, |model, _| model.select(2..8));
+        assert_eq!(transform_lines(&model, dedupe, cx), Some((3, 2)));
+        assert_eq!(text(&model, cx), "d\nb\nc\na\n");
+    }
+
+    #[gpui::test]
+    fn transform_lines_is_one_undo_step(cx: &mut TestAppContext) {
+        let model = model("3\n1\n2\n", cx);
+        transform_lines(&model, |lines| lines.sort(), cx);
+        perform(&model, EditorAction::Undo, cx);
+        assert_eq!(text(&model, cx), "3\n1\n2\n");
+
+        model.update(cx, |model, _| model.read_only = true);
+        assert_eq!(transform_lines(&model, |lines| lines.sort(), cx), None);
+        assert_eq!(text(&model, cx), "3\n1\n2\n");
+    }
+
+    #[gpui::test]
+    fn select_next_occurrence_wraps_around(cx: &mut TestAppContext) {
+        let model = model("foo bar foo\nfoo", cx);
+        model.update(cx, |model, _| {
+            model.select(8..11);
+            model.select_next_occurrence();
+            assert_eq!(model.selection, 12..15);
+            model.select_next_occurrence();
+            assert_eq!(model.selection, 0..3);
+            // every one is selected already
+            model.select_next_occurrence();
+            assert_eq!(model.selection, 0..3);
+            assert_eq!(model.selections(), [0..3, 8..11, 12..15]);
+        });
+    }
+
+    #[gpui::test]
+    fn select_occurrences_spanning_lines(cx: &mut TestAppContext) {
+        let model = model("a {\n}\nb {\n}\n{\n", cx);
+        model.update(cx, |model, _| {
+            model.select(2..5);
+            model.select_all_occurrences();
+            assert_eq!(model.selections(), [2..5, 8..11]);
+        });
+    }
+
+    /// Types `keys` in vim's normal mode, one key per char.
+    fn vim_keys(model: &Model<TextModel>, keys: &str, cx: &mut TestAppContext) {
+        model.update(cx, |model, cx| {
+            let mut vim = model.vim.take().unwrap_or_default();
+            for key in keys.chars() {
+                let keystroke = Keystroke::parse(&key.to_string()).unwrap();
+                vim.handle_key(&keystroke, model, cx);
+            }
+            model.vim = Some(vim);
+        });
+    }
+
+    #[gpui::test]
+    fn long_vim_counts_are_capped(cx: &mut TestAppContext) {
+        const LONG: &str = "99999999999999999999";
+        let model = model("one\ntwo\nthree", cx);
+        perform(&model, EditorAction::MoveToStart, cx);
+        vim_keys(&model, &format!("{LONG}j{LONG}w{LONG}${LONG}x"), cx);
+        assert_eq!(text(&model, cx), "one\ntwo\nthre");
+        vim_keys(&model, &format!("{LONG}dd"), cx);
+        assert_eq!(text(&model, cx), "one\ntwo");
+        vim_keys(&model, &format!("{LONG}p"), cx);
+        let line_count = model.read_with(cx, |model, _| model.buffer().line_count());
+        assert_eq!(line_count, 2 + 10_000);
+        vim_keys(&model, &format!("{LONG}u"), cx);
+        assert_eq!(text(&model, cx), "one\ntwo\nthree");
+    }
+
+    #[gpui::test]
+    fn deletes_the_selected_lines(cx: &mut TestAppContext) {
+        let model = model("one\ntwo\nthree\nfour", cx);
+        model.update(cx, |model, _| model.select(5..9));
+        perform(&model, EditorAction::DeleteLine, cx);
+        assert_eq!(text(&model, cx), "one\nfour");
+        assert_eq!(selection(&model, cx), 4..4);
+        perform(&model, EditorAction::DeleteLine, cx);
+        assert_eq!(text(&model, cx), "one");
+        assert_eq!(selection(&model, cx), 0..0);
+        perform(&model, EditorAction::DeleteLine, cx);
+        assert_eq!(text(&model, cx), "");
+    }
+
+    #[gpui::test]
+    fn counts_selected_chars(cx: &mut TestAppContext) {
+        let model = model("héllo\nwörld", cx);
+        model.update(cx, |model, _| {
+            model.select(1..10);
+            assert_eq!(model.selected_char_count(), 7);
+            model.select_all();
+            assert_eq!(model.selected_char_count(), 11);
+            model.select(3..3);
+            assert_eq!(model.selected_char_count(), 0);
+        });
+    }
 }
@@ -0,0 +1,602 @@
+use std::ops::Range;
+
+use gpui::*;
+
+use crate::{clipboard_history::copy_to_clipboard, macros::MacroRecorder, ui::TextModel};
+
+/// What the keys of a vim-style editor do.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+    /// Keys move the cursor and run commands.
+    #[default]
+    Normal,
+    /// Keys type text, like without vim mode.
+    Insert,
+    /// Motions extend a selection that the next operator acts on.
+    Visual,
+}
+
+impl Mode {
+    /// Shown in the status bar.
+    pub fn label(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+        }
+    }
+}
+
+/// Waits for a motion, then acts on the text it moved over.
+#[derive(Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Motion {
+    Left,
+    Down,
+    Up,
+    Right,
+    WordStart,
+    WordBack,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    /// `gg`, or the line of the count.
+    FirstLine,
+    /// `G`, or the line of the count.
+    LastLine,
+}
+
+impl Motion {
+    fn for_key(c: char) -> Option<Self> {
+        Some(match c {
+            'h' => Motion::Left,
+            'j' => Motion::Down,
+            'k' => Motion::Up,
+            'l' => Motion::Right,
+            'w' => Motion::WordStart,
+            'b' => Motion::WordBack,
+            'e' => Motion::WordEnd,
+            '0' => Motion::LineStart,
+            '$' => Motion::LineEnd,
+            'G' => Motion::LastLine,
+            _ => return None,
+        })
+    }
+
+    /// Operators act on whole lines for these.
+    fn linewise(self) -> bool {
+        matches!(
+            self,
+            Motion::Down | Motion::Up | Motion::FirstLine | Motion::LastLine
+        )
+    }
+
+    /// Operators include the char the motion ends on.
+    fn inclusive(self) -> bool {
+        matches!(self, Motion::WordEnd | Motion::LineEnd)
+    }
+}
+
+/// Counts stop growing at this or the line count, whichever is larger, so a
+/// long one can't overflow or keep a command repeating for ages.
+const MAX_COUNT: usize = 10_000;
+
+/// The state of vim mode for one `TextModel`, including a command that is
+/// only partly typed, e.g. the `3d` of `3dw`.
+#[derive(Default)]
+pub struct Vim {
+    pub mode: Mode,
+    count: Option<usize>,
+    operator: Option<Operator>,
+    /// The count typed before the operator, it multiplies the motion's.
+    operator_count: Option<usize>,
+    /// A key waiting for the next one, `g` of `gg`, or `i` and `a` of a text object.
+    prefix: Option<char>,
+    /// Where the visual selection started.
+    visual_anchor: usize,
+    /// The end of the visual selection the cursor is on.
+    visual_head: usize,
+    /// The last lines that were yanked or deleted, the clipboard is pasted as
+    /// lines while it still holds them.
+    yanked_lines: Option<String>,
+}
+
+impl Vim {
+    /// Runs `keystroke` as a vim key. Returns false for the keys the input
+    /// should handle as usual, e.g. typing in insert mode or page down.
+    pub fn handle_key(
+        &mut self,
+        keystroke: &Keystroke,
+        editor: &mut TextModel,
+        cx: &mut ModelContext<TextModel>,
+    ) -> bool {
+        // shortcuts like cmd-s work in every mode
+        if keystroke.modifiers.command || keystroke.modifiers.control {
+            return false;
+        }
+        if self.mode == Mode::Insert {
+            if keystroke.key != "escape" {
+                return false;
+            }
+            // the cursor goes back onto the last typed char
+            let head = editor.head();
+            if head > editor.line_start(head) {
+                editor.move_to(editor.prev_boundary(head));
+            } else {
+                editor.move_to(head);
+            }
+            self.set_mode(Mode::Normal, cx);
+            return true;
+        }
+
+        // every command is a step of its own for undo
+        editor.break_undo_group();
+        let key = match keystroke.key.as_str() {
+            "escape" => {
+                self.clear_pending();
+                if self.mode == Mode::Visual {
+                    self.exit_visual(editor, cx);
+                }
+                return true;
+            }
+            "left" | "backspace" => 'h',
+            "down" | "enter" => 'j',
+            "up" => 'k',
+            "right" => 'l',
+            "delete" => 'x',
+            _ => match typed_char(keystroke) {
+                Some(c) => c,
+                None => return false,
+            },
+        };
+        self.normal_key(key, editor, cx);
+        true
+    }
+
+    fn normal_key(&mut self, key: char, editor: &mut TextModel, cx: &mut ModelContext<TextModel>) {
+        if let Some(prefix) = self.prefix.take() {
+            match (prefix, key) {
+                ('g', 'g') => self.motion(Motion::FirstLine, editor, cx),
+                ('i' | 'a', 'w') => self.text_object(prefix == 'a', editor, cx),
+                _ => self.clear_pending(),
+            }
+            return;
+        }
+        if let Some(digit) = key.to_digit(10) {
+            // a leading 0 is a motion
+            if digit != 0 || self.count.is_some() {
+                let count = self.count.unwrap_or(0).saturating_mul(10);
+                self.count = Some(
+                    count
+                        .saturating_add(digit as usize)
+                        .min(count_limit(editor)),
+                );
+                return;
+            }
+        }
+        if let Some(motion) = Motion::for_key(key) {
+            self.motion(motion, editor, cx);
+            return;
+        }
+        let visual = self.mode == Mode::Visual;
+        match key {
+            'g' => {
+                self.prefix = Some('g');
+                return;
+            }
+            'i' | 'a' if self.operator.is_some() || visual => {
+                self.prefix = Some(key);
+                return;
+            }
+            'd' | 'c' | 'y' => {
+                let operator = match key {
+                    'd' => Operator::Delete,
+                    'c' => Operator::Change,
+                    _ => Operator::Yank,
+                };
+                if visual {
+                    let range = editor.selection.clone();
+                    self.exit_visual(editor, cx);
+                    self.apply(operator, range, false, editor, cx);
+                } else if self.operator == Some(operator) {
+                    // dd, cc and yy act on the cursor's line and the count - 1 after it
+                    let row = editor.buffer().row_for_offset(editor.head());
+                    let count = self.take_count(editor);
+                    let last_row = row
+                        .saturating_add(count - 1)
+                        .min(editor.buffer().line_count() - 1);
+                    let range = editor.buffer().line_start(row)..editor.buffer().line_end(last_row);
+                    self.apply(operator, range, true, editor, cx);
+                } else {
+                    self.operator = Some(operator);
+                    self.operator_count = self.count.take();
+                    return;
+                }
+            }
+            'x' if visual => {
+                let range = editor.selection.clone();
+                self.exit_visual(editor, cx);
+                self.apply(Operator::Delete, range, false, editor, cx);
+            }
+            'x' => {
+                let head = editor.head();
+                let line_end = editor.line_end(head);
+                let mut end = head;
+                for _ in 0..self.take_count(editor) {
+                    if end >= line_end {
+                        break;
+                    }
+                    end = editor.next_boundary(end);
+                }
+                if end > head {
+                    self.apply(Operator::Delete, head..end, false, editor, cx);
+                }
+            }
+            'q' => MacroRecorder::toggle(cx.entity_id(), cx),
+            'p' => {
+                let count = self.take_count(editor);
+                self.paste(count, editor, cx);
+            }
+            'u' if !editor.read_only => {
+                for _ in 0..self.take_count(editor) {
+                    if !editor.undo(cx) {
+                        break;
+                    }
+                }
+                let head = clamp_to_line(editor, editor.head());
+                editor.move_to(head);
+            }
+            'v' if visual => self.exit_visual(editor, cx),
+            'v' => {
+                self.visual_anchor = editor.head();
+                self.visual_head = editor.head();
+                self.set_mode(Mode::Visual, cx);
+                self.select_visual(editor);
+            }
+            'i' | 'a' | 'o' | 'O' if !editor.read_only && !visual => {
+                let head = editor.head();
+                match key {
+                    'i' => editor.move_to(head),
+                    'a' => {
+                        if head < editor.line_end(head) {
+                            editor.move_to(editor.next_boundary(head));
+                        }
+                    }
+                    'o' => {
+                        let line_end = editor.line_end(head);
+                        editor.insert_at(line_end, "\n", cx);
+                        editor.move_to(line_end + 1);
+                    }
+                    _ => {
+                        let line_start = editor.line_start(head);
+                        editor.insert_at(line_start, "\n", cx);
+                        editor.move_to(line_start);
+                    }
+                }
+                self.set_mode(Mode::Insert, cx);
+            }
+            _ => {}
+        }
+        self.clear_pending();
+    }
+
+    /// Moves the cursor, extends the visual selection or completes an operator.
+    fn motion(&mut self, motion: Motion, editor: &mut TextModel, cx: &mut ModelContext<TextModel>) {
+        let count = match (self.operator_count, self.count) {
+            (None, None) => None,
+            (before, after) => Some(
+                before
+                    .unwrap_or(1)
+                    .saturating_mul(after.unwrap_or(1))
+                    .min(count_limit(editor)),
+            ),
+        };
+        let operator = self.operator;
+        self.clear_pending();
+        let from = if self.mode == Mode::Visual {
+            self.visual_head
+        } else {
+            editor.head()
+        };
+        let mut to = motion_target(motion, from, count, editor);
+
+        if self.mode == Mode::Visual {
+            self.visual_head = clamp_to_line(editor, to);
+            self.select_visual(editor);
+            return;
+        }
+        let Some(operator) = operator else {
+            editor.move_to(clamp_to_line(editor, to));
+            return;
+        };
+        if motion == Motion::WordStart {
+            // like vim, dw on the last word of a line keeps the newline
+            let line_end = editor.line_end(from);
+            if to > line_end && from < line_end {
+                to = line_end;
+            }
+        }
+        let mut range = from.min(to)..from.max(to);
+        if motion.linewise() {
+            range = editor.line_start(range.start)..editor.line_end(range.end);
+        } else if motion.inclusive() && range.end < editor.line_end(range.end) {
+            range.end = editor.next_boundary(range.end);
+        }
+        self.apply(operator, range, motion.linewise(), editor, cx);
+    }
+
+    /// `iw` selects the word under the cursor, `aw` the word with the
+    /// whitespace after it.
+    fn text_object(
+        &mut self,
+        around: bool,
+        editor: &mut TextModel,
+        cx: &mut ModelContext<TextModel>,
+    ) {
+        let operator = self.operator;
+        self.clear_pending();
+        let head = if self.mode == Mode::Visual {
+            self.visual_head
+        } else {
+            editor.head()
+        };
+        let Some(mut range) = editor.word_range_at(head) else {
+            return;
+        };
+        if around {
+            let line_end = editor.line_end(range.end);
+            range.end = editor
+                .buffer()
+                .chars_at(range.end)
+                .find(|(i, c)| *i >= line_end || !c.is_whitespace())
+                .map_or(line_end, |(i, _)| i.min(line_end));
+        }
+        if self.mode == Mode::Visual {
+            self.visual_anchor = range.start;
+            self.visual_head = editor.prev_boundary(range.end).max(range.start);
+            self.select_visual(editor);
+        } else if let Some(operator) = operator {
+            self.apply(operator, range, false, editor, cx);
+        }
+    }
+
+    /// Runs `operator` on `range`, which covers whole lines when `linewise`
+    /// is set. What it deletes or yanks goes to the clipboard.
+    fn apply(
+        &mut self,
+        operator: Operator,
+        mut range: Range<usize>,
+        linewise: bool,
+        editor: &mut TextModel,
+        cx: &mut ModelContext<TextModel>,
+    ) {
+        if operator != Operator::Yank && editor.read_only {
+            return;
+        }
+        let mut text = editor.text_for_range(range.clone());
+        if linewise {
+            text.push('\n');
+            self.yanked_lines = Some(text.clone());
+        } else {
+            self.yanked_lines = None;
+        }
+        if !editor.masked {
+            copy_to_clipboard(text, cx);
+        }
+        match operator {
+            Operator::Yank => editor.move_to(clamp_to_line(editor, range.start)),
+            Operator::Delete => {
+                if linewise {
+                    // the newline after the lines goes too, or else the one before them
+                    if range.end < editor.len() {
+                        range.end += 1;
+                    } else {
+                        range.start = range.start.saturating_sub(1);
+                    }
+                }
+                editor.delete_range(range.clone(), cx);
+                let head = if linewise {
+                    editor.line_start(range.start.min(editor.len()))
+                } else {
+                    range.start
+                };
+                editor.move_to(clamp_to_line(editor, head));
+            }
+            Operator::Change => {
+                editor.delete_range(range.clone(), cx);
+                editor.move_to(range.start);
+                self.set_mode(Mode::Insert, cx);
+            }
+        }
+    }
+
+    /// Puts the clipboard after the cursor `count` times, or on new lines below
+    /// it when it holds yanked lines. In visual mode it replaces the selection.
+    fn paste(&mut self, count: usize, editor: &mut TextModel, cx: &mut ModelContext<TextModel>) {
+        let Some(clipboard) = cx.read_from_clipboard() else {
+            return;
+        };
+        if editor.read_only {
+            return;
+        }
+        let text = clipboard.text().replace("\r\n", "\n");
+        if self.mode == Mode::Visual {
+            let range = editor.selection.clone();
+            self.exit_visual(editor, cx);
+            editor.replace_range(range.clone(), &text, cx);
+            editor.move_to(clamp_to_line(editor, range.start + text.len()));
+            return;
+        }
+        let head = editor.head();
+        if self.yanked_lines.as_ref() == Some(&text) {
+            let line_end = editor.line_end(head);
+            let lines = format!("\n{}", text.strip_suffix('\n').unwrap_or(&text)).repeat(count);
+            editor.insert_at(line_end, &lines, cx);
+            editor.move_to(line_end + 1);
+        } else {
+            let at = if head < editor.line_end(head) {
+                editor.next_boundary(head)
+            } else {
+                head
+            };
+            let text = text.repeat(count);
+            editor.insert_at(at, &text, cx);
+            editor.move_to(editor.prev_boundary(at + text.len()).max(at));
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode, cx: &mut ModelContext<TextModel>) {
+        self.mode = mode;
+        cx.notify();
+    }
+
+    fn exit_visual(&mut self, editor: &mut TextModel, cx: &mut ModelContext<TextModel>) {
+        editor.move_to(self.visual_head);
+        self.set_mode(Mode::Normal, cx);
+    }
+
+    /// Selects from the anchor to the head, including the chars under both.
+    fn select_visual(&self, editor: &mut TextModel) {
+        let start = self.visual_anchor.min(self.visual_head);
+        let end = self.visual_anchor.max(self.visual_head);
+        editor.select(start..editor.next_boundary(end).max(end));
+        editor.selection_reversed = self.visual_head < self.visual_anchor;
+    }
+
+    fn take_count(&mut self, editor: &TextModel) -> usize {
+        let count = self.operator_count.take().unwrap_or(1);
+        count
+            .saturating_mul(self.count.take().unwrap_or(1))
+            .min(count_limit(editor))
+    }
+
+    fn clear_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.operator_count = None;
+        self.prefix = None;
+    }
+}
+
+/// The largest count `editor` takes, see `MAX_COUNT`.
+fn count_limit(editor: &TextModel) -> usize {
+    editor.buffer().line_count().max(MAX_COUNT)
+}
+
+/// The char a key types, with shift applied.
+fn typed_char(keystroke: &Keystroke) -> Option<char> {
+    if let Some(ime_key) = &keystroke.ime_key {
+        return ime_key.chars().next();
+    }
+    let mut chars = keystroke.key.chars();
+    let c = chars.next().filter(|_| chars.next().is_none())?;
+    Some(if keystroke.modifiers.shift {
+        c.to_ascii_uppercase()
+    } else {
+        c
+    })
+}
+
+/// Keeps the cursor on a char in normal mode, off the newline ending a line.
+fn clamp_to_line(editor: &TextModel, offset: usize) -> usize {
+    if offset > editor.line_start(offset) && offset == editor.line_end(offset) {
+        editor.prev_boundary(offset)
+    } else {
+        offset
+    }
+}
+
+/// Where `motion` moves from `offset`, repeated `count` times, or to the line
+/// of `count` for `gg` and `G`.
+fn motion_target(motion: Motion, offset: usize, count: Option<usize>, editor: &TextModel) -> usize {
+    let buffer = editor.buffer();
+    let row = buffer.row_for_offset(offset);
+    let last_row = buffer.line_count() - 1;
+    let times = count.unwrap_or(1);
+    match motion {
+        Motion::Left => {
+            let line_start = editor.line_start(offset);
+            repeat(offset, times, |offset| {
+                if offset > line_start {
+                    editor.prev_boundary(offset)
+                } else {
+                    offset
+                }
+            })
+        }
+        Motion::Right => {
+            let line_end = editor.line_end(offset);
+            repeat(offset, times, |offset| {
+                if offset < line_end {
+                    editor.next_boundary(offset)
+                } else {
+                    offset
+                }
+            })
+        }
+        Motion::Up => editor.offset_for_point(row.saturating_sub(times), editor.column(offset)),
+        Motion::Down => editor.offset_for_point(
+            row.saturating_add(times).min(last_row),
+            editor.column(offset),
+        ),
+        Motion::WordStart => repeat(offset, times, |offset| next_word_start(editor, offset)),
+        Motion::WordBack => repeat(offset, times, |offset| editor.word_boundary_before(offset)),
+        Motion::WordEnd => repeat(offset, times, |offset| {
+            let end = editor.word_boundary_after(editor.next_boundary(offset));
+            editor.prev_boundary(end).max(offset)
+        }),
+        Motion::LineStart => editor.line_start(offset),
+        Motion::LineEnd => buffer.line_end(row.saturating_add(times - 1).min(last_row)),
+        Motion::FirstLine | Motion::LastLine => {
+            let default = if motion == Motion::FirstLine {
+                0
+            } else {
+                last_row
+            };
+            let row = count.map_or(default, |line| line.saturating_sub(1).min(last_row));
+            first_non_blank(editor, row)
+        }
+    }
+}
+
+/// Applies `step` to `offset` `times` times, or until it stops moving.
+fn repeat(mut offset: usize, times: usize, step: impl Fn(usize) -> usize) -> usize {
+    for _ in 0..times {
+        let next = step(offset);
+        if next == offset {
+            break;
+        }
+        offset = next;
+    }
+    offset
+}
+
+/// The start of the word after the one at `offset`, past any whitespace.
+fn next_word_start(editor: &TextModel, offset: usize) -> usize {
+    let on_whitespace = editor
+        .buffer()
+        .chars_at(offset)
+        .next()
+        .map_or(true, |(_, c)| c.is_whitespace());
+    let word_end = if on_whitespace {
+        offset
+    } else {
+        editor.word_boundary_after(offset)
+    };
+    editor
+        .buffer()
+        .chars_at(word_end)
+        .find(|(_, c)| !c.is_whitespace())
+        .map_or(editor.len(), |(i, _)| i)
+}
+
+fn first_non_blank(editor: &TextModel, row: usize) -> usize {
+    let line = editor.buffer().line(row);
+    editor.buffer().line_start(row) + line.len() - line.trim_start().len()
+}
@@ -1,23 +1,20 @@
 use gpui::*;
 
-pub fn get_window_options() -> WindowOptions {
+use crate::session::WindowGeometry;
+
+/// Opens the window where `geometry` says, or centered at the default size.
+pub fn get_window_options(geometry: Option<WindowGeometry>) -> WindowOptions {
+    let default = WindowGeometry {
+        x: 0.,
+        y: 0.,
+        width: 800.,
+        height: 400.,
+    };
     return WindowOptions {
-        bounds: gpui::WindowBounds::Fixed(gpui::Bounds {
-            origin: gpui::Point {
-                x: GlobalPixels::from(0.),
-                y: GlobalPixels::from(0.),
-            },
-            size: gpui::Size {
-                width: GlobalPixels::from(800.),
-                height: GlobalPixels::from(400.),
-            },
-        }),
-        titlebar: Some(gpui::TitlebarOptions {
-            title: Some("Ted-itor".into()),
-            appears_transparent: true,
-            traffic_light_position: None,
-        }),
-        center: true,
+        bounds: geometry.unwrap_or(default).bounds(),
+        // frameless, the title bar draws its own window controls
+        titlebar: None,
+        center: geometry.is_none(),
         focus: true,
         show: true,
         kind: gpui::WindowKind::Normal,
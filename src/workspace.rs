@@ -1,69 +1,2135 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use gpui::{prelude::FluentBuilder, *};
-use smallvec::SmallVec;
 
 use crate::{
-    editor::Editor,
-    ui::{Background, Button, ButtonVariant, Divider, Layout, TextInput, TitleBar},
+    args::{Args, Stdin},
+    backup::{back_up, backups_for, Backup, BackupPicker, BackupPickerEvent},
+    clipboard_history::{copy_to_clipboard, ClipboardPicker, ClipboardPickerEvent},
+    command::{Command, CommandRegistry},
+    command_palette::{CommandPalette, CommandPaletteEvent},
+    config::EditorSettings,
+    editor::{
+        modified_time, write_atomically, Editor, LineEnding, LoadedCursor, Loading, WATCH_INTERVAL,
+    },
+    file_tree::{FileTree, FileTreeEvent},
+    go_to_line::{GoToLine, GoToLineEvent},
+    keymap::EditorAction,
+    macros::MacroRecorder,
+    markdown_preview::MarkdownPreview,
+    modal::{Modal, ModalLayer, ModalState},
+    project_search::{ProjectSearch, ProjectSearchEvent},
+    quick_open::{QuickOpen, QuickOpenEvent},
+    recovery::{remove_draft, Draft},
+    search::SearchBar,
+    session::{Session, SessionFile, WindowGeometry},
+    shell_command::{run_shell_command, ShellCommandEvent, ShellCommandMode, ShellCommandPrompt},
+    snippet::Snippets,
+    spell::Dictionary,
+    statistics::show_statistics,
+    theme::Theme,
+    toast::{Toast, ToastQueue, Toasts},
+    ui::{
+        Background, Button, ButtonEvent, ButtonVariant, Layout, SplitPane, SplitState, StatusBar,
+        Tab, TabBar, TextEvent, TextInput, TextModel, TitleBar, WindowControls,
+    },
 };
 
 /* cspell:disable-next-line */
 const INITIAL_EDITOR_TEXT: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.";
 
-pub fn build_workspace_view(cx: &mut WindowContext<'_>) -> View<Workspace> {
-    cx.new_view(|cx| Workspace {
-        editors: SmallVec::from_iter([
-            Editor {
-                text_input: TextInput::new(cx, INITIAL_EDITOR_TEXT.to_owned()),
-            },
-            Editor {
-                text_input: TextInput::new(cx, INITIAL_EDITOR_TEXT.to_owned()),
-            },
-        ]),
+/// How often the session is saved, besides on quit.
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Builds the window's content, with a tab for each file in `args` and one
+/// for stdin, or the files of `session`, or some sample text when there are none.
+pub fn build_workspace_view(
+    cx: &mut WindowContext<'_>,
+    args: Args,
+    session: Option<Session>,
+) -> View<Workspace> {
+    cx.new_view(|cx| {
+        let mut workspace = Workspace {
+            editors: Vec::new(),
+            active_editor: 0,
+            search_bar: SearchBar::new(cx),
+            search_target: None,
+            pending_action: None,
+            split: cx.new_model(|_cx| SplitState::new()),
+            split_input: None,
+            split_stacked: false,
+            markdown_preview: None,
+            theme_error: None,
+            status_bar: cx.new_view(StatusBar::new),
+            toasts: cx.new_view(Toasts::new),
+            command_palette: None,
+            quick_open: None,
+            file_tree: None,
+            project_search: None,
+            project_search_visible: false,
+            go_to_line: None,
+            clipboard_picker: None,
+            backup_picker: None,
+            shell_command: None,
+            vim_mode: false,
+            drafts: Vec::new(),
+        };
+        cx.observe(&workspace.split, |_this, _split, cx| cx.notify())
+            .detach();
+        let session = session.filter(|session| !session.files.is_empty());
+        let initial_text =
+            if args.files.is_empty() && args.stdin == Stdin::Ignore && session.is_none() {
+                INITIAL_EDITOR_TEXT.to_owned()
+            } else {
+                String::new()
+            };
+        workspace.add_editor(initial_text, cx);
+        workspace.update_status_bar(cx);
+        for input in [
+            &workspace.search_bar.query_input,
+            &workspace.search_bar.replace_input,
+        ] {
+            cx.subscribe(&input.model, |this, _input, event, cx| {
+                if let TextEvent::Cancel = event {
+                    this.close_search(cx);
+                }
+            })
+            .detach();
+        }
+        cx.observe_global::<ModalState>(|_this, cx| cx.notify())
+            .detach();
+        // a word added to the dictionary is no longer misspelled anywhere
+        cx.observe_global::<Dictionary>(|this, cx| {
+            for editor in &this.editors {
+                editor
+                    .text_input
+                    .model
+                    .update(cx, |model, cx| model.recheck_spelling(cx));
+            }
+        })
+        .detach();
+        register_commands(cx);
+        // the window controls dim while the window isn't active
+        cx.observe_window_activation(|_this, cx| cx.notify())
+            .detach();
+        let view = cx.view().downgrade();
+        cx.on_window_should_close(move |cx| {
+            view.update(cx, |this, cx| this.should_close(cx))
+                .unwrap_or(true)
+        });
+        // saved now and then too, so a crash doesn't lose it
+        cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(SESSION_SAVE_INTERVAL).await;
+                if this
+                    .update(&mut cx, |this, cx| this.save_session(cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .detach();
+        workspace.editors[0].text_input.focus(cx);
+        for file in args.files {
+            let cursor = file.position.map_or(LoadedCursor::Start, |(row, column)| {
+                LoadedCursor::Point(row, column)
+            });
+            workspace.open_file_at(&file.path, cursor, cx);
+        }
+        if args.stdin != Stdin::Ignore {
+            workspace.read_stdin(args.stdin, cx);
+        }
+        if let Some(session) = session {
+            workspace.restore_session(session, cx);
+        }
+        let drafts = cx.background_executor().spawn(async { Draft::load_all() });
+        cx.spawn(|this, mut cx| async move {
+            let drafts = drafts.await;
+            this.update(&mut cx, |this, cx| {
+                this.drafts = drafts;
+                this.show_drafts(cx);
+            })
+            .ok();
+        })
+        .detach();
+        workspace
     })
 }
 
+/// Something that would lose unsaved changes, held back until the user decides.
+#[derive(Clone, Copy, PartialEq)]
+enum PendingAction {
+    Close,
+    Hide,
+    Open,
+    /// Closes the tab at the index.
+    CloseTab(usize),
+}
+
 #[derive(Clone)]
 pub struct Workspace {
-    editors: SmallVec<[Editor; 2]>,
+    /// One per tab, in the order of the tab bar.
+    editors: Vec<Editor>,
+    /// The index of the tab shown, files are opened into its editor.
+    active_editor: usize,
+    search_bar: SearchBar,
+    /// The index of the editor being searched while the search bar is open.
+    search_target: Option<usize>,
+    /// Waits for the unsaved changes modal.
+    pending_action: Option<PendingAction>,
+    /// Where the divider between the two panes sits while split.
+    split: Model<SplitState>,
+    /// The second pane, another view of the active editor's text.
+    split_input: Option<TextInput>,
+    /// The second pane is under the first instead of right of it.
+    split_stacked: bool,
+    /// The second pane instead of `split_input`, the active editor's Markdown
+    /// rendered.
+    markdown_preview: Option<View<MarkdownPreview>>,
+    /// Why the watched theme file couldn't be applied, the last good theme stays.
+    theme_error: Option<SharedString>,
+    /// Follows the active editor.
+    status_bar: View<StatusBar>,
+    toasts: View<Toasts>,
+    /// Open after cmd+shift+p.
+    command_palette: Option<View<CommandPalette>>,
+    /// Open after cmd+p.
+    quick_open: Option<View<QuickOpen>>,
+    /// The sidebar, once a folder was opened. Quick open offers its files.
+    file_tree: Option<View<FileTree>>,
+    /// Kept with its results while hidden, until another folder is opened.
+    project_search: Option<View<ProjectSearch>>,
+    /// Shown after cmd+shift+f, until a result is opened.
+    project_search_visible: bool,
+    /// Open after ctrl+g.
+    go_to_line: Option<View<GoToLine>>,
+    /// Open after cmd+shift+v.
+    clipboard_picker: Option<View<ClipboardPicker>>,
+    /// Open after Restore from Backup in the palette.
+    backup_picker: Option<View<BackupPicker>>,
+    /// Open after one of the shell commands in the palette.
+    shell_command: Option<View<ShellCommandPrompt>>,
+    /// Whether editors use vim's keys, from the config.
+    vim_mode: bool,
+    /// Left behind by a run that didn't exit cleanly, offered in a modal.
+    drafts: Vec<Draft>,
+}
+
+impl Workspace {
+    /// Opens a tab with `text` after the others and returns its index.
+    fn add_editor(&mut self, text: String, cx: &mut ViewContext<Self>) -> usize {
+        let autosave = cx.global::<EditorSettings>().autosave_delay();
+        let editor = Editor::new(cx, text)
+            .show_line_numbers(true, cx)
+            .autosave(autosave, cx);
+        editor.set_vim_mode(self.vim_mode, cx);
+        editor
+            .text_input
+            .model
+            .update(cx, |model, cx| model.sync_spell_check(cx));
+        cx.subscribe(&editor.text_input.model, |this, model, event, cx| {
+            // tabs move when others close, so the editor is looked up each time
+            let Some(i) = this.index_of(&model) else {
+                return;
+            };
+            match event {
+                TextEvent::Focus => {
+                    this.active_editor = i;
+                    this.update_status_bar(cx);
+                    this.update_file_tree(cx);
+                    cx.notify();
+                }
+                TextEvent::Cancel => {
+                    // escape stops a load before it hides the window
+                    let loading = this.editors[i].cancel_loading(cx);
+                    if !loading {
+                        this.guard(PendingAction::Hide, cx);
+                    }
+                }
+                _ => {}
+            }
+        })
+        .detach();
+        // the title and tab bars show the file of each editor, the sidebar
+        // the active one's
+        cx.observe(&editor.state, |this, _state, cx| {
+            this.update_file_tree(cx);
+            cx.notify();
+        })
+        .detach();
+        self.editors.push(editor);
+        self.editors.len() - 1
+    }
+
+    fn index_of(&self, model: &Model<TextModel>) -> Option<usize> {
+        self.editors
+            .iter()
+            .position(|editor| editor.text_input.model.entity_id() == model.entity_id())
+    }
+
+    /// Shows the tab at `i` and focuses its editor.
+    fn activate_tab(&mut self, i: usize, cx: &mut ViewContext<Self>) {
+        let Some(editor) = self.editors.get(i).cloned() else {
+            return;
+        };
+        if self.search_target.is_some_and(|target| target != i) {
+            self.close_search(cx);
+        }
+        self.active_editor = i;
+        self.update_status_bar(cx);
+        self.update_file_tree(cx);
+        // the right pane follows the active tab
+        let model = &editor.text_input.model;
+        if self
+            .split_input
+            .as_ref()
+            .is_some_and(|input| input.model.entity_id() != model.entity_id())
+        {
+            self.split_input = Some(TextInput::with_model(model.clone(), cx));
+        }
+        if self
+            .markdown_preview
+            .as_ref()
+            .is_some_and(|preview| !preview.read(cx).shows(model))
+        {
+            self.markdown_preview = is_markdown(&editor, cx)
+                .then(|| cx.new_view(|cx| MarkdownPreview::new(&editor.text_input, cx)));
+        }
+        editor.text_input.focus(cx);
+        cx.notify();
+    }
+
+    /// Points the status bar at the active editor.
+    fn update_status_bar(&self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            let model = editor.text_input.model.clone();
+            self.status_bar
+                .update(cx, |status_bar, cx| status_bar.set_model(model, cx));
+        }
+    }
+
+    /// Shows the active editor in a second pane, or closes that pane.
+    fn toggle_split(&mut self, cx: &mut ViewContext<Self>) {
+        self.markdown_preview = None;
+        if self.split_input.take().is_some() {
+            self.focus_pane(0, cx);
+        } else if let Some(editor) = self.editors.get(self.active_editor) {
+            let input = TextInput::with_model(editor.text_input.model.clone(), cx);
+            input.focus(cx);
+            self.split_input = Some(input);
+        }
+        cx.notify();
+    }
+
+    /// Puts the second pane under the first one, or back right of it.
+    fn toggle_split_orientation(&mut self, cx: &mut ViewContext<Self>) {
+        self.split_stacked = !self.split_stacked;
+        cx.notify();
+    }
+
+    /// Renders the active editor's Markdown in a second pane, or closes that
+    /// pane. It replaces the split view.
+    fn toggle_markdown_preview(&mut self, cx: &mut ViewContext<Self>) {
+        if self.markdown_preview.take().is_some() {
+            cx.notify();
+            return;
+        }
+        let Some(editor) = self.editors.get(self.active_editor).cloned() else {
+            return;
+        };
+        if !is_markdown(&editor, cx) {
+            cx.global_mut::<ToastQueue>()
+                .push(Toast::info("Only Markdown files have a preview"));
+            return;
+        }
+        self.split_input = None;
+        self.markdown_preview =
+            Some(cx.new_view(|cx| MarkdownPreview::new(&editor.text_input, cx)));
+        editor.text_input.focus(cx);
+        cx.notify();
+    }
+
+    /// Focuses the left pane for 0 and the right one for 1, when split.
+    fn focus_pane(&mut self, pane: usize, cx: &mut ViewContext<Self>) {
+        let input = match pane {
+            0 => self
+                .editors
+                .get(self.active_editor)
+                .map(|editor| &editor.text_input),
+            _ => self.split_input.as_ref(),
+        };
+        if let Some(input) = input {
+            input.focus(cx);
+        }
+    }
+
+    fn new_tab(&mut self, cx: &mut ViewContext<Self>) {
+        let i = self.add_editor(String::new(), cx);
+        self.activate_tab(i, cx);
+    }
+
+    /// Closes the tab at `i`, once its changes are saved or discarded.
+    fn close_tab(&mut self, i: usize, cx: &mut ViewContext<Self>) {
+        self.activate_tab(i, cx);
+        self.guard(PendingAction::CloseTab(i), cx);
+    }
+
+    /// Removes the tab at `i` right away. The last tab is replaced by an
+    /// empty one, so there is always an editor to type in.
+    fn remove_tab(&mut self, i: usize, cx: &mut ViewContext<Self>) {
+        if i >= self.editors.len() {
+            return;
+        }
+        if self.search_target == Some(i) {
+            self.close_search(cx);
+        }
+        let editor = self.editors.remove(i);
+        editor.cancel_loading(cx);
+        editor.stop_watching(cx);
+        editor.discard_draft(cx);
+        if let Some(target) = self.search_target.as_mut().filter(|target| **target > i) {
+            *target -= 1;
+        }
+        if self.editors.is_empty() {
+            self.add_editor(String::new(), cx);
+        }
+        let active = if self.active_editor > i {
+            self.active_editor - 1
+        } else {
+            self.active_editor.min(self.editors.len() - 1)
+        };
+        self.activate_tab(active, cx);
+    }
+
+    /// Shows the next tab, or the previous one when `reverse` is set.
+    fn cycle_tabs(&mut self, reverse: bool, cx: &mut ViewContext<Self>) {
+        let count = self.editors.len();
+        if count == 0 {
+            return;
+        }
+        let next = if reverse {
+            (self.active_editor + count - 1) % count
+        } else {
+            (self.active_editor + 1) % count
+        };
+        self.activate_tab(next, cx);
+    }
+
+    /// The editors whose changes `action` would throw away.
+    fn dirty_editors(&self, action: PendingAction, cx: &AppContext) -> Vec<Editor> {
+        let editors: &[Editor] = match action {
+            PendingAction::Close | PendingAction::Hide => &self.editors,
+            PendingAction::Open => self
+                .editors
+                .get(self.active_editor..=self.active_editor)
+                .unwrap_or_default(),
+            PendingAction::CloseTab(i) => self.editors.get(i..=i).unwrap_or_default(),
+        };
+        editors
+            .iter()
+            .filter(|editor| editor.is_dirty(cx))
+            .cloned()
+            .collect()
+    }
+
+    /// Runs `action` right away, or asks first when it would lose changes.
+    fn guard(&mut self, action: PendingAction, cx: &mut ViewContext<Self>) {
+        if self.dirty_editors(action, cx).is_empty() {
+            self.run(action, cx);
+        } else {
+            self.show_dialog(action, cx);
+        }
+    }
+
+    fn should_close(&mut self, cx: &mut ViewContext<Self>) -> bool {
+        if self.dirty_editors(PendingAction::Close, cx).is_empty() {
+            self.save_session_now(cx);
+            self.discard_drafts(cx);
+            return true;
+        }
+        self.show_dialog(PendingAction::Close, cx);
+        false
+    }
+
+    /// Asks in a modal about the changes `action` would lose.
+    fn show_dialog(&mut self, action: PendingAction, cx: &mut ViewContext<Self>) {
+        self.pending_action = Some(action);
+        let count = self.dirty_editors(action, cx).len();
+        let message = if count == 1 {
+            "Do you want to save your changes?".to_string()
+        } else {
+            format!("Do you want to save the changes in {count} editors?")
+        };
+        let view = cx.view().downgrade();
+        let answer = |answer: fn(&mut Self, &mut ViewContext<Self>)| {
+            let view = view.clone();
+            move |cx: &mut WindowContext| {
+                view.update(cx, |this, cx| answer(this, cx)).ok();
+            }
+        };
+        Modal::new("Unsaved changes", message, answer(Self::cancel_pending))
+            .action(
+                "Cancel",
+                ButtonVariant::Secondary,
+                answer(Self::cancel_pending),
+            )
+            .action(
+                "Discard",
+                ButtonVariant::Danger,
+                answer(Self::discard_pending),
+            )
+            .action("Save", ButtonVariant::Primary, answer(Self::save_pending))
+            .open(cx);
+    }
+
+    fn run(&mut self, action: PendingAction, cx: &mut ViewContext<Self>) {
+        match action {
+            PendingAction::Close => {
+                self.save_session_now(cx);
+                self.discard_drafts(cx);
+                cx.remove_window();
+            }
+            PendingAction::Hide => cx.hide(),
+            PendingAction::Open => self.prompt_for_file(cx),
+            PendingAction::CloseTab(i) => self.remove_tab(i, cx),
+        }
+    }
+
+    /// Saves the editors the pending action would lose, then runs it. An editor
+    /// without a path asks for one instead, and the action is dropped.
+    fn save_pending(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(action) = self.pending_action.take() else {
+            return;
+        };
+        let dirty = self.dirty_editors(action, cx);
+        for editor in &dirty {
+            editor.save(cx);
+        }
+        if dirty
+            .iter()
+            .all(|editor| editor.state.read(cx).path.is_some())
+        {
+            self.run(action, cx);
+        }
+        cx.notify();
+    }
+
+    fn discard_pending(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(action) = self.pending_action.take() {
+            self.run(action, cx);
+        }
+        cx.notify();
+    }
+
+    fn cancel_pending(&mut self, cx: &mut ViewContext<Self>) {
+        self.pending_action = None;
+        cx.notify();
+    }
+
+    /// Deletes the editors' drafts, for a clean exit.
+    fn discard_drafts(&self, cx: &mut ViewContext<Self>) {
+        for editor in &self.editors {
+            editor.discard_draft(cx);
+        }
+    }
+
+    /// Lists the `drafts` in a modal, each can be restored or discarded.
+    /// Escape keeps them for the next start.
+    fn show_drafts(&mut self, cx: &mut ViewContext<Self>) {
+        if self.drafts.is_empty() {
+            return;
+        }
+        let view = cx.view().downgrade();
+        let answer = |answer: fn(&mut Self, &mut ViewContext<Self>)| {
+            let view = view.clone();
+            move |cx: &mut WindowContext| {
+                view.update(cx, |this, cx| answer(this, cx)).ok();
+            }
+        };
+        let draft_answer = |answer: fn(&mut Self, usize, &mut ViewContext<Self>), i: usize| {
+            let view = view.clone();
+            move |cx: &mut WindowContext| {
+                view.update(cx, |this, cx| {
+                    answer(this, i, cx);
+                    this.show_drafts(cx);
+                })
+                .ok();
+            }
+        };
+        let message = "ted-itor didn't exit cleanly, these changes were never saved.";
+        let mut modal = Modal::new(
+            "Recovered drafts",
+            message,
+            answer(|this, _cx| this.drafts.clear()),
+        );
+        for (i, draft) in self.drafts.iter().enumerate() {
+            modal = modal
+                .row(draft.label())
+                .row_action(
+                    "Discard",
+                    ButtonVariant::Secondary,
+                    draft_answer(Self::discard_recovered, i),
+                )
+                .row_action(
+                    "Restore",
+                    ButtonVariant::Secondary,
+                    draft_answer(Self::restore_recovered, i),
+                );
+        }
+        modal
+            .action(
+                "Discard All",
+                ButtonVariant::Danger,
+                answer(|this, _cx| {
+                    for draft in this.drafts.drain(..) {
+                        remove_draft(&draft.file);
+                    }
+                }),
+            )
+            .action(
+                "Restore All",
+                ButtonVariant::Primary,
+                answer(|this, cx| {
+                    for _ in 0..this.drafts.len() {
+                        this.restore_recovered(0, cx);
+                    }
+                }),
+            )
+            .open(cx);
+    }
+
+    /// Opens the recovered draft at `i` in the tab of its file, or else a new
+    /// one, its text dirty.
+    fn restore_recovered(&mut self, i: usize, cx: &mut ViewContext<Self>) {
+        if i >= self.drafts.len() {
+            return;
+        }
+        let mut draft = self.drafts.remove(i);
+        let open = draft
+            .path
+            .as_deref()
+            .and_then(|path| self.tab_for_path(path, cx));
+        let i = match open.or_else(|| self.reusable_tab(cx)) {
+            Some(i) => i,
+            None => self.add_editor(String::new(), cx),
+        };
+        if open.is_some() {
+            // the same file, maybe by another path
+            draft.path = self.editors[i].state.read(cx).path.clone();
+        }
+        self.editors[i].open_draft(draft, cx);
+        self.activate_tab(i, cx);
+    }
+
+    fn discard_recovered(&mut self, i: usize, _cx: &mut ViewContext<Self>) {
+        if i < self.drafts.len() {
+            remove_draft(&self.drafts.remove(i).file);
+        }
+    }
+
+    /// Asks for a file and opens it in the active editor, once its changes are
+    /// saved or discarded.
+    fn open_file(&mut self, cx: &mut ViewContext<Self>) {
+        self.guard(PendingAction::Open, cx);
+    }
+
+    /// Asks for a folder and shows it in the sidebar.
+    fn open_folder(&mut self, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Some(paths)) = paths.await else {
+                return;
+            };
+            if let Some(path) = paths.into_iter().next() {
+                this.update(&mut cx, |this, cx| this.open_folder_path(path, cx))
+                    .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Makes `folder` the root of the sidebar's file tree, replacing the one
+    /// before.
+    fn open_folder_path(&mut self, folder: PathBuf, cx: &mut ViewContext<Self>) {
+        let file_tree = cx.new_view(|cx| FileTree::new(folder, cx));
+        cx.subscribe(&file_tree, |this, _file_tree, event, cx| match event {
+            FileTreeEvent::Open(path) => this.open_or_activate(path, LoadedCursor::Start, cx),
+        })
+        .detach();
+        self.file_tree = Some(file_tree);
+        self.project_search = None;
+        self.project_search_visible = false;
+        self.update_file_tree(cx);
+        cx.notify();
+    }
+
+    /// Highlights the active editor's file in the sidebar.
+    fn update_file_tree(&self, cx: &mut ViewContext<Self>) {
+        let Some(file_tree) = &self.file_tree else {
+            return;
+        };
+        let path = self
+            .editors
+            .get(self.active_editor)
+            .and_then(|editor| editor.state.read(cx).path.clone());
+        file_tree.update(cx, |file_tree, cx| {
+            file_tree.set_active(path.as_deref(), cx)
+        });
+    }
+
+    /// Expands the sidebar's folders down to the active editor's file and
+    /// focuses it there. A file outside the sidebar's folder, or without one,
+    /// shows its own folder instead.
+    fn reveal_in_sidebar(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(path) = self
+            .editors
+            .get(self.active_editor)
+            .and_then(|editor| editor.state.read(cx).path.clone())
+        else {
+            if let Some(editor) = self.editors.get(self.active_editor) {
+                editor.set_error(Some("The file isn't saved yet".into()), cx);
+            }
+            return;
+        };
+        let revealed = self.file_tree.as_ref().is_some_and(|file_tree| {
+            file_tree.update(cx, |file_tree, cx| file_tree.reveal(&path, cx))
+        });
+        if !revealed {
+            let Some(folder) = path.parent() else {
+                return;
+            };
+            self.open_folder_path(folder.to_path_buf(), cx);
+            if let Some(file_tree) = &self.file_tree {
+                file_tree.update(cx, |file_tree, cx| file_tree.reveal(&path, cx));
+            }
+        }
+        if let Some(file_tree) = &self.file_tree {
+            file_tree.update(cx, |file_tree, cx| file_tree.focus(cx));
+        }
+    }
+
+    fn prompt_for_file(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(editor) = self.editors.get(self.active_editor).cloned() else {
+            return;
+        };
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        });
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Some(paths)) = paths.await else {
+                return;
+            };
+            if let Some(path) = paths.into_iter().next() {
+                this.update(&mut cx, |_this, cx| editor.open_path(&path, cx))
+                    .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Opens each of `paths` in a tab of its own, the active tab is reused when
+    /// it's empty and untitled. A folder is shown in the sidebar.
+    fn open_paths(&mut self, paths: &[PathBuf], cx: &mut ViewContext<Self>) {
+        for path in paths {
+            self.open_file_at(path, LoadedCursor::Start, cx);
+        }
+    }
+
+    /// Opens `path` in a new tab, or the active one when it's empty and
+    /// untitled, with the cursor at `cursor`. A folder is shown in the sidebar
+    /// instead.
+    fn open_file_at(&mut self, path: &Path, cursor: LoadedCursor, cx: &mut ViewContext<Self>) {
+        if path.is_dir() {
+            self.open_folder_path(path.to_path_buf(), cx);
+            return;
+        }
+        let i = match self.reusable_tab(cx) {
+            Some(i) => i,
+            None => self.add_editor(String::new(), cx),
+        };
+        self.editors[i].open_path_at(path, cursor, cx);
+        self.activate_tab(i, cx);
+    }
+
+    /// Opens the files of `session` in tabs, each with its cursor and scroll
+    /// position. Files that are gone are skipped and listed in a banner.
+    fn restore_session(&mut self, session: Session, cx: &mut ViewContext<Self>) {
+        let mut active = None;
+        let mut missing = Vec::new();
+        for (i, file) in session.files.into_iter().enumerate() {
+            if !file.path.is_file() {
+                missing.push(file.path.display().to_string());
+                continue;
+            }
+            self.open_file_at(&file.path, LoadedCursor::Selection(file.selection), cx);
+            self.editors[self.active_editor]
+                .text_input
+                .set_scroll_top(px(file.scroll_top), cx);
+            if i <= session.active {
+                active = Some(self.active_editor);
+            }
+        }
+        if let Some(active) = active {
+            self.activate_tab(active, cx);
+        }
+        if !missing.is_empty() {
+            let warning = format!(
+                "Files from the last session are gone: {}",
+                missing.join(", ")
+            );
+            self.editors[self.active_editor].set_warning(Some(warning.into()), cx);
+        }
+    }
+
+    /// What's open right now, files without a path are left out.
+    fn session(&self, cx: &WindowContext) -> Session {
+        let mut session = Session {
+            window: WindowGeometry::from_bounds(cx.window_bounds()),
+            ..Default::default()
+        };
+        for (i, editor) in self.editors.iter().enumerate() {
+            let Some(path) = editor.state.read(cx).path.clone() else {
+                continue;
+            };
+            if i == self.active_editor {
+                session.active = session.files.len();
+            }
+            session.files.push(SessionFile {
+                path,
+                selection: editor.text_input.model.read(cx).selection.clone(),
+                scroll_top: editor.text_input.scroll_top(cx).into(),
+            });
+        }
+        session
+    }
+
+    /// Writes the session in the background.
+    fn save_session(&self, cx: &mut ViewContext<Self>) {
+        let session = self.session(cx);
+        cx.background_executor()
+            .spawn(async move {
+                if let Err(error) = session.save() {
+                    eprintln!("Couldn't save the session: {error}");
+                }
+            })
+            .detach();
+    }
+
+    /// Writes the session before returning, for when the app is about to quit.
+    fn save_session_now(&self, cx: &mut ViewContext<Self>) {
+        if let Err(error) = self.session(cx).save() {
+            eprintln!("Couldn't save the session: {error}");
+        }
+    }
+
+    /// Turns vim mode on or off for every editor, and the ones opened later.
+    pub fn set_vim_mode(&mut self, enabled: bool, cx: &mut ViewContext<Self>) {
+        self.vim_mode = enabled;
+        for editor in &self.editors {
+            editor.set_vim_mode(enabled, cx);
+        }
+    }
+
+    /// Checks the theme file at `path` every `WATCH_INTERVAL` and applies it
+    /// when it changed.
+    pub fn watch_theme(&mut self, path: PathBuf, cx: &mut ViewContext<Self>) {
+        cx.spawn(|this, mut cx| async move {
+            let mut last_modified = modified_time(&path);
+            loop {
+                cx.background_executor().timer(WATCH_INTERVAL).await;
+                let (modified, theme) = cx
+                    .background_executor()
+                    .spawn({
+                        let path = path.clone();
+                        async move {
+                            let modified = modified_time(&path);
+                            if modified == last_modified {
+                                return (modified, None);
+                            }
+                            (modified, Some(Theme::load_file(&path)))
+                        }
+                    })
+                    .await;
+                last_modified = modified;
+                let Some(theme) = theme else {
+                    continue;
+                };
+                if this
+                    .update(&mut cx, |this, cx| this.apply_theme(theme, cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn apply_theme(&mut self, theme: anyhow::Result<Theme>, cx: &mut ViewContext<Self>) {
+        match theme {
+            Ok(theme) => {
+                self.theme_error = None;
+                Theme::replace(theme, cx);
+            }
+            Err(error) => {
+                self.theme_error = Some(format!("Couldn't reload the theme: {error:#}").into());
+                cx.notify();
+            }
+        }
+    }
+
+    /// Reads stdin in the background, so a slow producer doesn't hold up the
+    /// window, and shows it in a read-only tab without a path.
+    fn read_stdin(&mut self, stdin: Stdin, cx: &mut ViewContext<Self>) {
+        let read = cx.background_executor().spawn(async {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+        });
+        cx.spawn(|this, mut cx| async move {
+            let result = read.await;
+            this.update(&mut cx, |this, cx| this.open_stdin(stdin, result, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn open_stdin(
+        &mut self,
+        stdin: Stdin,
+        result: io::Result<Vec<u8>>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let bytes = match result {
+            // nothing was piped in, e.g. when launched from a file manager
+            Ok(bytes) if bytes.is_empty() && stdin == Stdin::Piped => return,
+            Ok(bytes) => bytes,
+            Err(error) => {
+                if let Some(editor) = self.editors.get(self.active_editor) {
+                    let error = format!("Couldn't read stdin: {error}");
+                    editor.set_error(Some(error.into()), cx);
+                }
+                return;
+            }
+        };
+        let i = match self.reusable_tab(cx) {
+            Some(i) => i,
+            None => self.add_editor(String::new(), cx),
+        };
+        self.editors[i].open_scratch(bytes, cx);
+        self.activate_tab(i, cx);
+    }
+
+    /// The active tab when it's empty and untitled, so opening a file there
+    /// loses nothing.
+    fn reusable_tab(&self, cx: &AppContext) -> Option<usize> {
+        let editor = self.editors.get(self.active_editor)?;
+        let pristine = editor.state.read(cx).path.is_none()
+            && !editor.is_dirty(cx)
+            && editor.text_input.model.read(cx).is_empty();
+        pristine.then_some(self.active_editor)
+    }
+
+    /// Saves the active editor, under a new name when `save_as` is set.
+    fn save(&mut self, save_as: bool, cx: &mut ViewContext<Self>) {
+        let Some(editor) = self.editors.get(self.active_editor) else {
+            return;
+        };
+        if save_as {
+            editor.save_as(cx);
+        } else {
+            editor.save(cx);
+        }
+    }
+
+    fn format_document(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            editor.format_document(cx);
+        }
+    }
+
+    /// Inserts a snippet's `body` in place of the active editor's selection.
+    fn insert_snippet(&mut self, body: &str, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            editor.text_input.model.update(cx, |model, cx| {
+                if !model.read_only {
+                    model.insert_snippet(model.selection.clone(), body, cx);
+                }
+            });
+        }
+    }
+
+    fn show_statistics(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            show_statistics(&editor.text_input.model, cx);
+        }
+    }
+
+    fn export_html(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            editor.export_html(cx);
+        }
+    }
+
+    fn copy_as_html(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            editor.copy_as_html(cx);
+        }
+    }
+
+    /// Switches the active editor between LF and CRLF.
+    fn toggle_line_ending(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            let line_ending = editor.state.read(cx).line_ending.toggled();
+            editor.set_line_ending(line_ending, cx);
+        }
+    }
+
+    /// Wraps the active editor's long lines, or stops wrapping them.
+    fn toggle_soft_wrap(&mut self, cx: &mut ViewContext<Self>) {
+        let model = self.editors[self.active_editor].text_input.model.clone();
+        model.update(cx, |editor, cx| {
+            editor.set_soft_wrap(!editor.soft_wrap(), cx);
+            // scrolls the cursor back into view on its new row
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+    }
+
+    /// Turns spell checking on or off in every editor.
+    fn toggle_spell_check(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global_mut::<EditorSettings>();
+        settings.spell_check = !settings.spell_check;
+        for editor in &self.editors {
+            editor
+                .text_input
+                .model
+                .update(cx, |model, cx| model.sync_spell_check(cx));
+        }
+    }
+
+    /// Opens the command palette, or closes it when it's open.
+    fn toggle_command_palette(&mut self, cx: &mut ViewContext<Self>) {
+        if self.command_palette.take().is_some() {
+            self.focus_active_editor(cx);
+            cx.notify();
+            return;
+        }
+        let palette = cx.new_view(CommandPalette::new);
+        cx.subscribe(&palette, |this, palette, event, cx| match event {
+            CommandPaletteEvent::Dismissed => {
+                // a command may have moved the focus on purpose
+                if palette.read(cx).is_focused(cx) {
+                    this.focus_active_editor(cx);
+                }
+                this.command_palette = None;
+                cx.notify();
+            }
+        })
+        .detach();
+        self.command_palette = Some(palette);
+        cx.notify();
+    }
+
+    /// Opens quick open on the recent files and the folder's, or the active
+    /// file's folder's without one. Closes it when it's open.
+    fn toggle_quick_open(&mut self, cx: &mut ViewContext<Self>) {
+        if self.quick_open.take().is_some() {
+            self.focus_active_editor(cx);
+            cx.notify();
+            return;
+        }
+        let root = self
+            .file_tree
+            .as_ref()
+            .map(|file_tree| file_tree.read(cx).root().to_path_buf());
+        let folder = root.or_else(|| {
+            let editor = self.editors.get(self.active_editor)?;
+            let path = editor.state.read(cx).path.clone()?;
+            Some(path.parent()?.to_path_buf())
+        });
+        let quick_open = cx.new_view(|cx| QuickOpen::new(folder, cx));
+        cx.subscribe(&quick_open, |this, quick_open, event, cx| {
+            if quick_open.read(cx).is_focused(cx) {
+                this.focus_active_editor(cx);
+            }
+            this.quick_open = None;
+            cx.notify();
+            if let QuickOpenEvent::Open(path) = event {
+                this.open_or_activate(path, LoadedCursor::Start, cx);
+            }
+        })
+        .detach();
+        self.quick_open = Some(quick_open);
+        cx.notify();
+    }
+
+    /// The tab `path` is open in, links followed.
+    fn tab_for_path(&self, path: &Path, cx: &AppContext) -> Option<usize> {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let path = canonical(path);
+        self.editors.iter().position(|editor| {
+            editor
+                .state
+                .read(cx)
+                .path
+                .as_deref()
+                .is_some_and(|other| canonical(other) == path)
+        })
+    }
+
+    /// Switches to the tab `path` is open in, or opens it. The cursor moves to
+    /// `cursor` either way.
+    fn open_or_activate(&mut self, path: &Path, cursor: LoadedCursor, cx: &mut ViewContext<Self>) {
+        let Some(i) = self.tab_for_path(path, cx) else {
+            self.open_file_at(path, cursor, cx);
+            return;
+        };
+        self.activate_tab(i, cx);
+        self.editors[i].text_input.model.update(cx, |editor, cx| {
+            let selection = match cursor {
+                LoadedCursor::Start => return,
+                LoadedCursor::Selection(selection) => selection,
+                LoadedCursor::Point(row, column) => {
+                    let offset = editor.offset_for_point(row, column);
+                    offset..offset
+                }
+            };
+            editor.select(selection);
+            // scrolls the cursor into view
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+    }
+
+    /// Opens the search through the sidebar's folder, or hides it when it's
+    /// shown.
+    fn toggle_project_search(&mut self, cx: &mut ViewContext<Self>) {
+        if self.project_search_visible {
+            self.project_search_visible = false;
+            self.focus_active_editor(cx);
+            cx.notify();
+            return;
+        }
+        let Some(root) = self
+            .file_tree
+            .as_ref()
+            .map(|file_tree| file_tree.read(cx).root().to_path_buf())
+        else {
+            cx.global_mut::<ToastQueue>()
+                .push(Toast::info("Open a folder to search the files in it"));
+            return;
+        };
+        let unsaved_texts = self.unsaved_texts(cx);
+        match &self.project_search {
+            Some(search) if search.read(cx).root() == root => {
+                search.update(cx, |search, cx| search.reopen(unsaved_texts, cx));
+            }
+            _ => {
+                let search = cx.new_view(|cx| ProjectSearch::new(root, unsaved_texts, cx));
+                cx.subscribe(&search, |this, search, event, cx| {
+                    if search.read(cx).is_focused(cx) {
+                        this.focus_active_editor(cx);
+                    }
+                    this.project_search_visible = false;
+                    cx.notify();
+                    if let ProjectSearchEvent::Open { path, row, column } = event {
+                        let cursor = LoadedCursor::Point(*row, *column);
+                        this.open_or_activate(path, cursor, cx);
+                    }
+                })
+                .detach();
+                self.project_search = Some(search);
+            }
+        }
+        self.project_search_visible = true;
+        cx.notify();
+    }
+
+    /// The texts of the editors with unsaved changes to a file, by the file's
+    /// canonical path.
+    fn unsaved_texts(&self, cx: &AppContext) -> HashMap<PathBuf, String> {
+        self.editors
+            .iter()
+            .filter(|editor| editor.is_dirty(cx))
+            .filter_map(|editor| {
+                let path = editor.state.read(cx).path.clone()?;
+                let path = path.canonicalize().unwrap_or(path);
+                Some((path, editor.text_input.model.read(cx).text()))
+            })
+            .collect()
+    }
+
+    /// Rewrites the lines of the active editor's selection with `transform`,
+    /// as one undo step, and tells what happened in a toast.
+    fn transform_lines(
+        &mut self,
+        transform: impl FnOnce(&mut Vec<String>),
+        message: impl FnOnce(usize, usize) -> String,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let model = self.editors[self.active_editor].text_input.model.clone();
+        let counts = model.update(cx, |editor, cx| {
+            editor.break_undo_group();
+            let counts = editor.transform_lines(transform, cx);
+            editor.break_undo_group();
+            counts
+        });
+        if let Some((before, after)) = counts {
+            cx.global_mut::<ToastQueue>()
+                .push(Toast::info(message(before, after)));
+        }
+    }
+
+    fn sort_lines(&mut self, case_sensitive: bool, cx: &mut ViewContext<Self>) {
+        self.transform_lines(
+            |lines| {
+                if case_sensitive {
+                    lines.sort();
+                } else {
+                    lines.sort_by_key(|line| line.to_lowercase());
+                }
+            },
+            |count, _| format!("Sorted {count} lines"),
+            cx,
+        );
+    }
+
+    fn reverse_lines(&mut self, cx: &mut ViewContext<Self>) {
+        self.transform_lines(
+            |lines| lines.reverse(),
+            |count, _| format!("Reversed {count} lines"),
+            cx,
+        );
+    }
+
+    /// Keeps the first of each set of equal lines, in their order.
+    fn remove_duplicate_lines(&mut self, cx: &mut ViewContext<Self>) {
+        self.transform_lines(
+            |lines| {
+                let mut seen = HashSet::new();
+                lines.retain(|line| seen.insert(line.clone()));
+            },
+            |before, after| format!("Removed {} duplicate lines", before - after),
+            cx,
+        );
+    }
+
+    /// Does what the key bound to `action` does in the active editor.
+    fn perform(&mut self, action: EditorAction, cx: &mut ViewContext<Self>) {
+        let model = self.editors[self.active_editor].text_input.model.clone();
+        model.update(cx, |editor, cx| {
+            if editor.read_only && action.edits_text() {
+                return;
+            }
+            editor.break_undo_group();
+            editor.perform(action, 1, cx);
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+    }
+
+    fn split_selection_into_lines(&mut self, cx: &mut ViewContext<Self>) {
+        let model = self.editors[self.active_editor].text_input.model.clone();
+        model.update(cx, |editor, cx| {
+            editor.split_selection_into_lines();
+            cx.emit(TextEvent::SelectionChanged {
+                selection: editor.selection.clone(),
+            });
+        });
+    }
+
+    /// Starts recording the keys typed into the active editor, or stops.
+    fn toggle_macro_recording(&mut self, cx: &mut ViewContext<Self>) {
+        let model = self.editors[self.active_editor]
+            .text_input
+            .model
+            .entity_id();
+        MacroRecorder::toggle(model, cx);
+    }
+
+    fn play_macro(&mut self, count: usize, cx: &mut ViewContext<Self>) {
+        self.editors[self.active_editor]
+            .text_input
+            .play_macro(count, cx);
+    }
+
+    /// Asks for a line to move the active editor's cursor to.
+    fn open_go_to_line(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(editor) = self.editors.get(self.active_editor) else {
+            return;
+        };
+        let model = editor.text_input.model.clone();
+        let go_to_line = cx.new_view(|cx| GoToLine::new(model, cx));
+        cx.subscribe(&go_to_line, |this, go_to_line, event, cx| match event {
+            GoToLineEvent::Dismissed => {
+                if go_to_line.read(cx).is_focused(cx) {
+                    this.focus_active_editor(cx);
+                }
+                this.go_to_line = None;
+                cx.notify();
+            }
+        })
+        .detach();
+        self.go_to_line = Some(go_to_line);
+        cx.notify();
+    }
+
+    /// Offers the texts copied before, the one picked is pasted into the
+    /// active editor like the clipboard.
+    fn open_clipboard_picker(&mut self, cx: &mut ViewContext<Self>) {
+        let picker = cx.new_view(ClipboardPicker::new);
+        cx.subscribe(&picker, |this, picker, event, cx| {
+            if picker.read(cx).is_focused(cx) {
+                this.focus_active_editor(cx);
+            }
+            this.clipboard_picker = None;
+            cx.notify();
+            if let ClipboardPickerEvent::Picked(text) = event {
+                copy_to_clipboard(text.clone(), cx);
+                this.perform(EditorAction::Paste, cx);
+            }
+        })
+        .detach();
+        self.clipboard_picker = Some(picker);
+        cx.notify();
+    }
+
+    /// Offers the backups of the active editor's file, the one picked opens
+    /// read-only in a new tab to compare with the file, see `restore_backup`.
+    fn open_backup_picker(&mut self, cx: &mut ViewContext<Self>) {
+        let path = self
+            .editors
+            .get(self.active_editor)
+            .and_then(|editor| editor.state.read(cx).path.clone());
+        let Some(path) = path else {
+            cx.global_mut::<ToastQueue>()
+                .push(Toast::info("Only a saved file has backups"));
+            return;
+        };
+        let backups = backups_for(&path);
+        if backups.is_empty() {
+            let message = format!("There are no backups of {}", path.display());
+            cx.global_mut::<ToastQueue>().push(Toast::info(message));
+            return;
+        }
+        let picker = cx.new_view(|cx| BackupPicker::new(backups, cx));
+        cx.subscribe(&picker, |this, picker, event, cx| {
+            if picker.read(cx).is_focused(cx) {
+                this.focus_active_editor(cx);
+            }
+            this.backup_picker = None;
+            cx.notify();
+            if let BackupPickerEvent::Picked(backup) = event {
+                this.open_backup(backup.clone(), cx);
+            }
+        })
+        .detach();
+        self.backup_picker = Some(picker);
+        cx.notify();
+    }
+
+    /// Reads `backup` in the background and shows it in a new tab.
+    fn open_backup(&mut self, backup: Backup, cx: &mut ViewContext<Self>) {
+        let read = cx.background_executor().spawn({
+            let path = backup.path.clone();
+            async move { fs::read(path) }
+        });
+        cx.spawn(|this, mut cx| async move {
+            let bytes = read.await;
+            this.update(&mut cx, |this, cx| match bytes {
+                Ok(bytes) => {
+                    let i = this.add_editor(String::new(), cx);
+                    this.editors[i].open_backup(backup, bytes, cx);
+                    this.activate_tab(i, cx);
+                }
+                Err(error) => {
+                    let error = format!(
+                        "Couldn't open the backup {}: {error}",
+                        backup.path.display()
+                    );
+                    cx.global_mut::<ToastQueue>().push(Toast::error(error));
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Asks before copying the backup `editor` shows over its file.
+    fn confirm_restore_backup(&mut self, editor: Editor, cx: &mut ViewContext<Self>) {
+        let Some(backup) = editor.state.read(cx).backup.clone() else {
+            return;
+        };
+        let message = format!(
+            "{} is replaced with the backup from {}.",
+            backup.original.display(),
+            backup.created_label()
+        );
+        let view = cx.view().downgrade();
+        Modal::confirm(
+            "Restore from Backup",
+            message,
+            move |cx| {
+                view.update(cx, |this, cx| this.restore_backup(editor.clone(), cx))
+                    .ok();
+            },
+            |_cx| {},
+        )
+        .open(cx);
+    }
+
+    /// Copies the backup `editor` shows over its file, closes the backup's tab
+    /// and shows the file. With `backup_on_save` the file is backed up first,
+    /// so the restore can be undone too.
+    fn restore_backup(&mut self, editor: Editor, cx: &mut ViewContext<Self>) {
+        let Some(backup) = editor.state.read(cx).backup.clone() else {
+            return;
+        };
+        let settings = cx.global::<EditorSettings>();
+        let back_up_first = settings
+            .backup_on_save
+            .then_some((settings.backup_location, settings.backup_count));
+        let copy = cx.background_executor().spawn({
+            let backup = backup.clone();
+            async move {
+                // read first, backing up may rotate this very backup out
+                let bytes = fs::read(&backup.path)?;
+                if let Some((location, count)) = back_up_first {
+                    back_up(&backup.original, location, count)?;
+                }
+                write_atomically(&backup.original, &bytes)
+            }
+        });
+        cx.spawn(|this, mut cx| async move {
+            let result = copy.await;
+            this.update(&mut cx, |this, cx| {
+                let original = backup.original.display();
+                if let Err(error) = result {
+                    let error = format!("Couldn't restore {original}: {error}");
+                    cx.global_mut::<ToastQueue>().push(Toast::error(error));
+                    return;
+                }
+                if let Some(i) = this.index_of(&editor.text_input.model) {
+                    this.remove_tab(i, cx);
+                }
+                // an open tab of the file reloads, or asks when it has changes
+                this.open_or_activate(&backup.original, LoadedCursor::Start, cx);
+                let message = format!("Restored {original} from the backup");
+                cx.global_mut::<ToastQueue>().push(Toast::info(message));
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Asks for a command to run with the shell on the active editor's text,
+    /// see `run_shell_command`.
+    fn open_shell_command(&mut self, mode: ShellCommandMode, cx: &mut ViewContext<Self>) {
+        let prompt = cx.new_view(|cx| ShellCommandPrompt::new(mode, cx));
+        cx.subscribe(&prompt, move |this, prompt, event, cx| {
+            if prompt.read(cx).is_focused(cx) {
+                this.focus_active_editor(cx);
+            }
+            this.shell_command = None;
+            cx.notify();
+            if let ShellCommandEvent::Submitted(command_line) = event {
+                let Some(editor) = this.editors.get(this.active_editor) else {
+                    return;
+                };
+                // commands run next to the file, like the ones in a terminal there
+                let dir = editor
+                    .state
+                    .read(cx)
+                    .path
+                    .as_ref()
+                    .and_then(|path| path.parent())
+                    .map(Path::to_path_buf);
+                let model = editor.text_input.model.clone();
+                run_shell_command(model, mode, command_line.clone(), dir, cx);
+            }
+        })
+        .detach();
+        self.shell_command = Some(prompt);
+        cx.notify();
+    }
+
+    /// The workspace's shortcuts, for keys the focused editor or sidebar
+    /// didn't handle.
+    fn on_key_down(&mut self, ev: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let modifiers = &ev.keystroke.modifiers;
+        if modifiers.control && ev.keystroke.key == "tab" {
+            self.cycle_tabs(modifiers.shift, cx);
+        } else if modifiers.control && ev.keystroke.key == "g" {
+            self.open_go_to_line(cx);
+        } else if modifiers.command && modifiers.alt && ev.keystroke.key == "t" {
+            Theme::toggle(cx);
+        } else if modifiers.command && ev.keystroke.key == "t" {
+            self.new_tab(cx);
+        } else if modifiers.command && ev.keystroke.key == "w" {
+            self.close_tab(self.active_editor, cx);
+        } else if modifiers.command && !modifiers.shift && ev.keystroke.key == "\\" {
+            self.toggle_split(cx);
+        } else if modifiers.command && ev.keystroke.key == "1" {
+            self.focus_pane(0, cx);
+        } else if modifiers.command && ev.keystroke.key == "2" {
+            self.focus_pane(1, cx);
+        } else if modifiers.command && modifiers.alt && ev.keystroke.key == "z" {
+            self.toggle_soft_wrap(cx);
+        } else if modifiers.command && matches!(ev.keystroke.key.as_str(), "=" | "+") {
+            Theme::zoom(Some(1), cx);
+        } else if modifiers.command && ev.keystroke.key == "-" {
+            Theme::zoom(Some(-1), cx);
+        } else if modifiers.command && ev.keystroke.key == "0" {
+            Theme::zoom(None, cx);
+        } else if modifiers.command && modifiers.shift && ev.keystroke.key == "e" {
+            self.focus_sidebar(cx);
+        } else if modifiers.command && modifiers.shift && ev.keystroke.key == "p" {
+            self.toggle_command_palette(cx);
+        } else if modifiers.command && ev.keystroke.key == "p" {
+            self.toggle_quick_open(cx);
+        } else if modifiers.command && modifiers.shift && !modifiers.alt && ev.keystroke.key == "v"
+        {
+            self.open_clipboard_picker(cx);
+        } else if modifiers.command && modifiers.shift && ev.keystroke.key == "f" {
+            self.toggle_project_search(cx);
+        } else if modifiers.command && ev.keystroke.key == "f" {
+            self.open_search(cx);
+        } else if modifiers.command && ev.keystroke.key == "o" {
+            self.open_file(cx);
+        } else if modifiers.command && ev.keystroke.key == "s" {
+            self.save(modifiers.shift, cx);
+        }
+    }
+
+    /// Moves the keyboard focus to the file tree, when a folder is open.
+    fn focus_sidebar(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(file_tree) = &self.file_tree {
+            file_tree.update(cx, |file_tree, cx| file_tree.focus(cx));
+        }
+    }
+
+    fn focus_active_editor(&self, cx: &mut ViewContext<Self>) {
+        if let Some(editor) = self.editors.get(self.active_editor) {
+            editor.text_input.focus(cx);
+        }
+    }
+
+    /// Opens the search bar on the focused editor, or the first one.
+    fn open_search(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(target) = self
+            .editors
+            .iter()
+            .position(|editor| editor.text_input.is_focused(cx))
+            .or(self.search_target)
+            .or((self.active_editor < self.editors.len()).then_some(self.active_editor))
+        else {
+            return;
+        };
+        let editor = self.editors[target].text_input.model.clone();
+        self.search_bar
+            .model
+            .update(cx, |search, cx| search.set_editor(Some(editor), cx));
+        // typing replaces the previous query
+        self.search_bar
+            .query_input
+            .model
+            .update(cx, |query, _cx| query.select_all());
+        self.search_bar.query_input.focus(cx);
+        self.search_target = Some(target);
+        cx.notify();
+    }
+
+    /// Closes the search bar and gives focus back to the editor it searched.
+    fn close_search(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(target) = self.search_target.take() else {
+            return;
+        };
+        self.search_bar
+            .model
+            .update(cx, |search, cx| search.set_editor(None, cx));
+        if let Some(editor) = self.editors.get(target) {
+            editor.text_input.focus(cx);
+        }
+        cx.notify();
+    }
 }
 
 impl Render for Workspace {
-    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
         let editors = self.editors.clone();
+        let search_bar = self.search_target.map(|_| self.search_bar.clone());
+        let theme = cx.global::<Theme>();
+        let (title, dirty, saved, line_ending, error, progress) =
+            self.editors.get(self.active_editor).map_or_else(
+                || {
+                    let title = SharedString::from("Untitled");
+                    (title, false, false, LineEnding::default(), None, None)
+                },
+                |editor| {
+                    let state = editor.state.read(cx);
+                    (
+                        editor.path_title(cx),
+                        state.dirty,
+                        state.saved_flash.is_some(),
+                        state.line_ending,
+                        state.error.clone(),
+                        state.loading.as_ref().map(Loading::progress),
+                    )
+                },
+            );
+        let danger_color = theme.danger_color;
+        let muted_color = theme.muted_color;
+        let banner = self.render_theme_banner(cx).or_else(|| {
+            self.editors
+                .get(self.active_editor)
+                .cloned()
+                .and_then(|editor| self.render_banner(editor, cx))
+        });
+        let tab_bar = self.render_tab_bar(cx);
+        let active_editor = self.editors.get(self.active_editor).cloned();
+        let second_pane = match (&self.markdown_preview, &self.split_input) {
+            (Some(preview), _) => Some(preview.clone().into_any_element()),
+            (None, Some(split_input)) => Some(split_input.clone().into_any_element()),
+            (None, None) => None,
+        };
+        let body = match (active_editor, second_pane) {
+            (Some(editor), Some(second_pane)) => Some(
+                SplitPane::new(self.split.clone(), editor, second_pane)
+                    .when(self.split_stacked, SplitPane::stacked)
+                    .into_any_element(),
+            ),
+            (editor, _) => editor.map(IntoElement::into_any_element),
+        };
+
+        // the workspace's shortcuts work in the sidebar too
+        let sidebar = self.file_tree.clone().map(|file_tree| {
+            div()
+                .h_full()
+                .on_key_down(cx.listener(Self::on_key_down))
+                .child(file_tree)
+        });
 
-        Background::new().child(
-            Layout::new()
-                .title_bar(
-                    TitleBar::new().child(
-                        div().flex().justify_end().items_center().w_full().child(
-                            Button::new(
-                                "Clear",
-                                Box::new(move |_ev, cx| {
-                                    for editor in editors.clone() {
-                                        editor.clear(cx);
-                                    }
-                                }),
+        Background::new()
+            .child(
+                Layout::new()
+                    .title_bar(
+                        TitleBar::new()
+                            .window_controls(WindowControls::new(Box::new(cx.listener(
+                                |this, _ev: &MouseDownEvent, cx| {
+                                    this.guard(PendingAction::Close, cx)
+                                },
+                            ))))
+                            .title(title)
+                            .subtitle(
+                                div()
+                                    .flex()
+                                    .gap_2()
+                                    .when(dirty, |this| this.child("●"))
+                                    .when(saved, |this| {
+                                        this.child(div().text_color(muted_color).child("Saved"))
+                                    })
+                                    .children(
+                                        error.map(|error| {
+                                            div().text_color(danger_color).child(error)
+                                        }),
+                                    ),
                             )
-                            .variant(ButtonVariant::Danger)
-                            .h_6()
-                            .mr_2()
-                            .text_sm(),
-                        ),
+                            .child(
+                                div()
+                                    .text_color(muted_color)
+                                    .cursor_pointer()
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|this, _ev: &MouseDownEvent, cx| {
+                                            // the title bar would move the window
+                                            cx.stop_propagation();
+                                            this.toggle_line_ending(cx)
+                                        }),
+                                    )
+                                    .child(line_ending.label()),
+                            )
+                            .child(
+                                Button::new(
+                                    "clear",
+                                    "Clear",
+                                    Box::new(move |_ev, cx| {
+                                        for editor in editors.clone() {
+                                            editor.clear(cx);
+                                        }
+                                    }),
+                                )
+                                .variant(ButtonVariant::Danger)
+                                .h_6(),
+                            ),
+                    )
+                    .progress(progress)
+                    .tab_bar(tab_bar)
+                    .when_some(banner, |layout, banner| layout.banner(banner))
+                    .when_some(search_bar, |layout, search_bar| {
+                        layout.search_bar(search_bar)
+                    })
+                    .when_some(sidebar, |layout, sidebar| layout.sidebar(sidebar))
+                    .status_bar(self.status_bar.clone())
+                    .body(
+                        div()
+                            .size_full()
+                            .flex()
+                            .flex_col()
+                            .on_key_down(cx.listener(Self::on_key_down))
+                            // only the active tab is rendered, the others keep
+                            // their selection and scroll position in their views
+                            .children(body),
                     ),
+            )
+            .children(self.command_palette.clone())
+            .children(self.quick_open.clone())
+            .children(
+                self.project_search
+                    .clone()
+                    .filter(|_| self.project_search_visible),
+            )
+            .children(self.go_to_line.clone())
+            .children(self.clipboard_picker.clone())
+            .children(self.backup_picker.clone())
+            .children(self.shell_command.clone())
+            .child(self.render_drop_target(cx))
+            .child(self.toasts.clone())
+            .child(ModalLayer)
+    }
+}
+
+impl Workspace {
+    /// Covers the window and opens the files dropped on it, it only shows
+    /// while files are dragged over the window.
+    fn render_drop_target(&self, cx: &mut ViewContext<Self>) -> Div {
+        let theme = cx.global::<Theme>();
+
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .group("drop-target")
+            .on_drop(
+                cx.listener(|this, paths: &ExternalPaths, cx| this.open_paths(paths.paths(), cx)),
+            )
+            .child(
+                div()
+                    .size_full()
+                    .flex()
+                    .justify_center()
+                    .items_center()
+                    .invisible()
+                    .bg(theme.backdrop_color)
+                    .group_drag_over::<ExternalPaths>("drop-target", |style| style.visible())
+                    .child(
+                        div()
+                            .p_4()
+                            .rounded_md()
+                            .border_1()
+                            .border_color(theme.border_color)
+                            .bg(theme.panel_color)
+                            .child("Drop to open"),
+                    ),
+            )
+    }
+
+    fn render_tab_bar(&self, cx: &mut ViewContext<Self>) -> TabBar {
+        TabBar::new().children(self.editors.iter().enumerate().map(|(i, editor)| {
+            Tab::new(
+                editor.title(cx),
+                Box::new(
+                    cx.listener(move |this, _ev: &MouseDownEvent, cx| this.activate_tab(i, cx)),
+                ),
+            )
+            .active(i == self.active_editor)
+            .dirty(editor.is_dirty(cx))
+            .on_close(Box::new(
+                cx.listener(move |this, _ev: &MouseDownEvent, cx| this.close_tab(i, cx)),
+            ))
+        }))
+    }
+
+    /// Tells that another program changed the file of `editor`, which has
+    /// unsaved changes, and asks which version to keep.
+    fn render_reload_banner(&self, editor: Editor, cx: &mut ViewContext<Self>) -> Div {
+        let message = format!("{} was changed on disk.", editor.title(cx));
+        banner(message, cx).child(
+            div()
+                .flex()
+                .gap_2()
+                .child(
+                    Button::new("keep-mine", "Keep mine", {
+                        let editor = editor.clone();
+                        Box::new(move |_ev, cx| editor.keep_local_changes(cx))
+                    })
+                    .variant(ButtonVariant::Secondary)
+                    .h_6(),
                 )
-                .body(div().when(self.editors.len() > 0, |this| {
-                    let mut children: Vec<AnyElement> = vec![];
-
-                    for i in 0..self.editors.len() {
-                        let editor = self.editors[i].clone();
-                        children.push(editor.into_any_element());
-                        if i != self.editors.len() - 1 {
-                            children.push(Divider::horizontal().into_any_element())
-                        }
-                    }
+                .child(
+                    Button::new(
+                        "reload",
+                        "Reload",
+                        Box::new(move |_ev, cx| editor.reload(cx)),
+                    )
+                    .variant(ButtonVariant::Danger)
+                    .h_6(),
+                ),
+        )
+    }
 
-                    this.children(children)
+    /// Tells which backup the active editor shows, with a button to copy it
+    /// over its file.
+    fn render_backup_banner(&self, editor: Editor, cx: &mut ViewContext<Self>) -> Div {
+        let message = editor.state.read(cx).backup.as_ref().map(|backup| {
+            format!(
+                "The backup of {} from {}, read-only.",
+                backup.original.display(),
+                backup.created_label()
+            )
+        });
+        banner(message.unwrap_or_default(), cx).child(
+            Button::new(
+                "restore-backup",
+                "Copy Over File",
+                Box::new(cx.listener(move |this, _ev: &ButtonEvent, cx| {
+                    this.confirm_restore_backup(editor.clone(), cx)
                 })),
+            )
+            .variant(ButtonVariant::Danger)
+            .h_6(),
+        )
+    }
+
+    /// The notice about the active editor's file, a change on disk comes
+    /// before a warning.
+    fn render_banner(&self, editor: Editor, cx: &mut ViewContext<Self>) -> Option<Div> {
+        let state = editor.state.read(cx);
+        if state.external_change.is_some() {
+            return Some(self.render_reload_banner(editor, cx));
+        }
+        if state.backup.is_some() {
+            return Some(self.render_backup_banner(editor, cx));
+        }
+        let warning = state.warning.clone()?;
+        Some(
+            banner(warning, cx).child(
+                Button::new(
+                    "dismiss",
+                    "Dismiss",
+                    Box::new(move |_ev, cx| editor.set_warning(None, cx)),
+                )
+                .variant(ButtonVariant::Ghost)
+                .h_6(),
+            ),
+        )
+    }
+
+    fn render_theme_banner(&self, cx: &mut ViewContext<Self>) -> Option<Div> {
+        let error = self.theme_error.clone()?;
+        Some(
+            banner(error, cx).child(
+                Button::new(
+                    "dismiss",
+                    "Dismiss",
+                    Box::new(cx.listener(|this, _ev: &ButtonEvent, cx| {
+                        this.theme_error = None;
+                        cx.notify();
+                    })),
+                )
+                .variant(ButtonVariant::Ghost)
+                .h_6(),
+            ),
+        )
+    }
+}
+
+/// Makes the workspace's shortcuts available in the command palette.
+fn register_commands(cx: &mut ViewContext<Workspace>) {
+    let view = cx.view().downgrade();
+    let command = |id: &'static str,
+                   label: &'static str,
+                   run: fn(&mut Workspace, &mut ViewContext<Workspace>)| {
+        let view = view.clone();
+        Command::new(id, label, move |cx| {
+            view.update(cx, |this, cx| run(this, cx)).ok();
+        })
+    };
+    let commands = [
+        command("workspace.new_tab", "New Tab", Workspace::new_tab).keybinding("⌘T"),
+        command("workspace.open", "Open…", Workspace::open_file).keybinding("⌘O"),
+        command(
+            "workspace.open_folder",
+            "Open Folder…",
+            Workspace::open_folder,
+        ),
+        command(
+            "workspace.reveal_in_sidebar",
+            "Reveal in Sidebar",
+            Workspace::reveal_in_sidebar,
+        ),
+        command(
+            "workspace.focus_sidebar",
+            "Focus Sidebar",
+            Workspace::focus_sidebar,
         )
+        .keybinding("⇧⌘E"),
+        command("workspace.focus_editor", "Focus Editor", |this, cx| {
+            this.focus_pane(0, cx)
+        })
+        .keybinding("⌘1"),
+        command(
+            "workspace.quick_open",
+            "Quick Open…",
+            Workspace::toggle_quick_open,
+        )
+        .keybinding("⌘P"),
+        command("workspace.save", "Save", |this, cx| this.save(false, cx)).keybinding("⌘S"),
+        command("workspace.save_as", "Save As…", |this, cx| {
+            this.save(true, cx)
+        })
+        .keybinding("⇧⌘S"),
+        command(
+            "editor.document_statistics",
+            "Document Statistics",
+            Workspace::show_statistics,
+        ),
+        command(
+            "workspace.export_html",
+            "Export as HTML…",
+            Workspace::export_html,
+        ),
+        command(
+            "editor.copy_as_html",
+            "Copy as HTML",
+            Workspace::copy_as_html,
+        ),
+        command("workspace.close_tab", "Close Tab", |this, cx| {
+            this.close_tab(this.active_editor, cx)
+        })
+        .keybinding("⌘W"),
+        command("workspace.find", "Find", Workspace::open_search).keybinding("⌘F"),
+        command(
+            "workspace.find_in_folder",
+            "Find in Folder…",
+            Workspace::toggle_project_search,
+        )
+        .keybinding("⇧⌘F"),
+        command(
+            "workspace.go_to_line",
+            "Go to Line…",
+            Workspace::open_go_to_line,
+        )
+        .keybinding("⌃G"),
+        command(
+            "workspace.toggle_split",
+            "Toggle Split",
+            Workspace::toggle_split,
+        )
+        .keybinding("⌘\\"),
+        command(
+            "workspace.toggle_split_orientation",
+            "Toggle Split Orientation",
+            Workspace::toggle_split_orientation,
+        ),
+        Command::new("theme.toggle", "Toggle Light and Dark Theme", Theme::toggle)
+            .keybinding("⌥⌘T"),
+        command(
+            "workspace.toggle_markdown_preview",
+            "Toggle Markdown Preview",
+            Workspace::toggle_markdown_preview,
+        ),
+        command(
+            "editor.toggle_soft_wrap",
+            "Toggle Soft Wrap",
+            Workspace::toggle_soft_wrap,
+        )
+        .keybinding("⌥⌘Z"),
+        command(
+            "editor.toggle_spell_check",
+            "Toggle Spell Check",
+            Workspace::toggle_spell_check,
+        ),
+        Command::new(
+            "editor.toggle_render_whitespace",
+            "Toggle Render Whitespace",
+            EditorSettings::toggle_render_whitespace,
+        ),
+        Command::new(
+            "editor.toggle_indent_guides",
+            "Toggle Indent Guides",
+            EditorSettings::toggle_indent_guides,
+        ),
+        Command::new(
+            "editor.toggle_ruler",
+            "Toggle Ruler",
+            EditorSettings::toggle_ruler,
+        ),
+        command(
+            "editor.restore_from_backup",
+            "Restore from Backup…",
+            Workspace::open_backup_picker,
+        ),
+        command(
+            "editor.paste_from_history",
+            "Paste from Clipboard History",
+            Workspace::open_clipboard_picker,
+        )
+        .keybinding("⇧⌘V"),
+        command("editor.paste_raw", "Paste as Raw Text", |this, cx| {
+            this.perform(EditorAction::PasteRaw, cx)
+        })
+        .keybinding("⌥⇧⌘V"),
+        command(
+            "editor.insert_shell_output",
+            "Insert Output of Shell Command…",
+            |this, cx| this.open_shell_command(ShellCommandMode::Insert, cx),
+        ),
+        command(
+            "editor.filter_selection",
+            "Filter Selection Through Command…",
+            |this, cx| this.open_shell_command(ShellCommandMode::Filter, cx),
+        ),
+        command(
+            "editor.format_document",
+            "Format Document",
+            Workspace::format_document,
+        ),
+        command("editor.sort_lines", "Sort Lines Ascending", |this, cx| {
+            this.sort_lines(true, cx)
+        }),
+        command(
+            "editor.sort_lines_case_insensitive",
+            "Sort Lines Ascending, Case Insensitive",
+            |this, cx| this.sort_lines(false, cx),
+        ),
+        command(
+            "editor.reverse_lines",
+            "Reverse Lines",
+            Workspace::reverse_lines,
+        ),
+        command(
+            "editor.remove_duplicate_lines",
+            "Remove Duplicate Lines",
+            Workspace::remove_duplicate_lines,
+        ),
+        command("editor.delete_line", "Delete Line", |this, cx| {
+            this.perform(EditorAction::DeleteLine, cx)
+        })
+        .keybinding("⇧⌘K"),
+        command("editor.join_lines", "Join Lines", |this, cx| {
+            this.perform(EditorAction::JoinLines, cx)
+        })
+        .keybinding("⌃J"),
+        command(
+            "editor.select_next_occurrence",
+            "Add Next Occurrence to Selection",
+            |this, cx| this.perform(EditorAction::SelectNextOccurrence, cx),
+        )
+        .keybinding("⌘D"),
+        command(
+            "editor.select_all_occurrences",
+            "Select All Occurrences",
+            |this, cx| this.perform(EditorAction::SelectAllOccurrences, cx),
+        )
+        .keybinding("⇧⌘L"),
+        command("editor.uppercase", "Transform to Uppercase", |this, cx| {
+            this.perform(EditorAction::Uppercase, cx)
+        })
+        .keybinding("⌃⇧U"),
+        command("editor.lowercase", "Transform to Lowercase", |this, cx| {
+            this.perform(EditorAction::Lowercase, cx)
+        })
+        .keybinding("⌃⇧L"),
+        command(
+            "editor.title_case",
+            "Transform to Title Case",
+            |this, cx| this.perform(EditorAction::TitleCase, cx),
+        )
+        .keybinding("⌃⇧T"),
+        command(
+            "editor.split_selection_into_lines",
+            "Split Selection into Lines",
+            Workspace::split_selection_into_lines,
+        ),
+        command(
+            "macro.toggle_recording",
+            "Start or Stop Recording Macro",
+            Workspace::toggle_macro_recording,
+        ),
+        Command::with_count("macro.play", "Play Macro", {
+            let view = view.clone();
+            move |count, cx| {
+                view.update(cx, |this, cx| this.play_macro(count, cx)).ok();
+            }
+        }),
+    ];
+    // every snippet can be inserted from the palette too, typing its prefix
+    // and tab does the same
+    let snippets: Vec<Command> = cx
+        .global::<Snippets>()
+        .all()
+        .iter()
+        .map(|snippet| {
+            let language = snippet.language.as_deref().unwrap_or("any");
+            let description = snippet.description.as_deref().unwrap_or(&snippet.prefix);
+            let label = match &snippet.language {
+                Some(language) => format!("Insert Snippet: {description} ({language})"),
+                None => format!("Insert Snippet: {description}"),
+            };
+            let view = view.clone();
+            let body = snippet.body.clone();
+            Command::new(
+                format!("snippet.{language}.{}", snippet.prefix),
+                label,
+                move |cx| {
+                    view.update(cx, |this, cx| this.insert_snippet(&body, cx))
+                        .ok();
+                },
+            )
+            .keybinding(format!("{}⇥", snippet.prefix))
+        })
+        .collect();
+    let registry = cx.global_mut::<CommandRegistry>();
+    for command in commands.into_iter().chain(snippets) {
+        registry.register(command);
     }
 }
+
+/// Whether `editor`'s text is Markdown, by its language.
+fn is_markdown(editor: &Editor, cx: &AppContext) -> bool {
+    editor.text_input.model.read(cx).settings.language == Some("markdown")
+}
+
+/// A row under the title bar with `message` on the left, buttons go right of it.
+fn banner(message: impl IntoElement, cx: &mut WindowContext) -> Div {
+    let theme = cx.global::<Theme>();
+    div()
+        .flex()
+        .items_center()
+        .justify_between()
+        .gap_2()
+        .px_6()
+        .py_2()
+        .text_sm()
+        .bg(theme.panel_color)
+        .border_b()
+        .border_color(theme.border_color)
+        .child(message)
+}